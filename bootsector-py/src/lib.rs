@@ -0,0 +1,79 @@
+//! Python bindings, built with `maturin`. See the README.
+
+use std::fs::File;
+use std::path::PathBuf;
+
+use bootsector::json::Entry;
+use bootsector::{Options, SectorSize};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+
+/// List the partitions in an image, given its path, or its contents as `bytes`.
+#[pyfunction]
+#[pyo3(signature = (source, sector_size = None))]
+fn list_partitions(
+    py: Python<'_>,
+    source: &PyAny,
+    sector_size: Option<u16>,
+) -> PyResult<Vec<PyObject>> {
+    let mut options = Options::default();
+    if let Some(size) = sector_size {
+        options.sector_size = SectorSize::Known(size);
+    }
+
+    let partitions = match source.downcast::<PyBytes>() {
+        Ok(bytes) => bootsector::list_partitions(bytes.as_bytes(), &options),
+        Err(_) => {
+            let path: PathBuf = source.extract()?;
+            let file = File::open(path)?;
+            bootsector::list_partitions(&file, &options)
+        }
+    }
+    .map_err(to_py_err)?;
+
+    partitions
+        .iter()
+        .map(|part| to_dict(py, &Entry::from(part)))
+        .collect()
+}
+
+fn to_dict(py: Python<'_>, entry: &Entry) -> PyResult<PyObject> {
+    let dict = PyDict::new(py);
+    dict.set_item("id", entry.id)?;
+    dict.set_item("first_byte", entry.first_byte)?;
+    dict.set_item("len", entry.len)?;
+    if let Some(bootable) = entry.bootable {
+        dict.set_item("bootable", bootable)?;
+    }
+    if let Some(type_code) = entry.type_code {
+        dict.set_item("type_code", type_code)?;
+    }
+    if let Some(type_guid) = &entry.type_guid {
+        dict.set_item("type_guid", type_guid)?;
+    }
+    if let Some(partition_guid) = &entry.partition_guid {
+        dict.set_item("partition_guid", partition_guid)?;
+    }
+    if let Some(attributes) = entry.attributes {
+        dict.set_item("attributes", attributes)?;
+    }
+    if let Some(name) = entry.name {
+        dict.set_item("name", name)?;
+    }
+    Ok(dict.into())
+}
+
+fn to_py_err(error: bootsector::Error) -> PyErr {
+    match error {
+        bootsector::Error::Io { source, .. } => source.into(),
+        other => PyValueError::new_err(other.to_string()),
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "bootsector")]
+fn module(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(list_partitions, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,216 @@
+//! Read partition tables from async readers, without depending on a runtime.
+//!
+//! The `async-futures` feature accepts `futures_io`'s traits, as implemented by async-std
+//! and smol; `async-tokio` accepts tokio's. Only the traits are needed, not an executor.
+//!
+//! The disc is read in 64KiB blocks, which are fetched as the (synchronous) parser asks
+//! for them; a typical table needs only the first block.
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "async-tokio")]
+//! # async fn go<R>(mut file: R) -> Result<(), bootsector::Error>
+//! # where R: tokio::io::AsyncRead + tokio::io::AsyncSeek + Unpin {
+//! // e.g. a tokio::fs::File
+//! let table =
+//!     bootsector::asynchronous::read_partition_table_tokio(&mut file, &Default::default())
+//!         .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::future::Future;
+use std::io::{self, SeekFrom};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use snafu::prelude::*;
+
+use crate::errors::IoSnafu;
+use crate::{Error, Options, PartitionTable};
+
+const BLOCK: u64 = 64 * 1024;
+
+/// Read the partition table from a `futures_io` reader, e.g. an `async_std::fs::File`.
+///
+/// This behaves like [`read_partition_table_sized`](crate::read_partition_table_sized),
+/// with the length of the disc found by seeking to the end.
+#[cfg(feature = "async-futures")]
+pub async fn read_partition_table_futures<R>(
+    reader: &mut R,
+    options: &Options,
+) -> Result<PartitionTable, Error>
+where
+    R: futures_io::AsyncRead + futures_io::AsyncSeek + Unpin,
+{
+    let disk_len = poll_fn(|cx| Pin::new(&mut *reader).poll_seek(cx, SeekFrom::End(0)))
+        .await
+        .context(IoSnafu { pos: 0u64 })?;
+
+    let mut disc = Prefetched::new(disk_len);
+    loop {
+        let (pos, mut buf) = match disc.attempt(options) {
+            Attempt::Done(result) => return *result,
+            Attempt::Fetch { pos, len } => (pos, vec![0u8; len]),
+        };
+
+        let fetched: io::Result<()> = async {
+            poll_fn(|cx| Pin::new(&mut *reader).poll_seek(cx, SeekFrom::Start(pos))).await?;
+            let mut done = 0;
+            while done < buf.len() {
+                let read =
+                    poll_fn(|cx| Pin::new(&mut *reader).poll_read(cx, &mut buf[done..])).await?;
+                if 0 == read {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                done += read;
+            }
+            Ok(())
+        }
+        .await;
+
+        fetched.context(IoSnafu { pos })?;
+        disc.insert(pos, buf);
+    }
+}
+
+/// Read the partition table from a tokio reader, e.g. a `tokio::fs::File`.
+///
+/// This behaves like [`read_partition_table_sized`](crate::read_partition_table_sized),
+/// with the length of the disc found by seeking to the end.
+#[cfg(feature = "async-tokio")]
+pub async fn read_partition_table_tokio<R>(
+    reader: &mut R,
+    options: &Options,
+) -> Result<PartitionTable, Error>
+where
+    R: tokio::io::AsyncRead + tokio::io::AsyncSeek + Unpin,
+{
+    use tokio::io::{AsyncSeek, ReadBuf};
+
+    async fn seek<R: AsyncSeek + Unpin>(reader: &mut R, to: SeekFrom) -> io::Result<u64> {
+        Pin::new(&mut *reader).start_seek(to)?;
+        poll_fn(|cx| Pin::new(&mut *reader).poll_complete(cx)).await
+    }
+
+    let disk_len = seek(reader, SeekFrom::End(0))
+        .await
+        .context(IoSnafu { pos: 0u64 })?;
+
+    let mut disc = Prefetched::new(disk_len);
+    loop {
+        let (pos, mut buf) = match disc.attempt(options) {
+            Attempt::Done(result) => return *result,
+            Attempt::Fetch { pos, len } => (pos, vec![0u8; len]),
+        };
+
+        let fetched: io::Result<()> = async {
+            seek(reader, SeekFrom::Start(pos)).await?;
+            let mut done = 0;
+            while done < buf.len() {
+                let mut read_buf = ReadBuf::new(&mut buf[done..]);
+                poll_fn(|cx| Pin::new(&mut *reader).poll_read(cx, &mut read_buf)).await?;
+                if read_buf.filled().is_empty() {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                done += read_buf.filled().len();
+            }
+            Ok(())
+        }
+        .await;
+
+        fetched.context(IoSnafu { pos })?;
+        disc.insert(pos, buf);
+    }
+}
+
+/// A future which calls a function until it's ready.
+struct PollFn<F>(F);
+
+fn poll_fn<T, F>(f: F) -> PollFn<F>
+where
+    F: FnMut(&mut Context<'_>) -> Poll<T> + Unpin,
+{
+    PollFn(f)
+}
+
+impl<T, F> Future for PollFn<F>
+where
+    F: FnMut(&mut Context<'_>) -> Poll<T> + Unpin,
+{
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        (self.0)(cx)
+    }
+}
+
+enum Attempt {
+    Done(Box<Result<PartitionTable, Error>>),
+    Fetch { pos: u64, len: usize },
+}
+
+/// The blocks of the disc fetched so far, and the first one the parser wanted but didn't get.
+struct Prefetched {
+    disk_len: u64,
+    blocks: BTreeMap<u64, Vec<u8>>,
+    missing: Cell<Option<u64>>,
+}
+
+impl Prefetched {
+    fn new(disk_len: u64) -> Prefetched {
+        Prefetched {
+            disk_len,
+            blocks: BTreeMap::new(),
+            missing: Cell::new(None),
+        }
+    }
+
+    /// Run the parser over what we have, which is only trusted if it never saw a gap:
+    /// some of its reads are allowed to fail, and it may have carried on regardless.
+    fn attempt(&self, options: &Options) -> Attempt {
+        self.missing.set(None);
+        let result = crate::read_with_len(self, options, Some(self.disk_len));
+        match self.missing.take() {
+            None => Attempt::Done(Box::new(result)),
+            Some(index) => {
+                let pos = index * BLOCK;
+                let len = BLOCK.min(self.disk_len - pos);
+                Attempt::Fetch {
+                    pos,
+                    len: usize::try_from(len).expect("less than a block"),
+                }
+            }
+        }
+    }
+
+    fn insert(&mut self, pos: u64, data: Vec<u8>) {
+        self.blocks.insert(pos / BLOCK, data);
+    }
+}
+
+impl positioned_io2::ReadAt for Prefetched {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        if pos >= self.disk_len || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let index = pos / BLOCK;
+        match self.blocks.get(&index) {
+            Some(block) => {
+                let skip = usize::try_from(pos % BLOCK).expect("less than a block");
+                let len = buf.len().min(block.len() - skip);
+                buf[..len].copy_from_slice(&block[skip..skip + len]);
+                Ok(len)
+            }
+            None => {
+                if self.missing.get().is_none() {
+                    self.missing.set(Some(index));
+                }
+                Err(io::Error::new(io::ErrorKind::Other, "not fetched yet"))
+            }
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! Interpret the bits of GPT partitions' attributes which are specific to their type.
+//!
+//! Bits 48 to 63 mean different things for each partition type. Each reader here takes
+//! the whole 64-bit value, as found in [`Attributes::GPT`], and each writer returns it with
+//! only its own bits replaced, ready for [`GptEditor::set_attributes`].
+//!
+//! [`GptEditor::set_attributes`]: crate::edit::GptEditor::set_attributes
+//!
+//! ```rust
+//! # fn go(disc: &mut [u8]) -> Result<(), bootsector::Error> {
+//! use bootsector::attributes::{self, ChromeOsKernel};
+//! use bootsector::edit::GptEditor;
+//!
+//! let mut editor = GptEditor::open(&*disc, 512)?;
+//! for part in editor.partitions()? {
+//!     if !attributes::is_chromeos_kernel(&part) {
+//!         continue;
+//!     }
+//!     let bits = attributes::bits(&part).expect("gpt");
+//!     let mut kernel = ChromeOsKernel::from_attributes(bits);
+//!     kernel.priority = 0;
+//!     editor.set_attributes(part.id, kernel.to_attributes(bits)?)?;
+//! }
+//! editor.commit(disc)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::gpt::guids;
+use crate::{Attributes, Error, Partition};
+
+/// The bit which BIOS boot code treats as the MBR "bootable" flag, on any partition type.
+pub const LEGACY_BIOS_BOOTABLE: u64 = 1 << 2;
+
+/// A GPT partition's attributes, as a number, so bit `n` is `1 << n`.
+///
+/// `None` for MBR partitions.
+pub fn bits(part: &Partition) -> Option<u64> {
+    match &part.attributes {
+        Attributes::GPT { attributes, .. } => Some(u64::from_le_bytes(*attributes)),
+        Attributes::MBR { .. } => None,
+    }
+}
+
+fn has_type(part: &Partition, expected: [u8; 16]) -> bool {
+    match &part.attributes {
+        Attributes::GPT { type_uuid, .. } => expected == *type_uuid,
+        Attributes::MBR { .. } => false,
+    }
+}
+
+/// Is this a ChromeOS kernel partition, i.e. one of the A/B slots?
+pub fn is_chromeos_kernel(part: &Partition) -> bool {
+    has_type(part, guids::CHROMEOS_KERNEL)
+}
+
+/// Is this a Microsoft basic data partition, e.g. NTFS or FAT on Windows?
+///
+/// Linux tools also use this type for FAT and exFAT, so it may not have come from Windows.
+pub fn is_microsoft_basic_data(part: &Partition) -> bool {
+    has_type(part, guids::MS_BASIC_DATA)
+}
+
+/// The boot state of a ChromeOS kernel partition, from bits 48 to 56.
+///
+/// The firmware boots the kernel with the highest priority which is either successful,
+/// or has tries remaining; it decrements the tries as it does so.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ChromeOsKernel {
+    /// 0 (never boot) to 15.
+    pub priority: u8,
+    /// 0 to 15.
+    pub tries_remaining: u8,
+    /// Set by the OS once it has booted properly.
+    pub successful: bool,
+}
+
+impl ChromeOsKernel {
+    pub fn from_attributes(attributes: u64) -> ChromeOsKernel {
+        let nibble = |shift: u32| (attributes >> shift).to_le_bytes()[0] & 0xf;
+        ChromeOsKernel {
+            priority: nibble(48),
+            tries_remaining: nibble(52),
+            successful: 0 != attributes & (1 << 56),
+        }
+    }
+
+    /// Replace bits 48 to 56 of `attributes` with this state.
+    pub fn to_attributes(&self, attributes: u64) -> Result<u64, Error> {
+        if self.priority > 15 || self.tries_remaining > 15 {
+            return Err(Error::InvalidStatic {
+                message: "chromeos priority and tries are at most 15",
+            });
+        }
+
+        let cleared = attributes & !(0x1ff << 48);
+        Ok(cleared
+            | u64::from(self.priority) << 48
+            | u64::from(self.tries_remaining) << 52
+            | u64::from(self.successful) << 56)
+    }
+}
+
+/// The flags of a Microsoft basic data partition, from bits 60 to 63.
+///
+/// Windows applies these to the volume when mounting it; they don't protect the data.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct MicrosoftBasicData {
+    /// Mount the volume read-only (bit 60).
+    pub read_only: bool,
+    /// The partition is a shadow copy of another (bit 61).
+    pub shadow_copy: bool,
+    /// Don't mount the volume, or show it to the user (bit 62).
+    pub hidden: bool,
+    /// Don't assign a drive letter when mounting (bit 63).
+    pub no_drive_letter: bool,
+}
+
+impl MicrosoftBasicData {
+    pub fn from_attributes(attributes: u64) -> MicrosoftBasicData {
+        let bit = |n: u32| 0 != attributes & (1 << n);
+        MicrosoftBasicData {
+            read_only: bit(60),
+            shadow_copy: bit(61),
+            hidden: bit(62),
+            no_drive_letter: bit(63),
+        }
+    }
+
+    /// Replace bits 60 to 63 of `attributes` with these flags.
+    pub fn to_attributes(&self, attributes: u64) -> u64 {
+        let cleared = attributes & !(0xf << 60);
+        cleared
+            | u64::from(self.read_only) << 60
+            | u64::from(self.shadow_copy) << 61
+            | u64::from(self.hidden) << 62
+            | u64::from(self.no_drive_letter) << 63
+    }
+}
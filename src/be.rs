@@ -0,0 +1,11 @@
+use core::convert::TryInto;
+
+#[inline]
+pub fn read_u32(slice: &[u8]) -> u32 {
+    u32::from_be_bytes(slice[..4].try_into().expect("fixed size slice"))
+}
+
+#[inline]
+pub fn read_u64(slice: &[u8]) -> u64 {
+    u64::from_be_bytes(slice[..8].try_into().expect("fixed size slice"))
+}
@@ -0,0 +1,201 @@
+//! A small command-line front-end to the library.
+//!
+//! ```text
+//! bootsector ls [--sector-size N] [--gpt-only | --mbr-only] <image>
+//! bootsector verify [--sector-size N] <image>
+//! bootsector scan [--sector-size N] [--gpt-only | --mbr-only] [--json] <dir>
+//! ```
+
+use std::env;
+use std::fs;
+use std::process;
+
+use bootsector::scan::scan_dir;
+use bootsector::verify::{verify, Severity};
+use bootsector::{list_partitions, Error, Options, ReadGPT, ReadMBR, SectorSize};
+
+const USAGE: &str = "usage:
+    bootsector ls [--sector-size N] [--gpt-only | --mbr-only] <image>
+    bootsector verify [--sector-size N] <image>
+    bootsector scan [--sector-size N] [--gpt-only | --mbr-only] [--json] <dir>";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let code = match args.first().map(|s| s.as_str()) {
+        Some("ls") => ls(&args[1..]),
+        Some("verify") => verify_cmd(&args[1..]),
+        Some("scan") => scan_cmd(&args[1..]),
+        Some("-h") | Some("--help") => {
+            println!("{}", USAGE);
+            0
+        }
+        _ => usage(),
+    };
+    process::exit(code);
+}
+
+fn usage() -> i32 {
+    eprintln!("{}", USAGE);
+    2
+}
+
+/// Parse the options shared by the subcommands, and the image path.
+fn parse_args(args: &[String]) -> Option<(Options, &str)> {
+    let mut options = Options::default();
+    let mut path = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--sector-size" => {
+                let size = args.next().and_then(|v| v.parse().ok())?;
+                options.sector_size = SectorSize::Known(size);
+            }
+            "--gpt-only" => options.mbr = ReadMBR::Never,
+            "--mbr-only" => options.gpt = ReadGPT::Never,
+            other if other.starts_with('-') => return None,
+            other if path.is_none() => path = Some(other),
+            _ => return None,
+        }
+    }
+
+    Some((options, path?))
+}
+
+fn open(path: &str) -> Option<fs::File> {
+    match fs::File::open(path) {
+        Ok(file) => Some(file),
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            None
+        }
+    }
+}
+
+fn ls(args: &[String]) -> i32 {
+    let (options, path) = match parse_args(args) {
+        Some(parsed) => parsed,
+        None => return usage(),
+    };
+
+    let file = match open(path) {
+        Some(file) => file,
+        None => return 1,
+    };
+
+    let partitions = match list_partitions(&file, &options) {
+        Ok(partitions) => partitions,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return 1;
+        }
+    };
+
+    println!(
+        "{:>3} B {:>16} {:>16} {:<36} NAME",
+        "ID", "START", "SIZE", "TYPE"
+    );
+    for part in &partitions {
+        println!(
+            "{:>3} {} {:>16} {:>16} {:<36} {}",
+            part.id,
+            if part.is_bootable() { "*" } else { " " },
+            part.first_byte,
+            part.len,
+            part.type_display(),
+            part.name().unwrap_or("")
+        );
+    }
+
+    0
+}
+
+fn verify_cmd(args: &[String]) -> i32 {
+    let (options, path) = match parse_args(args) {
+        Some(parsed) => parsed,
+        None => return usage(),
+    };
+
+    let file = match open(path) {
+        Some(file) => file,
+        None => return 1,
+    };
+
+    let disk_len = file
+        .metadata()
+        .ok()
+        .map(|meta| meta.len())
+        .filter(|len| 0 != *len);
+
+    let findings = match verify(&file, disk_len, &options) {
+        Ok(findings) => findings,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return 1;
+        }
+    };
+
+    for finding in &findings {
+        println!(
+            "{:<7} {:<28} {}",
+            finding.severity, finding.code, finding.message
+        );
+    }
+
+    if findings.iter().any(|f| Severity::Error == f.severity) {
+        1
+    } else {
+        0
+    }
+}
+
+fn scan_cmd(args: &[String]) -> i32 {
+    let json = args.iter().any(|arg| "--json" == arg);
+    let args: Vec<String> = args
+        .iter()
+        .filter(|arg| "--json" != *arg)
+        .cloned()
+        .collect();
+    let (options, dir) = match parse_args(&args) {
+        Some(parsed) => parsed,
+        None => return usage(),
+    };
+
+    let report = match scan_dir(dir, &options) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("{}: {}", dir, e);
+            return 1;
+        }
+    };
+
+    if json {
+        let scan = bootsector::json::Scan::from(&report);
+        println!(
+            "{}",
+            serde_json::to_string(&scan).expect("serialising to a string")
+        );
+        return 0;
+    }
+
+    let summary = report.summary();
+    for image in &report.images {
+        let detail = match &image.result {
+            Ok(table) => format!("{} partitions", table.partitions.len()),
+            Err(Error::NotFound) => String::new(),
+            Err(e) => e.to_string(),
+        };
+        println!(
+            "{:<13} {} {}",
+            image.class().name(),
+            image.path.display(),
+            detail
+        );
+    }
+    println!(
+        "{} mbr, {} gpt, {} unpartitioned, {} failed; {} partitions",
+        summary.mbr, summary.gpt, summary.unpartitioned, summary.failed, summary.partitions
+    );
+
+    0
+}
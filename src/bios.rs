@@ -0,0 +1,92 @@
+//! Check a disc is laid out to boot with a BIOS, i.e. without UEFI, using GRUB.
+//!
+//! GRUB's boot code in the MBR is too small to do anything but load its "core image",
+//! which must be stored somewhere no filesystem will overwrite: on an MBR disc, in the
+//! gap before the first partition; on a GPT disc, in a dedicated BIOS boot partition.
+//!
+//! ```rust
+//! # fn go(disc: &[u8]) -> Result<(), bootsector::Error> {
+//! use bootsector::bios::{grub_embedding_area, RECOMMENDED_EMBEDDING_LEN};
+//!
+//! let table = bootsector::read_partition_table(disc, &Default::default())?;
+//! match grub_embedding_area(&table) {
+//!     Some(area) if area.len() >= RECOMMENDED_EMBEDDING_LEN => println!("ok: {:?}", area),
+//!     Some(area) => println!("too small: {:?}", area),
+//!     None => println!("nowhere for GRUB to go"),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::gpt::guids;
+use crate::{Attributes, Partition, PartitionTable, TableType};
+
+/// The space GRUB recommends for its core image, which is what partitioning tools
+/// have left before the first partition for many years.
+///
+/// A minimal core image fits in much less, but one with support for e.g. RAID or LVM,
+/// needed to find the rest of GRUB, may not.
+pub const RECOMMENDED_EMBEDDING_LEN: u64 = 1024 * 1024;
+
+/// Where GRUB can embed its core image.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum EmbeddingArea {
+    /// The unpartitioned space after the boot sector of an MBR disc, before the first
+    /// partition.
+    MbrGap { first_byte: u64, len: u64 },
+
+    /// A BIOS boot partition on a GPT disc.
+    BiosBootPartition {
+        id: usize,
+        first_byte: u64,
+        len: u64,
+    },
+}
+
+impl EmbeddingArea {
+    /// The space available for the core image, in bytes.
+    pub fn len(&self) -> u64 {
+        match *self {
+            EmbeddingArea::MbrGap { len, .. } | EmbeddingArea::BiosBootPartition { len, .. } => len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        0 == self.len()
+    }
+}
+
+/// Is this a GPT BIOS boot partition?
+pub fn is_bios_boot(part: &Partition) -> bool {
+    match &part.attributes {
+        Attributes::GPT { type_uuid, .. } => guids::BIOS_BOOT == *type_uuid,
+        Attributes::MBR { .. } => false,
+    }
+}
+
+/// Find where GRUB's core image could go, if anywhere.
+///
+/// On GPT discs, this is the first BIOS boot partition; GRUB doesn't use the gap before
+/// the first partition, as it holds the GPT itself. On MBR discs, it's the gap after the
+/// boot sector, if there are any partitions to bound it.
+pub fn grub_embedding_area(table: &PartitionTable) -> Option<EmbeddingArea> {
+    match table.table_type {
+        TableType::GPT => table
+            .partitions
+            .iter()
+            .find(|part| is_bios_boot(part))
+            .map(|part| EmbeddingArea::BiosBootPartition {
+                id: part.id,
+                first_byte: part.first_byte,
+                len: part.len,
+            }),
+        TableType::MBR => {
+            let first = table.partitions.iter().map(|part| part.first_byte).min()?;
+            let first_byte = table.sector_size;
+            Some(EmbeddingArea::MbrGap {
+                first_byte,
+                len: first.saturating_sub(first_byte),
+            })
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! Find the partitions a machine might boot from, on either type of table.
+//!
+//! A BIOS boots the MBR partition marked bootable ("active"); on a GPT disc, boot code
+//! such as syslinux's `gptmbr.bin` looks for the "legacy BIOS bootable" attribute instead.
+//! UEFI firmware ignores both, and looks for an EFI system partition.
+//!
+//! ```rust
+//! # fn go(disc: &[u8]) -> Result<(), bootsector::Error> {
+//! let partitions = bootsector::list_partitions(disc, &Default::default())?;
+//! match bootsector::boot::find_bootable(&partitions).first() {
+//!     Some(part) => println!("would boot from partition {}", part.id),
+//!     None => println!("not bootable"),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::vec::Vec;
+
+use crate::attributes::LEGACY_BIOS_BOOTABLE;
+use crate::gpt::guids;
+use crate::{Attributes, Partition};
+
+/// The MBR type code of an EFI system partition.
+const EFI_SYSTEM_CODE: u8 = 0xef;
+
+/// Is this partition marked as bootable: the MBR flag, or the GPT "legacy BIOS
+/// bootable" attribute?
+pub fn is_marked_bootable(part: &Partition) -> bool {
+    match &part.attributes {
+        Attributes::MBR { bootable, .. } => *bootable,
+        Attributes::GPT { attributes, .. } => {
+            0 != u64::from_le_bytes(*attributes) & LEGACY_BIOS_BOOTABLE
+        }
+    }
+}
+
+/// Is this an EFI system partition, on either type of table?
+pub fn is_efi_system(part: &Partition) -> bool {
+    match &part.attributes {
+        Attributes::MBR { type_code, .. } => EFI_SYSTEM_CODE == *type_code,
+        Attributes::GPT { type_uuid, .. } => guids::ESP == *type_uuid,
+    }
+}
+
+/// The partitions which could be booted from, most likely first.
+///
+/// Those marked bootable come first, as someone chose them, then EFI system partitions
+/// which aren't marked; each group is in the order given. Nothing is read from the disc,
+/// so e.g. an EFI system partition may be empty.
+pub fn find_bootable(partitions: &[Partition]) -> Vec<&Partition> {
+    let marked = partitions.iter().filter(|part| is_marked_bootable(part));
+    let efi = partitions
+        .iter()
+        .filter(|part| is_efi_system(part) && !is_marked_bootable(part));
+    marked.chain(efi).collect()
+}
@@ -0,0 +1,281 @@
+//! Synthesise MBR and GPT images in memory, e.g. for tests, without shipping binary fixtures.
+//!
+//! Nothing here checks what it's given: overlapping partitions, partitions beyond the end
+//! of the disc, and damaged checksums are all deliberately possible, so tests can see how
+//! readers cope with them.
+//!
+//! ```rust
+//! # fn go() -> Result<(), bootsector::Error> {
+//! use bootsector::build::{Damage, GptImage};
+//!
+//! let mut image = GptImage::new(8 * 1024 * 1024);
+//! image.add(1024 * 1024, 1024 * 1024, "first");
+//! image.add(1536 * 1024, 1024 * 1024, "overlaps");
+//! image.damage.push(Damage::BackupHeaderCrc);
+//!
+//! let bytes = image.build()?;
+//! let partitions = bootsector::list_partitions(&bytes[..], &Default::default())?;
+//! assert_eq!(2, partitions.len());
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::{string::String, vec, vec::Vec};
+use core::convert::TryFrom;
+
+use crate::gpt::guids;
+use crate::gpt::{self, CreateOptions};
+use crate::io::WriteAt;
+use crate::{mbr, Error};
+
+/// An MBR image, with up to four primary partitions.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MbrImage {
+    pub disk_len: u64,
+
+    /// The unit of the LBAs in the entries. The table is always in the first 512 bytes.
+    pub sector_size: u64,
+
+    pub disk_signature: u32,
+
+    pub partitions: Vec<MbrEntry>,
+
+    /// Write the `55 AA` signature; clear this to make the table invalid.
+    pub boot_signature: bool,
+}
+
+/// A raw MBR partition entry. The CHS fields are not written.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct MbrEntry {
+    /// `0x80` for bootable, `0x00` otherwise; anything else is invalid.
+    pub status: u8,
+    pub type_code: u8,
+    pub first_lba: u32,
+    pub sectors: u32,
+}
+
+impl MbrImage {
+    /// An empty table, with 512-byte sectors.
+    pub fn new(disk_len: u64) -> MbrImage {
+        MbrImage {
+            disk_len,
+            sector_size: 512,
+            disk_signature: 0x0b00_7500,
+            partitions: Vec::new(),
+            boot_signature: true,
+        }
+    }
+
+    /// Add a Linux (`0x83`) partition, with its position in bytes.
+    pub fn add(&mut self, first_byte: u64, len: u64) -> &mut MbrEntry {
+        let lba = |bytes: u64| u32::try_from(bytes / self.sector_size).unwrap_or(u32::MAX);
+        let entry = MbrEntry {
+            status: 0,
+            type_code: 0x83,
+            first_lba: lba(first_byte),
+            sectors: lba(len),
+        };
+        self.partitions.push(entry);
+        self.partitions.last_mut().expect("just pushed")
+    }
+
+    pub fn build(&self) -> Result<Vec<u8>, Error> {
+        if self.partitions.len() > 4 {
+            return Err(Error::InvalidStatic {
+                message: "an MBR holds at most four partitions",
+            });
+        }
+
+        let mut image = zeroed(self.disk_len.max(512))?;
+
+        for (i, part) in self.partitions.iter().enumerate() {
+            let entry = &mut image[446 + i * 16..446 + (i + 1) * 16];
+            entry[0] = part.status;
+            entry[4] = part.type_code;
+            entry[8..12].copy_from_slice(&part.first_lba.to_le_bytes());
+            entry[12..16].copy_from_slice(&part.sectors.to_le_bytes());
+        }
+
+        let mut sector = [0u8; 512];
+        sector.copy_from_slice(&image[..512]);
+        mbr::set_disk_signature(&mut sector, self.disk_signature);
+        if self.boot_signature {
+            sector[510] = 0x55;
+            sector[511] = 0xAA;
+        }
+        image[..512].copy_from_slice(&sector);
+
+        Ok(image)
+    }
+}
+
+/// A GPT image, with its protective MBR, primary and backup.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct GptImage {
+    pub disk_len: u64,
+    pub sector_size: u64,
+    pub disk_guid: [u8; 16],
+
+    /// The number of slots in the entry array.
+    pub entries: u32,
+
+    /// The size of each entry, in bytes: 128 multiplied by a power of two.
+    pub entry_size: u32,
+
+    /// Written to the slots in order.
+    pub partitions: Vec<GptEntry>,
+
+    /// Damage to apply after the image is written.
+    pub damage: Vec<Damage>,
+}
+
+/// A raw GPT partition entry.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct GptEntry {
+    pub type_uuid: [u8; 16],
+    pub partition_uuid: [u8; 16],
+    pub first_lba: u64,
+    /// Inclusive.
+    pub last_lba: u64,
+    pub attributes: u64,
+    /// At most 36 UTF-16 code units, with no nuls.
+    pub name: String,
+}
+
+/// Ways to damage a GPT image.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Damage {
+    /// Corrupt the primary header's own checksum.
+    PrimaryHeaderCrc,
+    /// Corrupt the backup header's own checksum.
+    BackupHeaderCrc,
+    /// Store the wrong checksum for the entry array in the primary header.
+    PrimaryTableCrc,
+    /// Store the wrong checksum for the entry array in the backup header.
+    BackupTableCrc,
+    /// Zero the sector holding the primary header.
+    MissingPrimary,
+    /// Zero the sector holding the backup header.
+    MissingBackup,
+    /// Zero the first sector, so there's no protective MBR.
+    MissingProtectiveMbr,
+}
+
+impl GptImage {
+    /// An empty table, with 512-byte sectors, 128 entries, and a fixed disc GUID.
+    pub fn new(disk_len: u64) -> GptImage {
+        GptImage {
+            disk_len,
+            sector_size: 512,
+            disk_guid: *b"bootsector build",
+            entries: 128,
+            entry_size: 128,
+            partitions: Vec::new(),
+            damage: Vec::new(),
+        }
+    }
+
+    /// Add a Linux data partition, with its position in bytes, and a GUID derived from
+    /// its position in the list.
+    pub fn add(&mut self, first_byte: u64, len: u64, name: &str) -> &mut GptEntry {
+        let mut partition_uuid = *b"bootsector part ";
+        partition_uuid[15] = u8::try_from(self.partitions.len() % 256).expect("masked");
+
+        let first_lba = first_byte / self.sector_size;
+        let entry = GptEntry {
+            type_uuid: guids::LINUX_FILESYSTEM,
+            partition_uuid,
+            first_lba,
+            last_lba: (first_lba + len / self.sector_size).saturating_sub(1),
+            attributes: 0,
+            name: String::from(name),
+        };
+        self.partitions.push(entry);
+        self.partitions.last_mut().expect("just pushed")
+    }
+
+    pub fn build(&self) -> Result<Vec<u8>, Error> {
+        let mut options = CreateOptions::new(self.disk_guid);
+        options.entries = self.entries;
+        options.entry_size = self.entry_size;
+        let mut header = gpt::new_header(self.disk_len, self.sector_size, &options)?;
+
+        if self.partitions.len() > usize::try_from(self.entries).unwrap_or(usize::MAX) {
+            return Err(Error::InvalidStatic {
+                message: "more partitions than entries",
+            });
+        }
+
+        let mut table = vec![0u8; header.table_len().ok_or(Error::Overflow)?];
+        let entry_size = usize::try_from(self.entry_size).map_err(|_| Error::BiggerThanMemory)?;
+        for (part, entry) in self
+            .partitions
+            .iter()
+            .zip(table.chunks_exact_mut(entry_size))
+        {
+            encode_entry(part, entry)?;
+        }
+        header.entries_crc = gpt::checksum(&table);
+
+        let mut image = zeroed(self.disk_len)?;
+        let ss = self.sector_size;
+        let backup = header.alternate(ss).ok_or(Error::Overflow)?;
+        {
+            let mut writer = &mut image[..];
+            writer.write_all_at(0, &mbr::protective_mbr(self.disk_len / ss))?;
+            gpt::write_both(&mut writer, &header, &table, ss)?;
+
+            for damage in &self.damage {
+                match damage {
+                    Damage::PrimaryHeaderCrc => flip(&mut writer, ss + 0x10)?,
+                    Damage::BackupHeaderCrc => flip(&mut writer, backup.my_lba * ss + 0x10)?,
+                    Damage::PrimaryTableCrc => {
+                        let mut wrong = header.clone();
+                        wrong.entries_crc ^= 1;
+                        gpt::write_header(&mut writer, &wrong, ss)?;
+                    }
+                    Damage::BackupTableCrc => {
+                        let mut wrong = backup.clone();
+                        wrong.entries_crc ^= 1;
+                        gpt::write_header(&mut writer, &wrong, ss)?;
+                    }
+                    Damage::MissingPrimary => zero_sector(&mut writer, 1, ss)?,
+                    Damage::MissingBackup => zero_sector(&mut writer, backup.my_lba, ss)?,
+                    Damage::MissingProtectiveMbr => zero_sector(&mut writer, 0, ss)?,
+                }
+            }
+        }
+
+        Ok(image)
+    }
+}
+
+fn encode_entry(part: &GptEntry, entry: &mut [u8]) -> Result<(), Error> {
+    entry[0x00..0x10].copy_from_slice(&part.type_uuid);
+    entry[0x10..0x20].copy_from_slice(&part.partition_uuid);
+    entry[0x20..0x28].copy_from_slice(&part.first_lba.to_le_bytes());
+    entry[0x28..0x30].copy_from_slice(&part.last_lba.to_le_bytes());
+    entry[0x30..0x38].copy_from_slice(&part.attributes.to_le_bytes());
+    entry[0x38..0x80].copy_from_slice(&gpt::encode_name(&part.name)?);
+    Ok(())
+}
+
+fn zeroed(len: u64) -> Result<Vec<u8>, Error> {
+    Ok(vec![
+        0u8;
+        usize::try_from(len)
+            .map_err(|_| Error::BiggerThanMemory)?
+    ])
+}
+
+fn flip(image: &mut &mut [u8], pos: u64) -> Result<(), Error> {
+    let pos = usize::try_from(pos).map_err(|_| Error::BiggerThanMemory)?;
+    let byte = image.get_mut(pos).ok_or(Error::UnexpectedEof)?;
+    *byte ^= 0xff;
+    Ok(())
+}
+
+fn zero_sector(image: &mut &mut [u8], lba: u64, sector_size: u64) -> Result<(), Error> {
+    let len = usize::try_from(sector_size).map_err(|_| Error::BiggerThanMemory)?;
+    image.write_all_at(lba * sector_size, &vec![0u8; len])
+}
@@ -0,0 +1,227 @@
+//! Work with the data inside partitions, rather than the tables describing them.
+//!
+//! ```rust
+//! # fn go(disc: &[u8]) -> Result<(), bootsector::Error> {
+//! for part in bootsector::list_partitions(disc, &Default::default())? {
+//!     let crc = bootsector::content::crc32_partition(disc, &part)?;
+//!     println!("{}: {:08x}", part.id, crc);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::vec;
+use core::convert::TryFrom;
+
+use crc::{Crc, CRC_32_ISO_HDLC};
+use snafu::ResultExt;
+
+use crate::errors::ReadSnafu;
+use crate::{io, Error, Partition};
+
+/// How much is read at once, by default.
+const CHUNK: u64 = 1024 * 1024;
+
+static CRC32: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+
+/// Settings for [`copy_partition`].
+pub struct CopyOptions {
+    /// How much to read, and write, at once.
+    pub buffer_size: u64,
+
+    /// Don't write chunks which are entirely zero, e.g. when the destination is a new,
+    /// sparse, file. Only safe if the destination is already zero.
+    pub skip_zeros: bool,
+}
+
+impl Default for CopyOptions {
+    fn default() -> CopyOptions {
+        CopyOptions {
+            buffer_size: CHUNK,
+            skip_zeros: false,
+        }
+    }
+}
+
+/// What [`copy_partition`] did.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Copied {
+    /// Bytes written to the destination.
+    pub written: u64,
+
+    /// Bytes which were zero, and not written, as requested by [`CopyOptions::skip_zeros`].
+    pub skipped: u64,
+}
+
+/// Feed the contents of a partition, in order, to `update`, e.g. a hasher's `update` method.
+///
+/// After each chunk, `progress` is told how many bytes have been read so far, and the
+/// length of the partition.
+pub fn hash_partition<R, U, P>(
+    reader: R,
+    part: &Partition,
+    mut update: U,
+    mut progress: P,
+) -> Result<(), Error>
+where
+    R: io::ReadAt,
+    U: FnMut(&[u8]),
+    P: FnMut(u64, u64),
+{
+    for_each_chunk(&reader, part.first_byte, part.len, CHUNK, |done, chunk| {
+        update(chunk);
+        progress(done + chunk.len() as u64, part.len);
+        Ok(())
+    })
+}
+
+/// The CRC-32 (as used by zip and gzip) of a partition's contents.
+pub fn crc32_partition<R>(reader: R, part: &Partition) -> Result<u32, Error>
+where
+    R: io::ReadAt,
+{
+    let mut digest = CRC32.digest();
+    hash_partition(reader, part, |chunk| digest.update(chunk), |_, _| ())?;
+    Ok(digest.finalize())
+}
+
+/// The SHA-256 of a partition's contents.
+#[cfg(feature = "sha2")]
+pub fn sha256_partition<R>(reader: R, part: &Partition) -> Result<[u8; 32], Error>
+where
+    R: io::ReadAt,
+{
+    use sha2::Digest;
+
+    let mut hasher = sha2::Sha256::new();
+    hash_partition(reader, part, |chunk| hasher.update(chunk), |_, _| ())?;
+    Ok(hasher.finalize().into())
+}
+
+/// Copy the contents of a partition to `dst_offset` in another disc, e.g. the
+/// `first_byte` of a partition there, which must be at least as long.
+///
+/// After each chunk, `progress` is told how many bytes have been copied so far, and the
+/// length of the partition. The destination is flushed at the end.
+pub fn copy_partition<R, W, P>(
+    src: R,
+    src_part: &Partition,
+    mut dst: W,
+    dst_offset: u64,
+    options: &CopyOptions,
+    mut progress: P,
+) -> Result<Copied, Error>
+where
+    R: io::ReadAt,
+    W: io::WriteAt,
+    P: FnMut(u64, u64),
+{
+    dst_offset
+        .checked_add(src_part.len)
+        .ok_or(Error::Overflow)?;
+
+    let mut copied = Copied {
+        written: 0,
+        skipped: 0,
+    };
+
+    for_each_chunk(
+        &src,
+        src_part.first_byte,
+        src_part.len,
+        options.buffer_size,
+        |done, chunk| {
+            let len = chunk.len() as u64;
+            if options.skip_zeros && chunk.iter().all(|&b| 0 == b) {
+                copied.skipped += len;
+            } else {
+                dst.write_all_at(dst_offset + done, chunk)?;
+                copied.written += len;
+            }
+            progress(done + len, src_part.len);
+            Ok(())
+        },
+    )?;
+
+    dst.flush()?;
+    Ok(copied)
+}
+
+/// Overwrite a partition's contents with a repeating `pattern`, e.g. `&[0]` for zeros.
+///
+/// The pattern starts again at the start of the partition. Writes are whole, aligned,
+/// 1MiB chunks of the disc, except where the partition doesn't cover a whole chunk;
+/// nothing outside the partition is touched. The destination is flushed at the end.
+pub fn wipe_partition<W>(mut dst: W, part: &Partition, pattern: &[u8]) -> Result<(), Error>
+where
+    W: io::WriteAt,
+{
+    if pattern.is_empty() {
+        return Err(Error::InvalidStatic {
+            message: "wipe pattern must not be empty",
+        });
+    }
+
+    let end = part
+        .first_byte
+        .checked_add(part.len)
+        .ok_or(Error::Overflow)?;
+
+    let mut buf = vec![0u8; usize::try_from(CHUNK.min(part.len)).expect("small")];
+    let mut pos = part.first_byte;
+    while pos < end {
+        let next_boundary = (pos / CHUNK + 1).saturating_mul(CHUNK);
+        let len = usize::try_from(next_boundary.min(end) - pos).expect("at most a chunk");
+        let chunk = &mut buf[..len];
+
+        let phase = usize::try_from((pos - part.first_byte) % pattern.len() as u64)
+            .expect("less than the pattern");
+        for (b, p) in chunk.iter_mut().zip(pattern.iter().cycle().skip(phase)) {
+            *b = *p;
+        }
+
+        dst.write_all_at(pos, chunk)?;
+        pos += len as u64;
+    }
+
+    dst.flush()
+}
+
+/// Read `len` bytes from `start`, `chunk_size` at a time, passing each to `f` with its
+/// offset from `start`.
+pub(crate) fn for_each_chunk<R, F>(
+    reader: &R,
+    start: u64,
+    len: u64,
+    chunk_size: u64,
+    mut f: F,
+) -> Result<(), Error>
+where
+    R: io::ReadAt + ?Sized,
+    F: FnMut(u64, &[u8]) -> Result<(), Error>,
+{
+    start.checked_add(len).ok_or(Error::Overflow)?;
+    if 0 == chunk_size {
+        return Err(Error::InvalidStatic {
+            message: "chunk size must not be zero",
+        });
+    }
+
+    let buf_len = usize::try_from(chunk_size.min(len)).map_err(|_| Error::BiggerThanMemory)?;
+    let mut buf = vec![0u8; buf_len];
+    let mut done = 0u64;
+    while done < len {
+        let this = usize::try_from(chunk_size.min(len - done)).expect("at most buf_len");
+        let chunk = &mut buf[..this];
+        reader
+            .read_exact_at(start + done, chunk)
+            .context(ReadSnafu {
+                what: "the partition's contents",
+                pos: start + done,
+            })?;
+        f(done, chunk)?;
+        done += chunk.len() as u64;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,230 @@
+//! Convert partition tables between MBR and GPT layouts, as `gdisk` does when it
+//! finds an MBR disc.
+//!
+//! Offsets and sizes are preserved exactly; only the type information is translated.
+//! The result is an in-memory [`PartitionTable`], which can then be written out.
+
+use alloc::{format, string::String, vec, vec::Vec};
+
+use crate::attributes::LEGACY_BIOS_BOOTABLE;
+use crate::gpt::guids;
+use crate::{gpt, guid, mbr, Attributes, Error, Partition, PartitionTable, Stats, TableType};
+
+/// MBR type codes, and the GPT type which holds the same thing.
+///
+/// Where several codes map to the same GUID, the first is used for the reverse direction.
+const TYPES: &[(u8, [u8; 16])] = &[
+    (0x07, guids::MS_BASIC_DATA), // Microsoft basic data (NTFS, exFAT)
+    (0x01, guids::MS_BASIC_DATA), // FAT12
+    (0x04, guids::MS_BASIC_DATA), // FAT16 <32M
+    (0x06, guids::MS_BASIC_DATA), // FAT16
+    (0x0b, guids::MS_BASIC_DATA), // FAT32 (CHS)
+    (0x0c, guids::MS_BASIC_DATA), // FAT32 (LBA)
+    (0x0e, guids::MS_BASIC_DATA), // FAT16 (LBA)
+    (0x27, guids::MS_RECOVERY),
+    (0x42, guids::MS_LDM_DATA),
+    (0x82, guids::LINUX_SWAP),
+    (0x83, guids::LINUX_FILESYSTEM),
+    (0x8e, guids::LINUX_LVM),
+    (0xa5, guids::FREEBSD_DATA),
+    (0xa8, guids::APPLE_UFS),
+    (0xab, guids::APPLE_BOOT),
+    (0xaf, guids::APPLE_HFS),
+    (0xef, guids::ESP),
+    (0xfd, guids::LINUX_RAID),
+];
+
+/// Extended partitions are containers, and have no GPT equivalent.
+const EXTENDED: &[u8] = &[0x05, 0x0f, 0x85];
+
+/// The GPT type GUID equivalent to an MBR type code, if we know of one.
+pub fn type_code_to_guid(type_code: u8) -> Option<[u8; 16]> {
+    TYPES
+        .iter()
+        .find(|(code, _)| *code == type_code)
+        .map(|(_, type_uuid)| *type_uuid)
+}
+
+/// The MBR type code equivalent to a GPT type GUID, if we know of one.
+pub fn guid_to_type_code(type_uuid: &[u8; 16]) -> Option<u8> {
+    TYPES
+        .iter()
+        .find(|(_, known)| known == type_uuid)
+        .map(|(code, _)| *code)
+}
+
+/// Produce a GPT layout equivalent to an MBR table.
+///
+/// `new_guid` is called to generate the disc GUID, and a GUID for each partition;
+/// it should return random (version 4) GUIDs. Extended partitions are dropped, and
+/// the bootable flag is carried over as the "legacy BIOS bootable" attribute.
+///
+/// The table must leave room at the start of the disc for the GPT structures; room
+/// for the backup at the end of the disc is not checked here.
+pub fn mbr_to_gpt<F>(table: &PartitionTable, mut new_guid: F) -> Result<PartitionTable, Error>
+where
+    F: FnMut() -> [u8; 16],
+{
+    if TableType::MBR != table.table_type {
+        return Err(Error::InvalidStatic {
+            message: "can only convert an MBR table to GPT",
+        });
+    }
+
+    // protective mbr, header, and a 16KiB entry array
+    let first_usable = table
+        .sector_size
+        .checked_mul(2)
+        .and_then(|header| header.checked_add(128 * 128))
+        .ok_or(Error::Overflow)?;
+
+    let mut partitions = Vec::with_capacity(table.partitions.len());
+    for part in &table.partitions {
+        let (bootable, type_code) = match part.attributes {
+            Attributes::MBR {
+                bootable,
+                type_code,
+            } => (bootable, type_code),
+            Attributes::GPT { .. } => {
+                return Err(Error::InvalidStatic {
+                    message: "MBR table contains a GPT partition",
+                })
+            }
+        };
+
+        if EXTENDED.contains(&type_code) {
+            continue;
+        }
+
+        let type_uuid = type_code_to_guid(type_code).ok_or_else(|| Error::InvalidData {
+            message: format!(
+                "partition {} has MBR type {:02x}, which has no GPT equivalent",
+                part.id, type_code
+            ),
+        })?;
+
+        if part.first_byte < first_usable {
+            return Err(Error::InvalidData {
+                message: format!(
+                    "partition {} starts before there is room for the GPT",
+                    part.id
+                ),
+            });
+        }
+
+        let attributes = if bootable { LEGACY_BIOS_BOOTABLE } else { 0 };
+
+        partitions.push(Partition {
+            id: part.id,
+            first_byte: part.first_byte,
+            len: part.len,
+            attributes: Attributes::GPT {
+                type_uuid,
+                partition_uuid: new_guid(),
+                attributes: attributes.to_le_bytes(),
+                name: String::new(),
+                raw_name: vec![0; gpt::NAME_LEN],
+                extra: Vec::new(),
+            },
+            label: part.label.clone(),
+        });
+    }
+
+    Ok(PartitionTable {
+        table_type: TableType::GPT,
+        sector_size: table.sector_size,
+        disk_signature: table.disk_signature,
+        disk_guid: Some(new_guid()),
+        gpt_header: None,
+        mbr_partitions: Vec::new(),
+        mbr_layout: mbr::Layout::Standard,
+        boot_code: table.boot_code,
+        boot_code_bytes: table.boot_code_bytes.clone(),
+        warnings: Vec::new(),
+        partitions,
+        nested: Vec::new(),
+        stats: Stats::default(),
+    })
+}
+
+/// Produce an MBR layout equivalent to a GPT table, if one exists.
+///
+/// This requires at most four partitions, each with a type we can translate, and
+/// addressable with 32-bit, 512-byte sector LBAs. Names, GUIDs and most attributes are
+/// lost; the "legacy BIOS bootable" attribute becomes the bootable flag.
+pub fn gpt_to_mbr(table: &PartitionTable) -> Result<PartitionTable, Error> {
+    if TableType::GPT != table.table_type {
+        return Err(Error::InvalidStatic {
+            message: "can only convert a GPT table to MBR",
+        });
+    }
+
+    if table.partitions.len() > 4 {
+        return Err(Error::InvalidStatic {
+            message: "MBR can only hold four primary partitions",
+        });
+    }
+
+    let limit = u64::from(u32::MAX) * 512;
+
+    let mut partitions = Vec::with_capacity(table.partitions.len());
+    for (id, part) in table.partitions.iter().enumerate() {
+        let (type_uuid, attributes) = match &part.attributes {
+            Attributes::GPT {
+                type_uuid,
+                attributes,
+                ..
+            } => (type_uuid, u64::from_le_bytes(*attributes)),
+            Attributes::MBR { .. } => {
+                return Err(Error::InvalidStatic {
+                    message: "GPT table contains an MBR partition",
+                })
+            }
+        };
+
+        let type_code = guid_to_type_code(type_uuid).ok_or_else(|| Error::InvalidData {
+            message: format!(
+                "partition {} has GPT type {}, which has no MBR equivalent",
+                part.id,
+                guid::to_string(type_uuid)
+            ),
+        })?;
+
+        if 0 != part.first_byte % 512
+            || 0 != part.len % 512
+            || part.first_byte > limit
+            || part.len > limit
+        {
+            return Err(Error::InvalidData {
+                message: format!("partition {} can't be addressed by an MBR", part.id),
+            });
+        }
+
+        partitions.push(Partition {
+            id,
+            first_byte: part.first_byte,
+            len: part.len,
+            attributes: Attributes::MBR {
+                bootable: 0 != attributes & LEGACY_BIOS_BOOTABLE,
+                type_code,
+            },
+            label: part.label.clone(),
+        });
+    }
+
+    Ok(PartitionTable {
+        table_type: TableType::MBR,
+        sector_size: 512,
+        disk_signature: table.disk_signature,
+        disk_guid: None,
+        gpt_header: None,
+        mbr_partitions: partitions.clone(),
+        mbr_layout: mbr::Layout::Standard,
+        boot_code: table.boot_code,
+        boot_code_bytes: table.boot_code_bytes.clone(),
+        warnings: Vec::new(),
+        partitions,
+        nested: Vec::new(),
+        stats: Stats::default(),
+    })
+}
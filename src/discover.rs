@@ -0,0 +1,156 @@
+//! Find the discs attached to this machine.
+//!
+//! On Linux, this reads `/sys/block`. On Windows, `\\.\PhysicalDriveN` is probed for
+//! each N. On macOS, `/dev` is searched for whole discs, and their raw devices returned.
+//! Elsewhere, nothing is found.
+//!
+//! ```rust,no_run
+//! # fn go() -> Result<(), bootsector::Error> {
+//! for device in bootsector::discover::devices()? {
+//!     let disc = device.open()?;
+//!     let table = bootsector::read_partition_table(&disc, &device.options());
+//!     println!("{}: {:?}", device.path.display(), table.map(|t| t.partitions.len()));
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::TryFrom;
+use std::fs::File;
+use std::path::PathBuf;
+
+use crate::{Error, Options, SectorSize};
+
+/// A disc, which can be opened for reading.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Device {
+    /// The path to open, e.g. `/dev/sda` or `\\.\PhysicalDrive0`.
+    pub path: PathBuf,
+
+    /// The length of the disc, in bytes, if the OS told us without opening it.
+    pub len: Option<u64>,
+
+    /// The logical sector size, if the OS told us without opening the disc.
+    pub sector_size: Option<u32>,
+
+    /// Whether the disc is removable, if the OS told us.
+    pub removable: Option<bool>,
+}
+
+impl Device {
+    /// Open the disc for reading. This usually requires elevated privileges.
+    pub fn open(&self) -> Result<File, Error> {
+        File::open(&self.path).map_err(|source| Error::Io { source, pos: 0 })
+    }
+
+    /// [`Options`] with whatever we know about the disc filled in.
+    pub fn options(&self) -> Options {
+        Options {
+            sector_size: match self.sector_size.and_then(|size| u16::try_from(size).ok()) {
+                Some(size) => SectorSize::Known(size),
+                None => SectorSize::GuessOrAssume,
+            },
+            disk_len: self.len,
+            ..Options::default()
+        }
+    }
+}
+
+/// List the discs attached to the machine, ordered by path.
+pub fn devices() -> Result<Vec<Device>, Error> {
+    let mut found = platform_devices()?;
+    found.sort_by(|left, right| left.path.cmp(&right.path));
+    Ok(found)
+}
+
+#[cfg(target_os = "linux")]
+fn platform_devices() -> Result<Vec<Device>, Error> {
+    use std::fs;
+    use std::path::Path;
+
+    fn read_number(path: &Path) -> Option<u64> {
+        fs::read_to_string(path).ok()?.trim().parse().ok()
+    }
+
+    let mut ret = Vec::new();
+    let entries = fs::read_dir("/sys/block").map_err(|source| Error::Io { source, pos: 0 })?;
+    for entry in entries {
+        let entry = entry.map_err(|source| Error::Io { source, pos: 0 })?;
+        let sys = entry.path();
+
+        // always in 512 byte units, regardless of the device
+        let len = match read_number(&sys.join("size")) {
+            Some(0) | None => continue,
+            Some(sectors) => sectors.checked_mul(512),
+        };
+
+        ret.push(Device {
+            path: Path::new("/dev").join(entry.file_name()),
+            len,
+            sector_size: read_number(&sys.join("queue/logical_block_size"))
+                .and_then(|size| u32::try_from(size).ok()),
+            removable: read_number(&sys.join("removable")).map(|flag| 0 != flag),
+        });
+    }
+
+    Ok(ret)
+}
+
+#[cfg(windows)]
+fn platform_devices() -> Result<Vec<Device>, Error> {
+    // drive numbers can have gaps, e.g. after a removal; nobody has this many
+    const PROBE: u32 = 64;
+
+    let mut ret = Vec::new();
+    for number in 0..PROBE {
+        let path = PathBuf::from(format!(r"\\.\PhysicalDrive{}", number));
+        if File::open(&path).is_ok() {
+            ret.push(Device {
+                path,
+                len: None,
+                sector_size: None,
+                removable: None,
+            });
+        }
+    }
+
+    Ok(ret)
+}
+
+#[cfg(target_os = "macos")]
+fn platform_devices() -> Result<Vec<Device>, Error> {
+    use std::fs;
+
+    let mut ret = Vec::new();
+    let entries = fs::read_dir("/dev").map_err(|source| Error::Io { source, pos: 0 })?;
+    for entry in entries {
+        let entry = entry.map_err(|source| Error::Io { source, pos: 0 })?;
+        let name = entry.file_name();
+        let name = match name.to_str() {
+            Some(name) => name,
+            None => continue,
+        };
+
+        // whole discs only, i.e. `disk2` and not `disk2s1`
+        let number = match name.strip_prefix("disk") {
+            Some(number) if !number.is_empty() && number.bytes().all(|c| c.is_ascii_digit()) => {
+                number
+            }
+            _ => continue,
+        };
+
+        ret.push(Device {
+            path: PathBuf::from(format!("/dev/rdisk{}", number)),
+            len: None,
+            sector_size: None,
+            removable: None,
+        });
+    }
+
+    Ok(ret)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn platform_devices() -> Result<Vec<Device>, Error> {
+    Ok(Vec::new())
+}
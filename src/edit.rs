@@ -0,0 +1,395 @@
+//! Modify the entries of an existing GPT.
+//!
+//! Changes are made to an in-memory copy of the entry array. Nothing is written until
+//! [`GptEditor::commit`], which recomputes the checksums, and writes both the primary
+//! and the backup structures.
+//!
+//! ```rust
+//! # fn go(disc: &mut [u8]) -> Result<(), bootsector::Error> {
+//! use bootsector::edit::GptEditor;
+//!
+//! let mut editor = GptEditor::open(&*disc, 512)?;
+//! let id = editor.add(1024 * 1024, 1024 * 1024, [0x42; 16], [0x69; 16])?;
+//! editor.set_name(id, "data")?;
+//! editor.commit(disc)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::{format, vec::Vec};
+use core::convert::TryFrom;
+
+use crate::gpt::{self, Header};
+use crate::{io, le, Error, Order, Partition, Reserved};
+
+/// An in-memory copy of a GPT, which can be modified and then written back.
+pub struct GptEditor {
+    pub(crate) sector_size: u64,
+    pub(crate) header: Header,
+    pub(crate) table: Vec<u8>,
+}
+
+impl GptEditor {
+    /// Read the primary GPT from a disc, which must be valid.
+    pub fn open<R>(reader: R, sector_size: u64) -> Result<GptEditor, Error>
+    where
+        R: io::ReadAt,
+    {
+        GptEditor::load(&reader, sector_size)
+    }
+
+    pub(crate) fn load<R>(reader: &R, sector_size: u64) -> Result<GptEditor, Error>
+    where
+        R: io::ReadAt + ?Sized,
+    {
+        let (header, table) =
+            gpt::read_raw(reader, sector_size, &Reserved::MustBeZero, &mut Vec::new())?;
+
+        // ensure the existing entries are something we could write back out
+        gpt::parse_entries(&table, &header, sector_size)?;
+
+        Ok(GptEditor {
+            sector_size,
+            header,
+            table,
+        })
+    }
+
+    /// The partitions, as they would be after a commit.
+    pub fn partitions(&self) -> Result<Vec<Partition>, Error> {
+        gpt::parse_entries(&self.table, &self.header, self.sector_size)
+    }
+
+    /// Add a partition in the first free slot, returning its id.
+    ///
+    /// The partition must be whole sectors, lie within the usable range, and not
+    /// overlap any existing partition.
+    pub fn add(
+        &mut self,
+        first_byte: u64,
+        len: u64,
+        type_uuid: [u8; 16],
+        partition_uuid: [u8; 16],
+    ) -> Result<usize, Error> {
+        if [0u8; 16] == type_uuid {
+            return Err(Error::InvalidStatic {
+                message: "the empty type marks an unused entry",
+            });
+        }
+
+        let (first_lba, last_lba) = self.lbas(first_byte, len)?;
+        self.check_range(None, first_lba, last_lba)?;
+
+        let id = (0..self.slots())
+            .find(|id| self.entry(*id)[..0x10].iter().all(|x| 0 == *x))
+            .ok_or(Error::InvalidStatic {
+                message: "no free entries in the table",
+            })?;
+
+        let entry = self.entry_mut(id);
+        for byte in entry.iter_mut() {
+            *byte = 0;
+        }
+        entry[0x00..0x10].copy_from_slice(&type_uuid);
+        entry[0x10..0x20].copy_from_slice(&partition_uuid);
+        entry[0x20..0x28].copy_from_slice(&first_lba.to_le_bytes());
+        entry[0x28..0x30].copy_from_slice(&last_lba.to_le_bytes());
+
+        Ok(id)
+    }
+
+    /// Remove a partition, clearing its entry.
+    pub fn delete(&mut self, id: usize) -> Result<(), Error> {
+        for byte in self.used_entry_mut(id)?.iter_mut() {
+            *byte = 0;
+        }
+        Ok(())
+    }
+
+    /// Set a partition's name, which must fit in 36 UTF-16 code units.
+    pub fn set_name(&mut self, id: usize, name: &str) -> Result<(), Error> {
+        let field = gpt::encode_name(name)?;
+        self.used_entry_mut(id)?[0x38..0x80].copy_from_slice(&field);
+        Ok(())
+    }
+
+    /// Replace a partition's name field with exactly these 72 bytes, e.g. the
+    /// [`Attributes::GPT::raw_name`](crate::Attributes::GPT::raw_name) of an entry being
+    /// copied, even if they aren't valid UTF-16.
+    pub fn set_raw_name(&mut self, id: usize, raw_name: &[u8]) -> Result<(), Error> {
+        if gpt::NAME_LEN != raw_name.len() {
+            return Err(Error::InvalidStatic {
+                message: "raw names are exactly 72 bytes",
+            });
+        }
+
+        self.used_entry_mut(id)?[0x38..0x80].copy_from_slice(raw_name);
+        Ok(())
+    }
+
+    /// Change a partition's type GUID.
+    pub fn set_type(&mut self, id: usize, type_uuid: [u8; 16]) -> Result<(), Error> {
+        if [0u8; 16] == type_uuid {
+            return Err(Error::InvalidStatic {
+                message: "the empty type marks an unused entry; use delete",
+            });
+        }
+
+        self.used_entry_mut(id)?[0x00..0x10].copy_from_slice(&type_uuid);
+        Ok(())
+    }
+
+    /// Replace a partition's attribute bits.
+    pub fn set_attributes(&mut self, id: usize, attributes: u64) -> Result<(), Error> {
+        self.used_entry_mut(id)?[0x30..0x38].copy_from_slice(&attributes.to_le_bytes());
+        Ok(())
+    }
+
+    /// Move the partitions into the lowest slots, leaving no unused entries between them,
+    /// as `sgdisk --sort` does with [`Order::Disk`].
+    ///
+    /// With [`Order::Table`], the partitions keep their relative order; with
+    /// [`Order::Disk`], they are sorted by where they start. Returns the old id of each
+    /// partition, indexed by its new id.
+    pub fn compact(&mut self, order: Order) -> Vec<usize> {
+        let mut used: Vec<usize> = (0..self.slots())
+            .filter(|id| !self.entry(*id)[..0x10].iter().all(|x| 0 == *x))
+            .collect();
+
+        if let Order::Disk = order {
+            used.sort_by_key(|id| le::read_u64(&self.entry(*id)[0x20..0x28]));
+        }
+
+        let size = self.entry_size();
+        let mut table = alloc::vec![0u8; self.table.len()];
+        for (new, old) in used.iter().enumerate() {
+            table[new * size..(new + 1) * size].copy_from_slice(self.entry(*old));
+        }
+        self.table = table;
+
+        used
+    }
+
+    /// The primary header, as it will be written.
+    ///
+    /// The checksums are only updated on commit.
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// The GUID identifying the disc.
+    pub fn disk_guid(&self) -> [u8; 16] {
+        self.header.disk_guid
+    }
+
+    /// Replace the GUID identifying the disc.
+    pub fn set_disk_guid(&mut self, disk_guid: [u8; 16]) {
+        self.header.disk_guid = disk_guid;
+    }
+
+    /// Replace a partition's unique GUID.
+    pub fn set_partition_guid(&mut self, id: usize, partition_uuid: [u8; 16]) -> Result<(), Error> {
+        self.used_entry_mut(id)?[0x10..0x20].copy_from_slice(&partition_uuid);
+        Ok(())
+    }
+
+    /// Give the disc, and every partition, a new GUID, as is necessary after cloning
+    /// an image, so the copies can be told apart.
+    ///
+    /// `new_guid` should return random (version 4) GUIDs. The MBR's disc signature is not
+    /// part of the GPT; see [`crate::mbr::set_disk_signature`].
+    pub fn regenerate_guids<F>(&mut self, mut new_guid: F)
+    where
+        F: FnMut() -> [u8; 16],
+    {
+        self.header.disk_guid = new_guid();
+        for id in 0..self.slots() {
+            let entry = self.entry_mut(id);
+            if !entry[..0x10].iter().all(|x| 0 == *x) {
+                entry[0x10..0x20].copy_from_slice(&new_guid());
+            }
+        }
+    }
+
+    /// Recompute the checksums, and write the primary and backup GPTs.
+    ///
+    /// The MBR is not touched.
+    pub fn commit<W>(&mut self, mut writer: W) -> Result<(), Error>
+    where
+        W: io::WriteAt,
+    {
+        self.commit_to(&mut writer)
+    }
+
+    pub(crate) fn commit_to<W>(&mut self, writer: &mut W) -> Result<(), Error>
+    where
+        W: io::WriteAt + ?Sized,
+    {
+        self.header.entries_crc = gpt::checksum(&self.table);
+        gpt::write_both(writer, &self.header, &self.table, self.sector_size)?;
+        writer.flush()
+    }
+
+    /// Move the backup GPT to the end of a disc of a new size, e.g. after a disc image
+    /// has been grown. The usable area is adjusted to end just before the backup table.
+    ///
+    /// Existing partitions must still fit.
+    pub fn set_disk_len(&mut self, disk_len: u64) -> Result<(), Error> {
+        let table_sectors = self
+            .header
+            .table_sectors(self.sector_size)
+            .ok_or(Error::Overflow)?;
+
+        let alternate_lba =
+            (disk_len / self.sector_size)
+                .checked_sub(1)
+                .ok_or(Error::InvalidStatic {
+                    message: "disc is empty",
+                })?;
+
+        let last_usable_lba = alternate_lba
+            .checked_sub(1 + table_sectors)
+            .filter(|last| *last >= self.header.first_usable_lba)
+            .ok_or(Error::InvalidStatic {
+                message: "disc is too small to hold a GPT",
+            })?;
+
+        for id in 0..self.slots() {
+            let entry = self.entry(id);
+            if !entry[..0x10].iter().all(|x| 0 == *x)
+                && le::read_u64(&entry[0x28..0x30]) > last_usable_lba
+            {
+                return Err(Error::InvalidData {
+                    message: format!("partition {} would not fit on the disc", id),
+                });
+            }
+        }
+
+        self.header.alternate_lba = alternate_lba;
+        self.header.last_usable_lba = last_usable_lba;
+        Ok(())
+    }
+
+    /// The largest length partition `id` could be resized to, without moving its start.
+    pub fn max_len(&self, id: usize) -> Result<u64, Error> {
+        let first_lba = self.used_entry(id)?;
+
+        let mut limit = self.header.last_usable_lba;
+        for other in 0..self.slots() {
+            let entry = self.entry(other);
+            if other == id || entry[..0x10].iter().all(|x| 0 == *x) {
+                continue;
+            }
+
+            let other_first = le::read_u64(&entry[0x20..0x28]);
+            if other_first > first_lba && other_first <= limit {
+                limit = other_first - 1;
+            }
+        }
+
+        (limit - first_lba + 1)
+            .checked_mul(self.sector_size)
+            .ok_or(Error::Overflow)
+    }
+
+    /// Convert a byte range to an inclusive range of LBAs.
+    pub(crate) fn lbas(&self, first_byte: u64, len: u64) -> Result<(u64, u64), Error> {
+        if 0 == len {
+            return Err(Error::InvalidStatic {
+                message: "partitions can't be empty",
+            });
+        }
+
+        if 0 != first_byte % self.sector_size || 0 != len % self.sector_size {
+            return Err(Error::InvalidStatic {
+                message: "partitions must be a whole number of sectors",
+            });
+        }
+
+        let first_lba = first_byte / self.sector_size;
+        Ok((first_lba, first_lba + (len / self.sector_size - 1)))
+    }
+
+    /// Ensure a range is usable, and not used by any partition other than `except`.
+    pub(crate) fn check_range(
+        &self,
+        except: Option<usize>,
+        first_lba: u64,
+        last_lba: u64,
+    ) -> Result<(), Error> {
+        if first_lba < self.header.first_usable_lba || last_lba > self.header.last_usable_lba {
+            return Err(Error::InvalidStatic {
+                message: "partition is outside of the usable area",
+            });
+        }
+
+        for id in 0..self.slots() {
+            let entry = self.entry(id);
+            if Some(id) == except || entry[..0x10].iter().all(|x| 0 == *x) {
+                continue;
+            }
+
+            let other_first = le::read_u64(&entry[0x20..0x28]);
+            let other_last = le::read_u64(&entry[0x28..0x30]);
+            if first_lba <= other_last && other_first <= last_lba {
+                return Err(Error::InvalidData {
+                    message: format!("partition would overlap partition {}", id),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn slots(&self) -> usize {
+        // the table length was validated against these when it was read
+        usize::try_from(self.header.entries).expect("entries fit in memory")
+    }
+
+    fn entry_size(&self) -> usize {
+        usize::try_from(self.header.entry_size).expect("entry size fits in memory")
+    }
+
+    pub(crate) fn entry(&self, id: usize) -> &[u8] {
+        let size = self.entry_size();
+        &self.table[id * size..(id + 1) * size]
+    }
+
+    fn entry_mut(&mut self, id: usize) -> &mut [u8] {
+        let size = self.entry_size();
+        &mut self.table[id * size..(id + 1) * size]
+    }
+
+    /// The first LBA of an existing partition, or `NotFound`.
+    fn used_entry(&self, id: usize) -> Result<u64, Error> {
+        if id >= self.slots() || self.entry(id)[..0x10].iter().all(|x| 0 == *x) {
+            return Err(Error::NotFound);
+        }
+        Ok(le::read_u64(&self.entry(id)[0x20..0x28]))
+    }
+
+    /// The entry for an existing partition, or `NotFound`.
+    pub(crate) fn used_entry_mut(&mut self, id: usize) -> Result<&mut [u8], Error> {
+        self.used_entry(id)?;
+        Ok(self.entry_mut(id))
+    }
+}
+
+/// Change the length of a partition, keeping its start where it is.
+///
+/// The new length must be a whole number of sectors, and the partition must still
+/// lie within the usable area without overlapping its neighbours. To grow the last
+/// partition after enlarging a disc image, call [`GptEditor::set_disk_len`] first,
+/// then resize to [`GptEditor::max_len`].
+pub fn resize_partition(editor: &mut GptEditor, id: usize, new_len: u64) -> Result<(), Error> {
+    let first_lba = editor.used_entry(id)?;
+    let first_byte = first_lba
+        .checked_mul(editor.sector_size)
+        .ok_or(Error::Overflow)?;
+
+    let (_, last_lba) = editor.lbas(first_byte, new_len)?;
+    editor.check_range(Some(id), first_lba, last_lba)?;
+
+    editor.used_entry_mut(id)?[0x28..0x30].copy_from_slice(&last_lba.to_le_bytes());
+    Ok(())
+}
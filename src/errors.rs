@@ -1,4 +1,4 @@
-use alloc::string::String;
+use alloc::{boxed::Box, string::String};
 
 use snafu::prelude::*;
 
@@ -12,6 +12,15 @@ pub enum Error {
         pos: u64,
     },
 
+    /// The reader failed while we were reading part of the table, at this byte offset.
+    #[snafu(display("reading {what} at byte {pos}: {source}"))]
+    Read {
+        what: &'static str,
+        pos: u64,
+        #[snafu(source(from(Error, Box::new)))]
+        source: Box<Error>,
+    },
+
     NotFound,
 
     Overflow,
@@ -27,4 +36,51 @@ pub enum Error {
     InvalidData {
         message: String,
     },
+
+    /// No valid GPT header was found at LBA 1 for any sector size, nor at the start of the
+    /// protective partition, which is where the MBR says LBA 1 is.
+    #[snafu(display(
+        "no valid gpt header for any sector size up to {max_sector_size} bytes, \
+         or at the protective partition's start, {guess}: {source}"
+    ))]
+    SectorSizeGuess {
+        guess: u64,
+        max_sector_size: u64,
+        #[snafu(source(from(Error, Box::new)))]
+        source: Box<Error>,
+    },
+
+    #[snafu(display("{what} ends at byte {needed}, beyond the end of the disc at {disk_len}"))]
+    Truncated {
+        what: String,
+        needed: u64,
+        disk_len: u64,
+    },
+}
+
+/// Hand an error back through an `io` interface, unwrapping it if it came from there.
+///
+/// `NotFound` and `UnexpectedEof` keep their `ErrorKind`s, and a disc which is shorter
+/// than its table says is also an `UnexpectedEof`; anything else wrong with the data is
+/// `InvalidData`. A failed read keeps the kind of its cause, and says what was being read.
+#[cfg(feature = "std")]
+impl From<Error> for std::io::Error {
+    fn from(error: Error) -> std::io::Error {
+        match error {
+            Error::Io { source, .. } => source,
+            other => std::io::Error::new(kind(&other), other),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+fn kind(error: &Error) -> std::io::ErrorKind {
+    use std::io::ErrorKind;
+    match error {
+        Error::Io { source, .. } => source.kind(),
+        Error::Read { source, .. } | Error::SectorSizeGuess { source, .. } => kind(source),
+        Error::NotFound => ErrorKind::NotFound,
+        Error::UnexpectedEof | Error::Truncated { .. } => ErrorKind::UnexpectedEof,
+        _ => ErrorKind::InvalidData,
+    }
 }
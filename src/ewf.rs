@@ -0,0 +1,400 @@
+//! Read the disc inside an Expert Witness Format (EnCase `.E01`) forensic image.
+//!
+//! Images may be split across many segment files (`.E01`, `.E02`, ...), which are read
+//! together. Chunks are usually zlib compressed; no decompressor is built in, so pass one
+//! in, e.g. `miniz_oxide::inflate::decompress_to_vec_zlib`. The newer EWF2 (`.Ex01`)
+//! format is not supported.
+//!
+//! ```rust,no_run
+//! # fn go() -> Result<(), bootsector::Error> {
+//! # fn zlib_decompress(chunk: &[u8], len: usize) -> std::io::Result<Vec<u8>> { unimplemented!() }
+//! let disc = bootsector::ewf::Ewf::open_files("evidence.E01", zlib_decompress)?;
+//! let table = bootsector::read_partition_table_sized(&disc, &Default::default())?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::io::{ReadAt, Recent};
+use crate::le;
+use crate::Error;
+
+const SIGNATURE: &[u8; 8] = b"EVF\x09\x0d\x0a\xff\x00";
+const EWF2_SIGNATURE: &[u8; 8] = b"EVF2\x0d\x0a\x81\x00";
+const FILE_HEADER_LEN: u64 = 13;
+const SECTION_LEN: u64 = 76;
+
+const COMPRESSED: u32 = 1 << 31;
+
+/// How many decompressed chunks are kept.
+const CACHED_CHUNKS: usize = 8;
+
+/// A stored chunk of the image.
+struct Chunk {
+    segment: usize,
+    offset: u64,
+    /// Including the checksum, for uncompressed chunks.
+    stored_len: u64,
+    compressed: bool,
+}
+
+/// An EWF image, readable as the disc it contains.
+pub struct Ewf<R, D> {
+    segments: Vec<R>,
+    decompress: D,
+    chunk_size: u64,
+    disk_len: u64,
+    chunks: Vec<Chunk>,
+    cache: Recent,
+}
+
+impl<D> Ewf<File, D>
+where
+    D: Fn(&[u8], usize) -> io::Result<Vec<u8>>,
+{
+    /// Open the first segment file, e.g. `evidence.E01`, and any others next to it.
+    pub fn open_files<P: AsRef<Path>>(first: P, decompress: D) -> Result<Ewf<File, D>, Error> {
+        let mut segments = Vec::new();
+        for path in segment_paths(first.as_ref()) {
+            match File::open(&path) {
+                Ok(file) => segments.push(file),
+                Err(ref e) if io::ErrorKind::NotFound == e.kind() && !segments.is_empty() => break,
+                Err(source) => return Err(Error::Io { source, pos: 0 }),
+            }
+        }
+        Ewf::open(segments, decompress)
+    }
+}
+
+impl<R, D> Ewf<R, D>
+where
+    R: positioned_io2::ReadAt,
+    D: Fn(&[u8], usize) -> io::Result<Vec<u8>>,
+{
+    /// Read the sections of all of the segments, which must be in order.
+    ///
+    /// `decompress` is given a whole zlib stream, and the length it should decompress to.
+    pub fn open(segments: Vec<R>, decompress: D) -> Result<Ewf<R, D>, Error> {
+        let mut volume = None;
+        let mut chunks = Vec::new();
+        let mut done = false;
+
+        for (index, segment) in segments.iter().enumerate() {
+            if done {
+                return Err(Error::InvalidStatic {
+                    message: "EWF segments found after the last segment",
+                });
+            }
+
+            let mut header = [0u8; 13];
+            ReadAt::read_exact_at(segment, 0, &mut header)?;
+            if EWF2_SIGNATURE == &header[..8] {
+                return Err(Error::InvalidStatic {
+                    message: "EWF2 (Ex01) images are not supported",
+                });
+            }
+            if SIGNATURE != &header[..8] {
+                return Err(Error::NotFound);
+            }
+
+            let number = le::read_u16(&header[9..]);
+            if usize::from(number) != index + 1 {
+                return Err(Error::InvalidData {
+                    message: format!("EWF segment {} was given in position {}", number, index + 1),
+                });
+            }
+
+            done = read_sections(segment, index, &mut volume, &mut chunks)?;
+        }
+
+        if !done {
+            return Err(Error::InvalidStatic {
+                message: "EWF image is missing segments",
+            });
+        }
+
+        let (chunk_size, disk_len) = volume.ok_or(Error::InvalidStatic {
+            message: "EWF volume section not found",
+        })?;
+
+        let needed = disk_len
+            .checked_add(chunk_size - 1)
+            .ok_or(Error::Overflow)?
+            / chunk_size;
+        if (chunks.len() as u64) < needed {
+            return Err(Error::InvalidStatic {
+                message: "EWF image has fewer chunks than its disc needs",
+            });
+        }
+
+        Ok(Ewf {
+            segments,
+            decompress,
+            chunk_size,
+            disk_len,
+            chunks,
+            cache: Recent::new(CACHED_CHUNKS),
+        })
+    }
+}
+
+impl<R, D> Ewf<R, D> {
+    /// The length of the acquired disc, in bytes.
+    pub fn disk_len(&self) -> u64 {
+        self.disk_len
+    }
+
+    /// The size of the unit of compression.
+    pub fn chunk_size(&self) -> u64 {
+        self.chunk_size
+    }
+
+    pub fn into_inner(self) -> Vec<R> {
+        self.segments
+    }
+}
+
+/// The conventional names: `.E01` to `.E99`, then `.EAA` to `.EZZ`.
+fn segment_paths(first: &Path) -> Vec<PathBuf> {
+    let mut extensions: Vec<String> = (1..=99).map(|n| format!("E{:02}", n)).collect();
+    for a in b'A'..=b'Z' {
+        for b in b'A'..=b'Z' {
+            extensions.push(format!("E{}{}", char::from(a), char::from(b)));
+        }
+    }
+
+    // keep the case of the name we were given
+    let lower = first
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map_or(false, |ext| ext.starts_with('e'));
+
+    extensions
+        .into_iter()
+        .map(|ext| {
+            if lower {
+                first.with_extension(ext.to_lowercase())
+            } else {
+                first.with_extension(ext)
+            }
+        })
+        .collect()
+}
+
+/// Walk a segment's sections, returning whether it's the last segment.
+fn read_sections<R>(
+    segment: &R,
+    index: usize,
+    volume: &mut Option<(u64, u64)>,
+    chunks: &mut Vec<Chunk>,
+) -> Result<bool, Error>
+where
+    R: positioned_io2::ReadAt,
+{
+    let mut pos = FILE_HEADER_LEN;
+    let mut sectors_end = None;
+
+    loop {
+        let mut section = [0u8; 76];
+        ReadAt::read_exact_at(segment, pos, &mut section)?;
+
+        if le::read_u32(&section[72..]) != adler32(&section[..72]) {
+            return Err(Error::InvalidData {
+                message: format!("EWF section at {} has an invalid checksum", pos),
+            });
+        }
+
+        let kind = &section[..16];
+        let kind = &kind[..kind.iter().position(|&b| 0 == b).unwrap_or(16)];
+        let next = le::read_u64(&section[16..]);
+        let size = le::read_u64(&section[24..]);
+        let data = pos.checked_add(SECTION_LEN).ok_or(Error::Overflow)?;
+
+        match kind {
+            b"volume" | b"disk" => {
+                let mut raw = [0u8; 24];
+                ReadAt::read_exact_at(segment, data, &mut raw)?;
+                let sectors_per_chunk = u64::from(le::read_u32(&raw[8..]));
+                let bytes_per_sector = u64::from(le::read_u32(&raw[12..]));
+                let sectors = le::read_u64(&raw[16..]);
+                let chunk_size = sectors_per_chunk * bytes_per_sector;
+                if 0 == chunk_size || chunk_size > 64 * 1024 * 1024 {
+                    return Err(Error::InvalidData {
+                        message: format!("invalid EWF chunk size: {}", chunk_size),
+                    });
+                }
+                let disk_len = sectors
+                    .checked_mul(bytes_per_sector)
+                    .ok_or(Error::Overflow)?;
+                *volume = Some((chunk_size, disk_len));
+            }
+            b"sectors" => sectors_end = Some(pos.checked_add(size).ok_or(Error::Overflow)?),
+            b"table" => read_table(segment, index, data, sectors_end.unwrap_or(pos), chunks)?,
+            b"next" => return Ok(false),
+            b"done" => return Ok(true),
+            _ => (),
+        }
+
+        if next <= pos {
+            return Err(Error::InvalidData {
+                message: format!("EWF section at {} does not lead anywhere", pos),
+            });
+        }
+        pos = next;
+    }
+}
+
+/// Read a table of chunk offsets, the last of which ends at `end`.
+fn read_table<R>(
+    segment: &R,
+    index: usize,
+    pos: u64,
+    end: u64,
+    chunks: &mut Vec<Chunk>,
+) -> Result<(), Error>
+where
+    R: positioned_io2::ReadAt,
+{
+    let mut header = [0u8; 24];
+    ReadAt::read_exact_at(segment, pos, &mut header)?;
+    if le::read_u32(&header[20..]) != adler32(&header[..20]) {
+        return Err(Error::InvalidData {
+            message: format!("EWF table at {} has an invalid checksum", pos),
+        });
+    }
+
+    let count = le::read_u32(&header);
+    let base = le::read_u64(&header[8..]);
+
+    if count > 65534 {
+        return Err(Error::InvalidData {
+            message: format!("EWF table at {} has too many entries: {}", pos, count),
+        });
+    }
+
+    let mut raw = vec![0u8; usize::try_from(count).map_err(|_| Error::BiggerThanMemory)? * 4];
+    let entries_pos = pos.checked_add(24).ok_or(Error::Overflow)?;
+    ReadAt::read_exact_at(segment, entries_pos, &mut raw)?;
+
+    let entries: Vec<u32> = raw.chunks_exact(4).map(le::read_u32).collect();
+    for (i, &entry) in entries.iter().enumerate() {
+        let offset = base
+            .checked_add(u64::from(entry & !COMPRESSED))
+            .ok_or(Error::Overflow)?;
+        let chunk_end = match entries.get(i + 1) {
+            Some(next) => base
+                .checked_add(u64::from(next & !COMPRESSED))
+                .ok_or(Error::Overflow)?,
+            None => end,
+        };
+        let stored_len = chunk_end.checked_sub(offset).ok_or(Error::InvalidData {
+            message: format!("EWF table at {} has chunks out of order", pos),
+        })?;
+        chunks.push(Chunk {
+            segment: index,
+            offset,
+            stored_len,
+            compressed: 0 != entry & COMPRESSED,
+        });
+    }
+
+    Ok(())
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), &byte| {
+        let a = (a + u32::from(byte)) % 65521;
+        (a, (b + a) % 65521)
+    });
+    (b << 16) | a
+}
+
+impl<R, D> Ewf<R, D>
+where
+    R: positioned_io2::ReadAt,
+    D: Fn(&[u8], usize) -> io::Result<Vec<u8>>,
+{
+    fn chunk(&self, index: u64) -> io::Result<Arc<Vec<u8>>> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let too_big = || invalid(format!("EWF chunk {} is too large", index));
+
+        self.cache.get_or_load(index, || {
+            let chunk = usize::try_from(index)
+                .ok()
+                .and_then(|i| self.chunks.get(i))
+                .ok_or_else(|| invalid(format!("EWF chunk {} is missing", index)))?;
+
+            // the last chunk may be short
+            let start = index * self.chunk_size;
+            let expected = usize::try_from(self.chunk_size.min(self.disk_len - start))
+                .map_err(|_| too_big())?;
+
+            if chunk.stored_len > 2 * self.chunk_size + 1024 {
+                return Err(too_big());
+            }
+            let mut stored = vec![0u8; usize::try_from(chunk.stored_len).map_err(|_| too_big())?];
+            positioned_io2::ReadAt::read_exact_at(
+                &self.segments[chunk.segment],
+                chunk.offset,
+                &mut stored,
+            )?;
+
+            let mut data = if chunk.compressed {
+                (self.decompress)(&stored, expected)?
+            } else {
+                if stored.len() < expected + 4 {
+                    return Err(invalid(format!("EWF chunk {} is truncated", index)));
+                }
+                let (data, checksum) = stored.split_at(expected);
+                if le::read_u32(checksum) != adler32(data) {
+                    return Err(invalid(format!(
+                        "EWF chunk {} has an invalid checksum",
+                        index
+                    )));
+                }
+                stored.truncate(expected);
+                stored
+            };
+
+            if data.len() < expected {
+                return Err(invalid(format!(
+                    "EWF chunk {} decompressed to {} bytes, not {}",
+                    index,
+                    data.len(),
+                    expected
+                )));
+            }
+            data.truncate(expected);
+            Ok(data)
+        })
+    }
+}
+
+impl<R, D> positioned_io2::ReadAt for Ewf<R, D>
+where
+    R: positioned_io2::ReadAt,
+    D: Fn(&[u8], usize) -> io::Result<Vec<u8>>,
+{
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        if pos >= self.disk_len || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let data = self.chunk(pos / self.chunk_size)?;
+        let skip = usize::try_from(pos % self.chunk_size).expect("within a chunk");
+        let len = buf.len().min(data.len() - skip);
+        buf[..len].copy_from_slice(&data[skip..skip + len]);
+        Ok(len)
+    }
+}
+
+impl<R, D> positioned_io2::Size for Ewf<R, D> {
+    fn size(&self) -> io::Result<Option<u64>> {
+        Ok(Some(self.disk_len))
+    }
+}
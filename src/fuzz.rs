@@ -0,0 +1,75 @@
+//! Entry points for fuzzers, which parse buffers already in memory.
+//!
+//! These run the same validation as [`read_partition_table`](crate::read_partition_table),
+//! but never read, and never allocate space for anything that isn't in the buffer, so a
+//! mutated entry count can't make a harness run out of memory before the parse fails.
+//!
+//! ```rust
+//! // e.g. in a cargo-fuzz target: fuzz_target!(|data: &[u8]| { ... });
+//! # let data = &[0u8; 4096][..];
+//! if let Some(sector) = data.get(..512) {
+//!     let mut mbr = [0u8; 512];
+//!     mbr.copy_from_slice(sector);
+//!     let _ = bootsector::fuzz::fuzz_mbr(&mbr);
+//! }
+//! let _ = bootsector::fuzz::fuzz_gpt(data, 512);
+//! ```
+
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use crate::gpt::{self, Header};
+use crate::{mbr, Error, Partition, Reserved};
+
+/// Parse a boot sector as an MBR.
+///
+/// This is [`mbr::parse_partition_table`], with the signature checked first, as it is
+/// when reading a disc.
+pub fn fuzz_mbr(sector: &[u8; 512]) -> Result<Vec<Partition>, Error> {
+    if 0x55 != sector[510] || 0xAA != sector[511] {
+        return Err(Error::NotFound);
+    }
+
+    mbr::parse_partition_table(sector)
+}
+
+/// Parse a whole disc as a GPT, starting from the primary header at LBA 1.
+///
+/// The protective MBR is not looked at; use [`fuzz_mbr`] for that. Data in the reserved
+/// areas is tolerated, so the checks after it are reached.
+pub fn fuzz_gpt(disc: &[u8], sector_size: u64) -> Result<(Header, Vec<Partition>), Error> {
+    if sector_size < 512 {
+        return Err(Error::InvalidStatic {
+            message: "sector size is implausibly small",
+        });
+    }
+
+    let lba1 = range(disc, sector_size, sector_size)?;
+    let header = gpt::validate_primary(lba1, sector_size, &Reserved::Tolerate, &mut Vec::new())?;
+
+    let table_len =
+        u64::try_from(header.table_len().ok_or(Error::Overflow)?).map_err(|_| Error::Overflow)?;
+    let table_pos = header
+        .entries_lba
+        .checked_mul(sector_size)
+        .ok_or(Error::Overflow)?;
+    let table = range(disc, table_pos, table_len)?;
+
+    if header.entries_crc != gpt::checksum(table) {
+        return Err(Error::InvalidStatic {
+            message: "table crc invalid",
+        });
+    }
+
+    let partitions = gpt::parse_entries(table, &header, sector_size)?;
+    Ok((header, partitions))
+}
+
+fn range(disc: &[u8], pos: u64, len: u64) -> Result<&[u8], Error> {
+    let start = usize::try_from(pos).map_err(|_| Error::UnexpectedEof)?;
+    let len = usize::try_from(len).map_err(|_| Error::UnexpectedEof)?;
+    start
+        .checked_add(len)
+        .and_then(|end| disc.get(start..end))
+        .ok_or(Error::UnexpectedEof)
+}
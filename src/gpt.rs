@@ -3,8 +3,17 @@ use core::convert::TryFrom;
 use core::convert::TryInto;
 
 use crc::Crc;
+use snafu::ResultExt;
 
-use crate::{io, le, Attributes, Error, Partition};
+use crate::edit::GptEditor;
+use crate::errors::ReadSnafu;
+use crate::verify::{Finding, Severity};
+use crate::{
+    instrument, io, le, mbr, Attributes, EntrySizes, Error, InvalidNames, NameNormalization,
+    Partition, Reserved,
+};
+
+pub mod guids;
 
 // Apparently we have to pick a name from a random page on sourceforge.
 // Random sourceforge page: https://reveng.sourceforge.io/crc-catalogue/all.htm
@@ -21,8 +30,19 @@ use crate::{io, le, Attributes, Error, Partition};
 // (and the values check out)
 const CRC: Crc<u32> = Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
 
+/// The largest sector size considered when guessing, unless [`Options::max_sector_size`]
+/// says otherwise.
+///
+/// [`Options::max_sector_size`]: crate::Options::max_sector_size
+pub const MAX_SECTOR_SIZE: u64 = 16 * 1024;
+
 pub fn is_protective(partition: &Partition) -> bool {
-    const MAXIMUM_SECTOR_SIZE: u64 = 16 * 1024;
+    is_protective_up_to(partition, MAX_SECTOR_SIZE)
+}
+
+/// [`is_protective`], for discs whose sectors may be up to `max_sector_size` bytes, so
+/// whose protective partition, at LBA 1, may start that far in.
+pub fn is_protective_up_to(partition: &Partition, max_sector_size: u64) -> bool {
     const PROTECTIVE_TYPE: u8 = 0xee;
 
     match partition.attributes {
@@ -33,119 +53,919 @@ pub fn is_protective(partition: &Partition) -> bool {
         _ => return false,
     };
 
-    0 == partition.id && partition.first_byte <= MAXIMUM_SECTOR_SIZE
+    0 == partition.id && partition.first_byte <= max_sector_size
+}
+
+/// A looser [`is_protective`]: any 0xEE entry which starts in the first MiB, in any slot,
+/// even if it's marked as bootable.
+pub fn is_protective_relaxed(partition: &Partition) -> bool {
+    const LIMIT: u64 = 1024 * 1024;
+
+    match partition.attributes {
+        Attributes::MBR { type_code, .. } => 0xee == type_code && partition.first_byte < LIMIT,
+        _ => false,
+    }
 }
 
-struct Cursor<R: io::ReadAt> {
-    inner: R,
-    pos: u64,
+/// The fields of a GPT header, as stored on disc, with the offset each is read from.
+///
+/// A header returned from this crate has been validated, unless it says otherwise.
+/// LBAs are in units of the disc's sector size, so e.g. the entry array starts at
+/// byte `entries_lba * sector_size`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Header {
+    /// 0x08: the version of the specification; `0x0001_0000` for all known discs.
+    pub revision: u32,
+    /// 0x0c: the number of bytes covered by the header's checksum.
+    pub header_size: u32,
+    /// 0x10: the checksum of the header, calculated with this field set to zero.
+    pub header_crc: u32,
+    /// 0x14: should be zero.
+    pub reserved: u32,
+    /// 0x18: the LBA this header is stored at.
+    pub my_lba: u64,
+    /// 0x20: the LBA of the other copy of the header.
+    pub alternate_lba: u64,
+    /// 0x28: the first LBA which partitions may use.
+    pub first_usable_lba: u64,
+    /// 0x30: the last LBA which partitions may use, inclusive.
+    pub last_usable_lba: u64,
+    /// 0x38: the GUID identifying the disc.
+    pub disk_guid: [u8; 16],
+    /// 0x48: the LBA the entry array starts at.
+    pub entries_lba: u64,
+    /// 0x50: the number of slots in the entry array.
+    pub entries: u32,
+    /// 0x54: the size of each entry, in bytes.
+    pub entry_size: u32,
+    /// 0x58: the checksum of the whole entry array.
+    pub entries_crc: u32,
+}
+
+pub(crate) const SIGNATURE: &[u8; 8] = b"EFI PART";
+pub(crate) const REVISION_ONE: u32 = 0x0001_0000;
+pub(crate) const MINIMUM_HEADER_SIZE: usize = 92;
+
+impl Header {
+    /// Decode a header from the start of a sector, which must be at least 92 bytes long.
+    pub(crate) fn decode(sector: &[u8]) -> Header {
+        Header {
+            revision: le::read_u32(&sector[0x08..0x0c]),
+            header_size: le::read_u32(&sector[0x0c..0x10]),
+            header_crc: le::read_u32(&sector[0x10..0x14]),
+            reserved: le::read_u32(&sector[0x14..0x18]),
+            my_lba: le::read_u64(&sector[0x18..0x20]),
+            alternate_lba: le::read_u64(&sector[0x20..0x28]),
+            first_usable_lba: le::read_u64(&sector[0x28..0x30]),
+            last_usable_lba: le::read_u64(&sector[0x30..0x38]),
+            disk_guid: sector[0x38..0x48].try_into().expect("fixed size slice"),
+            entries_lba: le::read_u64(&sector[0x48..0x50]),
+            entries: le::read_u32(&sector[0x50..0x54]),
+            entry_size: le::read_u32(&sector[0x54..0x58]),
+            entries_crc: le::read_u32(&sector[0x58..0x5c]),
+        }
+    }
+
+    /// The checksum the header should have, or `None` if the header size doesn't fit the sector.
+    pub(crate) fn computed_crc(&self, sector: &[u8]) -> Option<u32> {
+        let header_size = usize::try_from(self.header_size).ok()?;
+        if header_size < MINIMUM_HEADER_SIZE || header_size > sector.len() {
+            return None;
+        }
+
+        // CRC is calculated with the CRC zero'd out
+        let mut digest = CRC.digest();
+        digest.update(&sector[..0x10]);
+        digest.update(&[0u8; 4]);
+        digest.update(&sector[0x14..header_size]);
+        Some(digest.finalize())
+    }
+
+    /// Write the header into the start of a sector, calculating the checksum.
+    ///
+    /// The header size is forced to the 92 bytes we know how to write.
+    pub(crate) fn encode(&self, sector: &mut [u8]) {
+        let mut header = self.clone();
+        header.header_size = 92;
+
+        sector[0x00..0x08].copy_from_slice(SIGNATURE);
+        sector[0x08..0x0c].copy_from_slice(&header.revision.to_le_bytes());
+        sector[0x0c..0x10].copy_from_slice(&header.header_size.to_le_bytes());
+        sector[0x14..0x18].copy_from_slice(&header.reserved.to_le_bytes());
+        sector[0x18..0x20].copy_from_slice(&header.my_lba.to_le_bytes());
+        sector[0x20..0x28].copy_from_slice(&header.alternate_lba.to_le_bytes());
+        sector[0x28..0x30].copy_from_slice(&header.first_usable_lba.to_le_bytes());
+        sector[0x30..0x38].copy_from_slice(&header.last_usable_lba.to_le_bytes());
+        sector[0x38..0x48].copy_from_slice(&header.disk_guid);
+        sector[0x48..0x50].copy_from_slice(&header.entries_lba.to_le_bytes());
+        sector[0x50..0x54].copy_from_slice(&header.entries.to_le_bytes());
+        sector[0x54..0x58].copy_from_slice(&header.entry_size.to_le_bytes());
+        sector[0x58..0x5c].copy_from_slice(&header.entries_crc.to_le_bytes());
+        for byte in &mut sector[MINIMUM_HEADER_SIZE..] {
+            *byte = 0;
+        }
+
+        let crc = header.computed_crc(sector).expect("92 byte header fits");
+        sector[0x10..0x14].copy_from_slice(&crc.to_le_bytes());
+    }
+
+    /// The header which should sit at the other end of the disc, with the entries
+    /// immediately before it (for a backup), or immediately after it (for a primary).
+    pub(crate) fn alternate(&self, sector_size: u64) -> Option<Header> {
+        let mut header = self.clone();
+        header.my_lba = self.alternate_lba;
+        header.alternate_lba = self.my_lba;
+        header.entries_lba = if 1 == header.my_lba {
+            2
+        } else {
+            header
+                .my_lba
+                .checked_sub(self.table_sectors(sector_size)?)?
+        };
+        Some(header)
+    }
+
+    /// The number of whole sectors occupied by the entry array.
+    pub(crate) fn table_sectors(&self, sector_size: u64) -> Option<u64> {
+        let len = u64::from(self.entries).checked_mul(u64::from(self.entry_size))?;
+        Some(len.checked_add(sector_size.checked_sub(1)?)? / sector_size)
+    }
+
+    /// The size of the entry array, in bytes, if it fits in memory.
+    pub(crate) fn table_len(&self) -> Option<usize> {
+        usize::try_from(self.entry_size)
+            .ok()?
+            .checked_mul(usize::try_from(self.entries).ok()?)
+    }
 }
 
-impl<R: io::ReadAt> Cursor<R> {
-    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), Error> {
-        let len = u64::try_from(buf.len()).map_err(|_| Error::BiggerThanMemory)?;
-        self.inner.read_exact_at(self.pos, buf)?;
-        self.pos += len;
-        Ok(())
+/// Decode a header if it has a valid signature and checksum, and is where it claims to be.
+pub(crate) fn decode_valid(sector: &[u8], lba: u64) -> Option<Header> {
+    if sector.len() < MINIMUM_HEADER_SIZE || SIGNATURE != &sector[..8] {
+        return None;
+    }
+
+    let header = Header::decode(sector);
+    if Some(header.header_crc) != header.computed_crc(sector) || lba != header.my_lba {
+        return None;
     }
+
+    Some(header)
+}
+
+/// The size of an entry's name field, in bytes.
+pub(crate) const NAME_LEN: usize = 0x80 - 0x38;
+
+/// Encode a name as it's stored in an entry, padded with nuls. It must fit in 36 UTF-16
+/// code units, and can't contain a nul, as that would end it early when it's read back.
+pub(crate) fn encode_name(name: &str) -> Result<Vec<u8>, Error> {
+    let mut field = vec![0u8; NAME_LEN];
+    for (pos, unit) in name.encode_utf16().enumerate() {
+        let at = 2 * pos;
+        if at >= NAME_LEN {
+            return Err(Error::InvalidData {
+                message: format!("name {:?} is longer than {} characters", name, NAME_LEN / 2),
+            });
+        }
+        if 0 == unit {
+            return Err(Error::InvalidStatic {
+                message: "name contains a nul",
+            });
+        }
+        field[at..at + 2].copy_from_slice(&unit.to_le_bytes());
+    }
+    Ok(field)
+}
+
+pub(crate) fn checksum(data: &[u8]) -> u32 {
+    CRC.checksum(data)
 }
 
 pub fn read<R>(reader: R, sector_size: u64) -> Result<Vec<Partition>, Error>
 where
     R: io::ReadAt,
 {
-    let mut reader = Cursor {
-        inner: reader,
-        pos: sector_size,
+    Ok(read_table(&reader, sector_size)?.1)
+}
+
+/// Find the sector size of a GPT disc, by looking for a valid primary header at
+/// LBA 1 for each plausible size, from 512 bytes to [`MAX_SECTOR_SIZE`].
+///
+/// This works even if the protective MBR is wrong, e.g. a 4Kn disc whose MBR was written
+/// assuming 512 byte sectors.
+pub fn detect_sector_size<R>(reader: R) -> Option<u64>
+where
+    R: io::ReadAt,
+{
+    probe_sector_size(&reader, MAX_SECTOR_SIZE)
+}
+
+pub(crate) fn probe_sector_size<R>(reader: &R, max_sector_size: u64) -> Option<u64>
+where
+    R: io::ReadAt + ?Sized,
+{
+    let mut size: u64 = 512;
+    while size <= max_sector_size {
+        let mut sector = vec![0u8; usize::try_from(size).ok()?];
+        if reader.read_exact_at(size, &mut sector).is_ok() && decode_valid(&sector, 1).is_some() {
+            debug!(sector_size = size, "found gpt header probing sector sizes");
+            return Some(size);
+        }
+        size = size.checked_mul(2)?;
+    }
+
+    None
+}
+
+/// Read the primary header, and the partitions it describes.
+///
+/// The header is validated exactly as for [`read`].
+pub fn read_with_header<R>(reader: R, sector_size: u64) -> Result<(Header, Vec<Partition>), Error>
+where
+    R: io::ReadAt,
+{
+    read_table(&reader, sector_size)
+}
+
+/// Read and validate the header stored at `byte_offset`, e.g. the backup header in the
+/// last sector of the disc, or a table which has been relocated.
+///
+/// Unlike [`read`], which insists on the primary layout, the header need only be at the
+/// LBA it claims to be (`byte_offset / sector_size`), with its entry array clear of it and
+/// of the usable area. Pass the header to [`read_entries`] to get its partitions.
+pub fn read_header_at<R>(reader: R, byte_offset: u64, sector_size: u64) -> Result<Header, Error>
+where
+    R: io::ReadAt,
+{
+    header_at(&reader, byte_offset, sector_size)
+}
+
+pub(crate) fn header_at<R>(reader: &R, byte_offset: u64, sector_size: u64) -> Result<Header, Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    if sector_size < 512 {
+        return Err(Error::InvalidStatic {
+            message: "sector size is implausibly small",
+        });
+    }
+
+    if 0 != byte_offset % sector_size {
+        return Err(Error::InvalidStatic {
+            message: "headers start on a sector boundary",
+        });
+    }
+
+    let mut sector = vec![0u8; usize::try_from(sector_size).map_err(|_| Error::BiggerThanMemory)?];
+    reader
+        .read_exact_at(byte_offset, &mut sector)
+        .context(ReadSnafu {
+            what: "the gpt header",
+            pos: byte_offset,
+        })?;
+
+    debug!(byte_offset, sector_size, "checking gpt header");
+    validate_header(
+        &sector,
+        byte_offset / sector_size,
+        sector_size,
+        &Reserved::MustBeZero,
+        &mut Vec::new(),
+    )
+    .map_err(|e| instrument::rejected("gpt header", e))
+}
+
+/// Read the partitions described by a header, e.g. one from [`read_header_at`].
+///
+/// The entry array's checksum must match the header.
+pub fn read_entries<R>(
+    reader: R,
+    header: &Header,
+    sector_size: u64,
+) -> Result<Vec<Partition>, Error>
+where
+    R: io::ReadAt,
+{
+    let table = read_entry_array(&reader, header, sector_size)?;
+    parse_entries(&table, header, sector_size)
+}
+
+/// Read the backup header from the last sector of the disc, and the partitions it
+/// describes, without looking at the start of the disc at all.
+///
+/// This is the way to rescue a disc whose MBR and primary GPT have been overwritten, e.g.
+/// by writing an ISO to the wrong device. If the sector size isn't known, try 512, then
+/// 4096. The primary can then be rebuilt with [`restore_primary`].
+pub fn read_backup<R>(
+    reader: R,
+    disk_len: u64,
+    sector_size: u64,
+) -> Result<(Header, Vec<Partition>), Error>
+where
+    R: io::ReadAt,
+{
+    let backup_lba =
+        (disk_len / sector_size.max(1))
+            .checked_sub(1)
+            .ok_or(Error::InvalidStatic {
+                message: "disc is empty",
+            })?;
+
+    let header = header_at(&reader, backup_lba * sector_size, sector_size)?;
+    let table = read_entry_array(&reader, &header, sector_size)?;
+    let partitions = parse_entries(&table, &header, sector_size)?;
+    Ok((header, partitions))
+}
+
+/// Settings for laying down a new, empty, GPT.
+pub struct CreateOptions {
+    /// The GUID identifying the disc. This should be random.
+    pub disk_guid: [u8; 16],
+
+    /// The number of slots in the entry array. 128 is conventional, and is the minimum
+    /// the specification allows (it requires at least 16KiB of entries).
+    pub entries: u32,
+
+    /// The size of each entry. The specification requires 128 multiplied by a power of
+    /// two; anything past the first 128 bytes is available as [`Attributes::GPT::extra`].
+    pub entry_size: u32,
+}
+
+impl CreateOptions {
+    /// The conventional layout, with 128 entries.
+    pub fn new(disk_guid: [u8; 16]) -> CreateOptions {
+        CreateOptions {
+            disk_guid,
+            entries: 128,
+            entry_size: 128,
+        }
+    }
+}
+
+/// Write a protective MBR, and an empty GPT (both primary and backup), to a disc.
+///
+/// This is the equivalent of `parted mklabel gpt`. The usable range is maximal: it
+/// starts immediately after the primary entry array, and ends immediately before the
+/// backup entry array.
+pub fn create<W>(
+    mut writer: W,
+    disk_len: u64,
+    sector_size: u64,
+    options: &CreateOptions,
+) -> Result<(), Error>
+where
+    W: io::WriteAt,
+{
+    let mut header = new_header(disk_len, sector_size, options)?;
+    let table = vec![0u8; header.table_len().ok_or(Error::Overflow)?];
+    header.entries_crc = CRC.checksum(&table);
+
+    let sector_size_mem = usize::try_from(sector_size).map_err(|_| Error::BiggerThanMemory)?;
+    let mut lba0 = vec![0u8; sector_size_mem];
+    lba0[..512].copy_from_slice(&mbr::protective_mbr(disk_len / sector_size));
+    writer.write_all_at(0, &lba0)?;
+
+    write_both(&mut writer, &header, &table, sector_size)?;
+
+    writer.flush()
+}
+
+/// The primary header for a new, maximal, GPT; `entries_crc` is left unset.
+pub(crate) fn new_header(
+    disk_len: u64,
+    sector_size: u64,
+    options: &CreateOptions,
+) -> Result<Header, Error> {
+    if sector_size < 512 {
+        return Err(Error::InvalidStatic {
+            message: "sector size is implausibly small",
+        });
+    }
+
+    if options.entry_size < 128 || !options.entry_size.is_power_of_two() {
+        return Err(Error::InvalidStatic {
+            message: "entry size must be 128 multiplied by a power of two",
+        });
+    }
+
+    let disk_lbas = disk_len / sector_size;
+
+    let mut header = Header {
+        revision: REVISION_ONE,
+        header_size: 92,
+        header_crc: 0,
+        reserved: 0,
+        my_lba: 1,
+        alternate_lba: disk_lbas.checked_sub(1).ok_or(Error::InvalidStatic {
+            message: "disc is empty",
+        })?,
+        first_usable_lba: 0,
+        last_usable_lba: 0,
+        disk_guid: options.disk_guid,
+        entries_lba: 2,
+        entries: options.entries,
+        entry_size: options.entry_size,
+        entries_crc: 0,
     };
 
+    let table_sectors = header.table_sectors(sector_size).ok_or(Error::Overflow)?;
+    header.first_usable_lba = 2 + table_sectors;
+    header.last_usable_lba = header
+        .alternate_lba
+        .checked_sub(1 + table_sectors)
+        .filter(|last| *last >= header.first_usable_lba)
+        .ok_or(Error::InvalidStatic {
+            message: "disc is too small to hold a GPT",
+        })?;
+
+    Ok(header)
+}
+
+/// Rewrite the backup GPT at the end of the disc, from the (valid) primary.
+///
+/// This is the usual repair after a disc image has been grown: the primary's alternate
+/// LBA and usable area are moved to match `disk_len`, and both checksums are recomputed.
+/// The protective MBR is not updated.
+pub fn repair_backup<D>(mut disc: D, disk_len: u64, sector_size: u64) -> Result<(), Error>
+where
+    D: io::ReadAt + io::WriteAt,
+{
+    let mut editor = GptEditor::load(&disc, sector_size)?;
+    editor.set_disk_len(disk_len)?;
+    editor.commit_to(&mut disc)
+}
+
+/// Rewrite the primary GPT from the backup, at the end of the disc, when the
+/// primary has been damaged.
+///
+/// The backup header, and its entry array, must be valid, and it must point back to
+/// the primary at LBA 1.
+pub fn restore_primary<D>(mut disc: D, disk_len: u64, sector_size: u64) -> Result<(), Error>
+where
+    D: io::ReadAt + io::WriteAt,
+{
+    if sector_size < 512 {
+        return Err(Error::InvalidStatic {
+            message: "sector size is implausibly small",
+        });
+    }
+
+    let sector_size_mem = usize::try_from(sector_size).map_err(|_| Error::BiggerThanMemory)?;
+
+    let backup_lba = (disk_len / sector_size)
+        .checked_sub(1)
+        .ok_or(Error::InvalidStatic {
+            message: "disc is empty",
+        })?;
+
+    let mut sector = vec![0u8; sector_size_mem];
+    let backup_pos = backup_lba * sector_size;
+    disc.read_exact_at(backup_pos, &mut sector)
+        .context(ReadSnafu {
+            what: "the backup gpt header",
+            pos: backup_pos,
+        })?;
+    let backup = decode_valid(&sector, backup_lba).ok_or(Error::InvalidStatic {
+        message: "backup header is invalid",
+    })?;
+
+    if 1 != backup.alternate_lba {
+        return Err(Error::InvalidStatic {
+            message: "backup header does not refer to a primary at lba 1",
+        });
+    }
+
+    let mut table = vec![0u8; backup.table_len().ok_or(Error::Overflow)?];
+    let table_pos = backup
+        .entries_lba
+        .checked_mul(sector_size)
+        .ok_or(Error::Overflow)?;
+    disc.read_exact_at(table_pos, &mut table)
+        .context(ReadSnafu {
+            what: "the backup gpt partition entries",
+            pos: table_pos,
+        })?;
+
+    if backup.entries_crc != CRC.checksum(&table) {
+        return Err(Error::InvalidStatic {
+            message: "backup table crc invalid",
+        });
+    }
+
+    let primary = backup.alternate(sector_size).ok_or(Error::Overflow)?;
+
+    // refuse to write anything we wouldn't be able to read back
+    primary.encode(&mut sector);
+    validate_primary(&sector, sector_size, &Reserved::MustBeZero, &mut Vec::new())?;
+    parse_entries(&table, &primary, sector_size)?;
+
+    write_header(&mut disc, &primary, sector_size)?;
+    disc.write_all_at(2 * sector_size, &table)?;
+    disc.flush()
+}
+
+/// Destroy the partition tables on a disc, like `sgdisk --zap-all`: the MBR, and both
+/// copies of the GPT header and entry array, are overwritten with zeros.
+///
+/// The GPT structures are found from whichever of the headers are valid. The usual
+/// primary location, directly after the MBR, is wiped even if its header is damaged,
+/// but nothing is wiped at the end of the disc unless a valid header points there, and
+/// entry arrays which would overlap the usable area are left alone.
+pub fn wipe_table<D>(mut disc: D, disk_len: u64, sector_size: u64) -> Result<(), Error>
+where
+    D: io::ReadAt + io::WriteAt,
+{
+    if sector_size < 512 {
+        return Err(Error::InvalidStatic {
+            message: "sector size is implausibly small",
+        });
+    }
+
+    let sector_size_mem = usize::try_from(sector_size).map_err(|_| Error::BiggerThanMemory)?;
+    let sectors = disk_len / sector_size;
+    if 0 == sectors {
+        return Err(Error::InvalidStatic {
+            message: "disc is empty",
+        });
+    }
+
+    // 128 entries of 128 bytes, after the MBR and the header
+    let usual_table = (16 * 1024 + sector_size - 1) / sector_size;
+    let mut doomed = vec![(0, 2 + usual_table)];
+
+    let mut sector = vec![0u8; sector_size_mem];
+    for &lba in &[1, sectors - 1] {
+        disc.read_exact_at(lba * sector_size, &mut sector)
+            .context(ReadSnafu {
+                what: "a gpt header",
+                pos: lba * sector_size,
+            })?;
+        let header = match decode_valid(&sector, lba) {
+            Some(header) => header,
+            None => continue,
+        };
+
+        let table_sectors = header.table_sectors(sector_size).ok_or(Error::Overflow)?;
+        for copy in header.alternate(sector_size).iter().chain(Some(&header)) {
+            doomed.push((copy.my_lba, copy.my_lba.saturating_add(1)));
+
+            let table = (
+                copy.entries_lba,
+                copy.entries_lba.saturating_add(table_sectors),
+            );
+            if table.1 <= header.first_usable_lba || table.0 > header.last_usable_lba {
+                doomed.push(table);
+            }
+        }
+    }
+
+    let zeros = vec![0u8; sector_size_mem];
+    for (start, end) in doomed {
+        for lba in start..end.min(sectors) {
+            disc.write_all_at(lba * sector_size, &zeros)?;
+        }
+    }
+
+    disc.flush()
+}
+
+/// Write the primary header and table, then the backup table and header.
+pub(crate) fn write_both<W>(
+    writer: &mut W,
+    primary: &Header,
+    table: &[u8],
+    sector_size: u64,
+) -> Result<(), Error>
+where
+    W: io::WriteAt + ?Sized,
+{
+    let backup = primary.alternate(sector_size).ok_or(Error::Overflow)?;
+
+    for header in &[primary, &backup] {
+        write_header(writer, header, sector_size)?;
+
+        let table_pos = header
+            .entries_lba
+            .checked_mul(sector_size)
+            .ok_or(Error::Overflow)?;
+        writer.write_all_at(table_pos, table)?;
+    }
+
+    Ok(())
+}
+
+/// Write a single header, and the rest of its sector.
+pub(crate) fn write_header<W>(
+    writer: &mut W,
+    header: &Header,
+    sector_size: u64,
+) -> Result<(), Error>
+where
+    W: io::WriteAt + ?Sized,
+{
+    let sector_size_mem = usize::try_from(sector_size).map_err(|_| Error::BiggerThanMemory)?;
+    let mut sector = vec![0u8; sector_size_mem];
+    header.encode(&mut sector);
+
+    let pos = header
+        .my_lba
+        .checked_mul(sector_size)
+        .ok_or(Error::Overflow)?;
+    writer.write_all_at(pos, &sector)
+}
+
+/// Read the primary header and the partitions it describes.
+fn read_table<R>(reader: &R, sector_size: u64) -> Result<(Header, Vec<Partition>), Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    let (header, table) = read_raw(reader, sector_size, &Reserved::MustBeZero, &mut Vec::new())?;
+    let partitions = parse_entries(&table, &header, sector_size)?;
+    Ok((header, partitions))
+}
+
+/// Read and validate the primary header and its entry array, without parsing the entries.
+pub(crate) fn read_raw<R>(
+    reader: &R,
+    sector_size: u64,
+    reserved: &Reserved,
+    warnings: &mut Vec<Finding>,
+) -> Result<(Header, Vec<u8>), Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    let header = read_primary(reader, sector_size, reserved, warnings)?;
+    let table = read_entry_array(reader, &header, sector_size)?;
+    Ok((header, table))
+}
+
+/// Read and validate the primary header, alone.
+pub(crate) fn read_primary<R>(
+    reader: &R,
+    sector_size: u64,
+    reserved: &Reserved,
+    warnings: &mut Vec<Finding>,
+) -> Result<Header, Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    // the header alone is 92 bytes, and nobody has ever shipped a disc with sectors this small
+    if sector_size < 512 {
+        return Err(Error::InvalidStatic {
+            message: "sector size is implausibly small",
+        });
+    }
+
     let sector_size_mem = usize::try_from(sector_size).map_err(|_| Error::BiggerThanMemory)?;
 
     let mut lba1 = vec![0u8; sector_size_mem];
-    reader.read_exact(&mut lba1)?;
+    reader
+        .read_exact_at(sector_size, &mut lba1)
+        .context(ReadSnafu {
+            what: "the gpt header",
+            pos: sector_size,
+        })?;
 
-    if b"EFI PART" != &lba1[0x00..0x08] {
+    debug!(sector_size, "checking primary gpt header");
+    validate_primary(&lba1, sector_size, reserved, warnings)
+        .map_err(|e| instrument::rejected("primary gpt header", e))
+}
+
+/// Read the entry array a (validated) header points to, and check its checksum.
+pub(crate) fn read_entry_array<R>(
+    reader: &R,
+    header: &Header,
+    sector_size: u64,
+) -> Result<Vec<u8>, Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    let table = read_entry_array_unchecked(reader, header, sector_size)?;
+
+    let computed = CRC.checksum(&table);
+    if header.entries_crc != computed {
+        debug!(
+            stored = header.entries_crc,
+            computed, "gpt entry array checksum mismatch"
+        );
         return Err(Error::InvalidStatic {
-            message: "bad EFI signature",
+            message: "table crc invalid",
         });
     }
 
-    if [0, 0, 1, 0] != lba1[0x08..0x0c] {
+    Ok(table)
+}
+
+/// Read the entry array a (validated) header points to, trusting it.
+pub(crate) fn read_entry_array_unchecked<R>(
+    reader: &R,
+    header: &Header,
+    sector_size: u64,
+) -> Result<Vec<u8>, Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    let mut table = vec![0u8; header.table_len().ok_or(Error::Overflow)?];
+    let table_pos = header
+        .entries_lba
+        .checked_mul(sector_size)
+        .ok_or(Error::Overflow)?;
+    reader
+        .read_exact_at(table_pos, &mut table)
+        .context(ReadSnafu {
+            what: "the gpt partition entries",
+            pos: table_pos,
+        })?;
+
+    Ok(table)
+}
+
+/// Read a single entry, without checking the array's checksum, which covers all of them.
+pub(crate) fn read_entry<R>(
+    reader: &R,
+    header: &Header,
+    sector_size: u64,
+    id: usize,
+) -> Result<Vec<u8>, Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    let entry_size = usize::try_from(header.entry_size).map_err(|_| Error::Overflow)?;
+    let mut entry = vec![0u8; entry_size];
+    let pos = u64::try_from(id)
+        .ok()
+        .and_then(|id| id.checked_mul(u64::from(header.entry_size)))
+        .and_then(|offset| {
+            header
+                .entries_lba
+                .checked_mul(sector_size)?
+                .checked_add(offset)
+        })
+        .ok_or(Error::Overflow)?;
+    reader.read_exact_at(pos, &mut entry).context(ReadSnafu {
+        what: "a gpt partition entry",
+        pos,
+    })?;
+    Ok(entry)
+}
+
+/// Check the primary header is something we can read.
+///
+/// Data in the reserved areas is an error, unless it's being tolerated, in which case
+/// it's recorded in `warnings`.
+pub(crate) fn validate_primary(
+    lba1: &[u8],
+    sector_size: u64,
+    reserved: &Reserved,
+    warnings: &mut Vec<Finding>,
+) -> Result<Header, Error> {
+    let header = validate_header(lba1, 1, sector_size, reserved, warnings)?;
+
+    if 2 != header.entries_lba {
         return Err(Error::InvalidStatic {
-            message: "unsupported revision",
+            message: "starting lba must be '2' for first header",
         });
     }
 
-    let header_size = le::read_u32(&lba1[0x0c..0x10]);
-    if header_size < 92 {
+    // the array may not fill its last sector, which is still unusable
+    let table_sectors = header.table_sectors(sector_size).ok_or(Error::Overflow)?;
+    if header.first_usable_lba < 2 + table_sectors {
         return Err(Error::InvalidStatic {
-            message: "header too short",
+            message: "first usable lba is too low",
         });
     }
 
-    let header_size = usize::try_from(header_size).map_err(|_| Error::InvalidStatic {
+    Ok(header)
+}
+
+/// Check the entry size is one the specification allows, i.e. 128 multiplied by a power of
+/// two, unless any size is being accepted, in which case odd sizes are recorded in `warnings`.
+pub(crate) fn check_entry_size(
+    header: &Header,
+    sizes: &EntrySizes,
+    warnings: &mut Vec<Finding>,
+) -> Result<(), Error> {
+    if 0 == header.entry_size % 128 && (header.entry_size / 128).is_power_of_two() {
+        return Ok(());
+    }
+
+    let message = format!(
+        "entry size of {} bytes is not 128 multiplied by a power of two",
+        header.entry_size
+    );
+    match sizes {
+        EntrySizes::Standard => Err(Error::InvalidData { message }),
+        EntrySizes::Any => {
+            warnings.push(Finding {
+                severity: Severity::Warning,
+                code: "gpt-entry-size",
+                message,
+            });
+            Ok(())
+        }
+    }
+}
+
+/// Check a header read from `lba` is something we can read, wherever it is on the disc:
+/// it must be at the LBA it claims, and its entry array must not overlap it, or the
+/// usable area.
+///
+/// Warnings are named for the primary header if `lba` is 1, and the backup otherwise.
+fn validate_header(
+    sector: &[u8],
+    lba: u64,
+    sector_size: u64,
+    reserved: &Reserved,
+    warnings: &mut Vec<Finding>,
+) -> Result<Header, Error> {
+    let which = if 1 == lba { "primary" } else { "backup" };
+
+    if SIGNATURE != &sector[0x00..0x08] {
+        return Err(Error::InvalidStatic {
+            message: "bad EFI signature",
+        });
+    }
+
+    let header = Header::decode(sector);
+
+    if REVISION_ONE != header.revision {
+        return Err(Error::InvalidStatic {
+            message: "unsupported revision",
+        });
+    }
+
+    let header_size = usize::try_from(header.header_size).map_err(|_| Error::InvalidStatic {
         message: "header size must fit in memory",
     })?;
 
-    let header_crc = le::read_u32(&lba1[0x10..0x14]);
-
-    // CRC is calculated with the CRC zero'd out
-    for crc_part in 0x10..0x14 {
-        lba1[crc_part] = 0;
+    if header_size < MINIMUM_HEADER_SIZE {
+        return Err(Error::InvalidStatic {
+            message: "header too short",
+        });
     }
 
-    if header_crc != CRC.checksum(&lba1[..header_size]) {
+    if header_size > sector.len() {
         return Err(Error::InvalidStatic {
-            message: "header checksum mismatch",
+            message: "header is larger than a sector",
         });
     }
 
-    if 0 != le::read_u32(&lba1[0x14..0x18]) {
+    if Some(header.header_crc) != header.computed_crc(sector) {
         return Err(Error::InvalidStatic {
-            message: "unsupported data in reserved field 0x0c",
+            message: "header checksum mismatch",
         });
     }
 
-    if 1 != le::read_u64(&lba1[0x18..0x20]) {
+    if 0 != header.reserved {
+        match reserved {
+            Reserved::MustBeZero => {
+                return Err(Error::InvalidStatic {
+                    message: "unsupported data in reserved field 0x0c",
+                })
+            }
+            Reserved::Tolerate => warnings.push(Finding {
+                severity: Severity::Warning,
+                code: if 1 == lba {
+                    "gpt-primary-reserved"
+                } else {
+                    "gpt-backup-reserved"
+                },
+                message: format!("{} header has data in its reserved field", which),
+            }),
+        }
+    }
+
+    if lba != header.my_lba {
         return Err(Error::InvalidStatic {
-            message: "current lba must be '1' for first header",
+            message: "header is not at the lba it claims to be",
         });
     }
 
     // backup lba [ignored]
 
-    let first_usable_lba = le::read_u64(&lba1[0x28..0x30]);
-    let last_usable_lba = le::read_u64(&lba1[0x30..0x38]);
-
-    if first_usable_lba > last_usable_lba {
+    if header.first_usable_lba > header.last_usable_lba {
         return Err(Error::InvalidStatic {
             message: "usable lbas are backwards?!",
         });
     }
 
-    if last_usable_lba > (u64::MAX / sector_size) {
+    if header.last_usable_lba > (u64::MAX / sector_size) {
         return Err(Error::InvalidStatic {
             message: "everything must be below the 2^64 point (~ eighteen million TB)",
         });
     }
 
-    let mut guid = [0u8; 16];
-    guid.copy_from_slice(&lba1[0x38..0x48]);
-
-    if 2 != le::read_u64(&lba1[0x48..0x50]) {
+    if header.my_lba >= header.first_usable_lba && header.my_lba <= header.last_usable_lba {
         return Err(Error::InvalidStatic {
-            message: "starting lba must be '2' for first header",
+            message: "header is inside the usable area",
         });
     }
 
-    let entries = le::read_u32(&lba1[0x50..0x54]);
-
-    let entries = u16::try_from(entries).map_err(|_| Error::InvalidStatic {
+    let entries = u16::try_from(header.entries).map_err(|_| Error::InvalidStatic {
         message: "entry count is implausible",
     })?;
 
-    let entry_size = le::read_u32(&lba1[0x54..0x58]);
-    let entry_size = u16::try_from(entry_size).map_err(|_| Error::InvalidStatic {
+    let entry_size = u16::try_from(header.entry_size).map_err(|_| Error::InvalidStatic {
         message: "entry size is implausibly large",
     })?;
 
@@ -155,86 +975,210 @@ where
         });
     }
 
-    // TODO: off-by-1? Not super important.
-    if first_usable_lba < 2 + ((u64::from(entry_size) * u64::from(entries)) / sector_size) {
+    // both are u16s, so this can't overflow
+    let table_sectors =
+        (u64::from(entries) * u64::from(entry_size) + sector_size - 1) / sector_size;
+    let table_last = header
+        .entries_lba
+        .checked_add(table_sectors.max(1) - 1)
+        .ok_or(Error::Overflow)?;
+
+    if header.entries_lba <= header.my_lba && header.my_lba <= table_last {
         return Err(Error::InvalidStatic {
-            message: "first usable lba is too low",
+            message: "entry array overlaps its header",
         });
     }
 
-    let table_crc = le::read_u32(&lba1[0x58..0x5c]);
-
-    if !all_zero(&lba1[header_size..]) {
+    if header.entries_lba <= header.last_usable_lba && header.first_usable_lba <= table_last {
         return Err(Error::InvalidStatic {
-            message: "reserved header tail is not all empty",
+            message: "entry array overlaps the usable area",
         });
     }
 
-    let mut table = vec![
-        0u8;
-        usize::from(entry_size)
-            .checked_mul(usize::from(entries))
-            .ok_or(Error::Overflow)?
-    ];
-    reader.read_exact(&mut table)?;
+    if !all_zero(&sector[header_size..]) {
+        match reserved {
+            Reserved::MustBeZero => {
+                return Err(Error::InvalidStatic {
+                    message: "reserved header tail is not all empty",
+                })
+            }
+            Reserved::Tolerate => warnings.push(Finding {
+                severity: Severity::Warning,
+                code: if 1 == lba {
+                    "gpt-primary-tail"
+                } else {
+                    "gpt-backup-tail"
+                },
+                message: format!("{} header sector has data after the header", which),
+            }),
+        }
+    }
+
+    Ok(header)
+}
 
-    if table_crc != CRC.checksum(&table) {
+/// Turn the populated entries of a (validated) table into partitions.
+pub(crate) fn parse_entries(
+    table: &[u8],
+    header: &Header,
+    sector_size: u64,
+) -> Result<Vec<Partition>, Error> {
+    decode_entries(
+        table,
+        header,
+        sector_size,
+        &InvalidNames::Reject,
+        &NameNormalization::default(),
+        &mut Vec::new(),
+    )
+}
+
+/// [`parse_entries`], with names which aren't valid UTF-16 handled as requested,
+/// recording any which were tolerated in `warnings`, and then normalized.
+pub(crate) fn decode_entries(
+    table: &[u8],
+    header: &Header,
+    sector_size: u64,
+    names: &InvalidNames,
+    normalization: &NameNormalization,
+    warnings: &mut Vec<Finding>,
+) -> Result<Vec<Partition>, Error> {
+    let entry_size = usize::try_from(header.entry_size).map_err(|_| Error::Overflow)?;
+    if entry_size < 128 {
         return Err(Error::InvalidStatic {
-            message: "table crc invalid",
+            message: "entry size is implausibly small",
         });
     }
 
     let mut ret = Vec::with_capacity(16);
-    for id in 0..usize::from(entries) {
-        let entry_size = usize::from(entry_size);
-        let entry = &table[id * entry_size..(id + 1) * entry_size];
-        let type_uuid = &entry[0x00..0x10];
-        if all_zero(type_uuid) {
-            continue;
+    for (id, entry) in table.chunks_exact(entry_size).enumerate() {
+        if let Some(part) = decode_entry(
+            id,
+            entry,
+            header,
+            sector_size,
+            names,
+            normalization,
+            warnings,
+        )? {
+            ret.push(part);
         }
+    }
 
-        let type_uuid = type_uuid.try_into().expect("fixed size slice");
+    Ok(ret)
+}
 
-        let partition_uuid = entry[0x10..0x20].try_into().expect("fixed sized slice");
-        let first_lba = le::read_u64(&entry[0x20..0x28]);
-        let last_lba = le::read_u64(&entry[0x28..0x30]);
+/// Decode a single entry from a (validated) table, which is `None` if it's unused.
+pub(crate) fn decode_entry(
+    id: usize,
+    entry: &[u8],
+    header: &Header,
+    sector_size: u64,
+    names: &InvalidNames,
+    normalization: &NameNormalization,
+    warnings: &mut Vec<Finding>,
+) -> Result<Option<Partition>, Error> {
+    let type_uuid = &entry[0x00..0x10];
+    if all_zero(type_uuid) {
+        return Ok(None);
+    }
 
-        if first_lba > last_lba || first_lba < first_usable_lba || last_lba > last_usable_lba {
-            return Err(Error::InvalidStatic {
-                message: "partition entry is out of range",
+    let type_uuid = type_uuid.try_into().expect("fixed size slice");
+
+    let partition_uuid = entry[0x10..0x20].try_into().expect("fixed sized slice");
+    let first_lba = le::read_u64(&entry[0x20..0x28]);
+    let last_lba = le::read_u64(&entry[0x28..0x30]);
+
+    if first_lba > last_lba
+        || first_lba < header.first_usable_lba
+        || last_lba > header.last_usable_lba
+    {
+        return Err(Error::InvalidStatic {
+            message: "partition entry is out of range",
+        });
+    }
+
+    let attributes = entry[0x30..0x38].try_into().expect("fixed size slice");
+    let name_data = &entry[0x38..0x80];
+    let name_le: Vec<u16> = (0..(0x80 - 0x38) / 2)
+        .map(|idx| le::read_u16(&name_data[2 * idx..2 * (idx + 1)]))
+        .take_while(|val| 0 != *val)
+        .collect();
+
+    let name = match (String::from_utf16(&name_le), names) {
+        (Ok(name), _) => name,
+        (Err(e), InvalidNames::Reject) => {
+            return Err(Error::InvalidData {
+                message: format!("partition {} has an invalid name: {:?}", id, e),
             });
         }
-
-        let attributes = entry[0x30..0x38].try_into().expect("fixed size slice");
-        let name_data = &entry[0x38..0x80];
-        let name_le: Vec<u16> = (0..(0x80 - 0x38) / 2)
-            .map(|idx| le::read_u16(&name_data[2 * idx..2 * (idx + 1)]))
-            .take_while(|val| 0 != *val)
-            .collect();
-
-        let name = match String::from_utf16(&name_le) {
-            Ok(name) => name,
-            Err(e) => {
-                return Err(Error::InvalidData {
-                    message: format!("partition {} has an invalid name: {:?}", id, e),
-                });
+        (Err(_), tolerated) => {
+            warnings.push(Finding {
+                severity: Severity::Warning,
+                code: "gpt-name-invalid",
+                message: format!("partition {} has a name which isn't valid UTF-16", id),
+            });
+            match tolerated {
+                InvalidNames::Replace => String::from_utf16_lossy(&name_le),
+                _ => String::new(),
             }
-        };
+        }
+    };
+    let name = normalize_name(id, name, normalization)?;
 
-        ret.push(Partition {
-            id,
-            first_byte: first_lba * sector_size,
-            len: (last_lba - first_lba + 1) * sector_size,
-            attributes: Attributes::GPT {
-                type_uuid,
-                partition_uuid,
-                attributes,
-                name,
-            },
+    let first_byte = first_lba.checked_mul(sector_size).ok_or(Error::Overflow)?;
+    let len = (last_lba - first_lba)
+        .checked_add(1)
+        .and_then(|lbas| lbas.checked_mul(sector_size))
+        .ok_or(Error::Overflow)?;
+
+    Ok(Some(Partition {
+        id,
+        first_byte,
+        len,
+        attributes: Attributes::GPT {
+            type_uuid,
+            partition_uuid,
+            attributes,
+            name,
+            raw_name: name_data.to_vec(),
+            extra: entry[0x80..].to_vec(),
+        },
+        label: None,
+    }))
+}
+
+/// Tidy a decoded name as requested, in the order the fields of [`NameNormalization`]
+/// are listed.
+fn normalize_name(
+    id: usize,
+    name: String,
+    normalization: &NameNormalization,
+) -> Result<String, Error> {
+    #[cfg(feature = "nfc")]
+    let name = if normalization.nfc {
+        use unicode_normalization::UnicodeNormalization;
+        name.nfc().collect()
+    } else {
+        name
+    };
+
+    let name = if normalization.trim {
+        String::from(name.trim_end())
+    } else {
+        name
+    };
+
+    if normalization.reject_control && name.chars().any(char::is_control) {
+        return Err(Error::InvalidData {
+            message: format!(
+                "partition {} has a control character in its name: {:?}",
+                id, name
+            ),
         });
     }
 
-    Ok(ret)
+    Ok(name)
 }
 
 fn all_zero(val: &[u8]) -> bool {
@@ -0,0 +1,187 @@
+//! Type GUIDs for common kinds of partition, as stored on disc, for comparing with
+//! [`Attributes::GPT::type_uuid`](crate::Attributes::GPT::type_uuid), or creating
+//! partitions.
+//!
+//! ```rust
+//! use bootsector::gpt::guids;
+//! use bootsector::{Attributes, Partition};
+//!
+//! fn is_swap(part: &Partition) -> bool {
+//!     match &part.attributes {
+//!         Attributes::GPT { type_uuid, .. } => guids::LINUX_SWAP == *type_uuid,
+//!         Attributes::MBR { type_code, .. } => 0x82 == *type_code,
+//!     }
+//! }
+//! ```
+//!
+//! The text form of each is in its documentation; see [`guid`](crate::guid) for why the
+//! bytes aren't in the same order.
+
+/// An EFI system partition, which UEFI firmware boots from.
+///
+/// `C12A7328-F81F-11D2-BA4B-00A0C93EC93B`
+pub const ESP: [u8; 16] = [
+    0x28, 0x73, 0x2a, 0xc1, 0x1f, 0xf8, 0xd2, 0x11, 0xba, 0x4b, 0x00, 0xa0, 0xc9, 0x3e, 0xc9, 0x3b,
+];
+
+/// Space for GRUB's core image, on a BIOS machine booting from a GPT disc.
+///
+/// `21686148-6449-6E6F-744E-656564454649`
+pub const BIOS_BOOT: [u8; 16] = [
+    0x48, 0x61, 0x68, 0x21, 0x49, 0x64, 0x6f, 0x6e, 0x74, 0x4e, 0x65, 0x65, 0x64, 0x45, 0x46, 0x49,
+];
+
+// Linux
+
+/// Any Linux filesystem.
+///
+/// `0FC63DAF-8483-4772-8E79-3D69D8477DE4`
+pub const LINUX_FILESYSTEM: [u8; 16] = [
+    0xaf, 0x3d, 0xc6, 0x0f, 0x83, 0x84, 0x72, 0x47, 0x8e, 0x79, 0x3d, 0x69, 0xd8, 0x47, 0x7d, 0xe4,
+];
+
+/// Linux swap.
+///
+/// `0657FD6D-A4AB-43C4-84E5-0933C84B4F4F`
+pub const LINUX_SWAP: [u8; 16] = [
+    0x6d, 0xfd, 0x57, 0x06, 0xab, 0xa4, 0xc4, 0x43, 0x84, 0xe5, 0x09, 0x33, 0xc8, 0x4b, 0x4f, 0x4f,
+];
+
+/// An LVM physical volume.
+///
+/// `E6D6D379-F507-44C2-A23C-238F2A3DF928`
+pub const LINUX_LVM: [u8; 16] = [
+    0x79, 0xd3, 0xd6, 0xe6, 0x07, 0xf5, 0xc2, 0x44, 0xa2, 0x3c, 0x23, 0x8f, 0x2a, 0x3d, 0xf9, 0x28,
+];
+
+/// A LUKS encrypted volume.
+///
+/// `CA7D7CCB-63ED-4C53-861C-1742536059CC`
+pub const LINUX_LUKS: [u8; 16] = [
+    0xcb, 0x7c, 0x7d, 0xca, 0xed, 0x63, 0x53, 0x4c, 0x86, 0x1c, 0x17, 0x42, 0x53, 0x60, 0x59, 0xcc,
+];
+
+/// A Linux software RAID member.
+///
+/// `A19D880F-05FC-4D3B-A006-743F0F84911E`
+pub const LINUX_RAID: [u8; 16] = [
+    0x0f, 0x88, 0x9d, 0xa1, 0xfc, 0x05, 0x3b, 0x4d, 0xa0, 0x06, 0x74, 0x3f, 0x0f, 0x84, 0x91, 0x1e,
+];
+
+/// `/home`, from the Discoverable Partitions Specification.
+///
+/// `933AC7E1-2EB4-4F13-B844-0E14E2AEF915`
+pub const LINUX_HOME: [u8; 16] = [
+    0xe1, 0xc7, 0x3a, 0x93, 0xb4, 0x2e, 0x13, 0x4f, 0xb8, 0x44, 0x0e, 0x14, 0xe2, 0xae, 0xf9, 0x15,
+];
+
+/// The root filesystem for x86-64, from the Discoverable Partitions Specification.
+///
+/// `4F68BCE3-E8CD-4DB1-96E7-FBCAF984B709`
+pub const LINUX_ROOT_X86_64: [u8; 16] = [
+    0xe3, 0xbc, 0x68, 0x4f, 0xcd, 0xe8, 0xb1, 0x4d, 0x96, 0xe7, 0xfb, 0xca, 0xf9, 0x84, 0xb7, 0x09,
+];
+
+/// The root filesystem for AArch64, from the Discoverable Partitions Specification.
+///
+/// `B921B045-1DF0-41C3-AF44-4C6F280D3FAE`
+pub const LINUX_ROOT_AARCH64: [u8; 16] = [
+    0x45, 0xb0, 0x21, 0xb9, 0xf0, 0x1d, 0xc3, 0x41, 0xaf, 0x44, 0x4c, 0x6f, 0x28, 0x0d, 0x3f, 0xae,
+];
+
+/// `/boot`, as the Boot Loader Specification's extended boot loader partition.
+///
+/// `BC13C2FF-59E6-4262-A352-B275FD6F7172`
+pub const LINUX_EXTENDED_BOOT: [u8; 16] = [
+    0xff, 0xc2, 0x13, 0xbc, 0xe6, 0x59, 0x62, 0x42, 0xa3, 0x52, 0xb2, 0x75, 0xfd, 0x6f, 0x71, 0x72,
+];
+
+// Windows
+
+/// NTFS, exFAT or FAT; also used by Linux before it had its own type.
+///
+/// `EBD0A0A2-B9E5-4433-87C0-68B6B72699C7`
+pub const MS_BASIC_DATA: [u8; 16] = [
+    0xa2, 0xa0, 0xd0, 0xeb, 0xe5, 0xb9, 0x33, 0x44, 0x87, 0xc0, 0x68, 0xb6, 0xb7, 0x26, 0x99, 0xc7,
+];
+
+/// The Microsoft reserved partition, which holds nothing, but which Windows expects.
+///
+/// `E3C9E316-0B5C-4DB8-817D-F92DF00215AE`
+pub const MS_RESERVED: [u8; 16] = [
+    0x16, 0xe3, 0xc9, 0xe3, 0x5c, 0x0b, 0xb8, 0x4d, 0x81, 0x7d, 0xf9, 0x2d, 0xf0, 0x02, 0x15, 0xae,
+];
+
+/// The Windows recovery environment.
+///
+/// `DE94BBA4-06D1-4D40-A16A-BFD50179D6AC`
+pub const MS_RECOVERY: [u8; 16] = [
+    0xa4, 0xbb, 0x94, 0xde, 0xd1, 0x06, 0x40, 0x4d, 0xa1, 0x6a, 0xbf, 0xd5, 0x01, 0x79, 0xd6, 0xac,
+];
+
+/// The metadata of a Windows dynamic disc.
+///
+/// `5808C8AA-7E8F-42E0-85D2-E1E90434CFB3`
+pub const MS_LDM_METADATA: [u8; 16] = [
+    0xaa, 0xc8, 0x08, 0x58, 0x8f, 0x7e, 0xe0, 0x42, 0x85, 0xd2, 0xe1, 0xe9, 0x04, 0x34, 0xcf, 0xb3,
+];
+
+/// The data of a Windows dynamic disc.
+///
+/// `AF9B60A0-1431-4F62-BC68-3311714A69AD`
+pub const MS_LDM_DATA: [u8; 16] = [
+    0xa0, 0x60, 0x9b, 0xaf, 0x31, 0x14, 0x62, 0x4f, 0xbc, 0x68, 0x33, 0x11, 0x71, 0x4a, 0x69, 0xad,
+];
+
+// Apple
+
+/// An APFS container.
+///
+/// `7C3457EF-0000-11AA-AA11-00306543ECAC`
+pub const APPLE_APFS: [u8; 16] = [
+    0xef, 0x57, 0x34, 0x7c, 0x00, 0x00, 0xaa, 0x11, 0xaa, 0x11, 0x00, 0x30, 0x65, 0x43, 0xec, 0xac,
+];
+
+/// HFS+.
+///
+/// `48465300-0000-11AA-AA11-00306543ECAC`
+pub const APPLE_HFS: [u8; 16] = [
+    0x00, 0x53, 0x46, 0x48, 0x00, 0x00, 0xaa, 0x11, 0xaa, 0x11, 0x00, 0x30, 0x65, 0x43, 0xec, 0xac,
+];
+
+/// Apple's UFS.
+///
+/// `55465300-0000-11AA-AA11-00306543ECAC`
+pub const APPLE_UFS: [u8; 16] = [
+    0x00, 0x53, 0x46, 0x55, 0x00, 0x00, 0xaa, 0x11, 0xaa, 0x11, 0x00, 0x30, 0x65, 0x43, 0xec, 0xac,
+];
+
+/// Apple's boot partition, for e.g. booting from a RAID set.
+///
+/// `426F6F74-0000-11AA-AA11-00306543ECAC`
+pub const APPLE_BOOT: [u8; 16] = [
+    0x74, 0x6f, 0x6f, 0x42, 0x00, 0x00, 0xaa, 0x11, 0xaa, 0x11, 0x00, 0x30, 0x65, 0x43, 0xec, 0xac,
+];
+
+/// An Apple RAID member.
+///
+/// `52414944-0000-11AA-AA11-00306543ECAC`
+pub const APPLE_RAID: [u8; 16] = [
+    0x44, 0x49, 0x41, 0x52, 0x00, 0x00, 0xaa, 0x11, 0xaa, 0x11, 0x00, 0x30, 0x65, 0x43, 0xec, 0xac,
+];
+
+// Others
+
+/// FreeBSD's disklabel, in a partition.
+///
+/// `516E7CB4-6ECF-11D6-8FF8-00022D09712B`
+pub const FREEBSD_DATA: [u8; 16] = [
+    0xb4, 0x7c, 0x6e, 0x51, 0xcf, 0x6e, 0xd6, 0x11, 0x8f, 0xf8, 0x00, 0x02, 0x2d, 0x09, 0x71, 0x2b,
+];
+
+/// A ChromeOS kernel, one of the A/B slots.
+///
+/// `FE3A2A5D-4F32-41A7-B725-ACCC3285A309`
+pub const CHROMEOS_KERNEL: [u8; 16] = [
+    0x5d, 0x2a, 0x3a, 0xfe, 0x32, 0x4f, 0xa7, 0x41, 0xb7, 0x25, 0xac, 0xcc, 0x32, 0x85, 0xa3, 0x09,
+];
@@ -0,0 +1,71 @@
+//! The text form of GUIDs, e.g. `C12A7328-F81F-11D2-BA4B-00A0C93EC93B`, as used by
+//! `sfdisk`, `sgdisk`, and everyone else, converted to and from the layout on disc.
+//!
+//! GPT stores the first three fields of a GUID little-endian, and the rest big-endian, so
+//! the bytes on disc aren't the digits in order; e.g. the type of an EFI system partition
+//! is stored starting `28 73 2a c1`.
+//!
+//! ```rust
+//! use bootsector::guid;
+//!
+//! let esp = guid::parse("C12A7328-F81F-11D2-BA4B-00A0C93EC93B")?;
+//! assert_eq!([0x28, 0x73, 0x2a, 0xc1], esp[..4]);
+//! assert_eq!("C12A7328-F81F-11D2-BA4B-00A0C93EC93B", guid::to_string(&esp));
+//! # Ok::<(), bootsector::Error>(())
+//! ```
+
+use alloc::{format, string::String};
+use core::convert::TryFrom;
+
+use crate::Error;
+
+/// Format a GUID as stored on disc in the canonical text form.
+///
+/// GPT stores the first three fields of a GUID little-endian, and the rest big-endian.
+/// Digits are upper case, as `sfdisk` and `sgdisk` print them.
+pub fn to_string(b: &[u8; 16]) -> String {
+    format!(
+        "{:02X}{:02X}{:02X}{:02X}-{:02X}{:02X}-{:02X}{:02X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}",
+        b[3], b[2], b[1], b[0], b[5], b[4], b[7], b[6],
+        b[8], b[9], b[10], b[11], b[12], b[13], b[14], b[15]
+    )
+}
+
+/// Parse the canonical text form of a GUID into the on-disc layout. Digits may be upper
+/// or lower case, but there are no braces, or `urn:uuid:` prefixes.
+///
+/// Returns `InvalidStatic` if the text isn't a GUID.
+pub fn parse(text: &str) -> Result<[u8; 16], Error> {
+    parse_opt(text).ok_or(Error::InvalidStatic {
+        message: "not a GUID, in the form 01234567-89AB-CDEF-0123-456789ABCDEF",
+    })
+}
+
+fn parse_opt(text: &str) -> Option<[u8; 16]> {
+    let text = text.as_bytes();
+    if 36 != text.len() {
+        return None;
+    }
+
+    let mut digits = [0u8; 16];
+    let mut nibbles = 0;
+    for (pos, &c) in text.iter().enumerate() {
+        if 8 == pos || 13 == pos || 18 == pos || 23 == pos {
+            if b'-' != c {
+                return None;
+            }
+            continue;
+        }
+
+        let val = (c as char).to_digit(16)?;
+        let byte = &mut digits[nibbles / 2];
+        *byte = (*byte << 4) | u8::try_from(val).ok()?;
+        nibbles += 1;
+    }
+
+    let d = digits;
+    Some([
+        d[3], d[2], d[1], d[0], d[5], d[4], d[7], d[6], d[8], d[9], d[10], d[11], d[12], d[13],
+        d[14], d[15],
+    ])
+}
@@ -0,0 +1,114 @@
+//! Read an image from a web server, with HTTP range requests, so only the parts of the
+//! disc which are needed are fetched; listing the partitions of a raw image takes a few KiB.
+//!
+//! Each read is a request, so wrap the reader in a [`CachingReadAt`] to fetch whole
+//! blocks, and serve the many small reads from those.
+//!
+//! [`CachingReadAt`]: crate::io::CachingReadAt
+//!
+//! ```rust,no_run
+//! # fn go() -> Result<(), bootsector::Error> {
+//! let image = bootsector::http::HttpReadAt::open("https://example.com/disc.img")?;
+//! let disc = bootsector::io::CachingReadAt::new(image, 64 * 1024, 16);
+//! let table = bootsector::read_partition_table_sized(&disc, &Default::default())?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::TryFrom;
+use std::io::{self, Read};
+
+use snafu::ResultExt;
+
+use crate::errors::IoSnafu;
+use crate::Error;
+
+/// An image on a web server, readable as a disc.
+pub struct HttpReadAt {
+    agent: ureq::Agent,
+    url: String,
+    disk_len: u64,
+}
+
+impl HttpReadAt {
+    /// Find the length of the image, which also checks the server supports range requests.
+    pub fn open(url: &str) -> Result<HttpReadAt, Error> {
+        HttpReadAt::with_agent(ureq::Agent::new(), url)
+    }
+
+    /// As [`HttpReadAt::open`], with an agent configured as required, e.g. with a proxy,
+    /// timeouts, or extra certificates.
+    pub fn with_agent(agent: ureq::Agent, url: &str) -> Result<HttpReadAt, Error> {
+        let response = fetch(&agent, url, 0, 0).context(IoSnafu { pos: 0u64 })?;
+
+        // "bytes 0-0/12345"; the length is `*` if the server doesn't know it
+        let disk_len = response
+            .header("Content-Range")
+            .and_then(|range| range.rsplit('/').next())
+            .and_then(|len| len.parse().ok())
+            .ok_or(Error::InvalidStatic {
+                message: "the server didn't say how long the image is",
+            })?;
+
+        Ok(HttpReadAt {
+            agent,
+            url: url.to_string(),
+            disk_len,
+        })
+    }
+
+    /// The length of the image, in bytes.
+    pub fn disk_len(&self) -> u64 {
+        self.disk_len
+    }
+
+    /// Where the image is being read from.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+/// Request the inclusive range of bytes `first..=last`, insisting the server honours it.
+fn fetch(agent: &ureq::Agent, url: &str, first: u64, last: u64) -> io::Result<ureq::Response> {
+    let response = agent
+        .get(url)
+        .set("Range", &format!("bytes={}-{}", first, last))
+        .call()
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+    // a server which doesn't support ranges sends the whole image, with a 200
+    if 206 != response.status() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "expected a partial response to a range request, not {}",
+                response.status()
+            ),
+        ));
+    }
+
+    Ok(response)
+}
+
+impl positioned_io2::ReadAt for HttpReadAt {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        if pos >= self.disk_len || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let remaining = usize::try_from(self.disk_len - pos).unwrap_or(usize::MAX);
+        let buf_len = buf.len().min(remaining);
+        let buf = &mut buf[..buf_len];
+        let last = pos + u64::try_from(buf.len()).expect("usize fits") - 1;
+
+        let response = fetch(&self.agent, &self.url, pos, last)?;
+        response.into_reader().read_exact(buf)?;
+        Ok(buf.len())
+    }
+}
+
+impl positioned_io2::Size for HttpReadAt {
+    fn size(&self) -> io::Result<Option<u64>> {
+        Ok(Some(self.disk_len))
+    }
+}
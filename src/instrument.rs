@@ -0,0 +1,58 @@
+//! Events for the `tracing` feature, which compile to nothing without it.
+//!
+//! Reads are `trace` events; checks, and what was decided from them, are `debug` events.
+
+#[cfg(feature = "tracing")]
+macro_rules! debug {
+    ($($arg:tt)*) => { tracing::debug!($($arg)*) };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! debug {
+    ($($arg:tt)*) => {};
+}
+
+/// Enter a `debug` span, until the returned guard is dropped.
+#[cfg(feature = "tracing")]
+macro_rules! span {
+    ($($arg:tt)*) => { tracing::debug_span!($($arg)*).entered() };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! span {
+    ($($arg:tt)*) => {
+        crate::instrument::NoSpan
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct NoSpan;
+
+use crate::Error;
+
+/// Record that a check failed, passing its error on.
+#[cfg(feature = "tracing")]
+pub(crate) fn rejected(what: &'static str, e: Error) -> Error {
+    tracing::debug!(error = %e, "{} rejected", what);
+    e
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline]
+pub(crate) fn rejected(_what: &'static str, e: Error) -> Error {
+    e
+}
+
+/// A reader which records each read.
+#[cfg(feature = "tracing")]
+pub(crate) struct Traced<'r, R: ?Sized>(pub(crate) &'r R);
+
+#[cfg(feature = "tracing")]
+impl<'r, R: crate::io::ReadAt + ?Sized> crate::io::ReadAt for Traced<'r, R> {
+    fn read_exact_at(&self, pos: u64, buf: &mut [u8]) -> Result<(), Error> {
+        tracing::trace!(pos, len = buf.len(), "read");
+        self.0
+            .read_exact_at(pos, buf)
+            .map_err(|e| rejected("read", e))
+    }
+}
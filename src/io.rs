@@ -1,5 +1,25 @@
 use crate::Error;
 
+/// Read from a position in a disc image, filling the whole buffer, or failing.
+///
+/// `ReadAt` is object safe, so readers of different types can be kept behind a
+/// `Box<dyn ReadAt>`, and passed by reference, or boxed, anywhere a reader is accepted.
+///
+/// ```rust
+/// use bootsector::io::ReadAt;
+///
+/// # fn go(image: &'static [u8], use_image: bool) -> Result<(), bootsector::Error> {
+/// let disc: Box<dyn ReadAt> = if use_image {
+///     Box::new(image)
+/// } else {
+///     Box::new(bootsector::io::ReadFn(|_pos: u64, _buf: &mut [u8]| {
+///         Err(bootsector::Error::UnexpectedEof)
+///     }))
+/// };
+/// let partitions = bootsector::list_partitions(disc, &Default::default())?;
+/// # Ok(())
+/// # }
+/// ```
 pub trait ReadAt {
     fn read_exact_at(&self, pos: u64, buf: &mut [u8]) -> Result<(), Error>;
 }
@@ -14,12 +34,12 @@ impl<R: positioned_io2::ReadAt> ReadAt for R {
 }
 
 #[cfg(not(feature = "std"))]
-impl<'a> ReadAt for &'a [u8] {
+impl ReadAt for &[u8] {
     fn read_exact_at(&self, pos: u64, buf: &mut [u8]) -> Result<(), Error> {
         use core::convert::TryFrom;
         let read_len = u64::try_from(buf.len()).map_err(|_| Error::BiggerThanMemory)?;
         let self_len = u64::try_from(self.len()).map_err(|_| Error::BiggerThanMemory)?;
-        if pos + read_len > self_len {
+        if pos.checked_add(read_len).ok_or(Error::Overflow)? > self_len {
             return Err(Error::UnexpectedEof);
         }
         let start = usize::try_from(pos).map_err(|_| Error::BiggerThanMemory)?;
@@ -31,3 +51,703 @@ impl<'a> ReadAt for &'a [u8] {
         Ok(())
     }
 }
+
+#[cfg(not(feature = "std"))]
+impl ReadAt for &mut [u8] {
+    fn read_exact_at(&self, pos: u64, buf: &mut [u8]) -> Result<(), Error> {
+        (&**self).read_exact_at(pos, buf)
+    }
+}
+
+impl<'r> ReadAt for &'r (dyn ReadAt + 'r) {
+    fn read_exact_at(&self, pos: u64, buf: &mut [u8]) -> Result<(), Error> {
+        (**self).read_exact_at(pos, buf)
+    }
+}
+
+impl<'r> ReadAt for &'r (dyn ReadAt + Send + Sync + 'r) {
+    fn read_exact_at(&self, pos: u64, buf: &mut [u8]) -> Result<(), Error> {
+        (**self).read_exact_at(pos, buf)
+    }
+}
+
+impl<'r> ReadAt for alloc::boxed::Box<dyn ReadAt + 'r> {
+    fn read_exact_at(&self, pos: u64, buf: &mut [u8]) -> Result<(), Error> {
+        (**self).read_exact_at(pos, buf)
+    }
+}
+
+impl<'r> ReadAt for alloc::boxed::Box<dyn ReadAt + Send + Sync + 'r> {
+    fn read_exact_at(&self, pos: u64, buf: &mut [u8]) -> Result<(), Error> {
+        (**self).read_exact_at(pos, buf)
+    }
+}
+
+/// Read using a function which fills the whole buffer from the given position, or fails.
+///
+/// This suits sources which aren't files, and works without `std`; e.g. in the browser, a
+/// function calling out to JavaScript to read a slice of a `File` or `Blob`, with a
+/// `FileReaderSync` in a worker.
+///
+/// ```rust
+/// # fn go(image: &[u8]) -> Result<(), bootsector::Error> {
+/// let disc = bootsector::io::ReadFn(|pos: u64, buf: &mut [u8]| {
+///     let start = pos as usize;
+///     let data = image
+///         .get(start..start + buf.len())
+///         .ok_or(bootsector::Error::UnexpectedEof)?;
+///     buf.copy_from_slice(data);
+///     Ok(())
+/// });
+/// let partitions = bootsector::list_partitions(disc, &Default::default())?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ReadFn<F>(pub F);
+
+#[cfg(feature = "std")]
+impl<F> positioned_io2::ReadAt for ReadFn<F>
+where
+    F: Fn(u64, &mut [u8]) -> Result<(), Error>,
+{
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        (self.0)(pos, buf)?;
+        Ok(buf.len())
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<F> ReadAt for ReadFn<F>
+where
+    F: Fn(u64, &mut [u8]) -> Result<(), Error>,
+{
+    fn read_exact_at(&self, pos: u64, buf: &mut [u8]) -> Result<(), Error> {
+        (self.0)(pos, buf)
+    }
+}
+
+/// Somewhere disc images are fetched from a range of bytes at a time, such as an object
+/// store, or a network block device.
+///
+/// Wrap a source in a [`Remote`] to read from it; nothing else is needed, so
+/// implementations are usually a request and some error handling. The `s3` module, behind
+/// the feature of the same name, is an example.
+pub trait RemoteBlockSource {
+    /// The length of the image, in bytes.
+    fn size(&self) -> Result<u64, Error>;
+
+    /// Fetch `len` bytes starting at `pos`. Reads never extend past the [`size`].
+    ///
+    /// [`size`]: RemoteBlockSource::size
+    fn fetch(&self, pos: u64, len: usize) -> Result<alloc::vec::Vec<u8>, Error>;
+}
+
+/// Read from a [`RemoteBlockSource`].
+///
+/// Each read is a fetch, so wrap this in a [`CachingReadAt`] to fetch whole blocks, and
+/// serve the many small reads from those.
+///
+/// ```rust
+/// use bootsector::io::{Remote, RemoteBlockSource};
+/// use bootsector::Error;
+///
+/// struct Memory(Vec<u8>);
+///
+/// impl RemoteBlockSource for Memory {
+///     fn size(&self) -> Result<u64, Error> {
+///         Ok(self.0.len() as u64)
+///     }
+///
+///     fn fetch(&self, pos: u64, len: usize) -> Result<Vec<u8>, Error> {
+///         Ok(self.0[pos as usize..][..len].to_vec())
+///     }
+/// }
+///
+/// # fn go() -> Result<(), Error> {
+/// let disc = Remote::new(Memory(vec![0u8; 1024 * 1024]))?;
+/// assert!(bootsector::list_partitions(disc, &Default::default()).is_err());
+/// # Ok(())
+/// # }
+/// # go().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Remote<S> {
+    source: S,
+    len: u64,
+}
+
+impl<S: RemoteBlockSource> Remote<S> {
+    /// Wrap a source, asking for its length once, up front.
+    pub fn new(source: S) -> Result<Remote<S>, Error> {
+        let len = source.size()?;
+        Ok(Remote { source, len })
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        0 == self.len
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.source
+    }
+
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+
+    /// Fetch as much of the buffer as there is, which is nothing at or past the end.
+    fn fetch_into(&self, pos: u64, buf: &mut [u8]) -> Result<usize, Error> {
+        use core::convert::TryFrom;
+
+        let remaining = usize::try_from(self.len.saturating_sub(pos)).unwrap_or(usize::MAX);
+        let len = buf.len().min(remaining);
+        if 0 == len {
+            return Ok(0);
+        }
+
+        let data = self.source.fetch(pos, len)?;
+        if data.len() != len {
+            return Err(Error::UnexpectedEof);
+        }
+        buf[..len].copy_from_slice(&data);
+        Ok(len)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: RemoteBlockSource> positioned_io2::ReadAt for Remote<S> {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        Ok(self.fetch_into(pos, buf)?)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: RemoteBlockSource> positioned_io2::Size for Remote<S> {
+    fn size(&self) -> std::io::Result<Option<u64>> {
+        Ok(Some(self.len))
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<S: RemoteBlockSource> ReadAt for Remote<S> {
+    fn read_exact_at(&self, pos: u64, buf: &mut [u8]) -> Result<(), Error> {
+        if self.fetch_into(pos, buf)? != buf.len() {
+            return Err(Error::UnexpectedEof);
+        }
+        Ok(())
+    }
+}
+
+/// Adapt a reader which only accepts whole, aligned, sectors, such as a block device opened
+/// with `O_DIRECT`, or a raw Windows handle.
+///
+/// Every read passed to the inner reader is a multiple of `alignment` in offset, in length,
+/// and in the address of the buffer, via an internal bounce buffer. The small, unaligned,
+/// reads done when parsing headers can then be served.
+///
+/// ```rust,no_run
+/// # fn go() -> Result<(), bootsector::Error> {
+/// # let file = std::fs::File::open("/dev/sda").unwrap();
+/// let disc = bootsector::io::Aligned::new(file, 4096);
+/// let table = bootsector::read_partition_table(&disc, &Default::default())?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct Aligned<R> {
+    inner: R,
+    alignment: u64,
+}
+
+#[cfg(feature = "std")]
+impl<R> Aligned<R> {
+    /// Wrap a reader. `alignment` must be a power of two, typically the sector size,
+    /// or 4096 to be safe.
+    ///
+    /// # Panics
+    ///
+    /// If `alignment` is not a power of two.
+    pub fn new(inner: R, alignment: u64) -> Aligned<R> {
+        assert!(
+            alignment.is_power_of_two(),
+            "alignment must be a power of two"
+        );
+        Aligned { inner, alignment }
+    }
+
+    pub fn alignment(&self) -> u64 {
+        self.alignment
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: positioned_io2::ReadAt> positioned_io2::ReadAt for Aligned<R> {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        read_aligned(&self.inner, self.alignment, pos, buf)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: positioned_io2::Size> positioned_io2::Size for Aligned<R> {
+    fn size(&self) -> std::io::Result<Option<u64>> {
+        self.inner.size()
+    }
+}
+
+/// Read from a device which only accepts whole, aligned, sectors, via a bounce buffer.
+///
+/// The offset, the length, and the address of the buffer passed to `inner` are all
+/// multiples of `alignment`, which must be a power of two.
+#[cfg(feature = "std")]
+pub(crate) fn read_aligned<R>(
+    inner: &R,
+    alignment: u64,
+    pos: u64,
+    buf: &mut [u8],
+) -> std::io::Result<usize>
+where
+    R: positioned_io2::ReadAt + ?Sized,
+{
+    use std::convert::TryFrom;
+    use std::io;
+
+    if buf.is_empty() {
+        return Ok(0);
+    }
+
+    let too_big = || io::Error::new(io::ErrorKind::InvalidInput, "read is too large");
+    let len = u64::try_from(buf.len()).map_err(|_| too_big())?;
+    let align_mem = usize::try_from(alignment).map_err(|_| too_big())?;
+
+    let start = pos - pos % alignment;
+    let end = pos
+        .checked_add(len)
+        .and_then(|end| end.checked_add(alignment - 1))
+        .ok_or_else(too_big)?
+        / alignment
+        * alignment;
+    let bounce_len = usize::try_from(end - start).map_err(|_| too_big())?;
+
+    // over-allocate, so we can find a suitably aligned address inside
+    let mut storage = vec![0u8; bounce_len + align_mem];
+    let offset = (align_mem - storage.as_ptr() as usize % align_mem) % align_mem;
+    let bounce = &mut storage[offset..offset + bounce_len];
+
+    let mut filled = 0;
+    while filled < bounce.len() {
+        let at = start + u64::try_from(filled).map_err(|_| too_big())?;
+        match inner.read_at(at, &mut bounce[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(ref e) if io::ErrorKind::Interrupted == e.kind() => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    let skip = usize::try_from(pos - start).map_err(|_| too_big())?;
+    if filled <= skip {
+        return Ok(0);
+    }
+
+    let available = (filled - skip).min(buf.len());
+    buf[..available].copy_from_slice(&bounce[skip..skip + available]);
+    Ok(available)
+}
+
+pub trait WriteAt {
+    fn write_all_at(&mut self, pos: u64, buf: &[u8]) -> Result<(), Error>;
+
+    fn flush(&mut self) -> Result<(), Error>;
+}
+
+#[cfg(feature = "std")]
+impl<W: positioned_io2::WriteAt> WriteAt for W {
+    fn write_all_at(&mut self, pos: u64, buf: &[u8]) -> Result<(), Error> {
+        use crate::errors::IoSnafu;
+        use snafu::prelude::*;
+        positioned_io2::WriteAt::write_all_at(self, pos, buf).context(IoSnafu { pos })
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        use crate::errors::IoSnafu;
+        use snafu::prelude::*;
+        positioned_io2::WriteAt::flush(self).context(IoSnafu { pos: 0u64 })
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl WriteAt for &mut [u8] {
+    fn write_all_at(&mut self, pos: u64, buf: &[u8]) -> Result<(), Error> {
+        use core::convert::TryFrom;
+        let start = usize::try_from(pos).map_err(|_| Error::BiggerThanMemory)?;
+        let end = start
+            .checked_add(buf.len())
+            .ok_or(Error::BiggerThanMemory)?;
+        if end > self.len() {
+            return Err(Error::UnexpectedEof);
+        }
+
+        self[start..end].copy_from_slice(buf);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Keep recently read blocks in memory, so the same sectors aren't fetched repeatedly
+/// from a slow reader, e.g. when listing partitions, then verifying, then probing
+/// filesystems.
+///
+/// Blocks are evicted least-recently-used first. Data is assumed not to change underneath
+/// the cache.
+///
+/// ```rust
+/// # fn go() -> Result<(), bootsector::Error> {
+/// # let file = std::fs::File::open("/dev/null").unwrap();
+/// let disc = bootsector::io::CachingReadAt::new(file, 64 * 1024, 16);
+/// let table = bootsector::read_partition_table(&disc, &Default::default())?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct CachingReadAt<R> {
+    inner: R,
+    block_size: u64,
+    block_len: usize,
+    blocks: Recent,
+}
+
+#[cfg(feature = "std")]
+impl<R> CachingReadAt<R> {
+    /// Cache up to `capacity` blocks of `block_size` bytes each.
+    ///
+    /// # Panics
+    ///
+    /// If `block_size` or `capacity` are zero, or a block can't fit in memory.
+    pub fn new(inner: R, block_size: u64, capacity: usize) -> CachingReadAt<R> {
+        use std::convert::TryFrom;
+        assert_ne!(0, block_size, "block_size must be positive");
+        assert_ne!(0, capacity, "capacity must be positive");
+        let block_len = usize::try_from(block_size).expect("block_size is too large");
+        CachingReadAt {
+            inner,
+            block_size,
+            block_len,
+            blocks: Recent::new(capacity),
+        }
+    }
+
+    pub fn block_size(&self) -> u64 {
+        self.block_size
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.blocks.capacity
+    }
+
+    /// Forget everything, e.g. if the underlying data has been changed.
+    pub fn clear(&self) {
+        self.blocks.clear();
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: positioned_io2::ReadAt> CachingReadAt<R> {
+    /// Fetch a block, which is shorter than `block_size` at the end of the data.
+    fn block(&self, index: u64) -> std::io::Result<std::sync::Arc<Vec<u8>>> {
+        use std::io;
+
+        self.blocks.get_or_load(index, || {
+            let mut data = vec![0u8; self.block_len];
+            let start = index * self.block_size;
+            let mut filled = 0;
+            while filled < data.len() {
+                match self
+                    .inner
+                    .read_at(start + filled as u64, &mut data[filled..])
+                {
+                    Ok(0) => break,
+                    Ok(read) => filled += read,
+                    Err(ref e) if io::ErrorKind::Interrupted == e.kind() => continue,
+                    Err(e) => return Err(e),
+                }
+            }
+            data.truncate(filled);
+            Ok(data)
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: positioned_io2::ReadAt> positioned_io2::ReadAt for CachingReadAt<R> {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        use std::convert::TryFrom;
+        let mut done = 0;
+        while done < buf.len() {
+            let at = pos + done as u64;
+            let block = self.block(at / self.block_size)?;
+            let skip = usize::try_from(at % self.block_size).expect("less than block_len");
+            if block.len() <= skip {
+                break;
+            }
+            let available = (block.len() - skip).min(buf.len() - done);
+            buf[done..done + available].copy_from_slice(&block[skip..skip + available]);
+            done += available;
+            if block.len() != self.block_len {
+                break;
+            }
+        }
+        Ok(done)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: positioned_io2::Size> positioned_io2::Size for CachingReadAt<R> {
+    fn size(&self) -> std::io::Result<Option<u64>> {
+        self.inner.size()
+    }
+}
+
+/// Retry failed reads, as a flaky USB bridge, or network block device, will sometimes fail
+/// one read, which would otherwise fail the whole table.
+///
+/// Each retry waits twice as long as the one before, starting with `backoff`. By default,
+/// every error is retried except those which can't succeed on a second try, such as
+/// reading past the end, or a missing file; [`RetryingReadAt::retry_if`] replaces this.
+///
+/// ```rust
+/// # fn go() -> Result<(), bootsector::Error> {
+/// # let file = std::fs::File::open("/dev/null").unwrap();
+/// use std::time::Duration;
+///
+/// let disc = bootsector::io::RetryingReadAt::new(file, 3, Duration::from_millis(100))
+///     .retry_if(|e| e.raw_os_error() == Some(5));
+/// let table = bootsector::read_partition_table(&disc, &Default::default())?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "std")]
+pub struct RetryingReadAt<R> {
+    inner: R,
+    retries: usize,
+    backoff: std::time::Duration,
+    should_retry: Box<dyn Fn(&std::io::Error) -> bool + Send + Sync>,
+}
+
+#[cfg(feature = "std")]
+impl<R> RetryingReadAt<R> {
+    /// Try each read up to `retries` more times.
+    pub fn new(inner: R, retries: usize, backoff: std::time::Duration) -> RetryingReadAt<R> {
+        RetryingReadAt {
+            inner,
+            retries,
+            backoff,
+            should_retry: Box::new(is_transient),
+        }
+    }
+
+    /// Only retry errors for which `should_retry` is true.
+    pub fn retry_if<F>(self, should_retry: F) -> RetryingReadAt<R>
+    where
+        F: Fn(&std::io::Error) -> bool + Send + Sync + 'static,
+    {
+        RetryingReadAt {
+            should_retry: Box::new(should_retry),
+            ..self
+        }
+    }
+
+    pub fn retries(&self) -> usize {
+        self.retries
+    }
+
+    pub fn backoff(&self) -> std::time::Duration {
+        self.backoff
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+/// Could the same read succeed if tried again?
+#[cfg(feature = "std")]
+fn is_transient(e: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+    !matches!(
+        e.kind(),
+        ErrorKind::InvalidInput
+            | ErrorKind::InvalidData
+            | ErrorKind::NotFound
+            | ErrorKind::PermissionDenied
+            | ErrorKind::UnexpectedEof
+    )
+}
+
+#[cfg(feature = "std")]
+impl<R: positioned_io2::ReadAt> positioned_io2::ReadAt for RetryingReadAt<R> {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut wait = self.backoff;
+        let mut retries = 0;
+        loop {
+            match self.inner.read_at(pos, buf) {
+                Err(ref e) if retries < self.retries && (self.should_retry)(e) => {
+                    debug!(pos, error = %e, "retrying failed read");
+                    std::thread::sleep(wait);
+                    wait = wait.checked_mul(2).unwrap_or(wait);
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: positioned_io2::Size> positioned_io2::Size for RetryingReadAt<R> {
+    fn size(&self) -> std::io::Result<Option<u64>> {
+        self.inner.size()
+    }
+}
+
+/// A handful of recently used blocks, for the readers which decode or fetch whole blocks.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub(crate) struct Recent {
+    capacity: usize,
+    /// most recently used at the end
+    blocks: std::sync::Mutex<Vec<(u64, std::sync::Arc<Vec<u8>>)>>,
+}
+
+#[cfg(feature = "std")]
+impl Recent {
+    pub(crate) fn new(capacity: usize) -> Recent {
+        Recent {
+            capacity,
+            blocks: std::sync::Mutex::new(Vec::with_capacity(capacity)),
+        }
+    }
+
+    pub(crate) fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// The block for `key`, calling `load` if it isn't already present.
+    ///
+    /// The lock is not held during `load`, so two threads may both load a block.
+    pub(crate) fn get_or_load<F>(
+        &self,
+        key: u64,
+        load: F,
+    ) -> std::io::Result<std::sync::Arc<Vec<u8>>>
+    where
+        F: FnOnce() -> std::io::Result<Vec<u8>>,
+    {
+        {
+            let mut blocks = self.lock();
+            if let Some(found) = blocks.iter().position(|(k, _)| *k == key) {
+                let entry = blocks.remove(found);
+                let data = entry.1.clone();
+                blocks.push(entry);
+                return Ok(data);
+            }
+        }
+
+        let data = std::sync::Arc::new(load()?);
+
+        let mut blocks = self.lock();
+        if !blocks.iter().any(|(k, _)| *k == key) {
+            if blocks.len() >= self.capacity {
+                blocks.remove(0);
+            }
+            blocks.push((key, data.clone()));
+        }
+        Ok(data)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<(u64, std::sync::Arc<Vec<u8>>)>> {
+        // the cache is always consistent, so a panic elsewhere doesn't matter
+        match self.blocks.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+/// The bytes of a reader from `start`, for `len` bytes, e.g. a partition, read as if
+/// it were a disc of its own.
+pub(crate) struct Within<'r, R: ?Sized> {
+    pub(crate) inner: &'r R,
+    pub(crate) start: u64,
+    pub(crate) len: u64,
+}
+
+impl<'r, R: ReadAt + ?Sized> ReadAt for Within<'r, R> {
+    fn read_exact_at(&self, pos: u64, buf: &mut [u8]) -> Result<(), Error> {
+        use core::convert::TryFrom;
+        let read_len = u64::try_from(buf.len()).map_err(|_| Error::BiggerThanMemory)?;
+        if pos.checked_add(read_len).ok_or(Error::Overflow)? > self.len {
+            return Err(Error::UnexpectedEof);
+        }
+        let pos = self.start.checked_add(pos).ok_or(Error::Overflow)?;
+        self.inner.read_exact_at(pos, buf)
+    }
+}
+
+/// A reader which counts what's read through it, for [`crate::Stats`].
+pub(crate) struct Counted<'r, R: ?Sized> {
+    inner: &'r R,
+    reads: core::cell::Cell<usize>,
+    bytes: core::cell::Cell<u64>,
+}
+
+impl<'r, R: ?Sized> Counted<'r, R> {
+    pub(crate) fn new(inner: &'r R) -> Self {
+        Counted {
+            inner,
+            reads: core::cell::Cell::new(0),
+            bytes: core::cell::Cell::new(0),
+        }
+    }
+
+    /// The number of reads, and the bytes they asked for.
+    pub(crate) fn counts(&self) -> (usize, u64) {
+        (self.reads.get(), self.bytes.get())
+    }
+}
+
+impl<'r, R: ReadAt + ?Sized> ReadAt for Counted<'r, R> {
+    fn read_exact_at(&self, pos: u64, buf: &mut [u8]) -> Result<(), Error> {
+        self.reads.set(self.reads.get() + 1);
+        self.bytes.set(self.bytes.get() + buf.len() as u64);
+        self.inner.read_exact_at(pos, buf)
+    }
+}
@@ -0,0 +1,176 @@
+//! Recognise isohybrid images: CD images, with an ISO 9660 filesystem, which also have an
+//! MBR, or a GPT, or both, so they can be written to a USB stick and booted.
+//!
+//! Most Linux installers are built like this, by `isohybrid` or `xorriso`. Their tables
+//! are unusual: the first MBR partition usually starts at byte 0, covering the whole ISO
+//! filesystem, and so overlaps the EFI system partition; and the GPT, if there is one,
+//! may not have a protective MBR, so isn't found by a normal read. This reads each table
+//! on its own.
+//!
+//! ```rust
+//! # fn go(disc: &[u8]) -> Result<(), bootsector::Error> {
+//! match bootsector::iso::read_isohybrid(disc)? {
+//!     Some(iso) => println!(
+//!         "isohybrid {:?}: mbr {}, gpt {}",
+//!         iso.volume.volume_id,
+//!         iso.mbr.is_some(),
+//!         iso.gpt.is_some()
+//!     ),
+//!     None => println!("not an isohybrid image"),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::{string::String, vec};
+
+use snafu::ResultExt;
+
+use crate::errors::ReadSnafu;
+use crate::{io, le, Error, Options, PartitionTable, ReadGPT, ReadMBR, Strictness};
+
+/// ISO 9660's volume descriptors start at sector 16, of 2048 bytes.
+const DESCRIPTORS: u64 = 16 * 2048;
+
+/// Where the volume descriptor set should have ended by; it's usually a handful long.
+const MAX_DESCRIPTORS: u64 = 32;
+
+/// What the ISO 9660 primary volume descriptor says about the filesystem.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PrimaryVolume {
+    /// The name of the volume, e.g. `Ubuntu 22.04 LTS amd64`, without its padding.
+    pub volume_id: String,
+
+    /// How long the filesystem says it is, in bytes. The image may be longer, e.g. with
+    /// an appended partition.
+    pub len: u64,
+}
+
+/// An isohybrid image, from [`read_isohybrid`]: the ISO filesystem, and the tables
+/// which were read around it. At least one of the tables is present.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Isohybrid {
+    pub volume: PrimaryVolume,
+
+    /// The MBR, read literally, even if it's protective, or hybrid.
+    pub mbr: Option<PartitionTable>,
+
+    /// The GPT, which is looked for at LBA 1 even if the MBR doesn't point to it.
+    pub gpt: Option<PartitionTable>,
+}
+
+/// Look for an ISO 9660 primary volume descriptor, at 32KiB.
+///
+/// `Ok(None)` if there isn't one, including if the reader is too short to hold one.
+pub fn read_primary_volume<R>(reader: R) -> Result<Option<PrimaryVolume>, Error>
+where
+    R: io::ReadAt,
+{
+    primary_volume(&reader)
+}
+
+pub(crate) fn primary_volume<R>(reader: &R) -> Result<Option<PrimaryVolume>, Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    let mut descriptor = vec![0u8; 2048];
+    for index in 0..MAX_DESCRIPTORS {
+        let pos = DESCRIPTORS + index * 2048;
+        match reader.read_exact_at(pos, &mut descriptor) {
+            Ok(()) => (),
+            Err(e) if is_eof(&e) => return Ok(None),
+            Err(e) => {
+                return Err(e).context(ReadSnafu {
+                    what: "an iso 9660 volume descriptor",
+                    pos,
+                })
+            }
+        }
+
+        if b"CD001" != &descriptor[1..6] || 1 != descriptor[6] {
+            return Ok(None);
+        }
+
+        match descriptor[0] {
+            // the primary volume descriptor
+            1 => (),
+            // the set terminator
+            255 => return Ok(None),
+            // e.g. an El Torito boot record, or a Joliet supplementary descriptor
+            _ => continue,
+        }
+
+        // both-endian fields, of which we take the little-endian half
+        let blocks = u64::from(le::read_u32(&descriptor[80..]));
+        let block_size = u64::from(le::read_u16(&descriptor[128..]));
+        let volume_id = String::from_utf8_lossy(&descriptor[40..72])
+            .trim_end_matches(|c| ' ' == c || '\0' == c)
+            .into();
+
+        return Ok(Some(PrimaryVolume {
+            volume_id,
+            len: blocks.checked_mul(block_size).ok_or(Error::Overflow)?,
+        }));
+    }
+
+    Ok(None)
+}
+
+fn is_eof(e: &Error) -> bool {
+    match e {
+        Error::UnexpectedEof => true,
+        #[cfg(feature = "std")]
+        Error::Io { source, .. } => std::io::ErrorKind::UnexpectedEof == source.kind(),
+        _ => false,
+    }
+}
+
+/// Read an isohybrid image: an ISO 9660 filesystem, with an MBR, or a GPT, or both.
+///
+/// `Ok(None)` if there's no ISO filesystem, or neither table can be read. The tables
+/// are read as [`Strictness::Permissive`] would, as these images are made by tools
+/// which don't follow the specifications closely; anything tolerated is in each
+/// table's `warnings`. The tables aren't checked against the length of the image.
+pub fn read_isohybrid<R>(reader: R) -> Result<Option<Isohybrid>, Error>
+where
+    R: io::ReadAt,
+{
+    isohybrid(&reader)
+}
+
+pub(crate) fn isohybrid<R>(reader: &R) -> Result<Option<Isohybrid>, Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    let volume = match primary_volume(reader)? {
+        Some(volume) => volume,
+        None => return Ok(None),
+    };
+
+    let mbr = crate::read_borrowed(
+        reader,
+        &Options {
+            gpt: ReadGPT::Never,
+            ..Options::with_strictness(Strictness::Permissive)
+        },
+        None,
+    );
+
+    let gpt = crate::read_borrowed(
+        reader,
+        &Options {
+            mbr: ReadMBR::Never,
+            // xorriso's GPTs are for 512 byte sectors, even on a CD
+            gpt_search_offsets: vec![512],
+            ..Options::with_strictness(Strictness::Permissive)
+        },
+        None,
+    );
+
+    let (mbr, gpt) = (mbr.ok(), gpt.ok());
+    if mbr.is_none() && gpt.is_none() {
+        return Ok(None);
+    }
+
+    Ok(Some(Isohybrid { volume, mbr, gpt }))
+}
@@ -0,0 +1,206 @@
+//! A stable, serialisable representation of a [`PartitionTable`].
+//!
+//! The types in here exist only to be serialised; their shape is the schema, and
+//! changes to it will bump [`SCHEMA_VERSION`]. GUIDs are rendered in their canonical
+//! text form, e.g. `C12A7328-F81F-11D2-BA4B-00A0C93EC93B`.
+//!
+//! ```rust
+//! # fn go(reader: &[u8]) -> Result<(), bootsector::Error> {
+//! let table = bootsector::read_partition_table(reader, &Default::default())?;
+//! let json = bootsector::json::Table::from(&table);
+//! // serde_json::to_string(&json)
+//! # Ok(())
+//! # }
+//! ```
+
+#[cfg(feature = "scan")]
+use alloc::string::ToString;
+use alloc::{string::String, vec::Vec};
+
+use serde::Serialize;
+
+use crate::{guid, Attributes, Partition, PartitionTable, TableType};
+
+/// The version of the schema produced by this module.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A whole partition table.
+#[derive(Debug, Clone, Serialize)]
+pub struct Table<'t> {
+    /// Always [`SCHEMA_VERSION`].
+    pub schema_version: u32,
+
+    /// `"mbr"` or `"gpt"`.
+    pub table_type: &'static str,
+
+    pub sector_size: u64,
+
+    pub disk_signature: u32,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disk_guid: Option<String>,
+
+    pub partitions: Vec<Entry<'t>>,
+}
+
+/// A single partition. Fields which only apply to the other table type are omitted.
+#[derive(Debug, Clone, Serialize)]
+pub struct Entry<'t> {
+    pub id: usize,
+    pub first_byte: u64,
+    pub len: u64,
+
+    /// MBR only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bootable: Option<bool>,
+
+    /// MBR only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub type_code: Option<u8>,
+
+    /// GPT only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub type_guid: Option<String>,
+
+    /// GPT only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partition_guid: Option<String>,
+
+    /// GPT only; the attribute field, as a little-endian integer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<u64>,
+
+    /// GPT only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<&'t str>,
+}
+
+impl<'t> From<&'t PartitionTable> for Table<'t> {
+    fn from(table: &'t PartitionTable) -> Self {
+        Table {
+            schema_version: SCHEMA_VERSION,
+            table_type: match table.table_type {
+                TableType::MBR => "mbr",
+                TableType::GPT => "gpt",
+            },
+            sector_size: table.sector_size,
+            disk_signature: table.disk_signature,
+            disk_guid: table.disk_guid.as_ref().map(guid::to_string),
+            partitions: table.partitions.iter().map(Entry::from).collect(),
+        }
+    }
+}
+
+impl<'t> From<&'t Partition> for Entry<'t> {
+    fn from(part: &'t Partition) -> Self {
+        let mut entry = Entry {
+            id: part.id,
+            first_byte: part.first_byte,
+            len: part.len,
+            bootable: None,
+            type_code: None,
+            type_guid: None,
+            partition_guid: None,
+            attributes: None,
+            name: None,
+        };
+
+        match &part.attributes {
+            Attributes::MBR {
+                bootable,
+                type_code,
+            } => {
+                entry.bootable = Some(*bootable);
+                entry.type_code = Some(*type_code);
+            }
+            Attributes::GPT {
+                type_uuid,
+                partition_uuid,
+                attributes,
+                name,
+                ..
+            } => {
+                entry.type_guid = Some(guid::to_string(type_uuid));
+                entry.partition_guid = Some(guid::to_string(partition_uuid));
+                entry.attributes = Some(u64::from_le_bytes(*attributes));
+                entry.name = Some(name);
+            }
+        }
+
+        entry
+    }
+}
+
+/// Every image in a directory, from [`scan_dir`](crate::scan::scan_dir).
+#[cfg(feature = "scan")]
+#[derive(Debug, Clone, Serialize)]
+pub struct Scan {
+    pub images: Vec<ScannedImage>,
+    pub summary: ScanSummary,
+}
+
+/// How many images were of each class, as [`Summary`](crate::scan::Summary).
+#[cfg(feature = "scan")]
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanSummary {
+    pub mbr: usize,
+    pub gpt: usize,
+    pub unpartitioned: usize,
+    pub failed: usize,
+    pub partitions: usize,
+}
+
+/// One image in a [`Scan`]. Exactly one of `partitions` and `error` is present.
+#[cfg(feature = "scan")]
+#[derive(Debug, Clone, Serialize)]
+pub struct ScannedImage {
+    pub path: String,
+
+    /// `"mbr"`, `"gpt"`, `"unpartitioned"` or `"failed"`.
+    pub class: &'static str,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partitions: Option<usize>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[cfg(feature = "scan")]
+impl From<&crate::scan::Report> for Scan {
+    fn from(report: &crate::scan::Report) -> Self {
+        Scan {
+            images: report.images.iter().map(ScannedImage::from).collect(),
+            summary: ScanSummary::from(&report.summary()),
+        }
+    }
+}
+
+#[cfg(feature = "scan")]
+impl From<&crate::scan::Scanned> for ScannedImage {
+    fn from(image: &crate::scan::Scanned) -> Self {
+        let (partitions, error) = match &image.result {
+            Ok(table) => (Some(table.partitions.len()), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        ScannedImage {
+            path: image.path.to_string_lossy().into_owned(),
+            class: image.class().name(),
+            partitions,
+            error,
+        }
+    }
+}
+
+#[cfg(feature = "scan")]
+impl From<&crate::scan::Summary> for ScanSummary {
+    fn from(summary: &crate::scan::Summary) -> Self {
+        ScanSummary {
+            mbr: summary.mbr,
+            gpt: summary.gpt,
+            unpartitioned: summary.unpartitioned,
+            failed: summary.failed,
+            partitions: summary.partitions,
+        }
+    }
+}
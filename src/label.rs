@@ -0,0 +1,212 @@
+//! Find the volume label of the filesystem inside a partition.
+//!
+//! MBR entries have no name, so this is usually the only way to tell MBR partitions
+//! apart. FAT, NTFS and ext2/3/4 are recognised. Set [`Options::probe_labels`] to fill
+//! in [`Partition::label`] while reading the table.
+//!
+//! [`Options::probe_labels`]: crate::Options::probe_labels
+//!
+//! ```rust
+//! # fn go(disc: &[u8]) -> Result<(), bootsector::Error> {
+//! for part in bootsector::list_partitions(disc, &Default::default())? {
+//!     let label = bootsector::label::probe_label(disc, &part)?;
+//!     println!("{}: {}", part.id, label.as_deref().unwrap_or("(no label)"));
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::{string::String, vec, vec::Vec};
+use core::convert::TryFrom;
+
+use snafu::ResultExt;
+
+use crate::errors::ReadSnafu;
+
+use crate::{io, le, Error, Partition};
+
+/// Read the label of the filesystem in a partition.
+///
+/// `Ok(None)` if the filesystem isn't one we recognise, or has no label. Errors are only
+/// returned if the reader fails, or the filesystem's own structures are out of range.
+pub fn probe_label<R>(reader: R, part: &Partition) -> Result<Option<String>, Error>
+where
+    R: io::ReadAt,
+{
+    probe(&reader, part)
+}
+
+pub(crate) fn probe<R>(reader: &R, part: &Partition) -> Result<Option<String>, Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    probe_at(reader, part.first_byte, part.len)
+}
+
+/// Read the label of a filesystem which is `len` bytes long, starting at `first_byte`.
+pub(crate) fn probe_at<R>(reader: &R, first_byte: u64, len: u64) -> Result<Option<String>, Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    // enough for the boot sector, and the ext superblock at 1024
+    let mut start = [0u8; 2048];
+    if len < start.len() as u64 {
+        return Ok(None);
+    }
+    reader
+        .read_exact_at(first_byte, &mut start)
+        .context(ReadSnafu {
+            what: "the start of the partition",
+            pos: first_byte,
+        })?;
+
+    if b"NTFS    " == &start[3..11] {
+        return ntfs(reader, first_byte, len, &start);
+    }
+
+    if 0xEF53 == le::read_u16(&start[1024 + 0x38..]) {
+        return Ok(ext(&start[1024..]));
+    }
+
+    if 0x55 == start[510] && 0xAA == start[511] {
+        return Ok(fat(&start));
+    }
+
+    Ok(None)
+}
+
+/// The label in the extended BIOS parameter block, which is further in on FAT32.
+///
+/// The label is also stored in the root directory, which is what Windows shows; tools
+/// which set it keep both copies in step.
+fn fat(boot: &[u8]) -> Option<String> {
+    let sectors_per_fat_16 = le::read_u16(&boot[0x16..]);
+    let (signature, label, fs_type) = if 0 == sectors_per_fat_16 {
+        (boot[0x42], &boot[0x47..0x52], &boot[0x52..0x57])
+    } else {
+        (boot[0x26], &boot[0x2b..0x36], &boot[0x36..0x3b])
+    };
+
+    if 0x29 != signature || b"FAT" != &fs_type[..3] {
+        return None;
+    }
+
+    // an unlabelled volume is given this label by Windows
+    if b"NO NAME    " == label {
+        return None;
+    }
+
+    non_empty(String::from_utf8_lossy(label).trim_end())
+}
+
+/// The volume name in the superblock, which is nul-padded, and (by convention) UTF-8.
+fn ext(superblock: &[u8]) -> Option<String> {
+    let name = &superblock[0x78..0x88];
+    let len = name.iter().position(|b| 0 == *b).unwrap_or(name.len());
+    non_empty(&String::from_utf8_lossy(&name[..len]))
+}
+
+/// The `$VOLUME_NAME` attribute of the `$Volume` file, the fourth record in the MFT.
+fn ntfs<R>(reader: &R, first_byte: u64, len: u64, boot: &[u8]) -> Result<Option<String>, Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    let bytes_per_sector = u64::from(le::read_u16(&boot[0x0b..]));
+    let cluster = bytes_per_sector * u64::from(boot[0x0d]);
+    let mft_cluster = le::read_u64(&boot[0x30..]);
+
+    // positive values count clusters; negative ones are log2 of the size in bytes
+    let record_len = match boot[0x40] {
+        n @ 0..=0x7f => cluster * u64::from(n),
+        n => 1u64.checked_shl(u32::from(n.wrapping_neg())).unwrap_or(0),
+    };
+
+    if 0 == bytes_per_sector || !(0x100..=64 * 1024).contains(&record_len) {
+        return Ok(None);
+    }
+
+    let record_pos = mft_cluster
+        .checked_mul(cluster)
+        .and_then(|mft| mft.checked_add(3 * record_len))
+        .filter(|pos| pos.saturating_add(record_len) <= len)
+        .ok_or(Error::InvalidStatic {
+            message: "ntfs $Volume record is outside the partition",
+        })?;
+
+    let mut record = vec![0u8; usize::try_from(record_len).expect("at most 64KiB")];
+    let pos = first_byte + record_pos;
+    reader.read_exact_at(pos, &mut record).context(ReadSnafu {
+        what: "the ntfs $Volume record",
+        pos,
+    })?;
+
+    if b"FILE" != &record[..4] || !apply_fixups(&mut record, bytes_per_sector) {
+        return Ok(None);
+    }
+
+    let mut offset = usize::from(le::read_u16(&record[0x14..]));
+    while offset + 0x18 <= record.len() {
+        let attribute = &record[offset..];
+        let kind = le::read_u32(attribute);
+        let len = usize::try_from(le::read_u32(&attribute[4..])).unwrap_or(0);
+        if 0xffff_ffff == kind || len < 0x18 || len > attribute.len() {
+            break;
+        }
+
+        // VOLUME_NAME, which is always resident
+        if 0x60 == kind && 0 == attribute[8] {
+            let value_len = usize::try_from(le::read_u32(&attribute[0x10..])).unwrap_or(0);
+            let value_offset = usize::from(le::read_u16(&attribute[0x14..]));
+            let value = value_offset
+                .checked_add(value_len)
+                .and_then(|value_end| attribute[..len].get(value_offset..value_end));
+            let value = match value {
+                Some(value) => value,
+                None => return Ok(None),
+            };
+
+            let units: Vec<u16> = value.chunks_exact(2).map(le::read_u16).collect();
+            return Ok(non_empty(&String::from_utf16_lossy(&units)));
+        }
+
+        offset += len;
+    }
+
+    Ok(None)
+}
+
+/// Restore the last two bytes of each sector of an MFT record, which are replaced on
+/// disc by a sequence number, to detect torn writes. False if the record is damaged.
+fn apply_fixups(record: &mut [u8], bytes_per_sector: u64) -> bool {
+    let usa_offset = usize::from(le::read_u16(&record[0x04..]));
+    let usa_count = usize::from(le::read_u16(&record[0x06..]));
+    let sector = match usize::try_from(bytes_per_sector) {
+        Ok(sector) if sector >= 2 => sector,
+        _ => return false,
+    };
+
+    if 0 == usa_count || usa_offset + 2 * usa_count > record.len() {
+        return false;
+    }
+
+    let sequence = [record[usa_offset], record[usa_offset + 1]];
+    for i in 1..usa_count {
+        let end = i * sector;
+        if end > record.len() || record[end - 2..end] != sequence {
+            return false;
+        }
+        let at = usa_offset + 2 * i;
+        let original = [record[at], record[at + 1]];
+        record[end - 2..end].copy_from_slice(&original);
+    }
+
+    true
+}
+
+fn non_empty(label: &str) -> Option<String> {
+    if label.is_empty() {
+        None
+    } else {
+        Some(String::from(label))
+    }
+}
@@ -40,20 +40,87 @@
 
 extern crate alloc;
 
-use alloc::{string::String, vec::Vec};
+use alloc::{boxed::Box, format, string::String, vec, vec::Vec};
+use core::convert::TryFrom;
 
+use snafu::ResultExt;
+
+use crate::errors::ReadSnafu;
+
+// first, so its macros are available to every other module
+#[macro_use]
+mod instrument;
+
+#[cfg(any(feature = "async-futures", feature = "async-tokio"))]
+pub mod asynchronous;
+pub mod attributes;
+#[cfg(any(feature = "qcow2", feature = "vhd"))]
+mod be;
+pub mod bios;
+pub mod boot;
+#[cfg(feature = "build")]
+pub mod build;
+pub mod content;
+pub mod convert;
+#[cfg(feature = "discover")]
+pub mod discover;
+pub mod edit;
 mod errors;
+#[cfg(feature = "ewf")]
+pub mod ewf;
+pub mod fuzz;
 pub mod gpt;
+pub mod guid;
+#[cfg(feature = "http")]
+pub mod http;
 pub mod io;
+pub mod iso;
+#[cfg(feature = "serde")]
+pub mod json;
+pub mod label;
 mod le;
+#[cfg(all(feature = "linux", target_os = "linux"))]
+pub mod linux;
+#[cfg(all(feature = "macos", target_os = "macos"))]
+pub mod macos;
 pub mod mbr;
+pub mod minix;
+#[cfg(all(feature = "mmap", unix))]
+pub mod mmap;
+#[cfg(feature = "qcow2")]
+pub mod qcow2;
+pub mod recover;
+pub mod render;
+#[cfg(feature = "s3")]
+pub mod s3;
+#[cfg(feature = "scan")]
+pub mod scan;
+#[cfg(feature = "seekable")]
+pub mod seekable;
+pub mod sfdisk;
+pub mod sgdisk;
+pub mod solaris;
+#[cfg(feature = "std")]
+pub mod stream;
+pub mod superfloppy;
+#[cfg(feature = "vdi")]
+pub mod vdi;
+pub mod verify;
+#[cfg(feature = "vhd")]
+pub mod vhd;
+#[cfg(feature = "vhd")]
+pub mod vhdx;
+#[cfg(feature = "vmdk")]
+pub mod vmdk;
+#[cfg(all(feature = "windows", windows))]
+pub mod windows;
 
 pub use crate::errors::Error;
 #[cfg(feature = "positioned-io2")]
 pub use positioned_io2 as pio;
 
 /// Table-specific information about a partition.
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
 pub enum Attributes {
     MBR {
         bootable: bool,
@@ -64,11 +131,20 @@ pub enum Attributes {
         partition_uuid: [u8; 16],
         attributes: [u8; 8],
         name: String,
+        /// The name field exactly as it was stored: 72 bytes of UTF-16LE, including
+        /// anything after the terminating nul, for writing the entry back unchanged.
+        /// For partitions which weren't read from a disc, this is `name`, padded with nuls.
+        raw_name: Vec<u8>,
+        /// Any bytes in the entry beyond the 128 defined by the specification, which
+        /// some tools use for their own purposes. Almost always empty.
+        extra: Vec<u8>,
     },
 }
 
 /// An entry in the partition table.
-#[derive(Debug, Clone, Eq, PartialEq)]
+///
+/// Partitions are ordered by their fields, in order, so by `id` first.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
 pub struct Partition {
     /// The number of this partition, 0-indexed.
     pub id: usize,
@@ -81,6 +157,251 @@ pub struct Partition {
 
     /// Table-specific attributes about this partition.
     pub attributes: Attributes,
+
+    /// The label of the filesystem inside the partition, if [`Options::probe_labels`]
+    /// was set, and one was found.
+    pub label: Option<String>,
+}
+
+impl Partition {
+    /// The bytes of the disc the partition covers, from `first_byte`, up to but not
+    /// including `first_byte + len`, clamped to `u64::MAX`.
+    pub fn byte_range(&self) -> core::ops::Range<u64> {
+        self.first_byte..self.end_byte()
+    }
+
+    /// The byte after the end of the partition, `first_byte + len`, clamped to `u64::MAX`
+    /// for partitions which claim to extend beyond it.
+    pub fn end_byte(&self) -> u64 {
+        self.first_byte.saturating_add(self.len)
+    }
+
+    /// The last byte in the partition, or `None` if it's empty.
+    pub fn last_byte(&self) -> Option<u64> {
+        if 0 == self.len {
+            None
+        } else {
+            Some(self.end_byte() - 1)
+        }
+    }
+
+    /// Is this byte of the disc inside the partition?
+    pub fn contains(&self, byte_offset: u64) -> bool {
+        self.byte_range().contains(&byte_offset)
+    }
+
+    /// Do the two partitions share any bytes? An empty partition overlaps nothing.
+    pub fn overlaps(&self, other: &Partition) -> bool {
+        let (ours, theirs) = (self.byte_range(), other.byte_range());
+        ours.start.max(theirs.start) < ours.end.min(theirs.end)
+    }
+
+    /// Is the partition marked bootable: the MBR flag, or the GPT "legacy BIOS bootable"
+    /// attribute? As [`boot::is_marked_bootable`].
+    pub fn is_bootable(&self) -> bool {
+        boot::is_marked_bootable(self)
+    }
+
+    /// The partition's type, as `fdisk` shows it: an MBR type code as e.g. `0x83`, or a
+    /// GPT type GUID in its text form.
+    pub fn type_display(&self) -> String {
+        match &self.attributes {
+            Attributes::MBR { type_code, .. } => format!("0x{:02x}", type_code),
+            Attributes::GPT { type_uuid, .. } => guid::to_string(type_uuid),
+        }
+    }
+
+    /// The GPT partition name, if it's not empty. MBR partitions don't have names.
+    pub fn name(&self) -> Option<&str> {
+        match &self.attributes {
+            Attributes::GPT { name, .. } if !name.is_empty() => Some(name),
+            _ => None,
+        }
+    }
+}
+
+/// What identifies a partition across reads of the same disc, from
+/// [`PartitionTable::identity`], e.g. to match partitions up after a rescan.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum Identity {
+    /// GPT partitions have their own GUID, which survives them being moved, or resized.
+    GPT { partition_uuid: [u8; 16] },
+
+    /// MBR partitions have nothing of their own, so are identified by the disc's
+    /// signature, and where they are.
+    MBR {
+        disk_signature: u32,
+        id: usize,
+        first_byte: u64,
+    },
+}
+
+/// Which kind of partition table was found.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TableType {
+    MBR,
+    GPT,
+}
+
+/// A partition table, and what we learnt about the disc while reading it.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PartitionTable {
+    /// The kind of table the partitions were read from.
+    pub table_type: TableType,
+
+    /// The sector size used to interpret the table, in bytes.
+    pub sector_size: u64,
+
+    /// The MBR disc signature, from 0x1b8. This is present (but typically ignored) on GPT discs.
+    pub disk_signature: u32,
+
+    /// The GPT disc GUID, for GPT tables.
+    pub disk_guid: Option<[u8; 16]>,
+
+    /// The primary GPT header, for GPT tables.
+    pub gpt_header: Option<gpt::Header>,
+
+    /// The entries in the MBR. For GPT tables, this is the protective entry, or the
+    /// entries of a hybrid MBR; for MBR tables, it's the same as `partitions`, without any
+    /// [`Options::minix_subpartitions`] or [`Options::solaris_slices`].
+    pub mbr_partitions: Vec<Partition>,
+
+    /// How the MBR's entries were laid out; always [`mbr::Layout::Standard`] unless
+    /// [`Options::legacy_mbr_layouts`] was set.
+    pub mbr_layout: mbr::Layout,
+
+    /// What's in the MBR's bootstrap area, the code a BIOS runs; [`mbr::BootCode::Empty`]
+    /// if it's all zeros, so boot code must be installed before a BIOS can boot the disc.
+    /// `None` for tables which weren't read from a disc.
+    pub boot_code: Option<mbr::BootCode>,
+
+    /// The bootstrap area itself, as [`mbr::bootstrap_code`], if
+    /// [`Options::keep_boot_code`] was set, e.g. to compare with a known-good image.
+    pub boot_code_bytes: Option<Vec<u8>>,
+
+    /// Problems which were tolerated while reading the table, as allowed by the [`Options`].
+    pub warnings: Vec<verify::Finding>,
+
+    /// The partitions in the table.
+    pub partitions: Vec<Partition>,
+
+    /// The tables found inside partitions, if [`Options::recurse`] was set.
+    pub nested: Vec<Nested>,
+
+    /// What reading the table involved.
+    pub stats: Stats,
+}
+
+/// What was done to the reader to read a table, e.g. to see why a slow reader is slow, or
+/// to pick a block size for a caching reader.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Stats {
+    /// How many reads were made. For a [`Nested`] table, its reads are counted in the
+    /// outermost table's.
+    pub reads: usize,
+
+    /// The total length of the reads, in bytes. Some areas may be read more than once.
+    pub bytes_read: u64,
+
+    /// What was looked for, in order: `mbr`, `gpt sector size` (if it was guessed),
+    /// `gpt header`, `gpt entries`, `relocated gpt` (if the table wasn't found in the
+    /// usual place), then `minix subpartitions`, `solaris slices`, `labels` and
+    /// `nested tables`, as requested by the [`Options`].
+    pub probes: Vec<&'static str>,
+}
+
+/// A partition table found inside a partition, such as a whole disc image written into
+/// a partition of another.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Nested {
+    /// The [`Partition::id`] of the partition it was found in.
+    pub parent: usize,
+
+    /// The table, as read from inside the partition. Its partitions, and `mbr_partitions`,
+    /// have been moved to where they are on the outer disc, so can be opened from the
+    /// same reader; the header and its LBAs are as stored, relative to the partition.
+    pub table: PartitionTable,
+}
+
+impl PartitionTable {
+    /// A key for a partition in this table, which is the same each time the disc is
+    /// read, as long as the partition isn't changed.
+    pub fn identity(&self, part: &Partition) -> Identity {
+        match &part.attributes {
+            Attributes::GPT { partition_uuid, .. } => Identity::GPT {
+                partition_uuid: *partition_uuid,
+            },
+            Attributes::MBR { .. } => Identity::MBR {
+                disk_signature: self.disk_signature,
+                id: part.id,
+                first_byte: part.first_byte,
+            },
+        }
+    }
+
+    /// The first LBA partitions may use, for GPT tables.
+    pub fn first_usable_lba(&self) -> Option<u64> {
+        self.gpt_header
+            .as_ref()
+            .map(|header| header.first_usable_lba)
+    }
+
+    /// The last LBA partitions may use, inclusive, for GPT tables.
+    pub fn last_usable_lba(&self) -> Option<u64> {
+        self.gpt_header
+            .as_ref()
+            .map(|header| header.last_usable_lba)
+    }
+
+    /// Where the header says the other copy of itself is, for GPT tables. For the usual,
+    /// primary, header, this is the backup, which should be in the last sector of the disc.
+    pub fn alternate_lba(&self) -> Option<u64> {
+        self.gpt_header.as_ref().map(|header| header.alternate_lba)
+    }
+
+    /// The bytes partitions may use, for GPT tables; free space is whatever in here isn't
+    /// covered by a partition.
+    pub fn usable_range(&self) -> Option<core::ops::Range<u64>> {
+        let header = self.gpt_header.as_ref()?;
+        let start = header.first_usable_lba.checked_mul(self.sector_size)?;
+        let end = header
+            .last_usable_lba
+            .checked_add(1)?
+            .checked_mul(self.sector_size)?;
+        Some(start..end)
+    }
+
+    /// How many partitions the table has room for: the number of entries in a GPT's
+    /// array, or the slots of the MBR's [`layout`](PartitionTable::mbr_layout). `None` for a GPT table which wasn't
+    /// read from a disc, so has no header.
+    pub fn capacity(&self) -> Option<usize> {
+        match self.table_type {
+            TableType::MBR => Some(self.mbr_layout.entries()),
+            TableType::GPT => self
+                .gpt_header
+                .as_ref()
+                .and_then(|header| usize::try_from(header.entries).ok()),
+        }
+    }
+
+    /// How many of the slots are in use. Subpartitions and slices don't use the table's slots.
+    pub fn used_slots(&self) -> usize {
+        match self.capacity() {
+            Some(capacity) => self
+                .partitions
+                .iter()
+                .filter(|part| part.id < capacity)
+                .count(),
+            None => self.partitions.len(),
+        }
+    }
+
+    /// How many more partitions could be added without reshaping the entry array, if
+    /// the [`capacity`](PartitionTable::capacity) is known.
+    pub fn free_slots(&self) -> Option<usize> {
+        self.capacity()
+            .map(|capacity| capacity.saturating_sub(self.used_slots()))
+    }
 }
 
 /// What type of MBR partition tables should we attempt to read?
@@ -89,6 +410,11 @@ pub enum ReadMBR {
     Modern,
     /// Require there to be a GPT partition present. The protective MBR is allowed, but ignored.
     Never,
+
+    /// As `Modern`, but an entry with a status byte other than `0x00` or `0x80` is read as
+    /// not bootable, instead of refusing the whole table. Some tools leave junk there in
+    /// unused slots. Each is recorded as an `mbr-status` warning.
+    Lenient,
 }
 
 /// What type of GPT partition tables should we attempt to read?
@@ -104,13 +430,109 @@ pub enum ReadGPT {
 pub enum SectorSize {
     /// Attempt to identify a valid GPT partition table at various locations, and use this
     /// information to derive the sector size. For MBR, it's very likely that 512 is a safe
-    /// assumption.
+    /// assumption. The size used is reported in [`PartitionTable::sector_size`].
     GuessOrAssume,
 
     /// Use a specific known sector size.
     Known(u16),
 }
 
+/// What counts as a protective MBR, i.e. one which tells us to go and look for a GPT?
+pub enum Protective {
+    /// A single 0xEE entry, in the first slot, as the specification requires.
+    Strict,
+
+    /// Any 0xEE entry starting within the first MiB, in any slot, even if it's marked as
+    /// bootable or has other entries alongside it. Some imaging tools produce these,
+    /// and hybrid MBRs look like this too.
+    Relaxed,
+}
+
+/// How should data in the GPT header's reserved areas be treated?
+pub enum Reserved {
+    /// Refuse the table, as the reserved field and the rest of the header's sector
+    /// must be zero.
+    MustBeZero,
+
+    /// Read the table anyway, recording a warning in [`PartitionTable::warnings`].
+    /// Some vendors' tools leave junk here, and every operating system ignores it.
+    Tolerate,
+}
+
+/// What should be done with GPT partition names which aren't valid UTF-16, e.g. those
+/// containing an unpaired surrogate?
+pub enum InvalidNames {
+    /// Refuse the whole table.
+    Reject,
+
+    /// Decode what we can, with U+FFFD REPLACEMENT CHARACTER in place of the invalid
+    /// code units, recording a warning in [`PartitionTable::warnings`].
+    Replace,
+
+    /// Give the partition an empty name, recording a warning in [`PartitionTable::warnings`].
+    Empty,
+}
+
+/// How should GPT partition names be tidied once decoded, so names which look the same
+/// compare equal? Nothing is done by default. [`Attributes::GPT::raw_name`] is always the
+/// name exactly as it was stored.
+///
+/// Names already end at the first nul, so the nuls they're padded with never appear.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct NameNormalization {
+    /// Compose names into Unicode Normalization Form C, so e.g. an `e` followed by a
+    /// combining acute accent becomes `é`, as macOS and Windows write them differently.
+    #[cfg(feature = "nfc")]
+    pub nfc: bool,
+
+    /// Remove trailing whitespace, which some tools pad names with.
+    pub trim: bool,
+
+    /// Refuse tables with names containing control characters, such as newlines, which
+    /// are never intended.
+    pub reject_control: bool,
+}
+
+/// Which sizes of GPT entry should be accepted?
+pub enum EntrySizes {
+    /// 128 bytes multiplied by a power of two, as the specification requires.
+    Standard,
+
+    /// Any size of at least 128 bytes, recording a warning in [`PartitionTable::warnings`]
+    /// for those the specification doesn't allow. Only the first 128 bytes of each entry
+    /// are understood; the rest is available as [`Attributes::GPT::extra`].
+    Any,
+}
+
+/// In what order should partitions be returned?
+pub enum Order {
+    /// The order of the entries in the table. For GPT, the slots can be in any order
+    /// on the disc, and there may be gaps.
+    Table,
+
+    /// Sorted by where the partitions start on the disc. The slot each partition
+    /// was read from is still available as [`Partition::id`]; its position in the
+    /// disc is its index in the returned list.
+    Disk,
+}
+
+/// How hard should the parser be on violations of the specification, which don't stop
+/// the table being understood? See [`Options::with_strictness`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Strictness {
+    /// Refuse anything the specification forbids; what a boot loader should accept.
+    /// This is the default.
+    Strict,
+
+    /// Accept what common operating systems accept: junk in reserved fields, and
+    /// undecodable names, with warnings.
+    Standard,
+
+    /// Accept anything which can be understood, recording warnings, e.g. for recovering
+    /// data from damaged or oddly-made discs.
+    Permissive,
+}
+
 /// Configuration for listing partitions.
 pub struct Options {
     /// What type of MBR partitions should we read?
@@ -121,6 +543,84 @@ pub struct Options {
 
     /// How should we handle sector sizes?
     pub sector_size: SectorSize,
+
+    /// The largest sector size to consider, in bytes, when guessing it, and so how far into
+    /// the disc a protective partition may start. [`gpt::MAX_SECTOR_SIZE`] by default;
+    /// no larger sectors have been seen. [`verify`] reports a larger guess as an error.
+    pub max_sector_size: u64,
+
+    /// In what order should partitions be returned?
+    pub order: Order,
+
+    /// Should we accept junk in the GPT header's reserved areas?
+    pub reserved: Reserved,
+
+    /// How closely must the protective MBR follow the specification?
+    pub protective: Protective,
+
+    /// What should we do with GPT partition names which can't be decoded?
+    pub invalid_names: InvalidNames,
+
+    /// How should we tidy GPT partition names?
+    pub name_normalization: NameNormalization,
+
+    /// Which sizes of GPT entry should we accept?
+    pub entry_sizes: EntrySizes,
+
+    /// Should we look for the extra entries some old tools added to the MBR, e.g. when
+    /// imaging machines from the early 1990s? See [`mbr::Layout`] for those recognised.
+    ///
+    /// They're marked by signatures in what is usually boot code, so modern boot code
+    /// could be mistaken for one of them; this is off by default, and not set by any
+    /// [`Strictness`].
+    pub legacy_mbr_layouts: bool,
+
+    /// How many levels of partitions to look inside, for further tables, reported in
+    /// [`PartitionTable::nested`]. 0, the default, doesn't look.
+    ///
+    /// The same options are used for each level. A partition which doesn't contain a
+    /// valid table, including one which starts at the beginning of its parent, or one
+    /// whose table doesn't fit inside it, has nothing nested. [`iter_partitions`] doesn't
+    /// recurse.
+    pub recurse: usize,
+
+    /// Should we list the subpartitions inside MBR partitions used by Minix? They follow
+    /// the table's own partitions, numbered on from its last slot, as Linux does; see
+    /// [`minix`].
+    pub minix_subpartitions: bool,
+
+    /// Should we list the slices inside MBR partitions used by Solaris? As with
+    /// [`minix_subpartitions`](Options::minix_subpartitions), they follow the table's own
+    /// partitions; see [`solaris`].
+    pub solaris_slices: bool,
+
+    /// Should we look inside each partition for a filesystem label? This reads a few
+    /// sectors from the start of each partition; see [`label`].
+    pub probe_labels: bool,
+
+    /// Should the MBR's boot code be kept, in [`PartitionTable::boot_code_bytes`]? It's
+    /// always identified, in [`PartitionTable::boot_code`].
+    pub keep_boot_code: bool,
+
+    /// The length of the disc, if known, in bytes. The table is checked to fit, so
+    /// truncated images are reported as such, rather than failing when read later.
+    pub disk_len: Option<u64>,
+
+    /// Byte offsets to look for a GPT header at, in order, if no table is found in the
+    /// usual place; e.g. after an appliance's vendor boot area, or the backup header of
+    /// a disc which was copied into a larger file.
+    ///
+    /// The header must be at the LBA it claims to be. Where it was found is reported as a
+    /// `gpt-relocated` warning, and by the header's `my_lba`.
+    pub gpt_search_offsets: Vec<u64>,
+
+    /// Don't check the checksum of the GPT's entry array, so damaged entries will be
+    /// read as if they were valid. This is recorded as a `gpt-entries-unchecked` warning.
+    ///
+    /// The checksum covers every entry, so checking it means reading the whole array.
+    /// Without it, [`iter_partitions`] (in [`Order::Table`]) reads each entry as it's
+    /// reached, so finding an early entry on a slow link is quick.
+    pub dangerously_skip_entries_crc: bool,
 }
 
 impl Default for Options {
@@ -131,6 +631,74 @@ impl Default for Options {
             mbr: ReadMBR::Modern,
             gpt: ReadGPT::RevisionOne,
             sector_size: SectorSize::GuessOrAssume,
+            max_sector_size: gpt::MAX_SECTOR_SIZE,
+            order: Order::Table,
+            reserved: Reserved::MustBeZero,
+            protective: Protective::Strict,
+            invalid_names: InvalidNames::Reject,
+            name_normalization: NameNormalization::default(),
+            entry_sizes: EntrySizes::Standard,
+            legacy_mbr_layouts: false,
+            recurse: 0,
+            minix_subpartitions: false,
+            solaris_slices: false,
+            probe_labels: false,
+            keep_boot_code: false,
+            disk_len: None,
+            gpt_search_offsets: Vec::new(),
+            dangerously_skip_entries_crc: false,
+        }
+    }
+}
+
+impl Options {
+    /// The default options, with each of the tolerances set for a level of strictness.
+    ///
+    /// This sets [`mbr`](Options::mbr), [`reserved`](Options::reserved) (which covers the
+    /// header's tail bytes, too), [`protective`](Options::protective),
+    /// [`invalid_names`](Options::invalid_names), and [`entry_sizes`](Options::entry_sizes).
+    /// Individual checks can then be overridden:
+    ///
+    /// ```rust
+    /// use bootsector::{InvalidNames, Options, Strictness};
+    ///
+    /// let options = Options {
+    ///     invalid_names: InvalidNames::Empty,
+    ///     ..Options::with_strictness(Strictness::Permissive)
+    /// };
+    /// ```
+    pub fn with_strictness(strictness: Strictness) -> Options {
+        let (mbr, reserved, protective, invalid_names, entry_sizes) = match strictness {
+            Strictness::Strict => (
+                ReadMBR::Modern,
+                Reserved::MustBeZero,
+                Protective::Strict,
+                InvalidNames::Reject,
+                EntrySizes::Standard,
+            ),
+            Strictness::Standard => (
+                ReadMBR::Modern,
+                Reserved::Tolerate,
+                Protective::Strict,
+                InvalidNames::Replace,
+                EntrySizes::Any,
+            ),
+            Strictness::Permissive => (
+                ReadMBR::Lenient,
+                Reserved::Tolerate,
+                Protective::Relaxed,
+                InvalidNames::Replace,
+                EntrySizes::Any,
+            ),
+        };
+
+        Options {
+            mbr,
+            reserved,
+            protective,
+            invalid_names,
+            entry_sizes,
+            ..Options::default()
         }
     }
 }
@@ -140,49 +708,772 @@ impl Default for Options {
 /// # Returns
 ///
 /// * A possibly empty list of partitions.
-/// * `ErrorKind::NotFound` if the boot magic is not found,
-///        or you asked for partition types that are not there
-/// * `ErrorKind::InvalidData` if anything is not as we expect,
-///       including it looking like there should be GPT but its magic is missing.
-/// * Other IO errors directly from the underlying reader, including `UnexpectedEOF`.
+/// * [`Error::NotFound`] if the boot magic is not found,
+///   or you asked for partition types that are not there
+/// * [`Error::InvalidData`] or [`Error::InvalidStatic`] if anything is not as we expect,
+///   including it looking like there should be GPT but its magic is missing.
+/// * Errors from the underlying reader, including [`Error::UnexpectedEof`].
+///
+/// With `std`, errors convert to `std::io::Error`, with the matching `ErrorKind`s.
 pub fn list_partitions<R>(reader: R, options: &Options) -> Result<Vec<Partition>, Error>
 where
     R: io::ReadAt,
 {
-    let header_table = {
-        let mut disc_header = [0u8; 512];
-        reader.read_exact_at(0, &mut disc_header)?;
+    Ok(read_partition_table(reader, options)?.partitions)
+}
+
+/// Read the partition table, including information about the disc itself.
+///
+/// This behaves exactly like [`list_partitions`], but returns more of what it found.
+pub fn read_partition_table<R>(reader: R, options: &Options) -> Result<PartitionTable, Error>
+where
+    R: io::ReadAt,
+{
+    read_with_len(reader, options, options.disk_len)
+}
 
-        if 0x55 != disc_header[510] || 0xAA != disc_header[511] {
-            return Err(Error::NotFound);
+/// Read the partitions one at a time, so a search for a particular partition can stop
+/// as soon as it's found.
+///
+/// The headers, and the GPT's entry array, are read and checked up front, as its checksum
+/// covers all of the entries; each entry is only decoded, and checked, when it's reached.
+/// Any error ends the iteration. With [`Order::Disk`], every entry must be decoded first.
+/// See [`Options::dangerously_skip_entries_crc`] to read the entries one at a time, too.
+///
+/// ```rust
+/// # fn go(disc: &[u8]) -> Result<(), bootsector::Error> {
+/// let big = bootsector::iter_partitions(disc, &Default::default())
+///     .find(|part| part.as_ref().map_or(true, |part| part.len > 1 << 30))
+///     .transpose()?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn iter_partitions<'o, R>(
+    reader: R,
+    options: &'o Options,
+) -> impl Iterator<Item = Result<Partition, Error>> + 'o
+where
+    R: io::ReadAt + 'o,
+{
+    Lazy {
+        reader,
+        options,
+        state: LazyState::Start,
+    }
+}
+
+/// The partition table, read a little at a time, for [`iter_partitions`].
+struct Lazy<'o, R> {
+    reader: R,
+    options: &'o Options,
+    state: LazyState,
+}
+
+enum LazyState {
+    Start,
+    Entries {
+        header: gpt::Header,
+        sector_size: u64,
+        /// `None` if each entry is to be read from the disc as it's reached.
+        entries: Option<Vec<u8>>,
+        next: usize,
+    },
+    Decoded(alloc::vec::IntoIter<Partition>),
+    Done,
+}
+
+impl<'o, R: io::ReadAt> Lazy<'o, R> {
+    /// Read the headers, and decide how the partitions will be produced.
+    fn start(&self) -> Result<LazyState, Error> {
+        let options = self.options;
+        let (mut table, relocated) = match read_headers(&self.reader, options) {
+            Ok(table) => (table, false),
+            Err(e) => (read_relocated(&self.reader, options).ok_or(e)?, true),
+        };
+
+        if let Some(disk_len) = options.disk_len {
+            check_header_fits(&table, disk_len)?;
         }
 
-        mbr::parse_partition_table(&disc_header)?
+        add_subpartitions(&self.reader, &mut table, options)?;
+
+        let header = match table.gpt_header {
+            Some(header) if !relocated => header,
+            _ => return Ok(LazyState::Decoded(table.partitions.into_iter())),
+        };
+
+        if options.dangerously_skip_entries_crc {
+            if let Order::Table = options.order {
+                return Ok(LazyState::Entries {
+                    header,
+                    sector_size: table.sector_size,
+                    entries: None,
+                    next: 0,
+                });
+            }
+        }
+
+        let entries = read_entries(
+            &self.reader,
+            &header,
+            table.sector_size,
+            options,
+            &mut table.warnings,
+        )?;
+
+        if let Order::Disk = options.order {
+            let mut partitions = gpt::decode_entries(
+                &entries,
+                &header,
+                table.sector_size,
+                &options.invalid_names,
+                &options.name_normalization,
+                &mut table.warnings,
+            )?;
+            partitions.sort_by_key(|part| (part.first_byte, part.id));
+            return Ok(LazyState::Decoded(partitions.into_iter()));
+        }
+
+        Ok(LazyState::Entries {
+            header,
+            sector_size: table.sector_size,
+            entries: Some(entries),
+            next: 0,
+        })
+    }
+
+    /// The next partition, before it has been checked, or `None` at the end.
+    fn next_partition(&mut self) -> Result<Option<Partition>, Error> {
+        if let LazyState::Start = self.state {
+            self.state = self.start()?;
+        }
+
+        match &mut self.state {
+            LazyState::Start | LazyState::Done => Ok(None),
+            LazyState::Decoded(partitions) => Ok(partitions.next()),
+            LazyState::Entries {
+                header,
+                sector_size,
+                entries,
+                next,
+            } => {
+                let entry_size = usize::try_from(header.entry_size).map_err(|_| Error::Overflow)?;
+                let count = usize::try_from(header.entries).map_err(|_| Error::Overflow)?;
+                while *next < count {
+                    let id = *next;
+                    *next += 1;
+                    let fetched;
+                    let entry = match entries {
+                        Some(entries) => &entries[id * entry_size..(id + 1) * entry_size],
+                        None => {
+                            fetched = gpt::read_entry(&self.reader, header, *sector_size, id)?;
+                            &fetched[..]
+                        }
+                    };
+                    let decoded = gpt::decode_entry(
+                        id,
+                        entry,
+                        header,
+                        *sector_size,
+                        &self.options.invalid_names,
+                        &self.options.name_normalization,
+                        &mut Vec::new(),
+                    )?;
+                    if decoded.is_some() {
+                        return Ok(decoded);
+                    }
+                }
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl<'o, R: io::ReadAt> Iterator for Lazy<'o, R> {
+    type Item = Result<Partition, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let found = self.next_partition().and_then(|part| match part {
+            Some(mut part) => {
+                if let Some(disk_len) = self.options.disk_len {
+                    check_partition_fits(&part, disk_len)?;
+                }
+                if self.options.probe_labels {
+                    part.label = label::probe(&self.reader, &part).unwrap_or(None);
+                }
+                Ok(Some(part))
+            }
+            None => Ok(None),
+        });
+
+        match found {
+            Ok(Some(part)) => Some(Ok(part)),
+            Ok(None) => {
+                self.state = LazyState::Done;
+                None
+            }
+            Err(e) => {
+                self.state = LazyState::Done;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Read the partition table, checking it fits inside the reader.
+///
+/// This is [`read_partition_table`], with [`Options::disk_len`] taken from the reader,
+/// if it knows its size.
+#[cfg(feature = "std")]
+pub fn read_partition_table_sized<R>(reader: R, options: &Options) -> Result<PartitionTable, Error>
+where
+    R: positioned_io2::ReadAt + positioned_io2::Size,
+{
+    use crate::errors::IoSnafu;
+
+    let disk_len = reader.size().context(IoSnafu { pos: 0u64 })?;
+    read_with_len(reader, options, disk_len.or(options.disk_len))
+}
+
+pub(crate) fn read_with_len<R>(
+    reader: R,
+    options: &Options,
+    disk_len: Option<u64>,
+) -> Result<PartitionTable, Error>
+where
+    R: io::ReadAt,
+{
+    read_borrowed(&reader, options, disk_len)
+}
+
+pub(crate) fn read_borrowed<R>(
+    reader: &R,
+    options: &Options,
+    disk_len: Option<u64>,
+) -> Result<PartitionTable, Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    #[cfg(feature = "tracing")]
+    let reader = &instrument::Traced(reader);
+
+    let counted = io::Counted::new(reader);
+    let mut table = read_to_depth(&counted, options, disk_len, options.recurse)?;
+    let (reads, bytes_read) = counted.counts();
+    table.stats.reads = reads;
+    table.stats.bytes_read = bytes_read;
+    Ok(table)
+}
+
+/// Read the table, and those nested inside its partitions, `depth` levels deep.
+fn read_to_depth<R>(
+    reader: &R,
+    options: &Options,
+    disk_len: Option<u64>,
+    depth: usize,
+) -> Result<PartitionTable, Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    let _span = span!("read_partition_table", depth);
+
+    let mut table = match read_table_in_order(reader, options) {
+        Ok(table) => table,
+        Err(e) => {
+            read_relocated(reader, options).ok_or_else(|| instrument::rejected("table", e))?
+        }
+    };
+    debug!(
+        table_type = ?table.table_type,
+        sector_size = table.sector_size,
+        partitions = table.partitions.len(),
+        "found table"
+    );
+
+    add_subpartitions(reader, &mut table, options)?;
+
+    if let Some(disk_len) = disk_len {
+        check_fits(&table, disk_len)?;
+    }
+
+    if options.probe_labels {
+        table.stats.probes.push("labels");
+        for part in &mut table.partitions {
+            // an unreadable filesystem shouldn't stop the table being read
+            part.label = label::probe(reader, part).unwrap_or(None);
+        }
+    }
+
+    if depth > 0 {
+        table.stats.probes.push("nested tables");
+        for part in &table.partitions {
+            if let Some(inner) = read_inside(reader, part, options, depth - 1) {
+                debug!(parent = part.id, "found nested table");
+                table.nested.push(Nested {
+                    parent: part.id,
+                    table: inner,
+                });
+            }
+        }
+    }
+
+    if let Order::Disk = options.order {
+        table
+            .partitions
+            .sort_by_key(|part| (part.first_byte, part.id));
+    }
+
+    Ok(table)
+}
+
+/// Read a table from inside a partition, moving its partitions to where they are on the
+/// outer disc. Any error means there's no table, as most partitions contain filesystems.
+fn read_inside<R>(
+    reader: &R,
+    part: &Partition,
+    options: &Options,
+    depth: usize,
+) -> Option<PartitionTable>
+where
+    R: io::ReadAt + ?Sized,
+{
+    // a partition at the start of its parent would find the parent's table again
+    if 0 == part.first_byte {
+        return None;
+    }
+
+    // as a trait object, so each level doesn't need its own copy of the reading code
+    let within: &dyn io::ReadAt = &io::Within {
+        inner: reader,
+        start: part.first_byte,
+        len: part.len,
+    };
+    let mut table = read_to_depth(within, options, Some(part.len), depth).ok()?;
+    relocate(&mut table, part.first_byte).ok()?;
+    Some(table)
+}
+
+/// Move every partition in a nested table, and those nested inside it, `by` bytes.
+fn relocate(table: &mut PartitionTable, by: u64) -> Result<(), Error> {
+    for part in table
+        .partitions
+        .iter_mut()
+        .chain(table.mbr_partitions.iter_mut())
+    {
+        part.first_byte = part.first_byte.checked_add(by).ok_or(Error::Overflow)?;
+    }
+    for nested in &mut table.nested {
+        relocate(&mut nested.table, by)?;
+    }
+    Ok(())
+}
+
+/// Add the Minix subpartitions and Solaris slices of an MBR's partitions, as requested,
+/// numbered after its slots.
+fn add_subpartitions<R>(
+    reader: &R,
+    table: &mut PartitionTable,
+    options: &Options,
+) -> Result<(), Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    if !options.minix_subpartitions && !options.solaris_slices {
+        return Ok(());
+    }
+
+    let mut next = match (table.table_type, table.capacity()) {
+        (TableType::MBR, Some(capacity)) => capacity,
+        _ => return Ok(()),
+    };
+
+    if options.minix_subpartitions {
+        table.stats.probes.push("minix subpartitions");
+    }
+    if options.solaris_slices {
+        table.stats.probes.push("solaris slices");
+    }
+
+    let mut found = Vec::new();
+    for part in &table.partitions {
+        let mut nested = Vec::new();
+        if options.minix_subpartitions {
+            nested.extend(minix::read(reader, part)?);
+        }
+        if options.solaris_slices {
+            nested.extend(solaris::slices(reader, part)?);
+        }
+        for mut sub in nested {
+            sub.id = next;
+            next += 1;
+            found.push(sub);
+        }
+    }
+
+    table.partitions.extend(found);
+    Ok(())
+}
+
+/// Ensure everything the table refers to is inside the disc.
+fn check_fits(table: &PartitionTable, disk_len: u64) -> Result<(), Error> {
+    check_header_fits(table, disk_len)?;
+    for part in &table.partitions {
+        check_partition_fits(part, disk_len)?;
+    }
+    Ok(())
+}
+
+fn fits(what: &dyn Fn() -> String, end: Option<u64>, disk_len: u64) -> Result<(), Error> {
+    match end {
+        Some(end) if end <= disk_len => Ok(()),
+        _ => Err(Error::Truncated {
+            what: what(),
+            needed: end.unwrap_or(u64::MAX),
+            disk_len,
+        }),
+    }
+}
+
+/// Ensure the GPT's own structures, and its usable area, are inside the disc.
+fn check_header_fits(table: &PartitionTable, disk_len: u64) -> Result<(), Error> {
+    let check = |what: &dyn Fn() -> String, end: Option<u64>| fits(what, end, disk_len);
+
+    if let Some(header) = &table.gpt_header {
+        let sector_end = |lba: u64| {
+            lba.checked_add(1)
+                .and_then(|sectors| sectors.checked_mul(table.sector_size))
+        };
+        check(
+            &|| String::from("the backup gpt header"),
+            sector_end(header.alternate_lba),
+        )?;
+        check(
+            &|| String::from("the usable area"),
+            sector_end(header.last_usable_lba),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn check_partition_fits(part: &Partition, disk_len: u64) -> Result<(), Error> {
+    fits(
+        &|| format!("partition {}", part.id),
+        part.first_byte.checked_add(part.len),
+        disk_len,
+    )
+}
+
+fn read_table_in_order<R>(reader: &R, options: &Options) -> Result<PartitionTable, Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    let mut table = read_headers(reader, options)?;
+    if let Some(header) = &table.gpt_header {
+        table.stats.probes.push("gpt entries");
+        let entries = read_entries(
+            reader,
+            header,
+            table.sector_size,
+            options,
+            &mut table.warnings,
+        )?;
+        table.partitions = gpt::decode_entries(
+            &entries,
+            header,
+            table.sector_size,
+            &options.invalid_names,
+            &options.name_normalization,
+            &mut table.warnings,
+        )?;
+    }
+    Ok(table)
+}
+
+/// Read the GPT's entry array, checking its checksum unless told not to.
+fn read_entries<R>(
+    reader: &R,
+    header: &gpt::Header,
+    sector_size: u64,
+    options: &Options,
+    warnings: &mut Vec<verify::Finding>,
+) -> Result<Vec<u8>, Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    if !options.dangerously_skip_entries_crc {
+        return gpt::read_entry_array(reader, header, sector_size);
+    }
+
+    warnings.push(verify::Finding {
+        severity: verify::Severity::Warning,
+        code: "gpt-entries-unchecked",
+        message: String::from("the gpt entry array's checksum was not checked"),
+    });
+    gpt::read_entry_array_unchecked(reader, header, sector_size)
+}
+
+/// Read the table, except for the GPT's entries, which the caller reads as it likes.
+/// For GPT tables, the partitions are empty.
+fn read_headers<R>(reader: &R, options: &Options) -> Result<PartitionTable, Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    let mut disc_header = [0u8; 512];
+    reader
+        .read_exact_at(0, &mut disc_header)
+        .context(ReadSnafu {
+            what: "the mbr",
+            pos: 0u64,
+        })?;
+
+    if 0x55 != disc_header[510] || 0xAA != disc_header[511] {
+        debug!("no boot signature");
+        return Err(Error::NotFound);
+    }
+
+    let mut warnings = Vec::new();
+    let mbr_layout = if options.legacy_mbr_layouts {
+        mbr::detect_layout(&disc_header)
+    } else {
+        mbr::Layout::Standard
+    };
+    debug!(layout = ?mbr_layout, "mbr layout");
+    let lenient = matches!(options.mbr, ReadMBR::Lenient);
+    let header_table = mbr::parse_layout(&disc_header, mbr_layout, lenient, &mut warnings)?;
+    let disk_signature = mbr::disk_signature(&disc_header);
+    let boot_code = mbr::identify_boot_code(&disc_header);
+    let boot_code_bytes = if options.keep_boot_code {
+        Some(mbr::bootstrap_code(&disc_header).to_vec())
+    } else {
+        None
+    };
+
+    let mbr_table = |partitions: Vec<Partition>, warnings| PartitionTable {
+        table_type: TableType::MBR,
+        sector_size: 512,
+        disk_signature,
+        disk_guid: None,
+        gpt_header: None,
+        mbr_partitions: partitions.clone(),
+        mbr_layout,
+        boot_code: Some(boot_code),
+        boot_code_bytes: boot_code_bytes.clone(),
+        warnings,
+        partitions,
+        nested: Vec::new(),
+        stats: Stats {
+            probes: vec!["mbr"],
+            ..Stats::default()
+        },
+    };
+
+    let strict = match header_table.len() {
+        1 if gpt::is_protective_up_to(&header_table[0], options.max_sector_size) => {
+            Some(&header_table[0])
+        }
+        _ => None,
+    };
+
+    let protective = match (strict, &options.protective) {
+        (Some(part), _) => Some(part),
+        (None, Protective::Strict) => None,
+        (None, Protective::Relaxed) => header_table
+            .iter()
+            .find(|part| gpt::is_protective_relaxed(part)),
     };
 
-    match header_table.len() {
-        1 if gpt::is_protective(&header_table[0]) => {}
-        _ => {
+    let protective_start = match protective {
+        Some(part) => part.first_byte,
+        None => {
+            debug!("no protective partition, so not gpt");
             return match options.mbr {
-                ReadMBR::Modern => Ok(header_table),
+                ReadMBR::Modern | ReadMBR::Lenient => Ok(mbr_table(header_table, warnings)),
                 ReadMBR::Never => Err(Error::NotFound),
-            }
+            };
         }
+    };
+
+    if strict.is_none() {
+        warnings.push(verify::Finding {
+            severity: verify::Severity::Warning,
+            code: "mbr-protective-shape",
+            message: String::from("protective partition is not a lone, non-bootable, first entry"),
+        });
     }
 
     match options.gpt {
-        ReadGPT::Never => Ok(header_table),
+        ReadGPT::Never => Ok(mbr_table(header_table, warnings)),
         ReadGPT::RevisionOne => {
             let sector_size = match options.sector_size {
                 SectorSize::Known(size) => u64::from(size),
-                SectorSize::GuessOrAssume => header_table[0].first_byte,
+                SectorSize::GuessOrAssume => {
+                    match gpt::probe_sector_size(reader, options.max_sector_size) {
+                        Some(size) => size,
+                        None => check_guess(reader, protective_start, options)?,
+                    }
+                }
+            };
+            debug!(sector_size, "sector size chosen");
+
+            let header = gpt::read_primary(reader, sector_size, &options.reserved, &mut warnings)?;
+            gpt::check_entry_size(&header, &options.entry_sizes, &mut warnings)?;
+
+            let mut probes = vec!["mbr"];
+            if let SectorSize::GuessOrAssume = options.sector_size {
+                probes.push("gpt sector size");
+            }
+            probes.push("gpt header");
+
+            let table = PartitionTable {
+                table_type: TableType::GPT,
+                sector_size,
+                disk_signature,
+                disk_guid: Some(header.disk_guid),
+                gpt_header: Some(header),
+                mbr_partitions: header_table,
+                mbr_layout,
+                boot_code: Some(boot_code),
+                boot_code_bytes,
+                warnings,
+                partitions: Vec::new(),
+                nested: Vec::new(),
+                stats: Stats {
+                    probes,
+                    ..Stats::default()
+                },
             };
 
-            gpt::read(reader, sector_size)
+            Ok(table)
         }
     }
 }
 
+/// No header was found by probing, so the protective partition's start is the only guess
+/// left at the sector size; only use it if there's a valid header there.
+pub(crate) fn check_guess<R>(reader: &R, guess: u64, options: &Options) -> Result<u64, Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    let failed = |source| Error::SectorSizeGuess {
+        guess,
+        max_sector_size: options.max_sector_size,
+        source: Box::new(source),
+    };
+
+    if guess < 512 || guess > options.max_sector_size {
+        return Err(failed(Error::InvalidStatic {
+            message: "the protective partition's start is not a plausible sector size",
+        }));
+    }
+
+    gpt::read_primary(reader, guess, &options.reserved, &mut Vec::new()).map_err(failed)?;
+    Ok(guess)
+}
+
+/// Look for a GPT at each of [`Options::gpt_search_offsets`], trying each plausible sector
+/// size, unless told which.
+fn read_relocated<R>(reader: &R, options: &Options) -> Option<PartitionTable>
+where
+    R: io::ReadAt + ?Sized,
+{
+    if let ReadGPT::Never = options.gpt {
+        return None;
+    }
+
+    let sector_sizes: Vec<u64> = match options.sector_size {
+        SectorSize::Known(size) => vec![u64::from(size)],
+        SectorSize::GuessOrAssume => (9..64)
+            .map(|shift| 1 << shift)
+            .take_while(|&size| size <= options.max_sector_size)
+            .collect(),
+    };
+
+    for &offset in &options.gpt_search_offsets {
+        let mut signature = [0u8; 8];
+        if reader.read_exact_at(offset, &mut signature).is_err() || gpt::SIGNATURE != &signature {
+            continue;
+        }
+
+        for &sector_size in &sector_sizes {
+            if 0 != offset % sector_size {
+                continue;
+            }
+
+            let mut warnings = Vec::new();
+            let found = gpt::header_at(reader, offset, sector_size).and_then(|header| {
+                gpt::check_entry_size(&header, &options.entry_sizes, &mut warnings)?;
+                let table = gpt::read_entry_array(reader, &header, sector_size)?;
+                let partitions = gpt::decode_entries(
+                    &table,
+                    &header,
+                    sector_size,
+                    &options.invalid_names,
+                    &options.name_normalization,
+                    &mut warnings,
+                )?;
+                Ok((header, partitions))
+            });
+
+            let (header, partitions) = match found {
+                Ok(found) => found,
+                Err(e) => {
+                    instrument::rejected("relocated gpt", e);
+                    continue;
+                }
+            };
+            debug!(offset, sector_size, "found relocated gpt");
+
+            warnings.insert(
+                0,
+                verify::Finding {
+                    severity: verify::Severity::Warning,
+                    code: "gpt-relocated",
+                    message: format!("gpt header found at byte {}", offset),
+                },
+            );
+
+            let mut disc_header = [0u8; 512];
+            let read = reader.read_exact_at(0, &mut disc_header).is_ok();
+            let disk_signature = if read {
+                mbr::disk_signature(&disc_header)
+            } else {
+                0
+            };
+            let boot_code_bytes = if read && options.keep_boot_code {
+                Some(mbr::bootstrap_code(&disc_header).to_vec())
+            } else {
+                None
+            };
+
+            return Some(PartitionTable {
+                table_type: TableType::GPT,
+                sector_size,
+                disk_signature,
+                disk_guid: Some(header.disk_guid),
+                gpt_header: Some(header),
+                mbr_partitions: Vec::new(),
+                mbr_layout: mbr::Layout::Standard,
+                boot_code: if read {
+                    Some(mbr::identify_boot_code(&disc_header))
+                } else {
+                    None
+                },
+                boot_code_bytes,
+                warnings,
+                partitions,
+                nested: Vec::new(),
+                stats: Stats {
+                    probes: vec!["mbr", "relocated gpt"],
+                    ..Stats::default()
+                },
+            });
+        }
+    }
+
+    None
+}
+
 /// Open the contents of a partition for reading.
 #[cfg(feature = "std")]
 pub fn open_partition<R>(inner: R, part: &Partition) -> Result<positioned_io2::Slice<R>, Error>
@@ -0,0 +1,304 @@
+//! Query the geometry of Linux block devices, so it doesn't have to be guessed, and
+//! expose partitions in images as loop devices.
+//!
+//! ```rust,no_run
+//! # fn go() -> Result<(), Box<dyn std::error::Error>> {
+//! let disc = std::fs::File::open("/dev/sda")?;
+//! let options = bootsector::linux::options(&disc)?;
+//! let table = bootsector::read_partition_table(&disc, &options)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::TryFrom;
+use std::fs::{File, OpenOptions};
+use std::os::raw::{c_int, c_uint};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+use crate::{Error, Options, Partition, SectorSize};
+
+// from linux/fs.h; the encoding of the direction differs between architectures
+#[cfg(any(
+    target_arch = "mips",
+    target_arch = "mips64",
+    target_arch = "powerpc",
+    target_arch = "powerpc64",
+    target_arch = "sparc",
+    target_arch = "sparc64"
+))]
+const IOC_NONE_READ: (u32, u32) = (1 << 29, 2 << 29);
+#[cfg(not(any(
+    target_arch = "mips",
+    target_arch = "mips64",
+    target_arch = "powerpc",
+    target_arch = "powerpc64",
+    target_arch = "sparc",
+    target_arch = "sparc64"
+)))]
+const IOC_NONE_READ: (u32, u32) = (0, 2 << 30);
+
+#[cfg(target_pointer_width = "64")]
+const SIZE_T_LEN: u32 = 8;
+#[cfg(not(target_pointer_width = "64"))]
+const SIZE_T_LEN: u32 = 4;
+
+const BLKSSZGET: u32 = IOC_NONE_READ.0 | 0x12 << 8 | 104;
+const BLKGETSIZE64: u32 = IOC_NONE_READ.1 | SIZE_T_LEN << 16 | 0x12 << 8 | 114;
+const BLKPBSZGET: u32 = IOC_NONE_READ.0 | 0x12 << 8 | 123;
+const BLKDISCARD: u32 = IOC_NONE_READ.0 | 0x12 << 8 | 119;
+
+// from linux/loop.h, which doesn't use the direction encoding
+const LOOP_SET_FD: u32 = 0x4c00;
+const LOOP_CLR_FD: u32 = 0x4c01;
+const LOOP_SET_STATUS64: u32 = 0x4c04;
+const LOOP_CTL_GET_FREE: u32 = 0x4c82;
+
+const LO_FLAGS_READ_ONLY: u32 = 1;
+const LO_FLAGS_AUTOCLEAR: u32 = 4;
+
+#[repr(C)]
+struct LoopInfo64 {
+    device: u64,
+    inode: u64,
+    rdevice: u64,
+    offset: u64,
+    size_limit: u64,
+    number: u32,
+    encrypt_type: u32,
+    encrypt_key_size: u32,
+    flags: u32,
+    file_name: [u8; 64],
+    crypt_name: [u8; 64],
+    encrypt_key: [u8; 32],
+    init: [u64; 2],
+}
+
+/// The size and sector sizes of a device.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Geometry {
+    /// The length of the device, in bytes.
+    pub len: u64,
+
+    /// The sector size used for addressing, and hence by the partition table.
+    pub logical_sector_size: u32,
+
+    /// The sector size the device actually writes; partitions should be aligned to this.
+    pub physical_sector_size: u32,
+}
+
+/// Ask the kernel about a block device.
+///
+/// Regular files (i.e. disc images) are also accepted, and assumed to have 512 byte sectors.
+pub fn geometry(file: &File) -> Result<Geometry, Error> {
+    let metadata = file
+        .metadata()
+        .map_err(|source| Error::Io { source, pos: 0 })?;
+    if metadata.is_file() {
+        return Ok(Geometry {
+            len: metadata.len(),
+            logical_sector_size: 512,
+            physical_sector_size: 512,
+        });
+    }
+
+    let mut len: u64 = 0;
+    ioctl(file, BLKGETSIZE64, &mut len)?;
+
+    let mut logical: c_int = 0;
+    ioctl(file, BLKSSZGET, &mut logical)?;
+
+    let mut physical: c_uint = 0;
+    ioctl(file, BLKPBSZGET, &mut physical)?;
+
+    Ok(Geometry {
+        len,
+        logical_sector_size: u32::try_from(logical).map_err(|_| Error::InvalidStatic {
+            message: "kernel reported a negative sector size",
+        })?,
+        physical_sector_size: physical,
+    })
+}
+
+/// [`Options`] with the sector size and disc length filled in from the device.
+pub fn options(file: &File) -> Result<Options, Error> {
+    let geometry = geometry(file)?;
+    Ok(Options {
+        sector_size: SectorSize::Known(u16::try_from(geometry.logical_sector_size).map_err(
+            |_| Error::InvalidStatic {
+                message: "sector size is implausibly large",
+            },
+        )?),
+        disk_len: Some(geometry.len),
+        ..Options::default()
+    })
+}
+
+/// Tell the device that a partition's contents are no longer needed, e.g. to quickly
+/// reset an SSD-backed machine, instead of writing zeros over it.
+///
+/// On block devices, this is `BLKDISCARD`; what is read back from the range afterwards
+/// depends on the device. Regular files (i.e. disc images) have the range deallocated,
+/// and it reads back as zeros. The range must lie on the device, and be aligned to
+/// its logical sectors.
+pub fn discard_partition(file: &File, part: &Partition) -> Result<(), Error> {
+    let geometry = geometry(file)?;
+
+    let end = part
+        .first_byte
+        .checked_add(part.len)
+        .ok_or(Error::Overflow)?;
+    if end > geometry.len {
+        return Err(Error::Truncated {
+            what: format!("partition {}", part.id),
+            needed: end,
+            disk_len: geometry.len,
+        });
+    }
+
+    let sector_size = u64::from(geometry.logical_sector_size);
+    if 0 != part.first_byte % sector_size || 0 != part.len % sector_size {
+        return Err(Error::InvalidStatic {
+            message: "discarded range must be whole sectors",
+        });
+    }
+
+    if 0 == part.len {
+        return Ok(());
+    }
+
+    if file
+        .metadata()
+        .map_err(|source| Error::Io { source, pos: 0 })?
+        .is_file()
+    {
+        let too_big = |_| Error::Overflow;
+        let ret = unsafe {
+            libc::fallocate(
+                file.as_raw_fd(),
+                libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE,
+                libc::off_t::try_from(part.first_byte).map_err(too_big)?,
+                libc::off_t::try_from(part.len).map_err(too_big)?,
+            )
+        };
+        return check(ret).map(|_| ());
+    }
+
+    let mut range = [part.first_byte, part.len];
+    ioctl(file, BLKDISCARD, &mut range)
+}
+
+/// A loop device, which is detached when dropped.
+///
+/// The device is also marked to be cleared by the kernel once nothing has it open, so a
+/// mounted filesystem keeps working after this is dropped.
+pub struct LoopDevice {
+    path: PathBuf,
+    device: File,
+}
+
+impl LoopDevice {
+    /// The path to the device, e.g. `/dev/loop3`.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Detach the device now, reporting any failure.
+    pub fn detach(self) -> Result<(), Error> {
+        let ret = ioctl_value(&self.device, LOOP_CLR_FD, 0);
+        // prevent the second attempt in drop
+        std::mem::forget(self);
+        ret.map(|_| ())
+    }
+}
+
+impl Drop for LoopDevice {
+    fn drop(&mut self) {
+        let _ = ioctl_value(&self.device, LOOP_CLR_FD, 0);
+    }
+}
+
+/// Expose a partition from an image as a block device, e.g. to mount it.
+///
+/// This requires root, or at least `CAP_SYS_ADMIN`.
+pub fn attach_partition(
+    image: &File,
+    part: &Partition,
+    read_only: bool,
+) -> Result<LoopDevice, Error> {
+    let control = File::open("/dev/loop-control").map_err(|source| Error::Io { source, pos: 0 })?;
+
+    // someone else can grab the free device before us, so try again a few times
+    let mut attempts = 0;
+    loop {
+        let number = ioctl_value(&control, LOOP_CTL_GET_FREE, 0)?;
+        let path = PathBuf::from(format!("/dev/loop{}", number));
+        let device = OpenOptions::new()
+            .read(true)
+            .write(!read_only)
+            .open(&path)
+            .map_err(|source| Error::Io { source, pos: 0 })?;
+
+        let fd = usize::try_from(image.as_raw_fd()).map_err(|_| Error::InvalidStatic {
+            message: "image has an invalid file descriptor",
+        })?;
+
+        match ioctl_value(&device, LOOP_SET_FD, fd) {
+            Ok(_) => (),
+            Err(Error::Io { ref source, .. })
+                if Some(libc::EBUSY) == source.raw_os_error() && attempts < 8 =>
+            {
+                attempts += 1;
+                continue;
+            }
+            Err(e) => return Err(e),
+        }
+
+        let device = LoopDevice { path, device };
+
+        let mut info = LoopInfo64 {
+            device: 0,
+            inode: 0,
+            rdevice: 0,
+            offset: part.first_byte,
+            size_limit: part.len,
+            number: 0,
+            encrypt_type: 0,
+            encrypt_key_size: 0,
+            flags: LO_FLAGS_AUTOCLEAR | if read_only { LO_FLAGS_READ_ONLY } else { 0 },
+            file_name: [0u8; 64],
+            crypt_name: [0u8; 64],
+            encrypt_key: [0u8; 32],
+            init: [0; 2],
+        };
+
+        // on failure, the device is detached by drop
+        ioctl(&device.device, LOOP_SET_STATUS64, &mut info)?;
+
+        return Ok(device);
+    }
+}
+
+fn ioctl<T>(file: &File, request: u32, value: &mut T) -> Result<(), Error> {
+    // the type of the request differs between libcs
+    #[allow(clippy::cast_lossless, clippy::cast_possible_wrap)]
+    let ret = unsafe { libc::ioctl(file.as_raw_fd(), request as _, value as *mut T) };
+    check(ret).map(|_| ())
+}
+
+/// An ioctl which takes an integer, rather than a pointer.
+fn ioctl_value(file: &File, request: u32, value: usize) -> Result<c_int, Error> {
+    #[allow(clippy::cast_lossless, clippy::cast_possible_wrap)]
+    let ret = unsafe { libc::ioctl(file.as_raw_fd(), request as _, value) };
+    check(ret)
+}
+
+fn check(ret: c_int) -> Result<c_int, Error> {
+    if -1 == ret {
+        return Err(Error::Io {
+            source: std::io::Error::last_os_error(),
+            pos: 0,
+        });
+    }
+    Ok(ret)
+}
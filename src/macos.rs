@@ -0,0 +1,118 @@
+//! Read raw discs on macOS, e.g. `/dev/rdisk0`.
+//!
+//! The raw (character) devices are much faster than the buffered `/dev/diskN` devices,
+//! but reject reads which don't cover whole blocks; [`RawDisk`] hides this.
+//!
+//! ```rust,no_run
+//! # fn go() -> Result<(), bootsector::Error> {
+//! let disc = bootsector::macos::RawDisk::open(0)?;
+//! let table = bootsector::read_partition_table(&disc, &disc.options()?)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io;
+use std::os::unix::io::AsRawFd;
+
+use crate::{Error, Options, SectorSize};
+
+// from sys/disk.h: _IOR('d', 24, uint32_t) and _IOR('d', 25, uint64_t)
+const DKIOCGETBLOCKSIZE: u32 = 0x4004_6418;
+const DKIOCGETBLOCKCOUNT: u32 = 0x4008_6419;
+
+/// The size and block size of a disc.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Geometry {
+    /// The length of the disc, in bytes.
+    pub len: u64,
+
+    /// The block (sector) size used for addressing, and hence by the partition table.
+    pub block_size: u32,
+}
+
+/// A raw disc device, readable at any offset.
+pub struct RawDisk {
+    file: File,
+    geometry: Geometry,
+}
+
+impl RawDisk {
+    /// Open `/dev/rdisk{number}` for reading. This typically requires root.
+    pub fn open(number: u32) -> Result<RawDisk, Error> {
+        let file = File::open(format!("/dev/rdisk{}", number))
+            .map_err(|source| Error::Io { source, pos: 0 })?;
+        RawDisk::from_file(file)
+    }
+
+    /// Wrap an already opened device.
+    pub fn from_file(file: File) -> Result<RawDisk, Error> {
+        let geometry = geometry(&file)?;
+        Ok(RawDisk { file, geometry })
+    }
+
+    /// The geometry reported by the device.
+    pub fn geometry(&self) -> Geometry {
+        self.geometry
+    }
+
+    /// [`Options`] with the sector size and disc length filled in from the device.
+    pub fn options(&self) -> Result<Options, Error> {
+        Ok(Options {
+            sector_size: SectorSize::Known(u16::try_from(self.geometry.block_size).map_err(
+                |_| Error::InvalidStatic {
+                    message: "sector size is implausibly large",
+                },
+            )?),
+            disk_len: Some(self.geometry.len),
+            ..Options::default()
+        })
+    }
+}
+
+impl positioned_io2::ReadAt for RawDisk {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        crate::io::read_aligned(&self.file, u64::from(self.geometry.block_size), pos, buf)
+    }
+}
+
+impl positioned_io2::Size for RawDisk {
+    fn size(&self) -> io::Result<Option<u64>> {
+        Ok(Some(self.geometry.len))
+    }
+}
+
+/// Ask the kernel for a disc's block size and count.
+pub fn geometry(file: &File) -> Result<Geometry, Error> {
+    let mut block_size: u32 = 0;
+    ioctl(file, DKIOCGETBLOCKSIZE, &mut block_size)?;
+
+    let mut block_count: u64 = 0;
+    ioctl(file, DKIOCGETBLOCKCOUNT, &mut block_count)?;
+
+    if block_size < 512 || !block_size.is_power_of_two() {
+        return Err(Error::InvalidStatic {
+            message: "disc reported an implausible block size",
+        });
+    }
+
+    Ok(Geometry {
+        len: block_count
+            .checked_mul(u64::from(block_size))
+            .ok_or(Error::Overflow)?,
+        block_size,
+    })
+}
+
+fn ioctl<T>(file: &File, request: u32, value: &mut T) -> Result<(), Error> {
+    #[allow(clippy::cast_lossless)]
+    let ret = unsafe { libc::ioctl(file.as_raw_fd(), request as _, value as *mut T) };
+    if -1 == ret {
+        return Err(Error::Io {
+            source: io::Error::last_os_error(),
+            pos: 0,
+        });
+    }
+    Ok(())
+}
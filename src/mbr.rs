@@ -1,53 +1,349 @@
-use alloc::{format, vec::Vec};
+use alloc::{format, string::String, vec::Vec};
 use core::convert::TryFrom;
 
-use crate::{le, Error, Partition};
+use crate::verify::{Finding, Severity};
+use crate::{le, Attributes, Error, Partition};
 
 const SECTOR_SIZE: usize = 512;
 
 /// Read a DOS/MBR partition table from a 512-byte boot sector, providing a disc sector size.
 pub fn parse_partition_table(sector: &[u8; SECTOR_SIZE]) -> Result<Vec<Partition>, Error> {
-    let mut partitions = Vec::with_capacity(4);
-
-    for entry_id in 0..4 {
-        let first_entry_offset = 446;
-        let entry_size = 16;
-        let entry_offset = first_entry_offset + entry_id * entry_size;
-        let partition = &sector[entry_offset..entry_offset + entry_size];
-        let status = partition[0];
-        let bootable = match status {
-            0x00 => false,
-            0x80 => true,
-            _ => {
-                return Err(Error::InvalidData {
-                    message: format!(
-                        "invalid status code in partition {}: {:x}",
-                        entry_id, status
-                    ),
-                });
-            }
-        };
+    let entries = parse_entries(sector).map_err(|invalid| invalid_status(&invalid))?;
+
+    Ok(to_partitions(&entries))
+}
+
+/// The layout of a boot sector's partition table. Some old tools squeezed extra entries
+/// in before the usual four, growing down into the boot code, and marked this with a
+/// signature of their own.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Layout {
+    /// The four primary entries, at `0x1be`.
+    Standard,
+    /// PTS-DOS's Advanced Active Partition, also written by DR-DOS's NEWLDR: a fifth
+    /// entry at `0x1ae`, marked by `78 56` at `0x1ac`. This overlaps the disc signature.
+    Aap,
+    /// AST and NEC MS-DOS, and SpeedStor: eight entries, marked by `5a a5` at `0x17c`.
+    AstNec,
+    /// Ontrack Disk Manager: sixteen entries, marked by `aa 55` at `0xfc`.
+    DiskManager,
+}
+
+impl Layout {
+    /// How many entries there are.
+    pub fn entries(self) -> usize {
+        match self {
+            Layout::Standard => 4,
+            Layout::Aap => 5,
+            Layout::AstNec => 8,
+            Layout::DiskManager => 16,
+        }
+    }
+}
 
-        let type_code = partition[4];
+/// Which layout the sector's signatures claim it has. These are in what is usually boot
+/// code, so can't be told apart from boot code which happens to contain the same bytes;
+/// only use this when reading discs which are expected to be old.
+pub fn detect_layout(sector: &[u8; SECTOR_SIZE]) -> Layout {
+    // the larger tables cover the smaller tables' signatures, so are checked first
+    if [0xaa, 0x55] == sector[0xfc..0xfe] {
+        Layout::DiskManager
+    } else if [0x5a, 0xa5] == sector[0x17c..0x17e] {
+        Layout::AstNec
+    } else if [0x78, 0x56] == sector[0x1ac..0x1ae] {
+        Layout::Aap
+    } else {
+        Layout::Standard
+    }
+}
 
-        if 0 == type_code {
-            continue;
+/// Read the entries of a table in the given layout. Those beyond the primary four have
+/// ids from 4, in the order their tools number them, i.e. downwards from `0x1ae`.
+///
+/// If `lenient`, an entry with a status byte other than `0x00` or `0x80` is read as not
+/// bootable, with an `mbr-status` warning, instead of refusing the whole sector.
+pub(crate) fn parse_layout(
+    sector: &[u8; SECTOR_SIZE],
+    layout: Layout,
+    lenient: bool,
+    warnings: &mut Vec<Finding>,
+) -> Result<Vec<Partition>, Error> {
+    let mut entries = Vec::with_capacity(layout.entries());
+    for entry_id in 0..layout.entries() {
+        let status = sector[entry_offset(entry_id)];
+        if 0x00 != status && 0x80 != status {
+            let invalid = InvalidStatus {
+                entry: entry_id,
+                status,
+            };
+            if !lenient {
+                return Err(invalid_status(&invalid));
+            }
+            warnings.push(Finding {
+                severity: Severity::Warning,
+                code: "mbr-status",
+                message: invalid_status_message(&invalid),
+            });
         }
+        entries.push(entry_at(sector, entry_id));
+    }
+
+    Ok(to_partitions(&entries))
+}
+
+fn invalid_status(invalid: &InvalidStatus) -> Error {
+    Error::InvalidData {
+        message: invalid_status_message(invalid),
+    }
+}
+
+fn invalid_status_message(invalid: &InvalidStatus) -> String {
+    format!(
+        "invalid status code in partition {}: {:x}",
+        invalid.entry, invalid.status
+    )
+}
 
-        let sector_size = u64::try_from(SECTOR_SIZE).expect("u64 constant");
-        let first_byte = u64::from(le::read_u32(&partition[8..])) * sector_size;
-        let len = u64::from(le::read_u32(&partition[12..])) * sector_size;
+fn to_partitions(entries: &[Option<Entry>]) -> Vec<Partition> {
+    let sector_size = u64::try_from(SECTOR_SIZE).expect("u64 constant");
+
+    let mut partitions = Vec::with_capacity(entries.len());
+    for (entry_id, entry) in entries.iter().enumerate() {
+        let entry = match entry {
+            Some(entry) => entry,
+            None => continue,
+        };
 
         partitions.push(Partition {
             id: entry_id,
-            first_byte,
-            len,
-            attributes: crate::Attributes::MBR {
-                type_code,
-                bootable,
+            first_byte: u64::from(entry.first_lba) * sector_size,
+            len: u64::from(entry.sectors) * sector_size,
+            attributes: Attributes::MBR {
+                type_code: entry.type_code,
+                bootable: entry.bootable,
             },
+            label: None,
         });
     }
 
-    Ok(partitions)
+    partitions
+}
+
+/// A primary partition entry, as decoded by [`parse_entries`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Entry {
+    pub bootable: bool,
+    pub type_code: u8,
+    /// In 512-byte sectors.
+    pub first_lba: u32,
+    /// In 512-byte sectors.
+    pub sectors: u32,
+}
+
+/// An entry's status byte is neither `0x00` nor `0x80`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct InvalidStatus {
+    /// Which of the entries, from zero.
+    pub entry: usize,
+    pub status: u8,
+}
+
+/// Decode the four primary entries of a boot sector, without allocating, so it can be
+/// used in a `const` context, e.g. to check a boot sector embedded in firmware at build
+/// time. Empty entries, with type code zero, are `None`.
+///
+/// As with [`parse_partition_table`], the `55 AA` signature is not checked.
+///
+/// ```rust
+/// use bootsector::mbr::{parse_entries, protective_mbr, Entry};
+///
+/// const SECTOR: [u8; 512] = [0u8; 512];
+/// const ENTRIES: [Option<Entry>; 4] = match parse_entries(&SECTOR) {
+///     Ok(entries) => entries,
+///     Err(_) => [None; 4],
+/// };
+/// assert_eq!([None; 4], ENTRIES);
+///
+/// let protective = parse_entries(&protective_mbr(8)).unwrap();
+/// assert_eq!(Some(0xee), protective[0].map(|entry| entry.type_code));
+/// ```
+pub const fn parse_entries(
+    sector: &[u8; SECTOR_SIZE],
+) -> Result<[Option<Entry>; 4], InvalidStatus> {
+    let mut entries = [None; 4];
+
+    let mut entry_id = 0;
+    while entry_id < 4 {
+        let status = sector[446 + entry_id * 16];
+        if 0x00 != status && 0x80 != status {
+            return Err(InvalidStatus {
+                entry: entry_id,
+                status,
+            });
+        }
+
+        entries[entry_id] = entry_at(sector, entry_id);
+        entry_id += 1;
+    }
+
+    Ok(entries)
+}
+
+/// Where an entry is: the primary four count up from `0x1be`, and any others down from it.
+const fn entry_offset(entry_id: usize) -> usize {
+    if entry_id < 4 {
+        446 + entry_id * 16
+    } else {
+        446 - (entry_id - 3) * 16
+    }
+}
+
+/// Decode an entry, which is bootable only if its status is exactly `0x80`.
+const fn entry_at(sector: &[u8; SECTOR_SIZE], entry_id: usize) -> Option<Entry> {
+    let offset = entry_offset(entry_id);
+    let type_code = sector[offset + 4];
+    if 0 == type_code {
+        return None;
+    }
+
+    Some(Entry {
+        bootable: 0x80 == sector[offset],
+        type_code,
+        first_lba: read_u32_const(sector, offset + 8),
+        sectors: read_u32_const(sector, offset + 12),
+    })
+}
+
+const fn read_u32_const(sector: &[u8; SECTOR_SIZE], at: usize) -> u32 {
+    u32::from_le_bytes([sector[at], sector[at + 1], sector[at + 2], sector[at + 3]])
+}
+
+/// The disc signature (or "NT disk identifier") from a boot sector.
+pub fn disk_signature(sector: &[u8; SECTOR_SIZE]) -> u32 {
+    le::read_u32(&sector[0x1b8..0x1bc])
+}
+
+/// Replace the disc signature in a boot sector, e.g. with a random value after cloning.
+pub fn set_disk_signature(sector: &mut [u8; SECTOR_SIZE], signature: u32) {
+    sector[0x1b8..0x1bc].copy_from_slice(&signature.to_le_bytes());
+}
+
+/// The type codes boot managers, such as OS/2 Boot Manager and Partition Magic's, hide
+/// DOS and Windows partitions behind, by setting `0x10`, so DOS doesn't give them a drive
+/// letter: FAT12, FAT16 (small, large, and LBA), NTFS/exFAT/HPFS, and FAT32 (CHS and LBA).
+const HIDDEN: [u8; 7] = [0x11, 0x14, 0x16, 0x17, 0x1b, 0x1c, 0x1e];
+
+/// Is this type code a hidden variant of another, e.g. `0x17`, a hidden NTFS partition?
+pub fn is_hidden_type(type_code: u8) -> bool {
+    HIDDEN.contains(&type_code)
+}
+
+/// The type code with any hiding undone, i.e. what the partition actually contains, e.g.
+/// `0x07` for `0x17`. Other codes are returned unchanged.
+///
+/// ```rust
+/// use bootsector::mbr::unhidden_type;
+///
+/// assert_eq!(0x0c, unhidden_type(0x1c));
+/// assert_eq!(0x83, unhidden_type(0x83));
+/// ```
+pub fn unhidden_type(type_code: u8) -> u8 {
+    if is_hidden_type(type_code) {
+        type_code - 0x10
+    } else {
+        type_code
+    }
+}
+
+/// Is this an MBR partition with a hidden type code? GPT has its own hidden attribute,
+/// in [`crate::attributes::MicrosoftBasicData`], which this doesn't look at.
+pub fn is_hidden(part: &Partition) -> bool {
+    match part.attributes {
+        Attributes::MBR { type_code, .. } => is_hidden_type(type_code),
+        Attributes::GPT { .. } => false,
+    }
+}
+
+/// The boot code at the start of the boot sector, which the BIOS runs.
+///
+/// This is the 440 bytes before the disc signature. Some older boot code extends into
+/// the signature, and the bytes after it; see [`BootCode`] for what we recognise.
+pub fn bootstrap_code(sector: &[u8; SECTOR_SIZE]) -> &[u8] {
+    &sector[..BOOTSTRAP_LEN]
+}
+
+/// The length of [`bootstrap_code`].
+pub const BOOTSTRAP_LEN: usize = 440;
+
+/// Well-known boot code, as identified by [`identify_boot_code`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum BootCode {
+    /// All zeros, e.g. from fdisk, or on a disc which has only ever booted with UEFI.
+    Empty,
+    /// GRUB's `boot.img`, or legacy GRUB's stage1.
+    Grub,
+    /// syslinux's `mbr.bin`.
+    Syslinux,
+    /// isolinux's `isohdp*.bin`, used by "isohybrid" CD images.
+    Isolinux,
+    /// LILO.
+    Lilo,
+    /// Windows NT, 2000, and XP.
+    WindowsNt,
+    /// Windows Vista and later, which can measure the boot into a TPM.
+    WindowsVista,
+    /// Something else.
+    Unknown,
+}
+
+/// Guess which boot loader wrote the boot code, from the messages it contains.
+pub fn identify_boot_code(sector: &[u8; SECTOR_SIZE]) -> BootCode {
+    let code = bootstrap_code(sector);
+    let contains = |needle: &[u8]| code.windows(needle.len()).any(|window| window == needle);
+
+    if code.iter().all(|&b| 0 == b) {
+        BootCode::Empty
+    } else if b"LILO" == &sector[6..10] {
+        BootCode::Lilo
+    } else if contains(b"GRUB \0Geom\0") {
+        BootCode::Grub
+    } else if contains(b"isolinux.bin missing or corrupt") {
+        BootCode::Isolinux
+    } else if contains(b"Missing operating system.\r\n") {
+        BootCode::Syslinux
+    } else if contains(b"Invalid partition table") {
+        if contains(b"TCPA") {
+            BootCode::WindowsVista
+        } else {
+            BootCode::WindowsNt
+        }
+    } else {
+        BootCode::Unknown
+    }
+}
+
+/// Build a protective MBR for a GPT disc of `disk_size_lbas` sectors.
+///
+/// The single 0xEE partition starts at LBA 1 and covers the rest of the disc,
+/// clamped to the largest size an MBR can express, as the UEFI specification requires.
+pub fn protective_mbr(disk_size_lbas: u64) -> [u8; SECTOR_SIZE] {
+    let mut sector = [0u8; SECTOR_SIZE];
+
+    let size = u32::try_from(disk_size_lbas.saturating_sub(1)).unwrap_or(u32::MAX);
+
+    let entry = &mut sector[446..446 + 16];
+    // not bootable
+    entry[0] = 0x00;
+    // CHS of the start, LBA 1: cylinder 0, head 0, sector 2
+    entry[1..4].copy_from_slice(&[0x00, 0x02, 0x00]);
+    entry[4] = 0xEE;
+    // CHS of the end, which never fits
+    entry[5..8].copy_from_slice(&[0xFF, 0xFF, 0xFF]);
+    entry[8..12].copy_from_slice(&1u32.to_le_bytes());
+    entry[12..16].copy_from_slice(&size.to_le_bytes());
+
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+
+    sector
 }
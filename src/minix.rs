@@ -0,0 +1,107 @@
+//! Minix's subpartitions: a second, MBR-style, table in the first sector of a Minix
+//! partition, which divides it up further. Set [`Options::minix_subpartitions`] to list
+//! these along with the disc's own partitions.
+//!
+//! [`Options::minix_subpartitions`]: crate::Options::minix_subpartitions
+//!
+//! ```rust
+//! # fn go(disc: &[u8]) -> Result<(), bootsector::Error> {
+//! for part in bootsector::list_partitions(disc, &Default::default())? {
+//!     for sub in bootsector::minix::read_subpartitions(disc, &part)? {
+//!         println!("{}: {} bytes", part.id, sub.len);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::vec::Vec;
+
+use snafu::ResultExt;
+
+use crate::errors::ReadSnafu;
+use crate::{io, mbr, Attributes, Error, Partition};
+
+/// The MBR type codes of Minix partitions: "old Minix", and Minix 1.4b and later.
+const TYPE_CODES: [u8; 2] = [0x80, 0x81];
+
+/// Is this a Minix partition, which may have subpartitions?
+pub fn is_minix(part: &Partition) -> bool {
+    match part.attributes {
+        Attributes::MBR { type_code, .. } => TYPE_CODES.contains(&type_code),
+        Attributes::GPT { .. } => false,
+    }
+}
+
+/// Read the subpartitions of a Minix partition. Anything else, or a Minix partition
+/// without a subpartition table, has none.
+///
+/// As in Linux, the table is only believed if its first entry is a Minix partition, and
+/// only Minix entries are returned. Their positions are from the start of the disc, as
+/// with the MBR's own partitions; any which aren't inside the partition are ignored.
+/// Each is given the id of its slot in the subpartition table.
+pub fn read_subpartitions<R>(reader: R, part: &Partition) -> Result<Vec<Partition>, Error>
+where
+    R: io::ReadAt,
+{
+    read(&reader, part)
+}
+
+pub(crate) fn read<R>(reader: &R, part: &Partition) -> Result<Vec<Partition>, Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    let mut sector = [0u8; 512];
+    if !is_minix(part) || part.len < sector.len() as u64 {
+        return Ok(Vec::new());
+    }
+
+    reader
+        .read_exact_at(part.first_byte, &mut sector)
+        .context(ReadSnafu {
+            what: "a minix subpartition table",
+            pos: part.first_byte,
+        })?;
+
+    if 0x55 != sector[510] || 0xAA != sector[511] {
+        return Ok(Vec::new());
+    }
+
+    let entries = match mbr::parse_entries(&sector) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(Vec::new()),
+    };
+
+    match entries[0] {
+        Some(entry) if TYPE_CODES.contains(&entry.type_code) => (),
+        _ => return Ok(Vec::new()),
+    }
+
+    let end = part.end_byte();
+    let mut partitions = Vec::with_capacity(entries.len());
+    for (id, entry) in entries.iter().enumerate() {
+        let entry = match entry {
+            Some(entry) if TYPE_CODES.contains(&entry.type_code) => entry,
+            _ => continue,
+        };
+
+        let first_byte = u64::from(entry.first_lba) * 512;
+        let len = u64::from(entry.sectors) * 512;
+        if first_byte < part.first_byte || first_byte + len > end {
+            continue;
+        }
+
+        partitions.push(Partition {
+            id,
+            first_byte,
+            len,
+            attributes: Attributes::MBR {
+                bootable: entry.bootable,
+                type_code: entry.type_code,
+            },
+            label: None,
+        });
+    }
+
+    Ok(partitions)
+}
@@ -0,0 +1,110 @@
+//! Read files by mapping them into memory, which avoids a system call for every read.
+//!
+//! This is much faster when scanning many small images. The usual caveat applies: if the
+//! file is modified (or, worse, truncated) while it's mapped, the contents will change
+//! underneath us, or the process may be killed. Only map files nobody else is writing.
+//!
+//! ```rust,no_run
+//! # fn go() -> Result<(), bootsector::Error> {
+//! let image = bootsector::mmap::MappedFile::open("disc.img")?;
+//! let table = bootsector::read_partition_table(&image, &Default::default())?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io;
+use std::ops::Deref;
+use std::os::raw::c_void;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+use crate::Error;
+
+/// A read-only mapping of a whole file.
+pub struct MappedFile {
+    ptr: *mut c_void,
+    len: usize,
+}
+
+// the mapping is read-only, and owned by us
+unsafe impl Send for MappedFile {}
+unsafe impl Sync for MappedFile {}
+
+impl MappedFile {
+    /// Open and map a file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<MappedFile, Error> {
+        let file = File::open(path).map_err(|source| Error::Io { source, pos: 0 })?;
+        MappedFile::map(&file)
+    }
+
+    /// Map an open file. The file can be closed afterwards; the mapping remains.
+    pub fn map(file: &File) -> Result<MappedFile, Error> {
+        let len = file
+            .metadata()
+            .map_err(|source| Error::Io { source, pos: 0 })?
+            .len();
+        let len = usize::try_from(len).map_err(|_| Error::BiggerThanMemory)?;
+
+        // empty mappings are not allowed
+        if 0 == len {
+            return Ok(MappedFile {
+                ptr: std::ptr::null_mut(),
+                len,
+            });
+        }
+
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+
+        if libc::MAP_FAILED == ptr {
+            return Err(Error::Io {
+                source: io::Error::last_os_error(),
+                pos: 0,
+            });
+        }
+
+        Ok(MappedFile { ptr, len })
+    }
+}
+
+impl Deref for MappedFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        if 0 == self.len {
+            return &[];
+        }
+
+        unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+}
+
+impl Drop for MappedFile {
+    fn drop(&mut self) {
+        if 0 != self.len {
+            unsafe { libc::munmap(self.ptr, self.len) };
+        }
+    }
+}
+
+impl positioned_io2::ReadAt for MappedFile {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        positioned_io2::ReadAt::read_at(&&**self, pos, buf)
+    }
+}
+
+impl positioned_io2::Size for MappedFile {
+    fn size(&self) -> io::Result<Option<u64>> {
+        Ok(u64::try_from(self.len).ok())
+    }
+}
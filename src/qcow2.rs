@@ -0,0 +1,209 @@
+//! Read the guest disc inside a qcow2 image, as used by qemu.
+//!
+//! Only uncompressed, unencrypted images without a backing file are supported. Clusters
+//! which have never been written read as zeros.
+//!
+//! ```rust,no_run
+//! # fn go() -> Result<(), bootsector::Error> {
+//! let file = std::fs::File::open("disc.qcow2").unwrap();
+//! let disc = bootsector::qcow2::Qcow2::open(file)?;
+//! let table = bootsector::read_partition_table_sized(&disc, &Default::default())?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::TryFrom;
+use std::io;
+
+use crate::be;
+use crate::io::ReadAt;
+use crate::Error;
+
+const MAGIC: &[u8; 4] = b"QFI\xfb";
+
+/// Bits 9 to 55 of an L1 or L2 entry.
+const OFFSET_MASK: u64 = 0x00ff_ffff_ffff_fe00;
+const COMPRESSED: u64 = 1 << 62;
+/// Version 3 only: the cluster reads as zeros, regardless of any allocation.
+const ZERO: u64 = 1;
+
+/// Incompatible feature bits which don't prevent reading: dirty, and compression type.
+const UNDERSTOOD_INCOMPATIBLE: u64 = 0b1001;
+
+/// Match qemu's limit on the size of the L1 table.
+const MAX_L1_BYTES: u64 = 32 * 1024 * 1024;
+
+/// A qcow2 image, readable as the disc it contains.
+pub struct Qcow2<R> {
+    inner: R,
+    cluster_bits: u32,
+    disk_len: u64,
+    l1: Vec<u64>,
+}
+
+impl<R: positioned_io2::ReadAt> Qcow2<R> {
+    /// Read and validate the header and the L1 table.
+    pub fn open(inner: R) -> Result<Qcow2<R>, Error> {
+        let mut header = [0u8; 104];
+        ReadAt::read_exact_at(&inner, 0, &mut header[..72])?;
+
+        if MAGIC != &header[..4] {
+            return Err(Error::NotFound);
+        }
+
+        let version = be::read_u32(&header[4..]);
+        match version {
+            2 => (),
+            3 => {
+                ReadAt::read_exact_at(&inner, 72, &mut header[72..])?;
+                let incompatible = be::read_u64(&header[72..]);
+                if 0 != incompatible & !UNDERSTOOD_INCOMPATIBLE {
+                    return Err(Error::InvalidData {
+                        message: format!("unsupported qcow2 features: {:#x}", incompatible),
+                    });
+                }
+            }
+            _ => {
+                return Err(Error::InvalidData {
+                    message: format!("unsupported qcow2 version: {}", version),
+                })
+            }
+        }
+
+        if 0 != be::read_u64(&header[8..]) {
+            return Err(Error::InvalidStatic {
+                message: "qcow2 images with backing files are not supported",
+            });
+        }
+
+        let cluster_bits = be::read_u32(&header[20..]);
+        if !(9..=21).contains(&cluster_bits) {
+            return Err(Error::InvalidData {
+                message: format!("invalid qcow2 cluster size: 2^{}", cluster_bits),
+            });
+        }
+
+        let disk_len = be::read_u64(&header[24..]);
+
+        if 0 != be::read_u32(&header[32..]) {
+            return Err(Error::InvalidStatic {
+                message: "encrypted qcow2 images are not supported",
+            });
+        }
+
+        let l1_entries = be::read_u32(&header[36..]);
+        let l1_offset = be::read_u64(&header[40..]);
+
+        // each L2 table is one cluster of 8-byte entries, each mapping a cluster
+        let l2_bits = cluster_bits - 3;
+        let needed = match disk_len {
+            0 => 0,
+            len => ((len - 1) >> cluster_bits >> l2_bits) + 1,
+        };
+        if u64::from(l1_entries) < needed {
+            return Err(Error::InvalidStatic {
+                message: "qcow2 L1 table is too small for the disc",
+            });
+        }
+
+        let l1_bytes = u64::from(l1_entries) * 8;
+        if l1_bytes > MAX_L1_BYTES {
+            return Err(Error::InvalidStatic {
+                message: "qcow2 L1 table is unreasonably large",
+            });
+        }
+
+        let mut raw = vec![0u8; usize::try_from(l1_bytes).map_err(|_| Error::BiggerThanMemory)?];
+        ReadAt::read_exact_at(&inner, l1_offset, &mut raw)?;
+        let l1 = raw.chunks_exact(8).map(be::read_u64).collect();
+
+        Ok(Qcow2 {
+            inner,
+            cluster_bits,
+            disk_len,
+            l1,
+        })
+    }
+
+    /// The length of the guest disc, in bytes.
+    pub fn disk_len(&self) -> u64 {
+        self.disk_len
+    }
+
+    /// The size of a cluster, the unit of allocation in the image.
+    pub fn cluster_size(&self) -> u64 {
+        1 << self.cluster_bits
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Where the cluster containing `pos` is stored in the image, if anywhere.
+    fn host_cluster(&self, pos: u64) -> io::Result<Option<u64>> {
+        let cluster = pos >> self.cluster_bits;
+        let l2_bits = self.cluster_bits - 3;
+
+        let l1_entry = usize::try_from(cluster >> l2_bits)
+            .ok()
+            .and_then(|index| self.l1.get(index))
+            .copied()
+            .unwrap_or(0);
+
+        let l2_offset = l1_entry & OFFSET_MASK;
+        if 0 == l2_offset {
+            return Ok(None);
+        }
+
+        let index = cluster & ((1 << l2_bits) - 1);
+        let mut entry = [0u8; 8];
+        positioned_io2::ReadAt::read_exact_at(&self.inner, l2_offset + index * 8, &mut entry)?;
+        let entry = be::read_u64(&entry);
+
+        if 0 != entry & COMPRESSED {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "compressed qcow2 clusters are not supported",
+            ));
+        }
+
+        if 0 != entry & ZERO {
+            return Ok(None);
+        }
+
+        let offset = entry & OFFSET_MASK;
+        Ok(if 0 == offset { None } else { Some(offset) })
+    }
+}
+
+impl<R: positioned_io2::ReadAt> positioned_io2::ReadAt for Qcow2<R> {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        if pos >= self.disk_len {
+            return Ok(0);
+        }
+
+        let remaining = usize::try_from(self.disk_len - pos).unwrap_or(usize::MAX);
+        let cluster_size = self.cluster_size();
+        let within = pos & (cluster_size - 1);
+        let available = usize::try_from(cluster_size - within).unwrap_or(usize::MAX);
+        let len = buf.len().min(remaining).min(available);
+        let buf = &mut buf[..len];
+
+        match self.host_cluster(pos)? {
+            Some(host) => positioned_io2::ReadAt::read_exact_at(&self.inner, host + within, buf)?,
+            None => buf.iter_mut().for_each(|b| *b = 0),
+        }
+
+        Ok(len)
+    }
+}
+
+impl<R> positioned_io2::Size for Qcow2<R> {
+    fn size(&self) -> io::Result<Option<u64>> {
+        Ok(Some(self.disk_len))
+    }
+}
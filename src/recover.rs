@@ -0,0 +1,228 @@
+//! Find what's left of partition tables which have been wiped, or overwritten, and of
+//! partitions which have been deleted.
+//!
+//! Nothing here writes to the disc; once a table has been found, it can be read with e.g.
+//! [`gpt::read_entries`], and written back with [`gpt::restore_primary`], or the editors.
+//!
+//! ```rust
+//! # fn go(disc: &[u8]) -> Result<(), bootsector::Error> {
+//! for found in bootsector::recover::find_gpt_headers(disc, 0, disc.len() as u64)? {
+//!     println!(
+//!         "header at byte {}, sector size {:?}, entries intact: {}",
+//!         found.byte_offset,
+//!         found.sector_size(),
+//!         found.entries_valid
+//!     );
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::{vec, vec::Vec};
+use core::convert::TryFrom;
+
+use snafu::ResultExt;
+
+use crate::errors::ReadSnafu;
+use crate::gpt::{self, Header};
+use crate::verify::GptCopy;
+use crate::{io, Error, InvalidNames, NameNormalization, Partition, Reserved};
+
+/// How much of the disc is read at once while searching.
+const CHUNK: usize = 1024 * 1024;
+
+/// A GPT header found by [`find_gpt_headers`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FoundHeader {
+    /// Where the header starts.
+    pub byte_offset: u64,
+
+    /// The header, whose checksum is right, but which is otherwise unchecked.
+    pub header: Header,
+
+    /// Whether the entry array the header points to is where it should be, and has the
+    /// checksum the header expects. This needs a [`sector_size`].
+    ///
+    /// [`sector_size`]: FoundHeader::sector_size
+    pub entries_valid: bool,
+}
+
+impl FoundHeader {
+    /// The sector size at which the header is at the LBA it claims to be, if there is one
+    /// which is plausible, i.e. a power of two from 512 bytes to [`gpt::MAX_SECTOR_SIZE`].
+    ///
+    /// A header without one has been moved, e.g. it's from a disc image stored in a
+    /// partition, or a file, so is not at the LBA it was written for.
+    pub fn sector_size(&self) -> Option<u64> {
+        sector_size(self.byte_offset, self.header.my_lba)
+    }
+}
+
+fn sector_size(byte_offset: u64, lba: u64) -> Option<u64> {
+    if 0 == lba || 0 != byte_offset % lba {
+        return None;
+    }
+
+    let size = byte_offset / lba;
+    if size.is_power_of_two() && (512..=gpt::MAX_SECTOR_SIZE).contains(&size) {
+        Some(size)
+    } else {
+        None
+    }
+}
+
+/// Search `len` bytes of the disc, starting at `first_byte`, for GPT headers, as `testdisk`
+/// does, returning those with a valid checksum, in order.
+///
+/// Every 512 byte boundary is checked for the `EFI PART` signature, so headers are found
+/// whatever the sector size, and even in disc images which have been copied to an unaligned
+/// location. The range must be on the disc.
+pub fn find_gpt_headers<R>(reader: R, first_byte: u64, len: u64) -> Result<Vec<FoundHeader>, Error>
+where
+    R: io::ReadAt,
+{
+    find(&reader, first_byte, len)
+}
+
+pub(crate) fn find<R>(reader: &R, first_byte: u64, len: u64) -> Result<Vec<FoundHeader>, Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    let end = first_byte.checked_add(len).ok_or(Error::Overflow)?;
+    let chunk = u64::try_from(CHUNK).expect("small");
+
+    let mut found = Vec::new();
+    let mut pos = first_byte;
+    while pos < end {
+        // read past the chunk, so a header which starts in it is read whole
+        let read_len = usize::try_from((end - pos).min(chunk + gpt::MAX_SECTOR_SIZE))
+            .map_err(|_| Error::BiggerThanMemory)?;
+        let mut data = vec![0u8; read_len];
+        reader.read_exact_at(pos, &mut data).context(ReadSnafu {
+            what: "the disc, searching for gpt headers",
+            pos,
+        })?;
+
+        let searched = read_len.min(CHUNK);
+        for offset in (0..searched).step_by(512) {
+            let sector = &data[offset..];
+            if sector.len() < gpt::MINIMUM_HEADER_SIZE || gpt::SIGNATURE != &sector[..8] {
+                continue;
+            }
+
+            let header = Header::decode(sector);
+            if Some(header.header_crc) != header.computed_crc(sector) {
+                continue;
+            }
+
+            let byte_offset = pos + u64::try_from(offset).expect("small");
+            // as for any header, an array larger than this is implausible
+            let plausible =
+                u16::try_from(header.entries).is_ok() && u16::try_from(header.entry_size).is_ok();
+            let entries_valid = match sector_size(byte_offset, header.my_lba) {
+                Some(sector_size) if plausible => {
+                    gpt::read_entry_array(reader, &header, sector_size).is_ok()
+                }
+                _ => false,
+            };
+
+            found.push(FoundHeader {
+                byte_offset,
+                header,
+                entries_valid,
+            });
+        }
+
+        pos += u64::try_from(searched).expect("small");
+    }
+
+    Ok(found)
+}
+
+/// An entry which is in use in one copy of the GPT, but empty in the other, as found by
+/// [`find_deleted_gpt_entries`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PossiblyDeleted {
+    /// The partition, as decoded from the copy which has it.
+    pub partition: Partition,
+
+    /// The copy the partition is in; it's empty in the other.
+    pub found_in: GptCopy,
+}
+
+/// Compare the primary and backup GPT entry arrays, slot by slot, returning the entries
+/// which are empty in one, but populated in the other.
+///
+/// Tools which delete partitions usually rewrite both copies, but an interrupted write, or
+/// a tool which only writes the primary, leaves the deleted partition in the backup; the
+/// normal read uses the primary, so such partitions are silently skipped. A partition only
+/// in the primary is more likely to be newly added, with the backup not yet updated.
+///
+/// Neither entry array's checksum is checked, as it may be exactly what's broken, but both
+/// headers must be valid. Entries which couldn't be read normally, e.g. as they're outside
+/// the usable area, are ignored.
+pub fn find_deleted_gpt_entries<R>(
+    reader: R,
+    disk_len: u64,
+    sector_size: u64,
+) -> Result<Vec<PossiblyDeleted>, Error>
+where
+    R: io::ReadAt,
+{
+    deleted(&reader, disk_len, sector_size)
+}
+
+pub(crate) fn deleted<R>(
+    reader: &R,
+    disk_len: u64,
+    sector_size: u64,
+) -> Result<Vec<PossiblyDeleted>, Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    let primary = gpt::read_primary(reader, sector_size, &Reserved::Tolerate, &mut Vec::new())?;
+    let primary_table = gpt::read_entry_array_unchecked(reader, &primary, sector_size)?;
+
+    let backup_lba = (disk_len / sector_size)
+        .checked_sub(1)
+        .ok_or(Error::InvalidStatic {
+            message: "disc is empty",
+        })?;
+    let backup = gpt::header_at(reader, backup_lba * sector_size, sector_size)?;
+    let backup_table = gpt::read_entry_array_unchecked(reader, &backup, sector_size)?;
+
+    let decode = |id, table: &[u8], header: &Header| -> Option<Partition> {
+        let entry_size = usize::try_from(header.entry_size).ok()?;
+        let entry = table.get(id * entry_size..(id + 1) * entry_size)?;
+        gpt::decode_entry(
+            id,
+            entry,
+            header,
+            sector_size,
+            &InvalidNames::Replace,
+            &NameNormalization::default(),
+            &mut Vec::new(),
+        )
+        .ok()?
+    };
+
+    let mut found = Vec::new();
+    let slots = primary.entries.min(backup.entries);
+    for id in 0..usize::try_from(slots).map_err(|_| Error::Overflow)? {
+        let in_primary = decode(id, &primary_table, &primary);
+        let in_backup = decode(id, &backup_table, &backup);
+        match (in_primary, in_backup) {
+            (None, Some(partition)) => found.push(PossiblyDeleted {
+                partition,
+                found_in: GptCopy::Backup,
+            }),
+            (Some(partition), None) => found.push(PossiblyDeleted {
+                partition,
+                found_in: GptCopy::Primary,
+            }),
+            _ => (),
+        }
+    }
+
+    Ok(found)
+}
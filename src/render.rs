@@ -0,0 +1,413 @@
+//! Human-readable descriptions of partitions and tables, for logs and command-line tools.
+//!
+//! [`Partition`] and [`Attributes`] implement `Display`, in one line; [`render_table`]
+//! lays out a whole table, one partition per line, like `fdisk -l`; [`write_layout`]
+//! draws the disc as a bar, to show where everything is. Sizes are formatted with
+//! [`ByteSize`].
+//!
+//! ```rust
+//! # fn go(disc: &[u8]) -> Result<(), bootsector::Error> {
+//! let table = bootsector::read_partition_table(disc, &Default::default())?;
+//! print!("{}", bootsector::render::render_table(&table));
+//! for part in &table.partitions {
+//!     log(&format!("found {}", part));
+//! }
+//! # Ok(())
+//! # }
+//! # fn log(_: &str) {}
+//! ```
+
+use alloc::{format, string::String, vec::Vec};
+use core::convert::TryFrom;
+use core::fmt::{self, Write};
+use core::ops::Range;
+
+use crate::{guid, Attributes, Partition, PartitionTable, TableType};
+
+/// e.g. `0x83, bootable`, or `C12A7328-F81F-11D2-BA4B-00A0C93EC93B "EFI"`.
+impl fmt::Display for Attributes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Attributes::MBR {
+                bootable,
+                type_code,
+            } => {
+                write!(f, "0x{:02x}", type_code)?;
+                if *bootable {
+                    write!(f, ", bootable")?;
+                }
+                Ok(())
+            }
+            Attributes::GPT {
+                type_uuid, name, ..
+            } => {
+                write!(f, "{}", guid::to_string(type_uuid))?;
+                if !name.is_empty() {
+                    write!(f, " {:?}", name)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// e.g. `1: 512.0 MiB at byte 1048576, 0x0c, bootable`.
+impl fmt::Display for Partition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} at byte {}, {}",
+            self.id,
+            ByteSize(self.len),
+            self.first_byte,
+            self.attributes
+        )?;
+        if let Some(label) = &self.label {
+            write!(f, ", label {:?}", label)?;
+        }
+        Ok(())
+    }
+}
+
+/// Lay out a table like `fdisk -l` does: a line describing the disc, then a line for
+/// each partition, with its id, its position in sectors, its size, and its type.
+/// Columns are aligned, and every line ends with a newline.
+pub fn render_table(table: &PartitionTable) -> String {
+    let mut out = String::new();
+
+    // writing to a String cannot fail
+    let _ = write_table(&mut out, table);
+
+    out
+}
+
+fn write_table(out: &mut String, table: &PartitionTable) -> fmt::Result {
+    match (table.table_type, table.disk_guid) {
+        (TableType::GPT, Some(disk_guid)) => writeln!(
+            out,
+            "GPT, {} byte sectors, disk guid {}",
+            table.sector_size,
+            guid::to_string(&disk_guid)
+        )?,
+        _ => writeln!(
+            out,
+            "MBR, {} byte sectors, disk signature 0x{:08x}",
+            table.sector_size, table.disk_signature
+        )?,
+    }
+
+    let mut rows: Vec<[String; 8]> = Vec::with_capacity(table.partitions.len() + 1);
+    rows.push([
+        String::from("ID"),
+        String::from("Boot"),
+        String::from("Start"),
+        String::from("End"),
+        String::from("Sectors"),
+        String::from("Size"),
+        String::from("Type"),
+        String::from("Name"),
+    ]);
+
+    for part in &table.partitions {
+        let sectors = part.len / table.sector_size;
+        let start = part.first_byte / table.sector_size;
+        let (boot, kind, name) = match &part.attributes {
+            Attributes::MBR {
+                bootable,
+                type_code,
+            } => (*bootable, format!("0x{:02x}", type_code), String::new()),
+            Attributes::GPT {
+                type_uuid, name, ..
+            } => (false, guid::to_string(type_uuid), name.clone()),
+        };
+
+        rows.push([
+            format!("{}", part.id),
+            String::from(if boot { "*" } else { "" }),
+            format!("{}", start),
+            format!("{}", (start + sectors).saturating_sub(1)),
+            format!("{}", sectors),
+            part.len_human(),
+            kind,
+            match &part.label {
+                Some(label) if name.is_empty() => label.clone(),
+                Some(label) => format!("{} ({})", name, label),
+                None => name,
+            },
+        ]);
+    }
+
+    let mut widths = [0usize; 8];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    for row in &rows {
+        let mut line = String::new();
+        for (col, (cell, width)) in row.iter().zip(widths.iter()).enumerate() {
+            match col {
+                // numbers are right-aligned, text left-aligned
+                0 | 2..=5 => write!(line, "{:>width$} ", cell, width = width)?,
+                _ => write!(line, "{:<width$} ", cell, width = width)?,
+            }
+        }
+        writeln!(out, "{}", line.trim_end())?;
+    }
+
+    Ok(())
+}
+
+/// How [`write_layout`] draws a disc.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LayoutOptions {
+    /// How many characters wide the bar is, not counting its ends.
+    pub width: usize,
+
+    /// Draw the bar with Unicode block characters, rather than plain ASCII.
+    pub unicode: bool,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> LayoutOptions {
+        LayoutOptions {
+            width: 64,
+            unicode: false,
+        }
+    }
+}
+
+/// Something drawn by [`write_layout`]: a partition, a table, or free space.
+struct Area {
+    range: Range<u64>,
+    mark: char,
+    what: String,
+}
+
+/// Draw the disc as a bar, to scale, followed by a key: a line for each partition, each
+/// area used by the table, and each gap, in order, then a line for each overlap.
+///
+/// Partitions are drawn with their id, as a digit, then a letter, and `#` after `z`; the
+/// tables as `=`, free space as `.`, and overlaps as `!`. Each character of the bar shows
+/// whatever covers most of it, so something much smaller than a character may not be
+/// drawn, but it's always in the key. The scale is `disk_len`, or wider, if partitions
+/// claim to extend past it.
+///
+/// ```rust
+/// # fn go(disc: &[u8]) -> Result<(), bootsector::Error> {
+/// use bootsector::render::{write_layout, LayoutOptions};
+///
+/// let table = bootsector::read_partition_table(disc, &Default::default())?;
+/// let mut out = String::new();
+/// write_layout(&mut out, &table, disc.len() as u64, &LayoutOptions::default())
+///     .expect("writing to a String cannot fail");
+/// print!("{}", out);
+/// # Ok(())
+/// # }
+/// ```
+pub fn write_layout<W: Write>(
+    out: &mut W,
+    table: &PartitionTable,
+    disk_len: u64,
+    options: &LayoutOptions,
+) -> fmt::Result {
+    let (table_mark, free_mark, overlap_mark, end) = if options.unicode {
+        ('\u{2593}', '\u{2591}', '\u{2573}', '\u{2502}')
+    } else {
+        ('=', '.', '!', '|')
+    };
+
+    let mut used = Vec::with_capacity(table.partitions.len() + 2);
+    match table.usable_range() {
+        Some(usable) => {
+            used.push(Area {
+                range: 0..usable.start,
+                mark: table_mark,
+                what: String::from("MBR, GPT header and entries"),
+            });
+            if usable.end < disk_len {
+                used.push(Area {
+                    range: usable.end..disk_len,
+                    mark: table_mark,
+                    what: String::from("backup GPT entries and header"),
+                });
+            }
+        }
+        None => used.push(Area {
+            range: 0..table.sector_size.min(disk_len),
+            mark: table_mark,
+            what: String::from(match table.table_type {
+                TableType::MBR => "MBR",
+                TableType::GPT => "protective MBR",
+            }),
+        }),
+    }
+
+    for part in &table.partitions {
+        let mut what = format!("{}", part.attributes);
+        if let Some(label) = &part.label {
+            write!(what, ", label {:?}", label)?;
+        }
+        used.push(Area {
+            range: part.byte_range(),
+            mark: u32::try_from(part.id)
+                .ok()
+                .and_then(|id| core::char::from_digit(id, 36))
+                .unwrap_or('#'),
+            what,
+        });
+    }
+    used.retain(|area| area.range.start < area.range.end);
+    used.sort_by_key(|area| (area.range.start, area.range.end));
+
+    let scale = used
+        .iter()
+        .map(|area| area.range.end)
+        .fold(disk_len, u64::max);
+
+    let mut areas = Vec::with_capacity(used.len() * 2 + 1);
+    let mut covered = 0;
+    for area in &used {
+        if covered < area.range.start {
+            areas.push(Area {
+                range: covered..area.range.start,
+                mark: free_mark,
+                what: String::from("free"),
+            });
+        }
+        covered = covered.max(area.range.end);
+        areas.push(Area {
+            range: area.range.clone(),
+            mark: area.mark,
+            what: area.what.clone(),
+        });
+    }
+    if covered < scale {
+        areas.push(Area {
+            range: covered..scale,
+            mark: free_mark,
+            what: String::from("free"),
+        });
+    }
+
+    let edge = |cell: usize| {
+        let edge = u128::from(scale) * cell as u128 / options.width as u128;
+        u64::try_from(edge).expect("no more than scale")
+    };
+
+    out.write_char(end)?;
+    for cell in 0..options.width {
+        let (start, stop) = (edge(cell), edge(cell + 1));
+        let clip = |area: &Area| area.range.start.max(start)..area.range.end.min(stop);
+
+        let overlapping = used.iter().enumerate().any(|(i, a)| {
+            used[i + 1..].iter().any(|b| {
+                let (a, b) = (clip(a), clip(b));
+                a.start.max(b.start) < a.end.min(b.end)
+            })
+        });
+
+        let mark = if overlapping {
+            overlap_mark
+        } else {
+            // the first area which covers the most of the cell
+            let mut best: Option<(u64, char)> = None;
+            for area in &areas {
+                let clipped = clip(area);
+                let len = clipped.end.saturating_sub(clipped.start);
+                if 0 != len && best.map_or(true, |(most, _)| len > most) {
+                    best = Some((len, area.mark));
+                }
+            }
+            best.map_or(free_mark, |(_, mark)| mark)
+        };
+        out.write_char(mark)?;
+    }
+    out.write_char(end)?;
+    out.write_char('\n')?;
+
+    for area in &areas {
+        writeln!(
+            out,
+            "{}: {}..{} ({}), {}",
+            area.mark,
+            area.range.start,
+            area.range.end,
+            ByteSize(area.range.end - area.range.start),
+            area.what
+        )?;
+    }
+
+    for (i, a) in used.iter().enumerate() {
+        for b in &used[i + 1..] {
+            let (start, stop) = (
+                a.range.start.max(b.range.start),
+                a.range.end.min(b.range.end),
+            );
+            if start < stop {
+                writeln!(
+                    out,
+                    "{}: {} and {} overlap, {}..{} ({})",
+                    overlap_mark,
+                    a.mark,
+                    b.mark,
+                    start,
+                    stop,
+                    ByteSize(stop - start)
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Formats a number of bytes in the largest unit it's at least one of, to one decimal
+/// place, e.g. `1.5 GiB`. The alternate form, `{:#}`, uses SI units, e.g. `1.6 GB`.
+///
+/// ```rust
+/// use bootsector::render::ByteSize;
+///
+/// assert_eq!("1.5 GiB", format!("{}", ByteSize(1536 * 1024 * 1024)));
+/// assert_eq!("1.6 GB", format!("{:#}", ByteSize(1536 * 1024 * 1024)));
+/// assert_eq!("512 B", format!("{}", ByteSize(512)));
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ByteSize(pub u64);
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const BINARY: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+        const SI: [&str; 6] = ["kB", "MB", "GB", "TB", "PB", "EB"];
+
+        let (base, units) = if f.alternate() {
+            (1000, SI)
+        } else {
+            (1024, BINARY)
+        };
+
+        let len = u128::from(self.0);
+        if len < base {
+            return write!(f, "{} B", len);
+        }
+
+        let mut unit = base;
+        let mut name = 0;
+        // rounding to tenths may carry into the next unit, e.g. 1023.99 KiB is 1.0 MiB
+        while name + 1 < units.len() && len * 10 + unit / 2 >= unit * base * 10 {
+            unit *= base;
+            name += 1;
+        }
+
+        let tenths = (len * 10 + unit / 2) / unit;
+        write!(f, "{}.{} {}", tenths / 10, tenths % 10, units[name])
+    }
+}
+
+impl Partition {
+    /// The length of the partition, in binary units, e.g. `512.0 MiB`; see [`ByteSize`].
+    pub fn len_human(&self) -> String {
+        format!("{}", ByteSize(self.len))
+    }
+}
@@ -0,0 +1,353 @@
+//! Read an image straight out of S3, or anything speaking its protocol, such as MinIO or
+//! Ceph, with ranged `GET`s, so the partitions of a large image can be listed without
+//! downloading it.
+//!
+//! This is an example of a [`RemoteBlockSource`]; requests are signed with AWS Signature
+//! Version 4, if credentials are given, and are otherwise anonymous, for public buckets.
+//!
+//! ```rust,no_run
+//! use bootsector::io::{CachingReadAt, Remote};
+//! use bootsector::s3::{Credentials, S3Object};
+//!
+//! # fn go() -> Result<(), bootsector::Error> {
+//! let object = S3Object::new("eu-west-1", "images", "disc.img", Credentials::from_env());
+//! let disc = CachingReadAt::new(Remote::new(object)?, 64 * 1024, 16);
+//! let table = bootsector::read_partition_table_sized(&disc, &Default::default())?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt::Write;
+use std::io::{self, Read};
+use std::time::SystemTime;
+
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use snafu::ResultExt;
+
+use crate::errors::IoSnafu;
+use crate::io::RemoteBlockSource;
+use crate::Error;
+
+/// The hash of an empty body, which is all a `GET` or `HEAD` sends.
+const EMPTY_SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+/// The keys requests are signed with.
+#[derive(Clone)]
+pub struct Credentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+
+    /// The token which goes with temporary credentials, e.g. from an instance role.
+    pub session_token: Option<String>,
+}
+
+impl Credentials {
+    /// Read `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`, and, if set,
+    /// `AWS_SESSION_TOKEN`, as the AWS tools do. `None` if either key is unset.
+    pub fn from_env() -> Option<Credentials> {
+        Some(Credentials {
+            access_key_id: std::env::var("AWS_ACCESS_KEY_ID").ok()?,
+            secret_access_key: std::env::var("AWS_SECRET_ACCESS_KEY").ok()?,
+            session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+        })
+    }
+}
+
+/// An object in a bucket, holding a disc image.
+pub struct S3Object {
+    agent: ureq::Agent,
+    /// e.g. `https://example.com`, with no trailing slash.
+    base: String,
+    host: String,
+    /// Encoded, starting with a slash.
+    path: String,
+    region: String,
+    credentials: Option<Credentials>,
+}
+
+impl S3Object {
+    /// An object in AWS, addressed as `https://{bucket}.s3.{region}.amazonaws.com/{key}`.
+    pub fn new(
+        region: &str,
+        bucket: &str,
+        key: &str,
+        credentials: Option<Credentials>,
+    ) -> S3Object {
+        let host = format!("{}.s3.{}.amazonaws.com", bucket, region);
+        S3Object {
+            agent: ureq::Agent::new(),
+            base: format!("https://{}", host),
+            host,
+            path: format!("/{}", encode(key, false)),
+            region: region.to_string(),
+            credentials,
+        }
+    }
+
+    /// An object on another server, e.g. `http://localhost:9000`, addressed with the
+    /// bucket in the path, as most servers other than AWS expect.
+    ///
+    /// Returns `InvalidStatic` if the endpoint isn't an `http` or `https` URL.
+    pub fn with_endpoint(
+        endpoint: &str,
+        region: &str,
+        bucket: &str,
+        key: &str,
+        credentials: Option<Credentials>,
+    ) -> Result<S3Object, Error> {
+        let (base, host) = split_endpoint(endpoint)?;
+        Ok(S3Object {
+            agent: ureq::Agent::new(),
+            base: base.to_string(),
+            host: host.to_string(),
+            path: format!("/{}/{}", encode(bucket, false), encode(key, false)),
+            region: region.to_string(),
+            credentials,
+        })
+    }
+
+    /// An object on a server dedicated to its bucket, e.g.
+    /// `https://examplebucket.s3.amazonaws.com`, so the path is just the key.
+    ///
+    /// Returns `InvalidStatic` if the endpoint isn't an `http` or `https` URL.
+    pub fn with_bucket_endpoint(
+        endpoint: &str,
+        region: &str,
+        key: &str,
+        credentials: Option<Credentials>,
+    ) -> Result<S3Object, Error> {
+        let (base, host) = split_endpoint(endpoint)?;
+        Ok(S3Object {
+            agent: ureq::Agent::new(),
+            base: base.to_string(),
+            host: host.to_string(),
+            path: format!("/{}", encode(key, false)),
+            region: region.to_string(),
+            credentials,
+        })
+    }
+
+    /// Use an agent configured as required, e.g. with a proxy, or timeouts.
+    pub fn agent(self, agent: ureq::Agent) -> S3Object {
+        S3Object { agent, ..self }
+    }
+
+    /// A request, signed if there are credentials. `range` is the value of the `Range`
+    /// header, if any.
+    fn request(&self, method: &str, range: Option<&str>) -> ureq::Request {
+        let mut request = self
+            .agent
+            .request(method, &format!("{}{}", self.base, self.path));
+        if let Some(range) = range {
+            request = request.set("Range", range);
+        }
+
+        for (name, value) in self.signed_headers(method, range, SystemTime::now()) {
+            request = request.set(name, &value);
+        }
+        request
+    }
+
+    /// The headers which sign a request with no body, made at `time`, including the
+    /// `Authorization` header; none if there are no credentials. `range` is the value of
+    /// the `Range` header, if any, which must also be sent.
+    pub fn signed_headers(
+        &self,
+        method: &str,
+        range: Option<&str>,
+        time: SystemTime,
+    ) -> Vec<(&'static str, String)> {
+        let credentials = match &self.credentials {
+            Some(credentials) => credentials,
+            None => return Vec::new(),
+        };
+
+        let amz_date = amz_date(time);
+        let mut headers = vec![
+            ("x-amz-content-sha256", EMPTY_SHA256.to_string()),
+            (
+                "Authorization",
+                self.authorization(credentials, method, range, &amz_date),
+            ),
+            ("x-amz-date", amz_date),
+        ];
+        if let Some(token) = &credentials.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        headers
+    }
+
+    /// The `Authorization` header for a request with no body or query string, made at
+    /// `amz_date`, e.g. `20130524T000000Z`.
+    fn authorization(
+        &self,
+        credentials: &Credentials,
+        method: &str,
+        range: Option<&str>,
+        amz_date: &str,
+    ) -> String {
+        // the headers must be sorted by name
+        let mut headers = vec![("host", self.host.as_str())];
+        if let Some(range) = range {
+            headers.push(("range", range));
+        }
+        headers.push(("x-amz-content-sha256", EMPTY_SHA256));
+        headers.push(("x-amz-date", amz_date));
+        if let Some(token) = &credentials.session_token {
+            headers.push(("x-amz-security-token", token));
+        }
+
+        let mut canonical = format!("{}\n{}\n\n", method, self.path);
+        for (name, value) in &headers {
+            writeln!(canonical, "{}:{}", name, value.trim()).expect("infallible");
+        }
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(";");
+        write!(canonical, "\n{}\n{}", signed_headers, EMPTY_SHA256).expect("infallible");
+
+        let date = &amz_date[..8];
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex(&Sha256::digest(canonical.as_bytes()))
+        );
+
+        let mut key = hmac(
+            format!("AWS4{}", credentials.secret_access_key).as_bytes(),
+            date,
+        );
+        for part in &[self.region.as_str(), "s3", "aws4_request"] {
+            key = hmac(&key, part);
+        }
+
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key_id,
+            scope,
+            signed_headers,
+            hex(&hmac(&key, &to_sign))
+        )
+    }
+}
+
+impl RemoteBlockSource for S3Object {
+    fn size(&self) -> Result<u64, Error> {
+        let response = self
+            .request("HEAD", None)
+            .call()
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+            .context(IoSnafu { pos: 0u64 })?;
+
+        response
+            .header("Content-Length")
+            .and_then(|len| len.parse().ok())
+            .ok_or(Error::InvalidStatic {
+                message: "the server didn't say how long the object is",
+            })
+    }
+
+    fn fetch(&self, pos: u64, len: usize) -> Result<Vec<u8>, Error> {
+        let mut data = vec![0u8; len];
+        if data.is_empty() {
+            return Ok(data);
+        }
+
+        let range = format!("bytes={}-{}", pos, pos + len as u64 - 1);
+        let response = self
+            .request("GET", Some(&range))
+            .call()
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+            .context(IoSnafu { pos })?;
+
+        if 206 != response.status() {
+            return Err(Error::InvalidStatic {
+                message: "the server ignored a range request",
+            });
+        }
+
+        response
+            .into_reader()
+            .read_exact(&mut data)
+            .context(IoSnafu { pos })?;
+        Ok(data)
+    }
+}
+
+/// The endpoint without any trailing slash, and its host.
+fn split_endpoint(endpoint: &str) -> Result<(&str, &str), Error> {
+    let base = endpoint.trim_end_matches('/');
+    let host = base
+        .strip_prefix("https://")
+        .or_else(|| base.strip_prefix("http://"))
+        .filter(|host| !host.is_empty() && !host.contains('/'))
+        .ok_or(Error::InvalidStatic {
+            message: "the endpoint must be an http or https URL, with no path",
+        })?;
+    Ok((base, host))
+}
+
+/// Percent-encode everything but the unreserved characters, and maybe slashes, as S3
+/// expects in a path.
+fn encode(s: &str, encode_slashes: bool) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for &b in s.as_bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(char::from(b))
+            }
+            b'/' if !encode_slashes => encoded.push('/'),
+            _ => write!(encoded, "%{:02X}", b).expect("infallible"),
+        }
+    }
+    encoded
+}
+
+fn hmac(key: &[u8], message: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac takes any key length");
+    mac.update(message.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        write!(hex, "{:02x}", b).expect("infallible");
+    }
+    hex
+}
+
+/// Format a time as `20130524T000000Z`.
+fn amz_date(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or(0);
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+
+    // Howard Hinnant's civil_from_days, for days since 1970-01-01
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
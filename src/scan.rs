@@ -0,0 +1,210 @@
+//! Read the partition tables of many images at once, on a thread pool, or of every image
+//! in a directory, with a summary of what was found.
+//!
+//! ```rust,no_run
+//! # fn go(paths: Vec<std::path::PathBuf>) {
+//! let tables = bootsector::scan::scan_many(paths.clone(), &Default::default());
+//! for (path, table) in paths.iter().zip(tables) {
+//!     match table {
+//!         Ok(table) => println!("{}: {:?}", path.display(), table.table_type),
+//!         Err(e) => println!("{}: {}", path.display(), e),
+//!     }
+//! }
+//! # }
+//! ```
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+
+use crate::{read_partition_table_sized, Error, Options, PartitionTable, TableType};
+
+/// Something [`scan_many`] can read a table from: a path, which is opened, or a reader.
+pub trait Image: Send {
+    type Reader: positioned_io2::ReadAt + positioned_io2::Size;
+
+    fn open(self) -> Result<Self::Reader, Error>;
+}
+
+impl Image for PathBuf {
+    type Reader = File;
+
+    fn open(self) -> Result<File, Error> {
+        self.as_path().open()
+    }
+}
+
+impl Image for &Path {
+    type Reader = File;
+
+    fn open(self) -> Result<File, Error> {
+        File::open(self).map_err(|source| Error::Io { source, pos: 0 })
+    }
+}
+
+impl Image for File {
+    type Reader = File;
+
+    fn open(self) -> Result<File, Error> {
+        Ok(self)
+    }
+}
+
+impl<'a> Image for &'a [u8] {
+    type Reader = &'a [u8];
+
+    fn open(self) -> Result<&'a [u8], Error> {
+        Ok(self)
+    }
+}
+
+impl Image for Vec<u8> {
+    type Reader = Vec<u8>;
+
+    fn open(self) -> Result<Vec<u8>, Error> {
+        Ok(self)
+    }
+}
+
+/// Read the partition table of each image, in parallel, on rayon's global thread pool.
+///
+/// The results are in the same order as the images. One image failing, even to open,
+/// doesn't affect the others.
+pub fn scan_many<I>(images: I, options: &Options) -> Vec<Result<PartitionTable, Error>>
+where
+    I: IntoIterator,
+    I::Item: Image,
+{
+    images
+        .into_iter()
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|image| read_partition_table_sized(image.open()?, options))
+        .collect()
+}
+
+/// What was found in an image.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Class {
+    MBR,
+    GPT,
+
+    /// The image was read, but has no partition table.
+    Unpartitioned,
+
+    /// The image couldn't be read, or its table is broken.
+    Failed,
+}
+
+impl Class {
+    /// e.g. `mbr`, or `unpartitioned`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Class::MBR => "mbr",
+            Class::GPT => "gpt",
+            Class::Unpartitioned => "unpartitioned",
+            Class::Failed => "failed",
+        }
+    }
+}
+
+/// An image found by [`scan_dir`], and what was read from it.
+#[derive(Debug)]
+pub struct Scanned {
+    pub path: PathBuf,
+    pub result: Result<PartitionTable, Error>,
+}
+
+impl Scanned {
+    pub fn class(&self) -> Class {
+        match &self.result {
+            Ok(table) => match table.table_type {
+                TableType::MBR => Class::MBR,
+                TableType::GPT => Class::GPT,
+            },
+            Err(Error::NotFound) => Class::Unpartitioned,
+            Err(_) => Class::Failed,
+        }
+    }
+}
+
+/// Every image in a directory, from [`scan_dir`].
+#[derive(Debug)]
+pub struct Report {
+    /// Ordered by path.
+    pub images: Vec<Scanned>,
+}
+
+/// How many images were of each [`Class`].
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Summary {
+    pub mbr: usize,
+    pub gpt: usize,
+    pub unpartitioned: usize,
+    pub failed: usize,
+
+    /// The total number of partitions in all the tables read.
+    pub partitions: usize,
+}
+
+impl Report {
+    pub fn summary(&self) -> Summary {
+        let mut summary = Summary::default();
+        for image in &self.images {
+            match image.class() {
+                Class::MBR => summary.mbr += 1,
+                Class::GPT => summary.gpt += 1,
+                Class::Unpartitioned => summary.unpartitioned += 1,
+                Class::Failed => summary.failed += 1,
+            }
+            if let Ok(table) = &image.result {
+                summary.partitions += table.partitions.len();
+            }
+        }
+        summary
+    }
+}
+
+/// Read every file under a directory, and its subdirectories, as an image, with
+/// [`scan_many`].
+///
+/// Files which aren't images are reported as [`Class::Unpartitioned`], or
+/// [`Class::Failed`]; it's up to the caller to tell these apart from broken images.
+/// Symlinks to files are followed; symlinks to directories aren't.
+///
+/// ```rust,no_run
+/// # fn go() -> Result<(), bootsector::Error> {
+/// let report = bootsector::scan::scan_dir("/srv/images", &Default::default())?;
+/// println!("{:?}", report.summary());
+/// # Ok(())
+/// # }
+/// ```
+pub fn scan_dir<P: AsRef<Path>>(dir: P, options: &Options) -> Result<Report, Error> {
+    let mut paths = Vec::new();
+    find_files(dir.as_ref(), &mut paths)?;
+    paths.sort();
+
+    let results = scan_many(paths.iter().map(|path| path.as_path()), options);
+    Ok(Report {
+        images: paths
+            .into_iter()
+            .zip(results)
+            .map(|(path, result)| Scanned { path, result })
+            .collect(),
+    })
+}
+
+fn find_files(dir: &Path, paths: &mut Vec<PathBuf>) -> Result<(), Error> {
+    let io = |source| Error::Io { source, pos: 0 };
+    for entry in std::fs::read_dir(dir).map_err(io)? {
+        let entry = entry.map_err(io)?;
+        let path = entry.path();
+        if entry.file_type().map_err(io)?.is_dir() {
+            find_files(&path, paths)?;
+        } else if path.is_file() {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,257 @@
+//! Read compressed images which are split into independently compressed frames, such as
+//! zstd's seekable format, decompressing only the frames which are actually read.
+//!
+//! No decompressor is built in; pass one in, e.g. `zstd::bulk::decompress`. Recently
+//! used frames are kept, so the repeated small reads done when parsing tables are cheap.
+//!
+//! ```rust,no_run
+//! # fn go() -> Result<(), bootsector::Error> {
+//! # fn zstd_decompress(frame: &[u8], len: usize) -> std::io::Result<Vec<u8>> { unimplemented!() }
+//! let file = std::fs::File::open("disc.img.zst").unwrap();
+//! let disc = bootsector::seekable::Seekable::zstd(file, zstd_decompress)?;
+//! let table = bootsector::read_partition_table_sized(&disc, &Default::default())?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::io;
+use std::sync::Arc;
+
+use crate::io::{ReadAt, Recent};
+use crate::le;
+use crate::Error;
+
+const SKIPPABLE_MAGIC: u32 = 0x184d_2a5e;
+const SEEKABLE_MAGIC: u32 = 0x8f92_eab1;
+const FOOTER_LEN: u64 = 9;
+
+/// How many decompressed frames are kept.
+const CACHED_FRAMES: usize = 4;
+
+/// The location of a compressed frame, and of its contents once decompressed.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Frame {
+    pub compressed_offset: u64,
+    pub compressed_len: u32,
+    pub decompressed_offset: u64,
+    pub decompressed_len: u32,
+}
+
+/// A compressed image, readable as the disc it contains.
+pub struct Seekable<R, D> {
+    inner: R,
+    decompress: D,
+    frames: Vec<Frame>,
+    disk_len: u64,
+    cache: Recent,
+}
+
+impl<R, D> Seekable<R, D>
+where
+    R: positioned_io2::ReadAt + positioned_io2::Size,
+    D: Fn(&[u8], usize) -> io::Result<Vec<u8>>,
+{
+    /// Open a file in zstd's seekable format, by reading the seek table from the end.
+    ///
+    /// `decompress` is given a whole zstd frame, and the length it must decompress to.
+    pub fn zstd(inner: R, decompress: D) -> Result<Seekable<R, D>, Error> {
+        let file_len = positioned_io2::Size::size(&inner)
+            .map_err(|source| Error::Io { source, pos: 0 })?
+            .ok_or(Error::InvalidStatic {
+                message: "the length of the file is required to find the seek table",
+            })?;
+
+        if file_len < 8 + FOOTER_LEN {
+            return Err(Error::NotFound);
+        }
+
+        let mut footer = [0u8; 9];
+        ReadAt::read_exact_at(&inner, file_len - FOOTER_LEN, &mut footer)?;
+        if SEEKABLE_MAGIC != le::read_u32(&footer[5..]) {
+            return Err(Error::NotFound);
+        }
+
+        let count = u64::from(le::read_u32(&footer));
+        let descriptor = footer[4];
+        if 0 != descriptor & 0b0111_1100 {
+            return Err(Error::InvalidStatic {
+                message: "reserved bits set in zstd seek table",
+            });
+        }
+        let entry_len = if 0 != descriptor & 0x80 { 12 } else { 8 };
+
+        let table_len = count * entry_len;
+        let frame_len = 8 + table_len + FOOTER_LEN;
+        if frame_len > file_len {
+            return Err(Error::InvalidStatic {
+                message: "zstd seek table is longer than the file",
+            });
+        }
+
+        let table_start = file_len - frame_len;
+        let mut table =
+            vec![0u8; usize::try_from(8 + table_len).map_err(|_| Error::BiggerThanMemory)?];
+        ReadAt::read_exact_at(&inner, table_start, &mut table)?;
+
+        if SKIPPABLE_MAGIC != le::read_u32(&table)
+            || u64::from(le::read_u32(&table[4..])) != table_len + FOOTER_LEN
+        {
+            return Err(Error::InvalidStatic {
+                message: "zstd seek table is not in a skippable frame",
+            });
+        }
+
+        let mut frames = Vec::new();
+        let mut compressed_offset = 0u64;
+        let mut decompressed_offset = 0u64;
+        for entry in table[8..].chunks_exact(usize::try_from(entry_len).expect("small")) {
+            let frame = Frame {
+                compressed_offset,
+                compressed_len: le::read_u32(entry),
+                decompressed_offset,
+                decompressed_len: le::read_u32(&entry[4..]),
+            };
+            compressed_offset += u64::from(frame.compressed_len);
+            decompressed_offset += u64::from(frame.decompressed_len);
+            frames.push(frame);
+        }
+
+        if compressed_offset > table_start {
+            return Err(Error::InvalidStatic {
+                message: "zstd seek table describes more data than the file contains",
+            });
+        }
+
+        Seekable::with_frames(inner, frames, decompress)
+    }
+}
+
+impl<R, D> Seekable<R, D>
+where
+    R: positioned_io2::ReadAt,
+    D: Fn(&[u8], usize) -> io::Result<Vec<u8>>,
+{
+    /// Use an index from elsewhere, e.g. for a gzip file with multiple members.
+    ///
+    /// The frames must be in order, and cover the decompressed data without gaps.
+    pub fn with_frames(
+        inner: R,
+        frames: Vec<Frame>,
+        decompress: D,
+    ) -> Result<Seekable<R, D>, Error> {
+        let mut disk_len = 0u64;
+        for frame in &frames {
+            if frame.decompressed_offset != disk_len {
+                return Err(Error::InvalidStatic {
+                    message: "frames must cover the data in order",
+                });
+            }
+            disk_len = disk_len
+                .checked_add(u64::from(frame.decompressed_len))
+                .ok_or(Error::Overflow)?;
+        }
+
+        Ok(Seekable {
+            inner,
+            decompress,
+            frames,
+            disk_len,
+            cache: Recent::new(CACHED_FRAMES),
+        })
+    }
+}
+
+impl<R, D> Seekable<R, D> {
+    /// The length of the decompressed data, in bytes.
+    pub fn disk_len(&self) -> u64 {
+        self.disk_len
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R, D> Seekable<R, D>
+where
+    R: positioned_io2::ReadAt,
+    D: Fn(&[u8], usize) -> io::Result<Vec<u8>>,
+{
+    fn frame(&self, index: usize) -> io::Result<Arc<Vec<u8>>> {
+        let too_big = || io::Error::new(io::ErrorKind::InvalidData, "frame is too large");
+        let key = u64::try_from(index).map_err(|_| too_big())?;
+        self.cache.get_or_load(key, || {
+            let frame = &self.frames[index];
+            let mut compressed =
+                vec![0u8; usize::try_from(frame.compressed_len).map_err(|_| too_big())?];
+            positioned_io2::ReadAt::read_exact_at(
+                &self.inner,
+                frame.compressed_offset,
+                &mut compressed,
+            )?;
+
+            let expected = usize::try_from(frame.decompressed_len).map_err(|_| too_big())?;
+            let data = (self.decompress)(&compressed, expected)?;
+            if data.len() != expected {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "frame {} decompressed to {} bytes, not {}",
+                        index,
+                        data.len(),
+                        expected
+                    ),
+                ));
+            }
+            Ok(data)
+        })
+    }
+}
+
+impl<R, D> positioned_io2::ReadAt for Seekable<R, D>
+where
+    R: positioned_io2::ReadAt,
+    D: Fn(&[u8], usize) -> io::Result<Vec<u8>>,
+{
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        if pos >= self.disk_len || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let index = match self.frames.binary_search_by(|f| {
+            if f.decompressed_offset + u64::from(f.decompressed_len) <= pos {
+                Ordering::Less
+            } else if f.decompressed_offset > pos {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        }) {
+            Ok(index) => index,
+            Err(_) => return Ok(0),
+        };
+
+        let data = self.frame(index)?;
+        let skip =
+            usize::try_from(pos - self.frames[index].decompressed_offset).expect("within a frame");
+        let len = buf.len().min(data.len() - skip);
+        buf[..len].copy_from_slice(&data[skip..skip + len]);
+        Ok(len)
+    }
+}
+
+impl<R, D> positioned_io2::Size for Seekable<R, D> {
+    fn size(&self) -> io::Result<Option<u64>> {
+        Ok(Some(self.disk_len))
+    }
+}
@@ -0,0 +1,416 @@
+//! Read and write the textual format produced by `sfdisk --dump`, from util-linux.
+//!
+//! ```text
+//! label: gpt
+//! label-id: 8A1F5C2E-4F4B-4B6C-9D0A-2F1C3E4D5B6A
+//! device: /dev/sda
+//! unit: sectors
+//! sector-size: 512
+//!
+//! /dev/sda1 : start=2048, size=1048576, type=C12A7328-F81F-11D2-BA4B-00A0C93EC93B, uuid=..., name="EFI"
+//! ```
+//!
+//! Only the `sectors` unit is supported, and partition types must be given in full
+//! (i.e. not as sfdisk's `L` / `U` style shortcuts).
+
+use alloc::{format, string::String, vec::Vec};
+use core::fmt::Write;
+
+use crate::attributes::LEGACY_BIOS_BOOTABLE;
+use crate::{gpt, guid, mbr, Attributes, Error, Partition, PartitionTable, Stats, TableType};
+
+const ATTRIBUTE_NAMES: &[(u32, &str)] = &[
+    (0, "RequiredPartition"),
+    (1, "NoBlockIOProtocol"),
+    (LEGACY_BIOS_BOOTABLE.trailing_zeros(), "LegacyBIOSBootable"),
+];
+
+/// Render a table in the `sfdisk --dump` format, naming partitions after `device`.
+pub fn to_dump(table: &PartitionTable, device: &str) -> String {
+    let mut out = String::new();
+
+    // writing to a String cannot fail
+    let _ = write_dump(&mut out, table, device);
+
+    out
+}
+
+fn write_dump(out: &mut String, table: &PartitionTable, device: &str) -> core::fmt::Result {
+    match table.table_type {
+        TableType::MBR => {
+            writeln!(out, "label: dos")?;
+            writeln!(out, "label-id: 0x{:08x}", table.disk_signature)?;
+        }
+        TableType::GPT => {
+            writeln!(out, "label: gpt")?;
+            if let Some(disk_guid) = &table.disk_guid {
+                writeln!(out, "label-id: {}", guid::to_string(disk_guid))?;
+            }
+        }
+    }
+    writeln!(out, "device: {}", device)?;
+    writeln!(out, "unit: sectors")?;
+    writeln!(out, "sector-size: {}", table.sector_size)?;
+    writeln!(out)?;
+
+    let separator = if device.ends_with(|c: char| c.is_ascii_digit()) {
+        "p"
+    } else {
+        ""
+    };
+
+    for part in &table.partitions {
+        write!(
+            out,
+            "{}{}{} : start={}, size={}",
+            device,
+            separator,
+            part.id + 1,
+            part.first_byte / table.sector_size,
+            part.len / table.sector_size
+        )?;
+
+        match &part.attributes {
+            Attributes::MBR {
+                bootable,
+                type_code,
+            } => {
+                write!(out, ", type={:x}", type_code)?;
+                if *bootable {
+                    write!(out, ", bootable")?;
+                }
+            }
+            Attributes::GPT {
+                type_uuid,
+                partition_uuid,
+                attributes,
+                name,
+                ..
+            } => {
+                write!(
+                    out,
+                    ", type={}, uuid={}",
+                    guid::to_string(type_uuid),
+                    guid::to_string(partition_uuid)
+                )?;
+                if !name.is_empty() {
+                    write!(out, ", name=\"{}\"", escape(name))?;
+                }
+                let attributes = u64::from_le_bytes(*attributes);
+                if 0 != attributes {
+                    write!(out, ", attrs=\"{}\"", format_attributes(attributes))?;
+                }
+            }
+        }
+        writeln!(out)?;
+    }
+
+    Ok(())
+}
+
+fn escape(name: &str) -> String {
+    let mut ret = String::with_capacity(name.len());
+    for c in name.chars() {
+        if '"' == c || '\\' == c {
+            ret.push('\\');
+        }
+        ret.push(c);
+    }
+    ret
+}
+
+fn format_attributes(attributes: u64) -> String {
+    let mut words: Vec<String> = Vec::new();
+    let mut guid_bits: Vec<String> = Vec::new();
+    for bit in 0..64 {
+        if 0 == attributes & (1 << bit) {
+            continue;
+        }
+        match ATTRIBUTE_NAMES.iter().find(|(known, _)| *known == bit) {
+            Some((_, name)) => words.push(String::from(*name)),
+            None => guid_bits.push(format!("{}", bit)),
+        }
+    }
+
+    if !guid_bits.is_empty() {
+        words.push(format!("GUID:{}", guid_bits.join(",")));
+    }
+
+    words.join(" ")
+}
+
+fn parse_attributes(text: &str) -> Result<u64, Error> {
+    let mut ret = 0u64;
+    for word in text.split_whitespace() {
+        if let Some(bits) = word.strip_prefix("GUID:") {
+            for bit in bits.split(',').filter(|bit| !bit.is_empty()) {
+                let bit: u32 = bit.parse().map_err(|_| invalid("attribute bit", bit))?;
+                if bit > 63 {
+                    return Err(invalid("attribute bit", word));
+                }
+                ret |= 1 << bit;
+            }
+            continue;
+        }
+
+        match ATTRIBUTE_NAMES.iter().find(|(_, name)| *name == word) {
+            Some((bit, _)) => ret |= 1 << bit,
+            None => return Err(invalid("attribute", word)),
+        }
+    }
+    Ok(ret)
+}
+
+fn invalid(what: &str, value: &str) -> Error {
+    Error::InvalidData {
+        message: format!("sfdisk dump has an invalid {}: {:?}", what, value),
+    }
+}
+
+/// Parse the output of `sfdisk --dump`.
+///
+/// The device names are used to number the partitions, where present.
+pub fn parse(dump: &str) -> Result<PartitionTable, Error> {
+    let mut table_type = None;
+    let mut label_id = None;
+    let mut sector_size = 512u64;
+    let mut lines = Vec::new();
+
+    for line in dump.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some((key, value)) = header_line(line) {
+            match key {
+                "label" => {
+                    table_type = Some(match value {
+                        "dos" => TableType::MBR,
+                        "gpt" => TableType::GPT,
+                        other => return Err(invalid("label", other)),
+                    })
+                }
+                "label-id" => label_id = Some(value),
+                "unit" if "sectors" != value => return Err(invalid("unit", value)),
+                "sector-size" => {
+                    sector_size = value
+                        .parse()
+                        .ok()
+                        .filter(|size| 0 != *size)
+                        .ok_or_else(|| invalid("sector size", value))?
+                }
+                _ => (),
+            }
+            continue;
+        }
+
+        lines.push(line);
+    }
+
+    let table_type = table_type.ok_or_else(|| invalid("label", ""))?;
+
+    let (disk_signature, disk_guid) = match (table_type, label_id) {
+        (_, None) => (0, None),
+        (TableType::MBR, Some(id)) => {
+            let hex = id.trim_start_matches("0x");
+            let sig = u32::from_str_radix(hex, 16).map_err(|_| invalid("label-id", id))?;
+            (sig, None)
+        }
+        (TableType::GPT, Some(id)) => (
+            0,
+            Some(guid::parse(id).map_err(|_| invalid("label-id", id))?),
+        ),
+    };
+
+    let mut partitions = Vec::with_capacity(lines.len());
+    for (pos, line) in lines.into_iter().enumerate() {
+        partitions.push(parse_partition(line, pos, table_type, sector_size)?);
+    }
+
+    Ok(PartitionTable {
+        table_type,
+        sector_size,
+        disk_signature,
+        disk_guid,
+        gpt_header: None,
+        mbr_partitions: match table_type {
+            TableType::MBR => partitions.clone(),
+            TableType::GPT => Vec::new(),
+        },
+        mbr_layout: mbr::Layout::Standard,
+        boot_code: None,
+        boot_code_bytes: None,
+        warnings: Vec::new(),
+        partitions,
+        nested: Vec::new(),
+        stats: Stats::default(),
+    })
+}
+
+/// Header lines are `key: value`, where the key is a single word.
+fn header_line(line: &str) -> Option<(&str, &str)> {
+    let colon = line.find(':')?;
+    let key = &line[..colon];
+    if key.is_empty() || key.contains(|c: char| c.is_whitespace() || '/' == c || '=' == c) {
+        return None;
+    }
+    Some((key, line[colon + 1..].trim()))
+}
+
+fn parse_partition(
+    line: &str,
+    pos: usize,
+    table_type: TableType,
+    sector_size: u64,
+) -> Result<Partition, Error> {
+    // the device name, if present, is separated by a colon before any fields
+    let (device, fields) = match (line.find(':'), line.find('=')) {
+        (Some(colon), Some(equals)) if colon < equals => {
+            (Some(line[..colon].trim()), &line[colon + 1..])
+        }
+        (Some(colon), None) => (Some(line[..colon].trim()), &line[colon + 1..]),
+        _ => (None, line),
+    };
+
+    let id = match device.map(|device| {
+        device
+            .rfind(|c: char| !c.is_ascii_digit())
+            .map_or(device, |last| &device[last + 1..])
+    }) {
+        Some(digits) if !digits.is_empty() => digits
+            .parse::<usize>()
+            .ok()
+            .and_then(|number| number.checked_sub(1))
+            .ok_or_else(|| invalid("device", device.unwrap_or("")))?,
+        _ => pos,
+    };
+
+    let mut start = None;
+    let mut size = None;
+    let mut type_field = None;
+    let mut uuid = None;
+    let mut name = String::new();
+    let mut attrs = 0u64;
+    let mut bootable = false;
+
+    for (key, value) in split_fields(fields)? {
+        match key.as_str() {
+            "start" => start = Some(value),
+            "size" => size = Some(value),
+            "type" | "Id" => type_field = Some(value),
+            "uuid" => uuid = Some(value),
+            "name" => name = value,
+            "attrs" => attrs = parse_attributes(&value)?,
+            "bootable" => bootable = true,
+            _ => (),
+        }
+    }
+
+    let sectors = |what: &str, value: Option<String>| -> Result<u64, Error> {
+        let value = value.ok_or_else(|| invalid(what, ""))?;
+        value
+            .parse::<u64>()
+            .ok()
+            .and_then(|sectors| sectors.checked_mul(sector_size))
+            .ok_or_else(|| invalid(what, &value))
+    };
+
+    let first_byte = sectors("start", start)?;
+    let len = sectors("size", size)?;
+    let type_field = type_field.ok_or_else(|| invalid("type", ""))?;
+
+    let attributes = match table_type {
+        TableType::MBR => {
+            let hex = type_field.trim_start_matches("0x");
+            Attributes::MBR {
+                bootable,
+                type_code: u8::from_str_radix(hex, 16).map_err(|_| invalid("type", &type_field))?,
+            }
+        }
+        TableType::GPT => Attributes::GPT {
+            type_uuid: guid::parse(&type_field).map_err(|_| invalid("type", &type_field))?,
+            partition_uuid: match uuid {
+                Some(uuid) => guid::parse(&uuid).map_err(|_| invalid("uuid", &uuid))?,
+                None => [0u8; 16],
+            },
+            attributes: attrs.to_le_bytes(),
+            raw_name: gpt::encode_name(&name)?,
+            name,
+            extra: Vec::new(),
+        },
+    };
+
+    Ok(Partition {
+        id,
+        first_byte,
+        len,
+        attributes,
+        label: None,
+    })
+}
+
+/// Split `a=1, b="x, y", c` into key/value pairs, unquoting as we go.
+fn split_fields(fields: &str) -> Result<Vec<(String, String)>, Error> {
+    let mut ret = Vec::new();
+    let mut chars = fields.chars().peekable();
+
+    loop {
+        while let Some(c) = chars.peek() {
+            if c.is_whitespace() || ',' == *c {
+                chars.next();
+            } else {
+                break;
+            }
+        }
+
+        if chars.peek().is_none() {
+            return Ok(ret);
+        }
+
+        let mut key = String::new();
+        while let Some(&c) = chars.peek() {
+            if '=' == c || ',' == c {
+                break;
+            }
+            key.push(c);
+            chars.next();
+        }
+
+        let mut value = String::new();
+        let mut quoted = false;
+        if Some('=') == chars.next() {
+            while let Some(' ') = chars.peek() {
+                chars.next();
+            }
+            if Some(&'"') == chars.peek() {
+                chars.next();
+                quoted = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) => value.push(c),
+                            None => return Err(invalid("quoted value", &value)),
+                        },
+                        Some(c) => value.push(c),
+                        None => return Err(invalid("quoted value", &value)),
+                    }
+                }
+            } else {
+                while let Some(&c) = chars.peek() {
+                    if ',' == c {
+                        break;
+                    }
+                    value.push(c);
+                    chars.next();
+                }
+            }
+        }
+
+        if !quoted {
+            value = String::from(value.trim());
+        }
+
+        ret.push((String::from(key.trim()), value));
+    }
+}
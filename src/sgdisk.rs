@@ -0,0 +1,172 @@
+//! Read and produce the binary files written by `sgdisk --backup`.
+//!
+//! These files contain the protective MBR, the primary header, and the backup header,
+//! each as a 512 byte block, followed by the partition entry array.
+
+use alloc::{vec, vec::Vec};
+use core::convert::TryFrom;
+
+use snafu::ResultExt;
+
+use crate::errors::ReadSnafu;
+use crate::gpt::{self, Header};
+use crate::{io, Error, Partition, Reserved};
+
+const BLOCK: usize = 512;
+
+/// The contents of an sgdisk backup file.
+#[derive(Clone)]
+pub struct Backup {
+    /// The (protective) MBR.
+    pub mbr: [u8; BLOCK],
+
+    /// The first 512 bytes of the sector holding the primary header.
+    pub primary_header: [u8; BLOCK],
+
+    /// The first 512 bytes of the sector holding the backup header.
+    pub backup_header: [u8; BLOCK],
+
+    /// The partition entry array.
+    pub entries: Vec<u8>,
+}
+
+impl Backup {
+    /// Parse and validate an sgdisk backup file.
+    ///
+    /// Both headers' checksums, and the checksum of the entry array, must be valid.
+    pub fn parse(data: &[u8]) -> Result<Backup, Error> {
+        if data.len() < 3 * BLOCK {
+            return Err(Error::InvalidStatic {
+                message: "backup file is too short to contain the headers",
+            });
+        }
+
+        let mut mbr = [0u8; BLOCK];
+        mbr.copy_from_slice(&data[..BLOCK]);
+        let mut primary_header = [0u8; BLOCK];
+        primary_header.copy_from_slice(&data[BLOCK..2 * BLOCK]);
+        let mut backup_header = [0u8; BLOCK];
+        backup_header.copy_from_slice(&data[2 * BLOCK..3 * BLOCK]);
+
+        if 0x55 != mbr[510] || 0xAA != mbr[511] {
+            return Err(Error::InvalidStatic {
+                message: "backup file's mbr has no boot signature",
+            });
+        }
+
+        let primary = gpt::decode_valid(&primary_header, 1).ok_or(Error::InvalidStatic {
+            message: "backup file's primary header is invalid",
+        })?;
+
+        gpt::decode_valid(&backup_header, primary.alternate_lba).ok_or(Error::InvalidStatic {
+            message: "backup file's backup header is invalid",
+        })?;
+
+        let table_len = primary.table_len().ok_or(Error::Overflow)?;
+        let entries = data
+            .get(3 * BLOCK..)
+            .and_then(|rest| rest.get(..table_len))
+            .ok_or(Error::InvalidStatic {
+                message: "backup file is too short to contain the entries",
+            })?;
+
+        if primary.entries_crc != gpt::checksum(entries) {
+            return Err(Error::InvalidStatic {
+                message: "table crc invalid",
+            });
+        }
+
+        Ok(Backup {
+            mbr,
+            primary_header,
+            backup_header,
+            entries: entries.to_vec(),
+        })
+    }
+
+    /// Read the structures from a disc, in preparation for writing them out.
+    ///
+    /// The primary GPT must be valid. If the backup header is damaged or missing,
+    /// a replacement is generated from the primary, as sgdisk would.
+    pub fn read<R>(reader: R, sector_size: u64) -> Result<Backup, Error>
+    where
+        R: io::ReadAt,
+    {
+        if sector_size < 512 {
+            return Err(Error::InvalidStatic {
+                message: "sector size is implausibly small",
+            });
+        }
+
+        let sector_size_mem = usize::try_from(sector_size).map_err(|_| Error::BiggerThanMemory)?;
+
+        let mut mbr = [0u8; BLOCK];
+        reader.read_exact_at(0, &mut mbr).context(ReadSnafu {
+            what: "the mbr",
+            pos: 0u64,
+        })?;
+
+        let mut sector = vec![0u8; sector_size_mem];
+        reader
+            .read_exact_at(sector_size, &mut sector)
+            .context(ReadSnafu {
+                what: "the gpt header",
+                pos: sector_size,
+            })?;
+        let primary =
+            gpt::validate_primary(&sector, sector_size, &Reserved::MustBeZero, &mut Vec::new())?;
+        let mut primary_header = [0u8; BLOCK];
+        primary_header.copy_from_slice(&sector[..BLOCK]);
+
+        let mut entries = vec![0u8; primary.table_len().ok_or(Error::Overflow)?];
+        let entries_pos = sector_size.checked_mul(2).ok_or(Error::Overflow)?;
+        reader
+            .read_exact_at(entries_pos, &mut entries)
+            .context(ReadSnafu {
+                what: "the gpt partition entries",
+                pos: entries_pos,
+            })?;
+        if primary.entries_crc != gpt::checksum(&entries) {
+            return Err(Error::InvalidStatic {
+                message: "table crc invalid",
+            });
+        }
+
+        let backup_pos = primary.alternate_lba.checked_mul(sector_size);
+        let found = match backup_pos {
+            Some(pos) => reader.read_exact_at(pos, &mut sector).is_ok(),
+            None => false,
+        };
+
+        if !found || gpt::decode_valid(&sector, primary.alternate_lba).is_none() {
+            let backup = primary.alternate(sector_size).ok_or(Error::Overflow)?;
+            backup.encode(&mut sector);
+        }
+
+        let mut backup_header = [0u8; BLOCK];
+        backup_header.copy_from_slice(&sector[..BLOCK]);
+
+        Ok(Backup {
+            mbr,
+            primary_header,
+            backup_header,
+            entries,
+        })
+    }
+
+    /// Serialise in the format sgdisk reads.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut ret = Vec::with_capacity(3 * BLOCK + self.entries.len());
+        ret.extend_from_slice(&self.mbr);
+        ret.extend_from_slice(&self.primary_header);
+        ret.extend_from_slice(&self.backup_header);
+        ret.extend_from_slice(&self.entries);
+        ret
+    }
+
+    /// The partitions described by the backup, for a disc with the given sector size.
+    pub fn partitions(&self, sector_size: u64) -> Result<Vec<Partition>, Error> {
+        let primary = Header::decode(&self.primary_header);
+        gpt::parse_entries(&self.entries, &primary, sector_size)
+    }
+}
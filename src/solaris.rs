@@ -0,0 +1,205 @@
+//! The slices of a Solaris or illumos x86 disc: a VTOC ("volume table of contents"),
+//! in the second sector of the MBR partition Solaris was installed into, divides it into
+//! up to 16 slices. Set [`Options::solaris_slices`] to list these along with the disc's
+//! own partitions.
+//!
+//! [`Options::solaris_slices`]: crate::Options::solaris_slices
+//!
+//! ```rust
+//! # fn go(disc: &[u8]) -> Result<(), bootsector::Error> {
+//! for part in bootsector::list_partitions(disc, &Default::default())? {
+//!     if let Some(vtoc) = bootsector::solaris::read_vtoc(disc, &part)? {
+//!         for slice in vtoc.slices {
+//!             println!("s{}: tag {}, {} bytes", slice.id, slice.tag, slice.len);
+//!         }
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::{format, vec::Vec};
+
+use snafu::ResultExt;
+
+use crate::errors::ReadSnafu;
+use crate::{io, le, Attributes, Error, Partition};
+
+/// The MBR type codes of Solaris partitions. 0x82 was used until Solaris 10, and is
+/// shared with Linux swap, so only a VTOC tells them apart.
+const TYPE_CODES: [u8; 2] = [0x82, 0xbf];
+
+/// `v_sanity`, which marks a VTOC.
+const SANITY: u32 = 0x600d_deee;
+
+/// `dkl_magic`, at the end of the disc label the VTOC is part of.
+const MAGIC: u16 = 0xdabe;
+
+/// The most slices a VTOC has room for.
+const MAX_SLICES: usize = 16;
+
+/// The offset of the slice table in the label.
+const SLICES: usize = 72;
+
+/// A Solaris x86 VTOC, as read by [`read_vtoc`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Vtoc {
+    /// The volume name, padded with nuls.
+    pub volume: [u8; 8],
+
+    /// The slices which aren't empty.
+    pub slices: Vec<Slice>,
+}
+
+/// A slice in a [`Vtoc`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Slice {
+    /// The number of the slice, as in the `s` of `c0t0d0s0`.
+    pub id: usize,
+
+    /// What the slice is for, e.g. 2 for root, 5 for the "backup" slice covering the
+    /// whole partition, or 8 for home.
+    pub tag: u16,
+
+    /// Permission flags: 1 for unmountable, 0x10 for read-only.
+    pub flag: u16,
+
+    /// The first byte of the disc in the slice. The VTOC stores this relative to the
+    /// partition; it has been added on.
+    pub first_byte: u64,
+
+    /// The length of the slice, in bytes.
+    pub len: u64,
+}
+
+/// Is this partition of a type Solaris uses, so may contain a VTOC?
+pub fn is_solaris(part: &Partition) -> bool {
+    match part.attributes {
+        Attributes::MBR { type_code, .. } => TYPE_CODES.contains(&type_code),
+        Attributes::GPT { .. } => false,
+    }
+}
+
+/// Read the VTOC from inside a Solaris partition, or `None` if there isn't one.
+///
+/// The label's magic number, and its checksum, must be right, and the VTOC must be
+/// version 1, as every x86 release has written. Slices which aren't inside the
+/// partition are ignored.
+pub fn read_vtoc<R>(reader: R, part: &Partition) -> Result<Option<Vtoc>, Error>
+where
+    R: io::ReadAt,
+{
+    read(&reader, part)
+}
+
+pub(crate) fn read<R>(reader: &R, part: &Partition) -> Result<Option<Vtoc>, Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    let mut label = [0u8; 512];
+    if !is_solaris(part) || part.len < 2 * label.len() as u64 {
+        return Ok(None);
+    }
+
+    let pos = part.first_byte + 512;
+    reader.read_exact_at(pos, &mut label).context(ReadSnafu {
+        what: "a solaris vtoc",
+        pos,
+    })?;
+
+    if SANITY != le::read_u32(&label[12..]) {
+        return Ok(None);
+    }
+
+    let magic = le::read_u16(&label[508..]);
+    if MAGIC != magic {
+        return Err(Error::InvalidData {
+            message: format!("invalid magic in solaris disc label: {:x}", magic),
+        });
+    }
+
+    // the checksum makes the xor of every word zero
+    if 0 != label
+        .chunks(2)
+        .fold(0, |sum, word| sum ^ le::read_u16(word))
+    {
+        return Err(Error::InvalidStatic {
+            message: "solaris disc label checksum mismatch",
+        });
+    }
+
+    let version = le::read_u32(&label[16..]);
+    if 1 != version {
+        return Err(Error::InvalidData {
+            message: format!("unsupported solaris vtoc version: {}", version),
+        });
+    }
+
+    let count = usize::from(le::read_u16(&label[30..]));
+    if count > MAX_SLICES {
+        return Err(Error::InvalidData {
+            message: format!("too many slices in solaris vtoc: {}", count),
+        });
+    }
+
+    let mut volume = [0u8; 8];
+    volume.copy_from_slice(&label[20..28]);
+
+    let end = part.end_byte();
+    let mut slices = Vec::with_capacity(count);
+    for id in 0..count {
+        let slice = &label[SLICES + id * 12..SLICES + (id + 1) * 12];
+        let sectors = u64::from(le::read_u32(&slice[8..]));
+        if 0 == sectors {
+            continue;
+        }
+
+        let first_byte = part.first_byte + u64::from(le::read_u32(&slice[4..])) * 512;
+        let len = sectors * 512;
+        if first_byte.saturating_add(len) > end {
+            continue;
+        }
+
+        slices.push(Slice {
+            id,
+            tag: le::read_u16(&slice[0..]),
+            flag: le::read_u16(&slice[2..]),
+            first_byte,
+            len,
+        });
+    }
+
+    Ok(Some(Vtoc { volume, slices }))
+}
+
+/// The slices of a Solaris partition, as partitions of the same type, or none if it has
+/// no VTOC. Each is given the id of its slice.
+pub(crate) fn slices<R>(reader: &R, part: &Partition) -> Result<Vec<Partition>, Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    let type_code = match part.attributes {
+        Attributes::MBR { type_code, .. } => type_code,
+        Attributes::GPT { .. } => return Ok(Vec::new()),
+    };
+
+    let vtoc = match read(reader, part)? {
+        Some(vtoc) => vtoc,
+        None => return Ok(Vec::new()),
+    };
+
+    Ok(vtoc
+        .slices
+        .into_iter()
+        .map(|slice| Partition {
+            id: slice.id,
+            first_byte: slice.first_byte,
+            len: slice.len,
+            attributes: Attributes::MBR {
+                bootable: false,
+                type_code,
+            },
+            label: None,
+        })
+        .collect())
+}
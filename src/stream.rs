@@ -0,0 +1,127 @@
+//! Read partition tables from the start of a stream, such as a pipe, which can't seek.
+//!
+//! Only as much of the stream as the table needs is read: the MBR, and for GPT, the
+//! primary header and entry array. The bytes read are handed back, so the rest of the
+//! stream can still be used.
+//!
+//! As nothing beyond the primary table is read, the backup GPT is never looked at, and,
+//! unless the stream ends first, or [`Options::disk_len`] is set, nobody checks that the
+//! partitions fit on the disc.
+//!
+//! ```rust,no_run
+//! # fn go() -> Result<(), bootsector::Error> {
+//! let stdin = std::io::stdin();
+//! let streamed = bootsector::stream::read_partition_table(stdin.lock(), &Default::default())?;
+//! println!("{:?}", streamed.table.partitions);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::cell::Cell;
+use std::convert::TryFrom;
+use std::io::{self, Read};
+
+use snafu::prelude::*;
+
+use crate::errors::IoSnafu;
+use crate::{Error, Options, PartitionTable};
+
+/// Refuse to buffer more than this, looking for a table.
+const LIMIT: usize = 32 * 1024 * 1024;
+
+/// Read in at least this much at a time.
+const CHUNK: usize = 64 * 1024;
+
+/// A table read from a stream, and the bytes which were consumed to find it.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Streamed {
+    pub table: PartitionTable,
+
+    /// The start of the stream, exactly as it was read. The reader is positioned just
+    /// after this.
+    pub prefix: Vec<u8>,
+}
+
+/// Read the partition table from the start of a stream.
+///
+/// This behaves like [`read_partition_table`](crate::read_partition_table), except that
+/// the disc's length is taken from where the stream ended, if it ended.
+pub fn read_partition_table<R>(mut reader: R, options: &Options) -> Result<Streamed, Error>
+where
+    R: Read,
+{
+    let mut prefix = Prefix {
+        data: Vec::new(),
+        ended: false,
+        wanted: Cell::new(None),
+    };
+
+    loop {
+        prefix.wanted.set(None);
+        let disk_len = if prefix.ended {
+            Some(prefix.data.len() as u64)
+        } else {
+            options.disk_len
+        };
+        let result = crate::read_with_len(&prefix, options, disk_len);
+
+        let wanted = match prefix.wanted.take() {
+            None => {
+                return result.map(|table| Streamed {
+                    table,
+                    prefix: prefix.data,
+                })
+            }
+            Some(wanted) => usize::try_from(wanted).unwrap_or(usize::MAX),
+        };
+
+        if wanted > LIMIT {
+            return Err(Error::InvalidStatic {
+                message: "table is too far into the stream",
+            });
+        }
+
+        let target = wanted.max(prefix.data.len() + CHUNK).min(LIMIT);
+        prefix.fill(&mut reader, target)?;
+    }
+}
+
+/// The start of the stream, and how far into it the parser wanted to look.
+struct Prefix {
+    data: Vec<u8>,
+    ended: bool,
+    wanted: Cell<Option<u64>>,
+}
+
+impl Prefix {
+    fn fill<R: Read>(&mut self, reader: &mut R, target: usize) -> Result<(), Error> {
+        let start = self.data.len() as u64;
+        let mut limited = reader.take((target - self.data.len()) as u64);
+        limited
+            .read_to_end(&mut self.data)
+            .context(IoSnafu { pos: start })?;
+        self.ended = self.data.len() < target;
+        Ok(())
+    }
+}
+
+impl positioned_io2::ReadAt for Prefix {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let available = usize::try_from(pos)
+            .ok()
+            .and_then(|pos| self.data.get(pos..))
+            .unwrap_or(&[]);
+
+        if available.is_empty() && !buf.is_empty() && !self.ended {
+            let end = pos.saturating_add(buf.len() as u64);
+            if self.wanted.get().map_or(true, |wanted| wanted < end) {
+                self.wanted.set(Some(end));
+            }
+            return Err(io::Error::new(io::ErrorKind::Other, "not read yet"));
+        }
+
+        let len = buf.len().min(available.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        Ok(len)
+    }
+}
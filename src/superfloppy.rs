@@ -0,0 +1,168 @@
+//! Recognise "super-floppies": discs with a filesystem starting at byte 0, and no
+//! partition table, as many USB sticks and SD cards are formatted.
+//!
+//! FAT and NTFS boot sectors end with the same `55 AA` signature as an MBR, so a normal
+//! read either fails, or finds nonsense partitions in the boot code. This looks at the
+//! boot sector first, and only reads a table if it's not a filesystem's.
+//!
+//! ```rust
+//! use bootsector::superfloppy::{read_table_or_filesystem, Contents};
+//!
+//! # fn go(disc: &[u8]) -> Result<(), bootsector::Error> {
+//! match read_table_or_filesystem(disc, &Default::default())? {
+//!     Contents::Table(table) => println!("{} partitions", table.partitions.len()),
+//!     Contents::WholeDevice(fs) => println!("a {:?} filesystem, with no table", fs.filesystem),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::{boxed::Box, string::String};
+
+use snafu::ResultExt;
+
+use crate::errors::ReadSnafu;
+use crate::{io, label, le, Error, Options, PartitionTable};
+
+/// The kinds of filesystem recognised at the start of a disc.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Filesystem {
+    FAT,
+    ExFAT,
+    NTFS,
+}
+
+/// A filesystem which covers the whole disc, from [`read_whole_device`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct WholeDevice {
+    pub filesystem: Filesystem,
+
+    /// How long the boot sector says the filesystem is, in bytes. The disc may be longer.
+    pub len: u64,
+
+    /// The volume label, for FAT and NTFS, as [`label::probe_label`] finds it.
+    pub label: Option<String>,
+}
+
+/// What's at the start of a disc, from [`read_table_or_filesystem`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Contents {
+    Table(Box<PartitionTable>),
+    WholeDevice(WholeDevice),
+}
+
+/// Look for a FAT, exFAT or NTFS boot sector at the start of the disc.
+///
+/// `Ok(None)` if it's anything else, including an MBR. The boot sector's parameters must
+/// be plausible, so boot code with a jump at the start, as GRUB's has, isn't mistaken for
+/// a filesystem. The label is read if possible, but failing to read it isn't an error.
+pub fn read_whole_device<R>(reader: R) -> Result<Option<WholeDevice>, Error>
+where
+    R: io::ReadAt,
+{
+    whole_device(&reader)
+}
+
+pub(crate) fn whole_device<R>(reader: &R) -> Result<Option<WholeDevice>, Error>
+where
+    R: io::ReadAt + ?Sized,
+{
+    let mut boot = [0u8; 512];
+    reader.read_exact_at(0, &mut boot).context(ReadSnafu {
+        what: "the boot sector",
+        pos: 0u64,
+    })?;
+
+    let (filesystem, len) = match identify(&boot) {
+        Some(found) => found,
+        None => return Ok(None),
+    };
+
+    let label = match filesystem {
+        Filesystem::ExFAT => None,
+        Filesystem::FAT | Filesystem::NTFS => label::probe_at(reader, 0, len).unwrap_or(None),
+    };
+
+    Ok(Some(WholeDevice {
+        filesystem,
+        len,
+        label,
+    }))
+}
+
+/// The filesystem a boot sector is for, and how long it says the filesystem is.
+fn identify(boot: &[u8; 512]) -> Option<(Filesystem, u64)> {
+    // a short jump over the parameters, or a near one
+    if !(0xeb == boot[0] && 0x90 == boot[2]) && 0xe9 != boot[0] {
+        return None;
+    }
+
+    let bytes_per_sector = le::read_u16(&boot[0x0b..]);
+    let plausible_sector = bytes_per_sector.is_power_of_two() && bytes_per_sector >= 512;
+
+    if b"EXFAT   " == &boot[3..11] {
+        // the shift, rather than the bytes, and the old parameters must be zero
+        let shift = boot[0x6c];
+        if !(9..=12).contains(&shift) || boot[0x0b..0x40].iter().any(|b| 0 != *b) {
+            return None;
+        }
+        let sectors = le::read_u64(&boot[0x48..]);
+        return Some((Filesystem::ExFAT, sectors.checked_shl(u32::from(shift))?));
+    }
+
+    if !plausible_sector || bytes_per_sector > 4096 {
+        return None;
+    }
+    let sectors_per_cluster = boot[0x0d];
+    if !sectors_per_cluster.is_power_of_two() {
+        return None;
+    }
+
+    if b"NTFS    " == &boot[3..11] {
+        let sectors = le::read_u64(&boot[0x28..]);
+        return Some((
+            Filesystem::NTFS,
+            sectors.checked_mul(u64::from(bytes_per_sector))?,
+        ));
+    }
+
+    let reserved = le::read_u16(&boot[0x0e..]);
+    let fats = boot[0x10];
+    let media = boot[0x15];
+    if 0 == reserved || !(1..=2).contains(&fats) || (0xf0 != media && media < 0xf8) {
+        return None;
+    }
+
+    let sectors = match le::read_u16(&boot[0x13..]) {
+        0 => le::read_u32(&boot[0x20..]),
+        small => u32::from(small),
+    };
+    if 0 == sectors {
+        return None;
+    }
+
+    Some((
+        Filesystem::FAT,
+        u64::from(sectors) * u64::from(bytes_per_sector),
+    ))
+}
+
+/// Read the partition table, unless the disc starts with a filesystem, in which case
+/// describe that instead.
+///
+/// The filesystem is looked for first, as a FAT or NTFS boot sector may well parse as an
+/// MBR, with nonsense partitions. Otherwise, this is [`read_partition_table`], so the
+/// table is checked against [`Options::disk_len`], if it's set.
+///
+/// [`read_partition_table`]: crate::read_partition_table
+pub fn read_table_or_filesystem<R>(reader: R, options: &Options) -> Result<Contents, Error>
+where
+    R: io::ReadAt,
+{
+    if let Some(found) = whole_device(&reader)? {
+        return Ok(Contents::WholeDevice(found));
+    }
+
+    crate::read_borrowed(&reader, options, options.disk_len)
+        .map(|table| Contents::Table(Box::new(table)))
+}
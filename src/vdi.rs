@@ -0,0 +1,176 @@
+//! Read the disc inside a VDI image, as used by VirtualBox.
+//!
+//! Dynamic and fixed images are supported; differencing images, which need their parent,
+//! are not.
+//!
+//! ```rust,no_run
+//! # fn go() -> Result<(), bootsector::Error> {
+//! let file = std::fs::File::open("disc.vdi").unwrap();
+//! let disc = bootsector::vdi::Vdi::open(file)?;
+//! let table = bootsector::read_partition_table_sized(&disc, &Default::default())?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::TryFrom;
+use std::io;
+
+use crate::io::ReadAt;
+use crate::le;
+use crate::Error;
+
+const SIGNATURE: u32 = 0xbeda_107f;
+const VERSION_1_1: u32 = 0x0001_0001;
+
+const DYNAMIC: u32 = 1;
+const FIXED: u32 = 2;
+
+/// Block map entries which aren't locations: never written, or explicitly zeroed.
+const UNALLOCATED: u32 = 0xffff_ffff;
+const ZERO: u32 = 0xffff_fffe;
+
+/// Far larger than any real block map: 8TiB, in VirtualBox's 1MiB blocks.
+const MAX_MAP_BYTES: u64 = 32 * 1024 * 1024;
+
+/// A VDI image, readable as the disc it contains.
+pub struct Vdi<R> {
+    inner: R,
+    disk_len: u64,
+    block_size: u64,
+    /// The offset of the first block in the image.
+    data_offset: u64,
+    /// The distance between blocks in the image, including their extra data.
+    block_stride: u64,
+    /// Skipped at the start of each block.
+    block_extra: u64,
+    map: Vec<u32>,
+}
+
+impl<R: positioned_io2::ReadAt> Vdi<R> {
+    /// Validate the header, and load the block map.
+    pub fn open(inner: R) -> Result<Vdi<R>, Error> {
+        let mut header = [0u8; 0x190];
+        ReadAt::read_exact_at(&inner, 0, &mut header)?;
+
+        if SIGNATURE != le::read_u32(&header[0x40..]) {
+            return Err(Error::NotFound);
+        }
+
+        let version = le::read_u32(&header[0x44..]);
+        if VERSION_1_1 != version {
+            return Err(Error::InvalidData {
+                message: format!("unsupported VDI version: {:#x}", version),
+            });
+        }
+
+        match le::read_u32(&header[0x4c..]) {
+            DYNAMIC | FIXED => (),
+            other => {
+                return Err(Error::InvalidData {
+                    message: format!("unsupported VDI image type: {}", other),
+                })
+            }
+        }
+
+        let map_offset = u64::from(le::read_u32(&header[0x154..]));
+        let data_offset = u64::from(le::read_u32(&header[0x158..]));
+        let disk_len = le::read_u64(&header[0x170..]);
+        let block_size = u64::from(le::read_u32(&header[0x178..]));
+        let block_extra = u64::from(le::read_u32(&header[0x17c..]));
+        let blocks = le::read_u32(&header[0x180..]);
+
+        if 0 == block_size || 0 != block_size % 512 {
+            return Err(Error::InvalidData {
+                message: format!("invalid VDI block size: {}", block_size),
+            });
+        }
+
+        if u64::from(blocks) * block_size < disk_len {
+            return Err(Error::InvalidStatic {
+                message: "VDI block map is too small for the disc",
+            });
+        }
+
+        let map_bytes = u64::from(blocks) * 4;
+        if map_bytes > MAX_MAP_BYTES {
+            return Err(Error::InvalidStatic {
+                message: "VDI block map is unreasonably large",
+            });
+        }
+
+        let map_len = usize::try_from(map_bytes).map_err(|_| Error::BiggerThanMemory)?;
+        let mut raw = vec![0u8; map_len];
+        ReadAt::read_exact_at(&inner, map_offset, &mut raw)?;
+
+        Ok(Vdi {
+            inner,
+            disk_len,
+            block_size,
+            data_offset,
+            block_stride: block_size.checked_add(block_extra).ok_or(Error::Overflow)?,
+            block_extra,
+            map: raw.chunks_exact(4).map(le::read_u32).collect(),
+        })
+    }
+}
+
+impl<R> Vdi<R> {
+    /// The length of the guest disc, in bytes.
+    pub fn disk_len(&self) -> u64 {
+        self.disk_len
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: positioned_io2::ReadAt> positioned_io2::ReadAt for Vdi<R> {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        if pos >= self.disk_len {
+            return Ok(0);
+        }
+
+        let remaining = usize::try_from(self.disk_len - pos).unwrap_or(usize::MAX);
+        let within = pos % self.block_size;
+        let available = usize::try_from(self.block_size - within).unwrap_or(usize::MAX);
+        let len = buf.len().min(remaining).min(available);
+        let buf = &mut buf[..len];
+
+        let entry = usize::try_from(pos / self.block_size)
+            .ok()
+            .and_then(|block| self.map.get(block))
+            .copied()
+            .unwrap_or(UNALLOCATED);
+
+        match entry {
+            UNALLOCATED | ZERO => buf.iter_mut().for_each(|b| *b = 0),
+            block => {
+                let host = u64::from(block)
+                    .checked_mul(self.block_stride)
+                    .and_then(|start| start.checked_add(self.data_offset))
+                    .and_then(|start| start.checked_add(self.block_extra))
+                    .and_then(|start| start.checked_add(within))
+                    .ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "VDI block is beyond the end of the file",
+                        )
+                    })?;
+                positioned_io2::ReadAt::read_exact_at(&self.inner, host, buf)?;
+            }
+        }
+
+        Ok(len)
+    }
+}
+
+impl<R> positioned_io2::Size for Vdi<R> {
+    fn size(&self) -> io::Result<Option<u64>> {
+        Ok(Some(self.disk_len))
+    }
+}
@@ -0,0 +1,698 @@
+//! Run every check we know about against a disc, collecting findings instead of
+//! stopping at the first problem.
+//!
+//! This is much more thorough than [`list_partitions`](crate::list_partitions), which
+//! only reads as much as it needs to. In particular, the backup GPT is read and compared
+//! against the primary, and the partitions are checked against each other.
+//!
+//! [`report`] also returns the evidence: every checksum and signature it looked at, and
+//! which copy of the GPT header the partitions were taken from.
+
+use alloc::{format, string::String, vec, vec::Vec};
+use core::convert::TryFrom;
+use core::fmt;
+
+use snafu::ResultExt;
+
+use crate::errors::ReadSnafu;
+use crate::gpt::{self, Header};
+use crate::{io, mbr, Error, Options, Partition, PartitionTable, SectorSize};
+
+/// How bad is a finding?
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Severity {
+    /// Worth knowing, but entirely legal.
+    Info,
+    /// Suspicious, or likely to upset some tools, but readable.
+    Warning,
+    /// The disc is not valid; some readers will refuse it, or read it differently.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// Something noticed during verification.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Finding {
+    pub severity: Severity,
+
+    /// A stable, machine-readable identifier for the check, e.g. `gpt-primary-header-crc`.
+    pub code: &'static str,
+
+    /// A human-readable explanation.
+    pub message: String,
+}
+
+/// Check everything we can about a disc.
+///
+/// `disk_len`, if known, enables checks that the structures fit on the disc, and that the
+/// backup GPT is where it should be. Only the `sector_size` and `max_sector_size` from the
+/// `options` are used.
+///
+/// Errors are only returned if the reader fails in a way which stops us looking at anything;
+/// problems with the disc's contents are reported as findings.
+pub fn verify<R>(reader: R, disk_len: Option<u64>, options: &Options) -> Result<Vec<Finding>, Error>
+where
+    R: io::ReadAt,
+{
+    Ok(report(reader, disk_len, options)?.findings)
+}
+
+/// Everything [`verify`] checked, and what it found.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Report {
+    pub findings: Vec<Finding>,
+
+    /// Every checksum which was compared, in the order they were checked.
+    pub checksums: Vec<Checksum>,
+
+    /// Every magic number which was looked for, in the order they were checked.
+    pub signatures: Vec<Signature>,
+
+    /// Which copy of the GPT header the partitions were read from, if any.
+    pub gpt_header_used: Option<GptCopy>,
+}
+
+/// A checksum stored on the disc, and the value we calculated for the data it covers.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Checksum {
+    /// e.g. `primary gpt header`, or `backup gpt entry array`.
+    pub what: &'static str,
+    /// The byte offset of the data covered.
+    pub pos: u64,
+    pub stored: u32,
+    pub computed: u32,
+}
+
+impl Checksum {
+    pub fn matches(&self) -> bool {
+        self.stored == self.computed
+    }
+}
+
+/// A check for a magic number, e.g. the `55 AA` at the end of the boot sector.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Signature {
+    /// e.g. `mbr boot signature`, or `primary gpt header signature`.
+    pub what: &'static str,
+    /// The byte offset of the signature.
+    pub pos: u64,
+    pub found: bool,
+}
+
+/// One of the two copies of the GPT header.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum GptCopy {
+    Primary,
+    Backup,
+}
+
+/// Run the same checks as [`verify`], recording the evidence along with the findings.
+pub fn report<R>(reader: R, disk_len: Option<u64>, options: &Options) -> Result<Report, Error>
+where
+    R: io::ReadAt,
+{
+    let mut report = Report::default();
+
+    let mut disc_header = [0u8; 512];
+    reader
+        .read_exact_at(0, &mut disc_header)
+        .context(ReadSnafu {
+            what: "the mbr",
+            pos: 0u64,
+        })?;
+
+    let boot_signature = 0x55 == disc_header[510] && 0xAA == disc_header[511];
+    report.signatures.push(Signature {
+        what: "mbr boot signature",
+        pos: 510,
+        found: boot_signature,
+    });
+
+    if !boot_signature {
+        report.error(
+            "mbr-signature",
+            String::from("boot sector signature is missing"),
+        );
+        return Ok(report);
+    }
+
+    let header_table = match mbr::parse_partition_table(&disc_header) {
+        Ok(table) => table,
+        Err(e) => {
+            report.error("mbr-entries", format!("{}", e));
+            return Ok(report);
+        }
+    };
+
+    let protective = header_table.iter().find(|part| is_ee(part));
+
+    let protective = match protective {
+        Some(protective) => protective,
+        None => {
+            check_partitions(&mut report, &header_table, disk_len, 512);
+            return Ok(report);
+        }
+    };
+
+    if !gpt::is_protective(protective) {
+        report.warning(
+            "mbr-protective-shape",
+            String::from("protective partition is not a lone, non-bootable, first entry"),
+        );
+    }
+
+    if header_table.len() > 1 {
+        report.warning(
+            "mbr-hybrid",
+            format!(
+                "hybrid MBR: {} entries alongside the protective partition",
+                header_table.len() - 1
+            ),
+        );
+    }
+
+    let sector_size = match options.sector_size {
+        SectorSize::Known(size) => u64::from(size),
+        SectorSize::GuessOrAssume => {
+            match gpt::probe_sector_size(&reader, options.max_sector_size) {
+                Some(size) => size,
+                // 512 is assumed anyway, so the header checks can say what's wrong with it
+                None if 512 == protective.first_byte => 512,
+                None => match crate::check_guess(&reader, protective.first_byte, options) {
+                    Ok(size) => size,
+                    Err(e) => {
+                        report.error("sector-size", format!("{}", e));
+                        return Ok(report);
+                    }
+                },
+            }
+        }
+    };
+
+    if sector_size < 512 {
+        report.error(
+            "sector-size",
+            format!("sector size of {} is implausible", sector_size),
+        );
+        return Ok(report);
+    }
+
+    // the mbr parser always assumes 512 byte sectors, so these are "mbr lbas"
+    if 512 != protective.first_byte {
+        report.error(
+            "mbr-protective-start",
+            String::from("protective partition does not start at lba 1"),
+        );
+    }
+
+    let disk_lbas = disk_len.map(|len| len / sector_size);
+
+    if let Some(disk_lbas) = disk_lbas {
+        let expected = disk_lbas.saturating_sub(1).min(0xFFFF_FFFF);
+        let actual = protective.len / 512;
+        if expected != actual {
+            report.warning(
+                "mbr-protective-size",
+                format!(
+                    "protective partition covers {} lbas, but the disc has {} after the mbr",
+                    actual, expected
+                ),
+            );
+        }
+    }
+
+    let primary = check_header(&mut report, &reader, &PRIMARY, 1, sector_size)?;
+
+    let last_lba = disk_lbas.and_then(|lbas| lbas.checked_sub(1));
+
+    let backup_lba = match (&primary, last_lba) {
+        (Some((header, _)), Some(last_lba)) => {
+            if header.alternate_lba != last_lba {
+                report.warning(
+                    "gpt-backup-location",
+                    format!(
+                        "backup header is at lba {}, not at the end of the disc ({})",
+                        header.alternate_lba, last_lba
+                    ),
+                );
+            }
+            Some(header.alternate_lba)
+        }
+        (Some((header, _)), None) => Some(header.alternate_lba),
+        (None, last_lba) => last_lba,
+    };
+
+    let backup = match backup_lba {
+        Some(lba) => check_header(&mut report, &reader, &BACKUP, lba, sector_size)?,
+        None => None,
+    };
+
+    if let (Some((primary, _)), Some((backup, _))) = (&primary, &backup) {
+        if backup.alternate_lba != 1 {
+            report.error(
+                "gpt-backup-alternate",
+                String::from("backup header does not point back at the primary"),
+            );
+        }
+
+        if primary.disk_guid != backup.disk_guid
+            || primary.first_usable_lba != backup.first_usable_lba
+            || primary.last_usable_lba != backup.last_usable_lba
+            || primary.entries != backup.entries
+            || primary.entry_size != backup.entry_size
+            || primary.entries_crc != backup.entries_crc
+        {
+            report.error(
+                "gpt-backup-mismatch",
+                String::from("primary and backup headers describe different tables"),
+            );
+        }
+    }
+
+    let (header, table) = match (primary, backup) {
+        (Some(found), _) => {
+            report.gpt_header_used = Some(GptCopy::Primary);
+            found
+        }
+        (None, Some(found)) => {
+            report.gpt_header_used = Some(GptCopy::Backup);
+            found
+        }
+        (None, None) => return Ok(report),
+    };
+
+    match gpt::parse_entries(&table, &header, sector_size) {
+        Ok(partitions) => check_partitions(&mut report, &partitions, disk_len, sector_size),
+        Err(e) => report.error("gpt-entries", format!("{}", e)),
+    }
+
+    Ok(report)
+}
+
+/// Check a table which has already been read for things which are legal, or at least
+/// readable, but which are untidy, or which some tools complain about, as `gdisk` prints
+/// warnings for.
+///
+/// Unlike [`verify`], nothing is read, so broken checksums and the like can't be found;
+/// every finding is a [`Severity::Warning`], or [`Severity::Info`]. The codes are those
+/// [`verify`] uses for the same problems.
+///
+/// ```rust
+/// # fn go(disc: &[u8]) -> Result<(), bootsector::Error> {
+/// let table = bootsector::read_partition_table(disc, &Default::default())?;
+/// for finding in bootsector::verify::lint(&table, disc.len() as u64) {
+///     println!("{}: {}", finding.severity, finding.message);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn lint(table: &PartitionTable, disk_len: u64) -> Vec<Finding> {
+    let mut report = Report::default();
+    let sector_size = table.sector_size;
+    let disk_lbas = disk_len / sector_size.max(1);
+
+    if let Some(header) = &table.gpt_header {
+        if let Some(protective) = table.mbr_partitions.iter().find(|part| is_ee(part)) {
+            // the mbr parser always assumes 512 byte sectors, so this is the raw count
+            let expected = disk_lbas.saturating_sub(1).min(0xFFFF_FFFF);
+            let actual = protective.len / 512;
+            if expected != actual {
+                report.warning(
+                    "mbr-protective-size",
+                    format!(
+                        "protective partition covers {} lbas, but the disc has {} after the mbr",
+                        actual, expected
+                    ),
+                );
+            }
+        }
+
+        let last_lba = disk_lbas.saturating_sub(1);
+        if header.alternate_lba != last_lba {
+            report.warning(
+                "gpt-backup-location",
+                format!(
+                    "backup header is at lba {}, not at the end of the disc ({})",
+                    header.alternate_lba, last_lba
+                ),
+            );
+        }
+
+        if let Some(array) = header.table_sectors(sector_size) {
+            // the mbr, the header, then the entries; and the same backwards at the end
+            let first = 2 + array;
+            if header.first_usable_lba > first {
+                report.info(
+                    "gpt-usable-start",
+                    format!(
+                        "first usable lba is {}, but could be {}",
+                        header.first_usable_lba, first
+                    ),
+                );
+            }
+
+            let last = last_lba.saturating_sub(1 + array);
+            if header.last_usable_lba < last {
+                report.warning(
+                    "gpt-usable-end",
+                    format!(
+                        "last usable lba is {}, but could be {}; has the disc grown?",
+                        header.last_usable_lba, last
+                    ),
+                );
+            }
+        }
+    }
+
+    let mut by_id: Vec<&Partition> = table.partitions.iter().collect();
+    by_id.sort_by_key(|part| part.id);
+    for pair in by_id.windows(2) {
+        if pair[1].first_byte < pair[0].first_byte {
+            report.info(
+                "partition-order",
+                format!(
+                    "partition {} comes before partition {} on the disc",
+                    pair[1].id, pair[0].id
+                ),
+            );
+        }
+    }
+
+    for part in &by_id {
+        check_alignment(&mut report, part, sector_size);
+
+        if let Some(name) = part.name() {
+            if name.chars().any(char::is_control) {
+                report.warning(
+                    "partition-name-control",
+                    format!("partition {} has control characters in its name", part.id),
+                );
+            }
+        }
+    }
+
+    report.findings
+}
+
+impl Report {
+    fn push(&mut self, severity: Severity, code: &'static str, message: String) {
+        self.findings.push(Finding {
+            severity,
+            code,
+            message,
+        })
+    }
+
+    fn info(&mut self, code: &'static str, message: String) {
+        self.push(Severity::Info, code, message)
+    }
+
+    fn warning(&mut self, code: &'static str, message: String) {
+        self.push(Severity::Warning, code, message)
+    }
+
+    fn error(&mut self, code: &'static str, message: String) {
+        self.push(Severity::Error, code, message)
+    }
+}
+
+fn is_ee(part: &Partition) -> bool {
+    match part.attributes {
+        crate::Attributes::MBR { type_code, .. } => 0xee == type_code,
+        _ => false,
+    }
+}
+
+/// The finding codes for one of the two copies of the header.
+struct HeaderCopy {
+    name: &'static str,
+    header: &'static str,
+    magic: &'static str,
+    entry_array: &'static str,
+    unreadable: &'static str,
+    signature: &'static str,
+    revision: &'static str,
+    header_crc: &'static str,
+    reserved: &'static str,
+    tail: &'static str,
+    my_lba: &'static str,
+    usable_range: &'static str,
+    table_crc: &'static str,
+}
+
+const PRIMARY: HeaderCopy = HeaderCopy {
+    name: "primary",
+    header: "primary gpt header",
+    magic: "primary gpt header signature",
+    entry_array: "primary gpt entry array",
+    unreadable: "gpt-primary-unreadable",
+    signature: "gpt-primary-signature",
+    revision: "gpt-primary-revision",
+    header_crc: "gpt-primary-header-crc",
+    reserved: "gpt-primary-reserved",
+    tail: "gpt-primary-tail",
+    my_lba: "gpt-primary-my-lba",
+    usable_range: "gpt-primary-usable-range",
+    table_crc: "gpt-primary-table-crc",
+};
+
+const BACKUP: HeaderCopy = HeaderCopy {
+    name: "backup",
+    header: "backup gpt header",
+    magic: "backup gpt header signature",
+    entry_array: "backup gpt entry array",
+    unreadable: "gpt-backup-unreadable",
+    signature: "gpt-backup-signature",
+    revision: "gpt-backup-revision",
+    header_crc: "gpt-backup-header-crc",
+    reserved: "gpt-backup-reserved",
+    tail: "gpt-backup-tail",
+    my_lba: "gpt-backup-my-lba",
+    usable_range: "gpt-backup-usable-range",
+    table_crc: "gpt-backup-table-crc",
+};
+
+/// Check a header and its table, returning them if they're usable.
+fn check_header<R: io::ReadAt>(
+    report: &mut Report,
+    reader: &R,
+    copy: &HeaderCopy,
+    lba: u64,
+    sector_size: u64,
+) -> Result<Option<(Header, Vec<u8>)>, Error> {
+    let which = copy.name;
+
+    let sector_size_mem = usize::try_from(sector_size).map_err(|_| Error::BiggerThanMemory)?;
+    let mut sector = vec![0u8; sector_size_mem];
+
+    let pos = match lba.checked_mul(sector_size) {
+        Some(pos) => pos,
+        None => {
+            report.error(
+                copy.unreadable,
+                format!("{} header lba is out of range", which),
+            );
+            return Ok(None);
+        }
+    };
+
+    if let Err(e) = reader.read_exact_at(pos, &mut sector) {
+        report.error(
+            copy.unreadable,
+            format!("{} header could not be read: {}", which, e),
+        );
+        return Ok(None);
+    }
+
+    let signature = gpt::SIGNATURE == &sector[..8];
+    report.signatures.push(Signature {
+        what: copy.magic,
+        pos,
+        found: signature,
+    });
+
+    if !signature {
+        report.error(
+            copy.signature,
+            format!("{} header signature missing", which),
+        );
+        return Ok(None);
+    }
+
+    let header = Header::decode(&sector);
+
+    if gpt::REVISION_ONE != header.revision {
+        report.warning(
+            copy.revision,
+            format!("{} header has revision {:08x}", which, header.revision),
+        );
+    }
+
+    let computed = header.computed_crc(&sector);
+    if let Some(computed) = computed {
+        report.checksums.push(Checksum {
+            what: copy.header,
+            pos,
+            stored: header.header_crc,
+            computed,
+        });
+    }
+
+    if Some(header.header_crc) != computed {
+        report.error(
+            copy.header_crc,
+            format!("{} header checksum mismatch", which),
+        );
+        return Ok(None);
+    }
+
+    if 0 != header.reserved {
+        report.warning(
+            copy.reserved,
+            format!("{} header has data in its reserved field", which),
+        );
+    }
+
+    // the checksum passing means the header size is sane
+    let header_size = usize::try_from(header.header_size).map_err(|_| Error::Overflow)?;
+    if sector[header_size..].iter().any(|x| 0 != *x) {
+        report.warning(
+            copy.tail,
+            format!("{} header sector has data after the header", which),
+        );
+    }
+
+    if header.my_lba != lba {
+        report.error(
+            copy.my_lba,
+            format!(
+                "{} header at lba {} claims to be at lba {}",
+                which, lba, header.my_lba
+            ),
+        );
+    }
+
+    if header.first_usable_lba > header.last_usable_lba {
+        report.error(
+            copy.usable_range,
+            format!("{} usable lbas are backwards", which),
+        );
+    }
+
+    // the limits gpt::validate_header applies, before trusting the size enough to allocate
+    let plausible = u16::try_from(header.entries).is_ok()
+        && u16::try_from(header.entry_size).is_ok()
+        && header.entry_size >= 128;
+
+    let table_len = match header.table_len() {
+        Some(len) if plausible => len,
+        _ => {
+            report.error(
+                copy.table_crc,
+                format!("{} entry array has an implausible size", which),
+            );
+            return Ok(None);
+        }
+    };
+
+    let mut table = vec![0u8; table_len];
+    let read = header
+        .entries_lba
+        .checked_mul(sector_size)
+        .ok_or(Error::Overflow)
+        .and_then(|pos| reader.read_exact_at(pos, &mut table).map(|()| pos));
+
+    let table_pos = match read {
+        Ok(pos) => pos,
+        Err(e) => {
+            report.error(
+                copy.unreadable,
+                format!("{} entry array could not be read: {}", which, e),
+            );
+            return Ok(None);
+        }
+    };
+
+    let computed = gpt::checksum(&table);
+    report.checksums.push(Checksum {
+        what: copy.entry_array,
+        pos: table_pos,
+        stored: header.entries_crc,
+        computed,
+    });
+
+    if header.entries_crc != computed {
+        report.error(
+            copy.table_crc,
+            format!("{} entry array checksum mismatch", which),
+        );
+        return Ok(None);
+    }
+
+    Ok(Some((header, table)))
+}
+
+fn check_partitions(
+    report: &mut Report,
+    partitions: &[Partition],
+    disk_len: Option<u64>,
+    sector_size: u64,
+) {
+    for (i, part) in partitions.iter().enumerate() {
+        let end = part.first_byte.checked_add(part.len);
+
+        if let Some(disk_len) = disk_len {
+            let past_end = match end {
+                Some(end) => end > disk_len,
+                None => true,
+            };
+            if past_end {
+                report.error(
+                    "partition-beyond-disk",
+                    format!("partition {} extends past the end of the disc", part.id),
+                );
+            }
+        }
+
+        for other in &partitions[i + 1..] {
+            if part.overlaps(other) {
+                report.error(
+                    "partition-overlap",
+                    format!("partitions {} and {} overlap", part.id, other.id),
+                );
+            }
+        }
+
+        check_alignment(report, part, sector_size);
+    }
+}
+
+fn check_alignment(report: &mut Report, part: &Partition, sector_size: u64) {
+    const MIB: u64 = 1024 * 1024;
+    const PHYSICAL: u64 = 4096;
+
+    let alignment = PHYSICAL.max(sector_size);
+    if 0 != part.first_byte % alignment {
+        report.warning(
+            "partition-unaligned",
+            format!(
+                "partition {} does not start on a {} byte boundary",
+                part.id, alignment
+            ),
+        );
+    } else if 0 != part.first_byte % MIB {
+        report.info(
+            "partition-alignment",
+            format!("partition {} is not 1MiB aligned", part.id),
+        );
+    }
+}
@@ -0,0 +1,262 @@
+//! Read the disc inside a fixed or dynamic VHD image, as used by Virtual PC, Hyper-V and Azure.
+//!
+//! Differencing images, which need their parent, are not supported. See [`crate::vhdx`]
+//! for the newer format.
+//!
+//! ```rust,no_run
+//! # fn go() -> Result<(), bootsector::Error> {
+//! let file = std::fs::File::open("disc.vhd").unwrap();
+//! let disc = bootsector::vhd::Vhd::open(file)?;
+//! let table = bootsector::read_partition_table_sized(&disc, &Default::default())?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::TryFrom;
+use std::io;
+
+use crate::be;
+use crate::io::ReadAt;
+use crate::Error;
+
+const FOOTER_COOKIE: &[u8; 8] = b"conectix";
+const DYNAMIC_COOKIE: &[u8; 8] = b"cxsparse";
+
+const FIXED: u32 = 2;
+const DYNAMIC: u32 = 3;
+const DIFFERENCING: u32 = 4;
+
+const UNALLOCATED: u32 = 0xffff_ffff;
+
+/// The type of a VHD image.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DiskType {
+    /// The disc is stored directly, followed by the footer.
+    Fixed,
+    /// Blocks are allocated as they are written.
+    Dynamic,
+}
+
+/// A VHD image, readable as the disc it contains.
+pub struct Vhd<R> {
+    inner: R,
+    disk_len: u64,
+    layout: Layout,
+}
+
+enum Layout {
+    Fixed,
+    Dynamic {
+        block_size: u64,
+        /// The space before the data in each block, holding a bitmap of written sectors.
+        bitmap_len: u64,
+        /// The sector holding each block, if allocated.
+        bat: Vec<u32>,
+    },
+}
+
+impl<R: positioned_io2::ReadAt + positioned_io2::Size> Vhd<R> {
+    /// Find and validate the footer, and, for dynamic images, load the block table.
+    pub fn open(inner: R) -> Result<Vhd<R>, Error> {
+        let file_len = positioned_io2::Size::size(&inner)
+            .map_err(|source| Error::Io { source, pos: 0 })?
+            .ok_or(Error::InvalidStatic {
+                message: "the length of the VHD file is required",
+            })?;
+
+        if file_len < 512 {
+            return Err(Error::NotFound);
+        }
+
+        let mut footer = [0u8; 512];
+        ReadAt::read_exact_at(&inner, file_len - 512, &mut footer)?;
+
+        if FOOTER_COOKIE != &footer[..8] {
+            // dynamic images keep a copy of the footer at the start, if the end is damaged
+            ReadAt::read_exact_at(&inner, 0, &mut footer)?;
+            if FOOTER_COOKIE != &footer[..8] {
+                return Err(Error::NotFound);
+            }
+        }
+
+        if be::read_u32(&footer[64..]) != checksum(&footer, 64) {
+            return Err(Error::InvalidStatic {
+                message: "VHD footer checksum is incorrect",
+            });
+        }
+
+        let disk_len = be::read_u64(&footer[48..]);
+
+        let layout = match be::read_u32(&footer[60..]) {
+            FIXED => {
+                if disk_len > file_len - 512 {
+                    return Err(Error::InvalidStatic {
+                        message: "VHD file is shorter than its disc",
+                    });
+                }
+                Layout::Fixed
+            }
+            DYNAMIC => read_dynamic(&inner, be::read_u64(&footer[16..]), disk_len, file_len)?,
+            DIFFERENCING => {
+                return Err(Error::InvalidStatic {
+                    message: "differencing VHD images are not supported",
+                })
+            }
+            other => {
+                return Err(Error::InvalidData {
+                    message: format!("unrecognised VHD disc type: {}", other),
+                })
+            }
+        };
+
+        Ok(Vhd {
+            inner,
+            disk_len,
+            layout,
+        })
+    }
+}
+
+impl<R> Vhd<R> {
+    /// The length of the guest disc, in bytes.
+    pub fn disk_len(&self) -> u64 {
+        self.disk_len
+    }
+
+    pub fn disk_type(&self) -> DiskType {
+        match self.layout {
+            Layout::Fixed => DiskType::Fixed,
+            Layout::Dynamic { .. } => DiskType::Dynamic,
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+fn read_dynamic<R>(
+    inner: &R,
+    header_offset: u64,
+    disk_len: u64,
+    file_len: u64,
+) -> Result<Layout, Error>
+where
+    R: positioned_io2::ReadAt,
+{
+    let mut header = [0u8; 1024];
+    ReadAt::read_exact_at(inner, header_offset, &mut header)?;
+
+    if DYNAMIC_COOKIE != &header[..8] {
+        return Err(Error::InvalidStatic {
+            message: "VHD dynamic disc header not found",
+        });
+    }
+
+    if be::read_u32(&header[36..]) != checksum(&header, 36) {
+        return Err(Error::InvalidStatic {
+            message: "VHD dynamic disc header checksum is incorrect",
+        });
+    }
+
+    let bat_offset = be::read_u64(&header[16..]);
+    let entries = be::read_u32(&header[28..]);
+    let block_size = u64::from(be::read_u32(&header[32..]));
+
+    if 0 == block_size || 0 != block_size % 512 {
+        return Err(Error::InvalidData {
+            message: format!("invalid VHD block size: {}", block_size),
+        });
+    }
+
+    if u64::from(entries) * block_size < disk_len {
+        return Err(Error::InvalidStatic {
+            message: "VHD block table is too small for the disc",
+        });
+    }
+
+    // entries is a u32, so this can't overflow
+    let bat_len = u64::from(entries) * 4;
+    if bat_offset
+        .checked_add(bat_len)
+        .map_or(true, |bat_end| bat_end > file_len)
+    {
+        return Err(Error::InvalidStatic {
+            message: "VHD block table extends beyond the end of the file",
+        });
+    }
+
+    let bat_len = usize::try_from(bat_len).map_err(|_| Error::BiggerThanMemory)?;
+    let mut raw = vec![0u8; bat_len];
+    ReadAt::read_exact_at(inner, bat_offset, &mut raw)?;
+    let bat = raw.chunks_exact(4).map(be::read_u32).collect();
+
+    // one bit per sector, padded to a whole sector
+    let bitmap_len = (block_size / 512 / 8 + 511) / 512 * 512;
+
+    Ok(Layout::Dynamic {
+        block_size,
+        bitmap_len,
+        bat,
+    })
+}
+
+/// The ones' complement of the sum of the bytes, skipping the checksum itself.
+fn checksum(data: &[u8], at: usize) -> u32 {
+    let sum = data
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !(at..at + 4).contains(i))
+        .fold(0u32, |sum, (_, &b)| sum.wrapping_add(u32::from(b)));
+    !sum
+}
+
+impl<R: positioned_io2::ReadAt> positioned_io2::ReadAt for Vhd<R> {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        if pos >= self.disk_len {
+            return Ok(0);
+        }
+
+        let remaining = usize::try_from(self.disk_len - pos).unwrap_or(usize::MAX);
+        let len = buf.len().min(remaining);
+        let buf = &mut buf[..len];
+
+        match &self.layout {
+            Layout::Fixed => self.inner.read_at(pos, buf),
+            Layout::Dynamic {
+                block_size,
+                bitmap_len,
+                bat,
+            } => {
+                let within = pos % block_size;
+                let available = usize::try_from(block_size - within).unwrap_or(usize::MAX);
+                let buf = &mut buf[..len.min(available)];
+
+                let sector = usize::try_from(pos / block_size)
+                    .ok()
+                    .and_then(|block| bat.get(block))
+                    .copied()
+                    .unwrap_or(UNALLOCATED);
+
+                if UNALLOCATED == sector {
+                    buf.iter_mut().for_each(|b| *b = 0);
+                } else {
+                    let host = u64::from(sector) * 512 + bitmap_len + within;
+                    positioned_io2::ReadAt::read_exact_at(&self.inner, host, buf)?;
+                }
+
+                Ok(buf.len())
+            }
+        }
+    }
+}
+
+impl<R> positioned_io2::Size for Vhd<R> {
+    fn size(&self) -> io::Result<Option<u64>> {
+        Ok(Some(self.disk_len))
+    }
+}
@@ -0,0 +1,364 @@
+//! Read the disc inside a VHDX image, as used by Hyper-V.
+//!
+//! Images with an outstanding log, which must be replayed before they're consistent,
+//! and differencing images, which need their parent, are not supported. See
+//! [`crate::vhd`] for the older format.
+//!
+//! ```rust,no_run
+//! # fn go() -> Result<(), bootsector::Error> {
+//! let file = std::fs::File::open("disc.vhdx").unwrap();
+//! let disc = bootsector::vhdx::Vhdx::open(file)?;
+//! let table = bootsector::read_partition_table_sized(&disc, &Default::default())?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::TryFrom;
+use std::io;
+
+use crc::Crc;
+
+use crate::guid;
+use crate::io::ReadAt;
+use crate::le;
+use crate::Error;
+
+const CRC: Crc<u32> = Crc::<u32>::new(&crc::CRC_32_ISCSI);
+
+const KIB: u64 = 1024;
+const MIB: u64 = 1024 * KIB;
+
+const FILE_SIGNATURE: &[u8; 8] = b"vhdxfile";
+const HEADER_SIGNATURE: &[u8; 4] = b"head";
+const REGION_SIGNATURE: &[u8; 4] = b"regi";
+const METADATA_SIGNATURE: &[u8; 8] = b"metadata";
+
+const BAT_REGION: &str = "2DC27766-F623-4200-9D64-115E9BFD4A08";
+const METADATA_REGION: &str = "8B7CA206-4790-4B9A-B8FE-575F050F886E";
+
+const FILE_PARAMETERS: &str = "CAA16737-FA36-4D43-B3B6-33F0AA44E76B";
+const VIRTUAL_DISK_SIZE: &str = "2FA54224-CD1B-4876-B211-5DBED83BF4B8";
+const LOGICAL_SECTOR_SIZE: &str = "8141BF1D-A96F-4709-BA47-F233A8FAAB5F";
+
+/// Block states, in the low bits of a BAT entry.
+const PAYLOAD_FULLY_PRESENT: u64 = 6;
+const PAYLOAD_PARTIALLY_PRESENT: u64 = 7;
+
+/// A VHDX image, readable as the disc it contains.
+pub struct Vhdx<R> {
+    inner: R,
+    disk_len: u64,
+    block_size: u64,
+    logical_sector_size: u32,
+    /// The number of data blocks between each sector bitmap entry in the BAT.
+    chunk_ratio: u64,
+    bat: Vec<u64>,
+}
+
+impl<R: positioned_io2::ReadAt> Vhdx<R> {
+    /// Validate the headers, and load the metadata and block table.
+    pub fn open(inner: R) -> Result<Vhdx<R>, Error> {
+        let mut signature = [0u8; 8];
+        ReadAt::read_exact_at(&inner, 0, &mut signature)?;
+        if FILE_SIGNATURE != &signature {
+            return Err(Error::NotFound);
+        }
+
+        let header = current_header(&inner)?;
+        if header[48..64].iter().any(|&b| 0 != b) {
+            return Err(Error::InvalidStatic {
+                message: "VHDX log must be replayed before the image can be read",
+            });
+        }
+
+        let regions = region_table(&inner)?;
+        let mut bat_region = None;
+        let mut metadata_region = None;
+        for entry in regions.chunks_exact(32) {
+            let location = (le::read_u64(&entry[16..]), le::read_u32(&entry[24..]));
+            if is(&entry[..16], BAT_REGION) {
+                bat_region = Some(location);
+            } else if is(&entry[..16], METADATA_REGION) {
+                metadata_region = Some(location);
+            } else if 0 != le::read_u32(&entry[28..]) & 1 {
+                return Err(Error::InvalidData {
+                    message: format!(
+                        "unsupported required VHDX region: {}",
+                        guid::to_string(&guid_at(entry))
+                    ),
+                });
+            }
+        }
+
+        let (bat_offset, bat_len) = bat_region.ok_or(Error::InvalidStatic {
+            message: "VHDX BAT region is missing",
+        })?;
+        let (metadata_offset, metadata_len) = metadata_region.ok_or(Error::InvalidStatic {
+            message: "VHDX metadata region is missing",
+        })?;
+
+        let metadata = Metadata::read(&inner, metadata_offset, metadata_len)?;
+
+        if !(MIB..=256 * MIB).contains(&metadata.block_size)
+            || !metadata.block_size.is_power_of_two()
+        {
+            return Err(Error::InvalidData {
+                message: format!("invalid VHDX block size: {}", metadata.block_size),
+            });
+        }
+
+        if 512 != metadata.logical_sector_size && 4096 != metadata.logical_sector_size {
+            return Err(Error::InvalidData {
+                message: format!(
+                    "invalid VHDX logical sector size: {}",
+                    metadata.logical_sector_size
+                ),
+            });
+        }
+
+        let chunk_ratio = (1 << 23) * u64::from(metadata.logical_sector_size) / metadata.block_size;
+        let blocks = metadata
+            .disk_len
+            .checked_add(metadata.block_size - 1)
+            .ok_or(Error::Overflow)?
+            / metadata.block_size;
+        let entries = blocks + (blocks.saturating_sub(1)) / chunk_ratio;
+        if entries * 8 > u64::from(bat_len) {
+            return Err(Error::InvalidStatic {
+                message: "VHDX BAT is too small for the disc",
+            });
+        }
+
+        let mut raw = vec![0u8; usize::try_from(entries * 8).map_err(|_| Error::BiggerThanMemory)?];
+        ReadAt::read_exact_at(&inner, bat_offset, &mut raw)?;
+        let bat = raw.chunks_exact(8).map(le::read_u64).collect();
+
+        Ok(Vhdx {
+            inner,
+            disk_len: metadata.disk_len,
+            block_size: metadata.block_size,
+            logical_sector_size: metadata.logical_sector_size,
+            chunk_ratio,
+            bat,
+        })
+    }
+}
+
+impl<R> Vhdx<R> {
+    /// The length of the guest disc, in bytes.
+    pub fn disk_len(&self) -> u64 {
+        self.disk_len
+    }
+
+    /// The sector size presented to the guest, and hence used by its partition table.
+    pub fn logical_sector_size(&self) -> u32 {
+        self.logical_sector_size
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+/// The header with the highest sequence number, of those with correct checksums.
+fn current_header<R: positioned_io2::ReadAt>(inner: &R) -> Result<Vec<u8>, Error> {
+    let mut best: Option<(u64, Vec<u8>)> = None;
+    for &offset in &[64 * KIB, 128 * KIB] {
+        let mut header = vec![0u8; 4096];
+        ReadAt::read_exact_at(inner, offset, &mut header)?;
+        if HEADER_SIGNATURE != &header[..4] || !checksum_valid(&mut header) {
+            continue;
+        }
+
+        let sequence = le::read_u64(&header[8..]);
+        if best.as_ref().map_or(true, |(seq, _)| sequence > *seq) {
+            best = Some((sequence, header));
+        }
+    }
+
+    best.map(|(_, header)| header).ok_or(Error::InvalidStatic {
+        message: "no valid VHDX header",
+    })
+}
+
+/// The entries of the first valid region table.
+fn region_table<R: positioned_io2::ReadAt>(inner: &R) -> Result<Vec<u8>, Error> {
+    for &offset in &[192 * KIB, 256 * KIB] {
+        let mut table = vec![0u8; 64 * 1024];
+        ReadAt::read_exact_at(inner, offset, &mut table)?;
+        if REGION_SIGNATURE != &table[..4] || !checksum_valid(&mut table) {
+            continue;
+        }
+
+        let count = usize::try_from(le::read_u32(&table[8..])).unwrap_or(usize::MAX);
+        if count > (table.len() - 16) / 32 {
+            continue;
+        }
+
+        return Ok(table[16..16 + count * 32].to_vec());
+    }
+
+    Err(Error::InvalidStatic {
+        message: "no valid VHDX region table",
+    })
+}
+
+struct Metadata {
+    block_size: u64,
+    disk_len: u64,
+    logical_sector_size: u32,
+}
+
+impl Metadata {
+    fn read<R: positioned_io2::ReadAt>(
+        inner: &R,
+        offset: u64,
+        len: u32,
+    ) -> Result<Metadata, Error> {
+        let mut region = vec![0u8; usize::try_from(len).map_err(|_| Error::BiggerThanMemory)?];
+        if region.len() < 64 * 1024 {
+            return Err(Error::InvalidStatic {
+                message: "VHDX metadata region is too short",
+            });
+        }
+        ReadAt::read_exact_at(inner, offset, &mut region)?;
+
+        if METADATA_SIGNATURE != &region[..8] {
+            return Err(Error::InvalidStatic {
+                message: "VHDX metadata table not found",
+            });
+        }
+
+        let count = usize::from(le::read_u16(&region[10..]));
+        if count > 2047 {
+            return Err(Error::InvalidStatic {
+                message: "VHDX metadata table has too many entries",
+            });
+        }
+
+        let mut parameters = None;
+        let mut disk_len = None;
+        let mut logical_sector_size = None;
+
+        for entry in region[32..32 + count * 32].chunks_exact(32) {
+            let item_offset = usize::try_from(le::read_u32(&entry[16..])).unwrap_or(usize::MAX);
+            let item_len = usize::try_from(le::read_u32(&entry[20..])).unwrap_or(usize::MAX);
+            let item = item_offset
+                .checked_add(item_len)
+                .and_then(|end| region.get(item_offset..end))
+                .ok_or(Error::InvalidStatic {
+                    message: "VHDX metadata item is outside the region",
+                })?;
+
+            let id = &entry[..16];
+            if is(id, FILE_PARAMETERS) && item.len() >= 8 {
+                parameters = Some((le::read_u32(item), le::read_u32(&item[4..])));
+            } else if is(id, VIRTUAL_DISK_SIZE) && item.len() >= 8 {
+                disk_len = Some(le::read_u64(item));
+            } else if is(id, LOGICAL_SECTOR_SIZE) && item.len() >= 4 {
+                logical_sector_size = Some(le::read_u32(item));
+            } else if 0 != le::read_u32(&entry[24..]) & 0b100 {
+                // required, but we don't know what it is; the parent locator lands here
+                return Err(Error::InvalidData {
+                    message: format!(
+                        "unsupported required VHDX metadata: {}",
+                        guid::to_string(&guid_at(entry))
+                    ),
+                });
+            }
+        }
+
+        let (block_size, flags) = parameters.ok_or(Error::InvalidStatic {
+            message: "VHDX file parameters are missing",
+        })?;
+
+        if 0 != flags & 0b10 {
+            return Err(Error::InvalidStatic {
+                message: "differencing VHDX images are not supported",
+            });
+        }
+
+        Ok(Metadata {
+            block_size: u64::from(block_size),
+            disk_len: disk_len.ok_or(Error::InvalidStatic {
+                message: "VHDX virtual disc size is missing",
+            })?,
+            logical_sector_size: logical_sector_size.ok_or(Error::InvalidStatic {
+                message: "VHDX logical sector size is missing",
+            })?,
+        })
+    }
+}
+
+/// Check, and clear, the CRC-32C stored at offset 4.
+fn checksum_valid(data: &mut [u8]) -> bool {
+    let expected = le::read_u32(&data[4..]);
+    data[4..8].iter_mut().for_each(|b| *b = 0);
+    let actual = CRC.checksum(data);
+    data[4..8].copy_from_slice(&expected.to_le_bytes());
+    expected == actual
+}
+
+fn guid_at(entry: &[u8]) -> [u8; 16] {
+    let mut id = [0u8; 16];
+    id.copy_from_slice(&entry[..16]);
+    id
+}
+
+fn is(id: &[u8], expected: &str) -> bool {
+    guid::parse(expected).map_or(false, |expected| id == &expected[..])
+}
+
+impl<R: positioned_io2::ReadAt> positioned_io2::ReadAt for Vhdx<R> {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        if pos >= self.disk_len {
+            return Ok(0);
+        }
+
+        let remaining = usize::try_from(self.disk_len - pos).unwrap_or(usize::MAX);
+        let within = pos % self.block_size;
+        let available = usize::try_from(self.block_size - within).unwrap_or(usize::MAX);
+        let len = buf.len().min(remaining).min(available);
+        let buf = &mut buf[..len];
+
+        let block = pos / self.block_size;
+        let index = block + block / self.chunk_ratio;
+        let entry = usize::try_from(index)
+            .ok()
+            .and_then(|index| self.bat.get(index))
+            .copied()
+            .unwrap_or(0);
+
+        match entry & 0b111 {
+            PAYLOAD_FULLY_PRESENT => {
+                let host = (entry & !(MIB - 1)).checked_add(within).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "VHDX block is beyond the end of the file",
+                    )
+                })?;
+                positioned_io2::ReadAt::read_exact_at(&self.inner, host, buf)?;
+            }
+            PAYLOAD_PARTIALLY_PRESENT => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "VHDX block is partially present, which requires a parent",
+                ))
+            }
+            // not present, undefined, zero, or unmapped
+            _ => buf.iter_mut().for_each(|b| *b = 0),
+        }
+
+        Ok(len)
+    }
+}
+
+impl<R> positioned_io2::Size for Vhdx<R> {
+    fn size(&self) -> io::Result<Option<u64>> {
+        Ok(Some(self.disk_len))
+    }
+}
@@ -0,0 +1,330 @@
+//! Read the disc inside a VMDK image, as used by VMware.
+//!
+//! Monolithic sparse images can be read from any reader with [`Vmdk::open`]. Flat images,
+//! and images split into multiple extents, are described by a text file naming the other
+//! files, so need [`Vmdk::open_descriptor`]. Compressed (stream optimised) and
+//! differencing images are not supported.
+//!
+//! ```rust,no_run
+//! # fn go() -> Result<(), bootsector::Error> {
+//! let disc = bootsector::vmdk::Vmdk::open_descriptor("disc.vmdk")?;
+//! let table = bootsector::read_partition_table_sized(&disc, &Default::default())?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use crate::io::ReadAt;
+use crate::le;
+use crate::Error;
+
+const SPARSE_MAGIC: &[u8; 4] = b"KDMV";
+
+const COMPRESSED: u32 = 1 << 16;
+
+/// A grain table entry meaning the grain reads as zeros.
+const ZERO_GRAIN: u32 = 1;
+
+/// Far larger than any real grain directory: 256TiB, in the usual 32MiB grain tables.
+const MAX_DIRECTORY_BYTES: u64 = 32 * 1024 * 1024;
+
+/// A VMDK image, readable as the disc it contains.
+pub struct Vmdk<R> {
+    extents: Vec<Extent<R>>,
+    disk_len: u64,
+}
+
+struct Extent<R> {
+    /// Where the extent starts in the disc, in bytes.
+    start: u64,
+    len: u64,
+    kind: Kind<R>,
+}
+
+enum Kind<R> {
+    Flat { file: R, offset: u64 },
+    Sparse(Sparse<R>),
+    Zero,
+}
+
+struct Sparse<R> {
+    file: R,
+    /// In bytes.
+    grain_size: u64,
+    /// The number of grains covered by each grain table.
+    gtes_per_gt: u64,
+    /// The sector of each grain table.
+    directory: Vec<u32>,
+}
+
+impl<R: positioned_io2::ReadAt> Vmdk<R> {
+    /// Open a monolithic sparse image, which is a single file.
+    pub fn open(inner: R) -> Result<Vmdk<R>, Error> {
+        let (sparse, disk_len) = Sparse::open(inner)?;
+        Ok(Vmdk {
+            extents: vec![Extent {
+                start: 0,
+                len: disk_len,
+                kind: Kind::Sparse(sparse),
+            }],
+            disk_len,
+        })
+    }
+}
+
+impl Vmdk<File> {
+    /// Open an image from the path of its descriptor, which is the file the user sees,
+    /// opening the extent files it names from the same directory.
+    ///
+    /// Monolithic sparse images, which contain their own descriptor, work here too.
+    pub fn open_descriptor<P: AsRef<Path>>(path: P) -> Result<Vmdk<File>, Error> {
+        let path = path.as_ref();
+        let open = |path: &Path| File::open(path).map_err(|source| Error::Io { source, pos: 0 });
+        let file = open(path)?;
+
+        let mut magic = [0u8; 4];
+        ReadAt::read_exact_at(&file, 0, &mut magic)?;
+        if SPARSE_MAGIC == &magic {
+            return Vmdk::open(file);
+        }
+
+        let text = std::fs::read_to_string(path).map_err(|source| Error::Io { source, pos: 0 })?;
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+        let mut extents = Vec::new();
+        let mut start = 0u64;
+        for line in descriptor_extents(&text)? {
+            let len = line.sectors.checked_mul(512).ok_or(Error::Overflow)?;
+            let kind = match (line.kind, line.file) {
+                ("FLAT", Some(name)) | ("VMFS", Some(name)) => Kind::Flat {
+                    file: open(&dir.join(name))?,
+                    offset: line.offset.checked_mul(512).ok_or(Error::Overflow)?,
+                },
+                ("SPARSE", Some(name)) => Kind::Sparse(Sparse::open(open(&dir.join(name))?)?.0),
+                ("ZERO", _) => Kind::Zero,
+                (kind, _) => {
+                    return Err(Error::InvalidData {
+                        message: format!("unsupported VMDK extent type: {}", kind),
+                    })
+                }
+            };
+            extents.push(Extent { start, len, kind });
+            start = start.checked_add(len).ok_or(Error::Overflow)?;
+        }
+
+        Ok(Vmdk {
+            extents,
+            disk_len: start,
+        })
+    }
+}
+
+impl<R> Vmdk<R> {
+    /// The length of the guest disc, in bytes.
+    pub fn disk_len(&self) -> u64 {
+        self.disk_len
+    }
+}
+
+impl<R: positioned_io2::ReadAt> Sparse<R> {
+    /// Validate the header, and load the grain directory. Also returns the capacity in bytes.
+    fn open(file: R) -> Result<(Sparse<R>, u64), Error> {
+        let mut header = [0u8; 512];
+        ReadAt::read_exact_at(&file, 0, &mut header)?;
+
+        if SPARSE_MAGIC != &header[..4] {
+            return Err(Error::NotFound);
+        }
+
+        let version = le::read_u32(&header[4..]);
+        if !(1..=3).contains(&version) {
+            return Err(Error::InvalidData {
+                message: format!("unsupported VMDK version: {}", version),
+            });
+        }
+
+        let flags = le::read_u32(&header[8..]);
+        if 0 != flags & COMPRESSED {
+            return Err(Error::InvalidStatic {
+                message: "compressed VMDK images are not supported",
+            });
+        }
+
+        let capacity_sectors = le::read_u64(&header[12..]);
+        if capacity_sectors > u64::MAX / 512 {
+            return Err(Error::Overflow);
+        }
+        let grain_sectors = le::read_u64(&header[20..]);
+        let gtes_per_gt = u64::from(le::read_u32(&header[44..]));
+        let directory_sector = le::read_u64(&header[56..]);
+
+        if !grain_sectors.is_power_of_two() || !(8..=2048).contains(&grain_sectors) {
+            return Err(Error::InvalidData {
+                message: format!("invalid VMDK grain size: {} sectors", grain_sectors),
+            });
+        }
+
+        if 0 == gtes_per_gt || gtes_per_gt > 4096 {
+            return Err(Error::InvalidData {
+                message: format!("invalid VMDK grain table size: {}", gtes_per_gt),
+            });
+        }
+
+        let per_table = grain_sectors * gtes_per_gt;
+        let tables = (capacity_sectors + per_table - 1) / per_table;
+        if tables * 4 > MAX_DIRECTORY_BYTES {
+            return Err(Error::InvalidStatic {
+                message: "VMDK grain directory is unreasonably large",
+            });
+        }
+
+        let directory_len = usize::try_from(tables * 4).map_err(|_| Error::BiggerThanMemory)?;
+        let mut raw = vec![0u8; directory_len];
+        let directory_pos = directory_sector.checked_mul(512).ok_or(Error::Overflow)?;
+        ReadAt::read_exact_at(&file, directory_pos, &mut raw)?;
+
+        let sparse = Sparse {
+            file,
+            grain_size: grain_sectors * 512,
+            gtes_per_gt,
+            directory: raw.chunks_exact(4).map(le::read_u32).collect(),
+        };
+        Ok((sparse, capacity_sectors * 512))
+    }
+
+    /// Where the grain containing `pos` is stored, if anywhere.
+    fn host_grain(&self, pos: u64) -> io::Result<Option<u64>> {
+        let grain = pos / self.grain_size;
+        let table = usize::try_from(grain / self.gtes_per_gt)
+            .ok()
+            .and_then(|table| self.directory.get(table))
+            .copied()
+            .unwrap_or(0);
+
+        if 0 == table {
+            return Ok(None);
+        }
+
+        let at = u64::from(table) * 512 + grain % self.gtes_per_gt * 4;
+        let mut entry = [0u8; 4];
+        positioned_io2::ReadAt::read_exact_at(&self.file, at, &mut entry)?;
+        Ok(match le::read_u32(&entry) {
+            0 | ZERO_GRAIN => None,
+            sector => Some(u64::from(sector) * 512),
+        })
+    }
+
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let within = pos % self.grain_size;
+        let available = usize::try_from(self.grain_size - within).unwrap_or(usize::MAX);
+        let buf_len = buf.len().min(available);
+        let buf = &mut buf[..buf_len];
+
+        match self.host_grain(pos)? {
+            Some(host) => positioned_io2::ReadAt::read_exact_at(&self.file, host + within, buf)?,
+            None => buf.iter_mut().for_each(|b| *b = 0),
+        }
+
+        Ok(buf.len())
+    }
+}
+
+struct ExtentLine<'t> {
+    sectors: u64,
+    kind: &'t str,
+    file: Option<&'t str>,
+    offset: u64,
+}
+
+/// The extent lines of a descriptor, e.g. `RW 4192256 FLAT "disc-flat.vmdk" 0`.
+fn descriptor_extents(text: &str) -> Result<Vec<ExtentLine<'_>>, Error> {
+    let mut extents = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.starts_with("parentCID") && !line.ends_with("ffffffff") {
+            return Err(Error::InvalidStatic {
+                message: "differencing VMDK images are not supported",
+            });
+        }
+
+        let access = line.split_whitespace().next();
+        if !matches!(access, Some("RW") | Some("RDONLY") | Some("NOACCESS")) {
+            continue;
+        }
+
+        // the file name is quoted, and may contain spaces
+        let (fields, file, rest) = match (line.find('"'), line.rfind('"')) {
+            (Some(open), Some(close)) if open < close => (
+                &line[..open],
+                Some(&line[open + 1..close]),
+                &line[close + 1..],
+            ),
+            _ => (line, None, ""),
+        };
+
+        let mut fields = fields.split_whitespace().skip(1);
+        let invalid = || Error::InvalidData {
+            message: format!("invalid VMDK extent: {}", line),
+        };
+        let sectors = fields
+            .next()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(invalid)?;
+        let kind = fields.next().ok_or_else(invalid)?;
+        let offset = match rest.split_whitespace().next() {
+            Some(offset) => offset.parse().map_err(|_| invalid())?,
+            None => 0,
+        };
+
+        extents.push(ExtentLine {
+            sectors,
+            kind,
+            file,
+            offset,
+        });
+    }
+
+    if extents.is_empty() {
+        return Err(Error::InvalidStatic {
+            message: "no extents found in VMDK descriptor",
+        });
+    }
+
+    Ok(extents)
+}
+
+impl<R: positioned_io2::ReadAt> positioned_io2::ReadAt for Vmdk<R> {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let extent = match self
+            .extents
+            .iter()
+            .find(|e| pos >= e.start && pos - e.start < e.len)
+        {
+            Some(extent) => extent,
+            None => return Ok(0),
+        };
+
+        let within = pos - extent.start;
+        let remaining = usize::try_from(extent.len - within).unwrap_or(usize::MAX);
+        let buf_len = buf.len().min(remaining);
+        let buf = &mut buf[..buf_len];
+
+        match &extent.kind {
+            Kind::Flat { file, offset } => file.read_at(offset + within, buf),
+            Kind::Sparse(sparse) => sparse.read_at(within, buf),
+            Kind::Zero => {
+                buf.iter_mut().for_each(|b| *b = 0);
+                Ok(buf.len())
+            }
+        }
+    }
+}
+
+impl<R> positioned_io2::Size for Vmdk<R> {
+    fn size(&self) -> io::Result<Option<u64>> {
+        Ok(Some(self.disk_len))
+    }
+}
@@ -0,0 +1,170 @@
+//! Read physical drives on Windows, e.g. `\\.\PhysicalDrive0`.
+//!
+//! Raw drive handles reject reads which don't cover whole sectors, which every
+//! partition table parser does all the time; [`PhysicalDrive`] hides this.
+//!
+//! ```rust,no_run
+//! # fn go() -> Result<(), bootsector::Error> {
+//! let drive = bootsector::windows::PhysicalDrive::open(0)?;
+//! let table = bootsector::read_partition_table(&drive, &drive.options()?)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io;
+use std::os::raw::c_void;
+use std::os::windows::io::AsRawHandle;
+
+use crate::{Error, Options, SectorSize};
+
+const IOCTL_DISK_GET_DRIVE_GEOMETRY_EX: u32 = 0x0007_00a0;
+
+#[repr(C)]
+struct DiskGeometry {
+    cylinders: i64,
+    media_type: u32,
+    tracks_per_cylinder: u32,
+    sectors_per_track: u32,
+    bytes_per_sector: u32,
+}
+
+#[repr(C)]
+struct DiskGeometryEx {
+    geometry: DiskGeometry,
+    disk_size: i64,
+    // followed by variable length partition and detection information, which we don't want
+    data: [u8; 64],
+}
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn DeviceIoControl(
+        device: *mut c_void,
+        control_code: u32,
+        in_buffer: *mut c_void,
+        in_buffer_size: u32,
+        out_buffer: *mut c_void,
+        out_buffer_size: u32,
+        bytes_returned: *mut u32,
+        overlapped: *mut c_void,
+    ) -> i32;
+}
+
+/// The size and sector size of a drive.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Geometry {
+    /// The length of the drive, in bytes.
+    pub len: u64,
+
+    /// The sector size used for addressing, and hence by the partition table.
+    pub bytes_per_sector: u32,
+}
+
+/// A physical drive, readable at any offset.
+pub struct PhysicalDrive {
+    file: File,
+    geometry: Geometry,
+}
+
+impl PhysicalDrive {
+    /// Open `\\.\PhysicalDrive{number}` for reading. This typically requires Administrator.
+    pub fn open(number: u32) -> Result<PhysicalDrive, Error> {
+        let file = File::open(format!(r"\\.\PhysicalDrive{}", number))
+            .map_err(|source| Error::Io { source, pos: 0 })?;
+        PhysicalDrive::from_file(file)
+    }
+
+    /// Wrap an already opened drive (or volume) handle.
+    pub fn from_file(file: File) -> Result<PhysicalDrive, Error> {
+        let geometry = geometry(&file)?;
+        Ok(PhysicalDrive { file, geometry })
+    }
+
+    /// The geometry reported by the drive.
+    pub fn geometry(&self) -> Geometry {
+        self.geometry
+    }
+
+    /// [`Options`] with the sector size and disc length filled in from the drive.
+    pub fn options(&self) -> Result<Options, Error> {
+        Ok(Options {
+            sector_size: SectorSize::Known(u16::try_from(self.geometry.bytes_per_sector).map_err(
+                |_| Error::InvalidStatic {
+                    message: "sector size is implausibly large",
+                },
+            )?),
+            disk_len: Some(self.geometry.len),
+            ..Options::default()
+        })
+    }
+}
+
+impl positioned_io2::ReadAt for PhysicalDrive {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        crate::io::read_aligned(
+            &self.file,
+            u64::from(self.geometry.bytes_per_sector),
+            pos,
+            buf,
+        )
+    }
+}
+
+impl positioned_io2::Size for PhysicalDrive {
+    fn size(&self) -> io::Result<Option<u64>> {
+        Ok(Some(self.geometry.len))
+    }
+}
+
+/// Ask the drive for its geometry, with `IOCTL_DISK_GET_DRIVE_GEOMETRY_EX`.
+pub fn geometry(file: &File) -> Result<Geometry, Error> {
+    let mut out = DiskGeometryEx {
+        geometry: DiskGeometry {
+            cylinders: 0,
+            media_type: 0,
+            tracks_per_cylinder: 0,
+            sectors_per_track: 0,
+            bytes_per_sector: 0,
+        },
+        disk_size: 0,
+        data: [0u8; 64],
+    };
+    let mut returned = 0u32;
+
+    let out_size = u32::try_from(std::mem::size_of::<DiskGeometryEx>()).expect("small struct");
+    let ok = unsafe {
+        DeviceIoControl(
+            file.as_raw_handle() as *mut c_void,
+            IOCTL_DISK_GET_DRIVE_GEOMETRY_EX,
+            std::ptr::null_mut(),
+            0,
+            &mut out as *mut DiskGeometryEx as *mut c_void,
+            out_size,
+            &mut returned,
+            std::ptr::null_mut(),
+        )
+    };
+
+    if 0 == ok {
+        return Err(Error::Io {
+            source: io::Error::last_os_error(),
+            pos: 0,
+        });
+    }
+
+    let bytes_per_sector = out.geometry.bytes_per_sector;
+    if bytes_per_sector < 512 || !bytes_per_sector.is_power_of_two() {
+        return Err(Error::InvalidStatic {
+            message: "drive reported an implausible sector size",
+        });
+    }
+
+    Ok(Geometry {
+        len: u64::try_from(out.disk_size).map_err(|_| Error::InvalidStatic {
+            message: "drive reported a negative size",
+        })?,
+        bytes_per_sector,
+    })
+}
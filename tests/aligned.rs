@@ -0,0 +1,56 @@
+#![cfg(feature = "std")]
+
+extern crate bootsector;
+
+use std::io;
+
+use bootsector::io::Aligned;
+use bootsector::pio;
+use bootsector::read_partition_table;
+
+/// Behave like a file opened with `O_DIRECT`: reject anything unaligned.
+struct Strict<'a> {
+    data: &'a [u8],
+    alignment: u64,
+}
+
+impl<'a> pio::ReadAt for Strict<'a> {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let alignment = self.alignment as usize;
+        if 0 != pos % self.alignment
+            || 0 != buf.len() % alignment
+            || 0 != buf.as_ptr() as usize % alignment
+        {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "unaligned"));
+        }
+        pio::ReadAt::read_at(&self.data, pos, buf)
+    }
+}
+
+#[test]
+fn unaligned_is_rejected() {
+    let data = include_bytes!("test-data/labels.img");
+    let strict = Strict {
+        data: &data[..],
+        alignment: 4096,
+    };
+    assert!(read_partition_table(&strict, &Default::default()).is_err());
+}
+
+#[test]
+fn aligned_reads() {
+    let data = include_bytes!("test-data/labels.img");
+    let disc = Aligned::new(
+        Strict {
+            data: &data[..],
+            alignment: 4096,
+        },
+        4096,
+    );
+    let table = read_partition_table(&disc, &Default::default()).unwrap();
+    assert_eq!(7, table.partitions.len());
+
+    let mut buf = [0u8; 3];
+    pio::ReadAt::read_exact_at(&disc, 513, &mut buf).unwrap();
+    assert_eq!(&data[513..516], &buf);
+}
@@ -0,0 +1,95 @@
+#![cfg(any(feature = "async-futures", feature = "async-tokio"))]
+
+extern crate bootsector;
+
+use std::future::Future;
+use std::io::Cursor;
+use std::ptr;
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use bootsector::{read_partition_table_sized, Options};
+
+/// Everything here is ready immediately, so there's no need for a real executor.
+fn block_on<F: Future>(future: F) -> F::Output {
+    fn raw() -> RawWaker {
+        RawWaker::new(ptr::null(), &VTABLE)
+    }
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(|_| raw(), |_| (), |_| (), |_| ());
+
+    let waker = unsafe { Waker::from_raw(raw()) };
+    let mut cx = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+/// Makes every other read return a single byte, to exercise partial reads.
+#[cfg(feature = "async-futures")]
+struct Stingy {
+    inner: Cursor<&'static [u8]>,
+    generous: bool,
+}
+
+#[cfg(feature = "async-futures")]
+impl futures_io::AsyncRead for Stingy {
+    fn poll_read(
+        mut self: std::pin::Pin<&mut Self>,
+        _: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        use std::io::Read;
+        self.generous = !self.generous;
+        let len = if self.generous { buf.len() } else { 1 };
+        Poll::Ready(self.inner.read(&mut buf[..len]))
+    }
+}
+
+#[cfg(feature = "async-futures")]
+impl futures_io::AsyncSeek for Stingy {
+    fn poll_seek(
+        mut self: std::pin::Pin<&mut Self>,
+        _: &mut Context<'_>,
+        pos: std::io::SeekFrom,
+    ) -> Poll<std::io::Result<u64>> {
+        use std::io::Seek;
+        Poll::Ready(self.inner.seek(pos))
+    }
+}
+
+#[cfg(feature = "async-futures")]
+#[test]
+fn futures() {
+    use bootsector::asynchronous::read_partition_table_futures;
+
+    for image in &[
+        &include_bytes!("test-data/labels.img")[..],
+        &include_bytes!("test-data/mbr-ubuntu-raspi3-16.04.img")[..],
+    ] {
+        let mut reader = Stingy {
+            inner: Cursor::new(image),
+            generous: false,
+        };
+        let table = block_on(read_partition_table_futures(
+            &mut reader,
+            &Options::default(),
+        ));
+        let expected = read_partition_table_sized(*image, &Options::default());
+        assert_eq!(format!("{:?}", expected), format!("{:?}", table));
+    }
+}
+
+#[cfg(feature = "async-tokio")]
+#[test]
+fn tokio() {
+    use bootsector::asynchronous::read_partition_table_tokio;
+
+    let image = &include_bytes!("test-data/4t-gpt.img")[..];
+    let expected = read_partition_table_sized(image, &Options::default());
+
+    let mut reader = Cursor::new(image);
+    let table = block_on(read_partition_table_tokio(&mut reader, &Options::default()));
+    assert_eq!(format!("{:?}", expected), format!("{:?}", table));
+}
@@ -0,0 +1,81 @@
+extern crate bootsector;
+
+use bootsector::attributes::{self, ChromeOsKernel, MicrosoftBasicData};
+use bootsector::edit::GptEditor;
+use bootsector::gpt::{self, CreateOptions};
+use bootsector::{list_partitions, Options};
+
+const MIB: u64 = 1024 * 1024;
+
+#[test]
+fn chromeos_kernel() {
+    // KERN-A, in its on-disc byte order
+    let kernel_type = [
+        0x5d, 0x2a, 0x3a, 0xfe, 0x32, 0x4f, 0xa7, 0x41, 0xb7, 0x25, 0xac, 0xcc, 0x32, 0x85, 0xa3,
+        0x09,
+    ];
+
+    let mut image = vec![0u8; 4 * MIB as usize];
+    gpt::create(&mut image[..], 4 * MIB, 512, &CreateOptions::new([7; 16])).unwrap();
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    let id = editor.add(MIB, MIB, kernel_type, [2; 16]).unwrap();
+    // tries 3, priority 2, and an unrelated bit
+    editor.set_attributes(id, 0x0032_0000_0000_0001).unwrap();
+    editor.commit(&mut image[..]).unwrap();
+
+    let part = &list_partitions(&image[..], &Options::default()).unwrap()[0];
+    assert!(attributes::is_chromeos_kernel(part));
+    let bits = attributes::bits(part).unwrap();
+    let mut kernel = ChromeOsKernel::from_attributes(bits);
+    assert_eq!(
+        ChromeOsKernel {
+            priority: 2,
+            tries_remaining: 3,
+            successful: false,
+        },
+        kernel
+    );
+
+    kernel.priority = 15;
+    kernel.tries_remaining = 0;
+    kernel.successful = true;
+    assert_eq!(0x010f_0000_0000_0001, kernel.to_attributes(bits).unwrap());
+
+    kernel.priority = 16;
+    assert!(kernel.to_attributes(bits).is_err());
+}
+
+#[test]
+fn microsoft_basic_data() {
+    let basic_data = [
+        0xa2, 0xa0, 0xd0, 0xeb, 0xe5, 0xb9, 0x33, 0x44, 0x87, 0xc0, 0x68, 0xb6, 0xb7, 0x26, 0x99,
+        0xc7,
+    ];
+
+    let mut image = vec![0u8; 4 * MIB as usize];
+    gpt::create(&mut image[..], 4 * MIB, 512, &CreateOptions::new([7; 16])).unwrap();
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    let id = editor.add(MIB, MIB, basic_data, [2; 16]).unwrap();
+    // hidden, no drive letter, and "required"
+    editor.set_attributes(id, 0xc000_0000_0000_0001).unwrap();
+    editor.commit(&mut image[..]).unwrap();
+
+    let part = &list_partitions(&image[..], &Options::default()).unwrap()[0];
+    assert!(attributes::is_microsoft_basic_data(part));
+    assert!(!attributes::is_chromeos_kernel(part));
+    let bits = attributes::bits(part).unwrap();
+    let mut flags = MicrosoftBasicData::from_attributes(bits);
+    assert_eq!(
+        MicrosoftBasicData {
+            read_only: false,
+            shadow_copy: false,
+            hidden: true,
+            no_drive_letter: true,
+        },
+        flags
+    );
+
+    flags.hidden = false;
+    flags.read_only = true;
+    assert_eq!(0x9000_0000_0000_0001, flags.to_attributes(bits));
+}
@@ -0,0 +1,52 @@
+extern crate bootsector;
+
+use bootsector::bios::{grub_embedding_area, is_bios_boot, EmbeddingArea};
+use bootsector::edit::GptEditor;
+use bootsector::gpt::{self, CreateOptions};
+use bootsector::{read_partition_table, Options};
+
+const MIB: u64 = 1024 * 1024;
+
+#[test]
+fn gpt() {
+    let mut image = vec![0u8; 8 * MIB as usize];
+    gpt::create(&mut image[..], 8 * MIB, 512, &CreateOptions::new([7; 16])).unwrap();
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    editor.add(2 * MIB, MIB, [1; 16], [2; 16]).unwrap();
+    editor.commit(&mut image[..]).unwrap();
+
+    let table = read_partition_table(&image[..], &Options::default()).unwrap();
+    assert_eq!(None, grub_embedding_area(&table));
+
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    // the type GUID, in its on-disc byte order
+    editor.add(MIB, MIB, *b"Hah!IdontNeedEFI", [3; 16]).unwrap();
+    editor.commit(&mut image[..]).unwrap();
+
+    let table = read_partition_table(&image[..], &Options::default()).unwrap();
+    assert!(is_bios_boot(&table.partitions[1]));
+    assert_eq!(
+        Some(EmbeddingArea::BiosBootPartition {
+            id: 1,
+            first_byte: MIB,
+            len: MIB
+        }),
+        grub_embedding_area(&table)
+    );
+}
+
+#[test]
+fn mbr() {
+    let image = &include_bytes!("test-data/mbr-ubuntu-raspi3-16.04.img")[..];
+    let table = read_partition_table(image, &Options::default()).unwrap();
+    let first = table.partitions.iter().map(|p| p.first_byte).min().unwrap();
+    let area = grub_embedding_area(&table).unwrap();
+    assert_eq!(
+        EmbeddingArea::MbrGap {
+            first_byte: 512,
+            len: first - 512
+        },
+        area
+    );
+    assert!(!is_bios_boot(&table.partitions[0]));
+}
@@ -0,0 +1,66 @@
+extern crate bootsector;
+
+use bootsector::boot::find_bootable;
+use bootsector::{Attributes, Partition};
+
+fn mbr(id: usize, type_code: u8, bootable: bool) -> Partition {
+    Partition {
+        id,
+        first_byte: (id as u64 + 1) * 1024 * 1024,
+        len: 1024 * 1024,
+        attributes: Attributes::MBR {
+            bootable,
+            type_code,
+        },
+        label: None,
+    }
+}
+
+fn gpt(id: usize, type_uuid: [u8; 16], attributes: u64) -> Partition {
+    Partition {
+        id,
+        first_byte: (id as u64 + 1) * 1024 * 1024,
+        len: 1024 * 1024,
+        attributes: Attributes::GPT {
+            type_uuid,
+            partition_uuid: [id as u8; 16],
+            attributes: attributes.to_le_bytes(),
+            name: String::new(),
+            raw_name: vec![0; 72],
+            extra: Vec::new(),
+        },
+        label: None,
+    }
+}
+
+fn ids(partitions: &[Partition]) -> Vec<usize> {
+    find_bootable(partitions).iter().map(|p| p.id).collect()
+}
+
+#[test]
+fn mbr_flags() {
+    assert_eq!(
+        vec![2, 0],
+        ids(&[mbr(0, 0xef, false), mbr(1, 0x83, false), mbr(2, 0x83, true)])
+    );
+    assert!(ids(&[mbr(0, 0x83, false)]).is_empty());
+}
+
+#[test]
+fn gpt_attributes() {
+    // C12A7328-F81F-11D2-BA4B-00A0C93EC93B, in its on-disc byte order
+    let esp = [
+        0x28, 0x73, 0x2a, 0xc1, 0x1f, 0xf8, 0xd2, 0x11, 0xba, 0x4b, 0x00, 0xa0, 0xc9, 0x3e, 0xc9,
+        0x3b,
+    ];
+    let linux = [1; 16];
+    assert_eq!(
+        vec![0, 2, 1],
+        ids(&[
+            gpt(0, esp, 1 << 2),
+            gpt(1, esp, 0),
+            gpt(2, linux, 1 << 2),
+            gpt(3, linux, 1)
+        ])
+    );
+}
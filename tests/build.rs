@@ -0,0 +1,87 @@
+#![cfg(feature = "build")]
+
+extern crate bootsector;
+
+use bootsector::build::{Damage, GptImage, MbrImage};
+use bootsector::verify::verify;
+use bootsector::{list_partitions, Attributes, Options, SectorSize};
+
+const MIB: u64 = 1024 * 1024;
+
+fn codes(image: &[u8]) -> Vec<&'static str> {
+    verify(image, Some(image.len() as u64), &Options::default())
+        .expect("readable")
+        .into_iter()
+        .map(|finding| finding.code)
+        .collect()
+}
+
+#[test]
+fn mbr() {
+    let mut image = MbrImage::new(8 * MIB);
+    image.add(MIB, 2 * MIB).status = 0x80;
+    image.add(3 * MIB, 4 * MIB).type_code = 0x07;
+    let bytes = image.build().unwrap();
+
+    let parts = list_partitions(&bytes[..], &Options::default()).unwrap();
+    assert_eq!(2, parts.len());
+    assert_eq!(MIB, parts[0].first_byte);
+    assert_eq!(2 * MIB, parts[0].len);
+    match parts[1].attributes {
+        Attributes::MBR { type_code, .. } => assert_eq!(0x07, type_code),
+        ref other => panic!("not mbr: {:?}", other),
+    }
+}
+
+#[test]
+fn gpt_with_large_sectors() {
+    let mut image = GptImage::new(16 * MIB);
+    image.sector_size = 4096;
+    image.add(MIB, 2 * MIB, "first");
+    image.add(4 * MIB, MIB, "second");
+    let bytes = image.build().unwrap();
+
+    let options = Options {
+        sector_size: SectorSize::Known(4096),
+        ..Default::default()
+    };
+    let parts = list_partitions(&bytes[..], &options).unwrap();
+    assert_eq!(2, parts.len());
+    assert_eq!(4 * MIB, parts[1].first_byte);
+    assert_eq!(MIB, parts[1].len);
+    match parts[0].attributes {
+        Attributes::GPT { ref name, .. } => assert_eq!("first", name),
+        ref other => panic!("not gpt: {:?}", other),
+    }
+}
+
+#[test]
+fn overlapping() {
+    let mut image = GptImage::new(8 * MIB);
+    image.add(MIB, 2 * MIB, "first");
+    image.add(2 * MIB, 2 * MIB, "second");
+    let bytes = image.build().unwrap();
+    assert!(codes(&bytes).contains(&"partition-overlap"));
+}
+
+#[test]
+fn damaged() {
+    let mut image = GptImage::new(8 * MIB);
+    image.add(MIB, 2 * MIB, "first");
+    assert!(codes(&image.build().unwrap()).is_empty());
+
+    image.damage.push(Damage::PrimaryHeaderCrc);
+    let bytes = image.build().unwrap();
+    assert!(codes(&bytes).contains(&"gpt-primary-header-crc"));
+
+    image.damage = vec![Damage::BackupTableCrc];
+    let bytes = image.build().unwrap();
+    assert_eq!(vec!["gpt-backup-table-crc"], codes(&bytes));
+}
+
+#[test]
+fn long_name() {
+    let mut image = GptImage::new(8 * MIB);
+    image.add(MIB, MIB, "this name is far too long to fit in an entry");
+    assert!(image.build().is_err());
+}
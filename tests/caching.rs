@@ -0,0 +1,62 @@
+#![cfg(feature = "std")]
+
+extern crate bootsector;
+
+use std::cell::Cell;
+use std::io;
+
+use bootsector::io::CachingReadAt;
+use bootsector::pio;
+use bootsector::read_partition_table;
+
+struct Counting<'a> {
+    data: &'a [u8],
+    reads: Cell<usize>,
+}
+
+impl<'a> pio::ReadAt for Counting<'a> {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        self.reads.set(self.reads.get() + 1);
+        pio::ReadAt::read_at(&self.data, pos, buf)
+    }
+}
+
+#[test]
+fn repeated_reads_are_cached() {
+    let data = include_bytes!("test-data/labels.img");
+    let disc = CachingReadAt::new(
+        Counting {
+            data: &data[..],
+            reads: Cell::new(0),
+        },
+        64 * 1024,
+        4,
+    );
+
+    let first = read_partition_table(&disc, &Default::default()).unwrap();
+    let reads = disc.get_ref().reads.get();
+    assert_ne!(0, reads);
+
+    let second = read_partition_table(&disc, &Default::default()).unwrap();
+    assert_eq!(first, second);
+    assert_eq!(reads, disc.get_ref().reads.get());
+
+    disc.clear();
+    read_partition_table(&disc, &Default::default()).unwrap();
+    assert_eq!(2 * reads, disc.get_ref().reads.get());
+}
+
+#[test]
+fn spans_blocks_and_end() {
+    let data: Vec<u8> = (0..1000u32).map(|i| i as u8).collect();
+    let disc = CachingReadAt::new(&data[..], 64, 2);
+
+    let mut buf = [0u8; 200];
+    pio::ReadAt::read_exact_at(&disc, 50, &mut buf).unwrap();
+    assert_eq!(&data[50..250], &buf[..]);
+
+    let mut buf = [0u8; 100];
+    assert_eq!(40, pio::ReadAt::read_at(&disc, 960, &mut buf).unwrap());
+    assert_eq!(&data[960..], &buf[..40]);
+    assert_eq!(0, pio::ReadAt::read_at(&disc, 1000, &mut buf).unwrap());
+}
@@ -0,0 +1,31 @@
+//! Helpers shared by the tests which damage GPT images by hand.
+
+#![allow(dead_code)]
+
+use crc::Crc;
+
+const CRC: Crc<u32> = Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+
+/// The primary header, for 512 byte sectors.
+const HEADER: usize = 512;
+
+fn read_u32(image: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([image[at], image[at + 1], image[at + 2], image[at + 3]])
+}
+
+/// Recalculate the primary header's own checksum, after editing it.
+pub fn fix_header_crc(image: &mut [u8]) {
+    let header_size = (read_u32(image, HEADER + 0x0c) as usize).min(512);
+    image[HEADER + 0x10..HEADER + 0x14].copy_from_slice(&[0; 4]);
+    let crc = CRC.checksum(&image[HEADER..HEADER + header_size]);
+    image[HEADER + 0x10..HEADER + 0x14].copy_from_slice(&crc.to_le_bytes());
+}
+
+/// Recalculate the checksum of the primary entry array, at lba 2, with the size the
+/// header gives it, then the header's own.
+pub fn fix_table_crc(image: &mut [u8]) {
+    let len = read_u32(image, HEADER + 0x50) as usize * read_u32(image, HEADER + 0x54) as usize;
+    let crc = CRC.checksum(&image[1024..1024 + len]);
+    image[HEADER + 0x58..HEADER + 0x5c].copy_from_slice(&crc.to_le_bytes());
+    fix_header_crc(image);
+}
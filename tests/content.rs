@@ -0,0 +1,119 @@
+extern crate bootsector;
+
+use bootsector::content::{crc32_partition, hash_partition};
+use bootsector::{list_partitions, Options};
+use crc::Crc;
+
+const CRC: Crc<u32> = Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+
+fn range<'a>(image: &'a [u8], part: &bootsector::Partition) -> &'a [u8] {
+    &image[part.first_byte as usize..(part.first_byte + part.len) as usize]
+}
+
+#[test]
+fn hash() {
+    let mut image = include_bytes!("test-data/labels.img").to_vec();
+    let parts = list_partitions(&image[..], &Options::default()).unwrap();
+    let part = &parts[1];
+    for (i, b) in image[part.first_byte as usize..][..part.len as usize]
+        .iter_mut()
+        .enumerate()
+    {
+        *b = (i % 251) as u8;
+    }
+
+    assert_eq!(
+        CRC.checksum(range(&image, part)),
+        crc32_partition(&image[..], part).unwrap()
+    );
+
+    let mut seen = Vec::new();
+    let mut reports = Vec::new();
+    hash_partition(
+        &image[..],
+        part,
+        |chunk| seen.extend_from_slice(chunk),
+        |done, total| reports.push((done, total)),
+    )
+    .unwrap();
+    assert_eq!(range(&image, part), &seen[..]);
+    assert_eq!(Some(&(part.len, part.len)), reports.last());
+}
+
+#[cfg(feature = "sha2")]
+#[test]
+fn sha256() {
+    let image = &include_bytes!("test-data/labels.img")[..];
+    let parts = list_partitions(image, &Options::default()).unwrap();
+    let hash = bootsector::content::sha256_partition(image, &parts[0]).unwrap();
+
+    // the partition is empty
+    use sha2::Digest;
+    let expected = sha2::Sha256::digest(vec![0u8; parts[0].len as usize]);
+    assert_eq!(&expected[..], &hash[..]);
+}
+
+#[test]
+fn copy() {
+    use bootsector::content::{copy_partition, CopyOptions};
+
+    let mut image = include_bytes!("test-data/labels.img").to_vec();
+    let parts = list_partitions(&image[..], &Options::default()).unwrap();
+    let (src, dst) = (&parts[1], &parts[2]);
+    image[src.first_byte as usize + 100] = 0x42;
+    image[dst.first_byte as usize + 5000] = 0x69;
+
+    let options = CopyOptions {
+        buffer_size: 4096,
+        skip_zeros: true,
+    };
+    let mut out = image.clone();
+    let mut reports = 0;
+    let copied = copy_partition(
+        &image[..],
+        src,
+        &mut out[..],
+        dst.first_byte,
+        &options,
+        |_, _| reports += 1,
+    )
+    .unwrap();
+
+    assert_eq!(4096, copied.written);
+    assert_eq!(src.len - 4096, copied.skipped);
+    assert_eq!(src.len / 4096, reports);
+
+    // in a skipped chunk, so left alone
+    assert_eq!(0x69, out[dst.first_byte as usize + 5000]);
+    out[dst.first_byte as usize + 5000] = 0;
+
+    let copy = &out[dst.first_byte as usize..][..src.len as usize];
+    assert!(range(&image, src) == copy);
+}
+
+#[test]
+fn wipe() {
+    use bootsector::content::wipe_partition;
+    use bootsector::Partition;
+
+    let mut image = vec![0xffu8; 4 * 1024 * 1024];
+    let part = Partition {
+        id: 0,
+        first_byte: 1000,
+        len: 2 * 1024 * 1024 + 3,
+        attributes: bootsector::Attributes::MBR {
+            bootable: false,
+            type_code: 0x83,
+        },
+        label: None,
+    };
+    wipe_partition(&mut image[..], &part, b"abc").unwrap();
+
+    assert!(image[..1000].iter().all(|&b| 0xff == b));
+    assert_eq!(b"abcabc", &image[1000..1006]);
+    let end = (part.first_byte + part.len) as usize;
+    assert_eq!(b"cab", &image[end - 3..end]);
+    assert!(image[end..].iter().all(|&b| 0xff == b));
+
+    assert!(wipe_partition(&mut image[..], &part, b"").is_err());
+}
@@ -0,0 +1,75 @@
+extern crate bootsector;
+
+use bootsector::convert::{gpt_to_mbr, mbr_to_gpt};
+use bootsector::{read_partition_table, Attributes, Options, TableType};
+
+fn counter() -> impl FnMut() -> [u8; 16] {
+    let mut next = 0u8;
+    move || {
+        next += 1;
+        [next; 16]
+    }
+}
+
+#[test]
+fn raspi_round_trip() {
+    let mbr = read_partition_table(
+        &include_bytes!("test-data/mbr-ubuntu-raspi3-16.04.img")[..],
+        &Options::default(),
+    )
+    .expect("success");
+
+    let gpt = mbr_to_gpt(&mbr, counter()).expect("convertible");
+    assert_eq!(TableType::GPT, gpt.table_type);
+    assert_eq!(Some([3; 16]), gpt.disk_guid);
+    assert_eq!(mbr.partitions[1].first_byte, gpt.partitions[1].first_byte);
+
+    match &gpt.partitions[0].attributes {
+        Attributes::GPT {
+            partition_uuid,
+            attributes,
+            ..
+        } => {
+            assert_eq!(&[1; 16], partition_uuid);
+            assert_eq!(4, attributes[0]);
+        }
+        _ => panic!("not gpt"),
+    }
+
+    let back = gpt_to_mbr(&gpt).expect("convertible");
+    assert_eq!(mbr.partitions[1], back.partitions[1]);
+
+    // FAT32 and NTFS are both "basic data"; the distinction is lost
+    match back.partitions[0].attributes {
+        Attributes::MBR {
+            bootable,
+            type_code,
+        } => {
+            assert!(bootable);
+            assert_eq!(0x07, type_code);
+        }
+        _ => panic!("not mbr"),
+    }
+}
+
+#[test]
+fn no_room_for_gpt() {
+    let mbr = read_partition_table(
+        &include_bytes!("test-data/tiny.img")[..],
+        &Options::default(),
+    )
+    .expect("success");
+
+    assert!(mbr_to_gpt(&mbr, counter()).is_err());
+}
+
+#[test]
+fn too_many_for_mbr() {
+    let gpt = read_partition_table(
+        &include_bytes!("test-data/labels.img")[..],
+        &Options::default(),
+    )
+    .expect("success");
+
+    assert!(gpt_to_mbr(&gpt).is_err());
+}
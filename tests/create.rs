@@ -0,0 +1,124 @@
+extern crate bootsector;
+
+use bootsector::edit::GptEditor;
+use bootsector::gpt::{self, CreateOptions};
+use bootsector::verify::{verify, Severity};
+use bootsector::{list_partitions, read_partition_table, Attributes, Error, Options, TableType};
+
+#[test]
+fn blank_disc() {
+    let mut image = vec![0u8; 1024 * 1024];
+    gpt::create(
+        &mut image[..],
+        1024 * 1024,
+        512,
+        &CreateOptions::new([7; 16]),
+    )
+    .expect("create");
+
+    let table = read_partition_table(&image[..], &Options::default()).expect("readable");
+    assert_eq!(TableType::GPT, table.table_type);
+    assert_eq!(Some([7; 16]), table.disk_guid);
+    assert!(table.partitions.is_empty());
+    assert_eq!(Some(34), table.first_usable_lba());
+    assert_eq!(Some(2014), table.last_usable_lba());
+    assert_eq!(Some(2047), table.alternate_lba());
+    assert_eq!(Some(34 * 512..2015 * 512), table.usable_range());
+    assert_eq!(Some(128), table.capacity());
+    assert_eq!(0, table.used_slots());
+    assert_eq!(Some(128), table.free_slots());
+
+    let findings = verify(&image[..], Some(image.len() as u64), &Options::default()).unwrap();
+    assert!(findings.is_empty(), "{:?}", findings);
+}
+
+#[test]
+fn four_k_sectors() {
+    let mut image = vec![0u8; 4 * 1024 * 1024];
+    gpt::create(
+        &mut image[..],
+        4 * 1024 * 1024,
+        4096,
+        &CreateOptions::new([7; 16]),
+    )
+    .expect("create");
+
+    // the protective mbr's start is in 4096 byte sectors, but found anyway
+    let table = read_partition_table(&image[..], &Options::default()).expect("readable");
+    assert_eq!(4096, table.sector_size);
+    assert_eq!(Some(4096), gpt::detect_sector_size(&image[..]));
+
+    let findings = verify(&image[..], Some(image.len() as u64), &Options::default()).unwrap();
+    assert!(
+        findings.iter().all(|f| Severity::Error != f.severity),
+        "{:?}",
+        findings
+    );
+}
+
+#[test]
+fn sector_size_guess() {
+    let mut image = vec![0u8; 4 * 1024 * 1024];
+    gpt::create(
+        &mut image[..],
+        4 * 1024 * 1024,
+        4096,
+        &CreateOptions::new([7; 16]),
+    )
+    .expect("create");
+
+    // the header is only found if 4096 byte sectors are considered
+    let small = Options {
+        max_sector_size: 2048,
+        ..Options::default()
+    };
+    match read_partition_table(&image[..], &small) {
+        Err(Error::SectorSizeGuess {
+            guess: 512,
+            max_sector_size: 2048,
+            ..
+        }) => (),
+        other => panic!("unexpected: {:?}", other),
+    }
+
+    // a broken header isn't read at the sector size the protective mbr suggests
+    let mut image = vec![0u8; 4 * 1024 * 1024];
+    gpt::create(
+        &mut image[..],
+        4 * 1024 * 1024,
+        512,
+        &CreateOptions::new([7; 16]),
+    )
+    .expect("create");
+    image[512 + 0x10] ^= 0xff;
+    assert!(matches!(
+        read_partition_table(&image[..], &Options::default()),
+        Err(Error::SectorSizeGuess { guess: 512, .. })
+    ));
+}
+
+#[test]
+fn too_small() {
+    let mut image = vec![0u8; 16 * 1024];
+    assert!(gpt::create(&mut image[..], 16 * 1024, 512, &CreateOptions::new([7; 16])).is_err());
+}
+
+#[test]
+fn large_entries() {
+    let mut image = vec![0u8; 1024 * 1024];
+    let options = CreateOptions {
+        entry_size: 256,
+        ..CreateOptions::new([7; 16])
+    };
+    gpt::create(&mut image[..], 1024 * 1024, 512, &options).expect("create");
+
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    editor.add(512 * 1024, 4096, [1; 16], [2; 16]).unwrap();
+    editor.commit(&mut image[..]).unwrap();
+
+    let parts = list_partitions(&image[..], &Options::default()).unwrap();
+    match &parts[0].attributes {
+        Attributes::GPT { extra, .. } => assert_eq!(128, extra.len()),
+        other => panic!("{:?}", other),
+    }
+}
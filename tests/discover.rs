@@ -0,0 +1,13 @@
+#![cfg(all(feature = "discover", target_os = "linux"))]
+
+extern crate bootsector;
+
+use bootsector::discover;
+
+#[test]
+fn sysfs() {
+    for device in discover::devices().expect("sysfs readable") {
+        assert!(device.path.starts_with("/dev"), "{:?}", device);
+        assert!(device.len.unwrap_or(0) > 0, "{:?}", device);
+    }
+}
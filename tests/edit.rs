@@ -0,0 +1,381 @@
+extern crate bootsector;
+
+mod common;
+
+use bootsector::edit::{resize_partition, GptEditor};
+use bootsector::gpt::{self, CreateOptions};
+use bootsector::verify::{verify, Severity};
+use bootsector::{
+    list_partitions, read_partition_table, Attributes, Error, InvalidNames, Options, Order,
+};
+
+const MIB: u64 = 1024 * 1024;
+
+fn blank() -> Vec<u8> {
+    let mut image = vec![0u8; 4 * MIB as usize];
+    gpt::create(&mut image[..], 4 * MIB, 512, &CreateOptions::new([7; 16])).expect("create");
+    image
+}
+
+#[test]
+fn add_name_delete() {
+    let mut image = blank();
+
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    let first = editor.add(MIB, MIB, [1; 16], [2; 16]).unwrap();
+    let second = editor.add(2 * MIB, MIB, [3; 16], [4; 16]).unwrap();
+    assert_eq!((0, 1), (first, second));
+    editor.set_name(first, "boot").unwrap();
+    editor.set_attributes(second, 1 << 60).unwrap();
+    editor.set_type(second, [5; 16]).unwrap();
+    editor.commit(&mut image[..]).unwrap();
+
+    let parts = list_partitions(&image[..], &Options::default()).unwrap();
+    assert_eq!(2, parts.len());
+    assert_eq!(MIB, parts[0].first_byte);
+    assert_eq!(MIB, parts[0].len);
+    match &parts[0].attributes {
+        Attributes::GPT { name, .. } => assert_eq!("boot", name),
+        other => panic!("{:?}", other),
+    }
+    match &parts[1].attributes {
+        Attributes::GPT {
+            type_uuid,
+            attributes,
+            ..
+        } => {
+            assert_eq!([5; 16], *type_uuid);
+            assert_eq!(1 << 60, u64::from_le_bytes(*attributes));
+        }
+        other => panic!("{:?}", other),
+    }
+
+    let findings = verify(&image[..], Some(image.len() as u64), &Options::default()).unwrap();
+    assert!(findings.is_empty(), "{:?}", findings);
+
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    editor.delete(first).unwrap();
+    editor.commit(&mut image[..]).unwrap();
+
+    let parts = list_partitions(&image[..], &Options::default()).unwrap();
+    assert_eq!(1, parts.len());
+    assert_eq!(1, parts[0].id);
+}
+
+#[test]
+fn rejects_bad_ranges() {
+    let image = blank();
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    editor.add(MIB, MIB, [1; 16], [2; 16]).unwrap();
+
+    // overlapping
+    assert!(editor.add(MIB + 512, 512, [1; 16], [2; 16]).is_err());
+    // before the usable area
+    assert!(editor.add(512, 512, [1; 16], [2; 16]).is_err());
+    // beyond it
+    assert!(editor.add(4 * MIB - 512, 512, [1; 16], [2; 16]).is_err());
+    // not whole sectors
+    assert!(editor.add(3 * MIB, 100, [1; 16], [2; 16]).is_err());
+
+    match editor.delete(5) {
+        Err(Error::NotFound) => (),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn resize() {
+    let mut image = blank();
+
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    let first = editor.add(MIB, MIB, [1; 16], [2; 16]).unwrap();
+    let second = editor.add(2 * MIB, MIB, [3; 16], [4; 16]).unwrap();
+
+    // can't grow into the neighbour
+    assert!(resize_partition(&mut editor, first, MIB + 512).is_err());
+    assert_eq!(MIB, editor.max_len(first).unwrap());
+    resize_partition(&mut editor, first, MIB / 2).unwrap();
+
+    // grow the disc, and the last partition into the new space
+    image.resize(8 * MIB as usize, 0);
+    editor.set_disk_len(8 * MIB).unwrap();
+    let max = editor.max_len(second).unwrap();
+    assert!(max > 5 * MIB, "{}", max);
+    resize_partition(&mut editor, second, max).unwrap();
+    editor.commit(&mut image[..]).unwrap();
+
+    let parts = list_partitions(&image[..], &Options::default()).unwrap();
+    assert_eq!(MIB / 2, parts[0].len);
+    assert_eq!(max, parts[1].len);
+    let findings = verify(&image[..], Some(image.len() as u64), &Options::default()).unwrap();
+    assert!(
+        findings.iter().all(|f| Severity::Error != f.severity),
+        "{:?}",
+        findings
+    );
+
+    // can't shrink the disc under a partition
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    assert!(editor.set_disk_len(4 * MIB).is_err());
+}
+
+#[test]
+fn regenerate_guids() {
+    let mut image = blank();
+
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    editor.add(MIB, MIB, [1; 16], [2; 16]).unwrap();
+    editor.add(2 * MIB, MIB, [3; 16], [4; 16]).unwrap();
+
+    let mut next = 0x10u8;
+    editor.regenerate_guids(|| {
+        next += 1;
+        [next; 16]
+    });
+    assert_eq!([0x11; 16], editor.disk_guid());
+    editor.commit(&mut image[..]).unwrap();
+
+    let mut sector = [0u8; 512];
+    sector.copy_from_slice(&image[..512]);
+    bootsector::mbr::set_disk_signature(&mut sector, 0xcafe_babe);
+    image[..512].copy_from_slice(&sector);
+
+    let table = read_partition_table(&image[..], &Options::default()).unwrap();
+    assert_eq!(Some([0x11; 16]), table.disk_guid);
+    assert_eq!(0xcafe_babe, table.disk_signature);
+    let guids: Vec<[u8; 16]> = table
+        .partitions
+        .iter()
+        .map(|part| match &part.attributes {
+            Attributes::GPT { partition_uuid, .. } => *partition_uuid,
+            other => panic!("{:?}", other),
+        })
+        .collect();
+    assert_eq!(vec![[0x12; 16], [0x13; 16]], guids);
+}
+
+#[test]
+fn disk_order() {
+    let mut image = blank();
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    editor.add(2 * MIB, MIB, [1; 16], [2; 16]).unwrap();
+    editor.add(MIB, MIB, [3; 16], [4; 16]).unwrap();
+    editor.commit(&mut image[..]).unwrap();
+
+    let ids = |order| -> Vec<usize> {
+        let options = Options {
+            order,
+            ..Options::default()
+        };
+        list_partitions(&image[..], &options)
+            .unwrap()
+            .iter()
+            .map(|part| part.id)
+            .collect()
+    };
+
+    assert_eq!(vec![0, 1], ids(Order::Table));
+    assert_eq!(vec![1, 0], ids(Order::Disk));
+}
+
+#[test]
+fn compact() {
+    let mut image = blank();
+
+    // slots: 0 at 3/2MiB, 1 unused, 2 at 5/4MiB
+    let sparse = || {
+        let mut editor = GptEditor::open(&image[..], 512).unwrap();
+        editor.add(3 * MIB / 2, MIB / 2, [1; 16], [2; 16]).unwrap();
+        editor.add(MIB, MIB / 4, [3; 16], [4; 16]).unwrap();
+        editor.add(5 * MIB / 4, MIB / 4, [5; 16], [6; 16]).unwrap();
+        editor.delete(1).unwrap();
+        editor
+    };
+
+    assert_eq!(vec![0, 2], sparse().compact(Order::Table));
+
+    let mut editor = sparse();
+    assert_eq!(vec![2, 0], editor.compact(Order::Disk));
+    editor.commit(&mut image[..]).unwrap();
+
+    let parts = list_partitions(&image[..], &Options::default()).unwrap();
+    let found: Vec<(usize, u64)> = parts.iter().map(|p| (p.id, p.first_byte)).collect();
+    assert_eq!(vec![(0, 5 * MIB / 4), (1, 3 * MIB / 2)], found);
+}
+
+#[test]
+fn invalid_names() {
+    let mut image = blank();
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    let id = editor.add(MIB, MIB, [1; 16], [2; 16]).unwrap();
+    editor.set_name(id, "ab").unwrap();
+    editor.commit(&mut image[..]).unwrap();
+
+    // replace the 'b' with an unpaired surrogate, and fix up the primary's checksums
+    image[1024 + 0x3a..1024 + 0x3c].copy_from_slice(&0xd800u16.to_le_bytes());
+    common::fix_table_crc(&mut image);
+
+    assert!(matches!(
+        read_partition_table(&image[..], &Options::default()).unwrap_err(),
+        Error::InvalidData { .. }
+    ));
+
+    let name = |invalid_names| {
+        let options = Options {
+            invalid_names,
+            ..Options::default()
+        };
+        let table = read_partition_table(&image[..], &options).unwrap();
+        assert_eq!("gpt-name-invalid", table.warnings[0].code);
+        match &table.partitions[0].attributes {
+            Attributes::GPT { name, .. } => name.clone(),
+            Attributes::MBR { .. } => unreachable!(),
+        }
+    };
+
+    assert_eq!("a\u{fffd}", name(InvalidNames::Replace));
+    assert_eq!("", name(InvalidNames::Empty));
+}
+
+#[test]
+fn raw_names() {
+    let mut image = blank();
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    let id = editor.add(MIB, MIB, [1; 16], [2; 16]).unwrap();
+    editor.set_name(id, "ab").unwrap();
+    editor.commit(&mut image[..]).unwrap();
+
+    let raw_name =
+        |image: &[u8]| match &list_partitions(image, &Options::default()).unwrap()[0].attributes {
+            Attributes::GPT { name, raw_name, .. } => (name.clone(), raw_name.clone()),
+            Attributes::MBR { .. } => unreachable!(),
+        };
+
+    let (name, raw) = raw_name(&image);
+    assert_eq!("ab", name);
+    assert_eq!(72, raw.len());
+    assert_eq!(&[b'a', 0, b'b', 0, 0, 0], &raw[..6]);
+
+    // junk after the nul, as left by some tools when renaming
+    let mut junk = raw;
+    junk[6..10].copy_from_slice(b"junk");
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    editor.set_raw_name(id, &junk).unwrap();
+    assert!(editor.set_raw_name(id, &junk[..70]).is_err());
+    editor.commit(&mut image[..]).unwrap();
+
+    assert_eq!(("ab".to_string(), junk), raw_name(&image));
+}
+
+#[test]
+fn normalized_names() {
+    use bootsector::NameNormalization;
+
+    let mut image = blank();
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    let id = editor.add(MIB, MIB, [1; 16], [2; 16]).unwrap();
+    editor.set_name(id, "cafe\u{301}  ").unwrap();
+    editor.commit(&mut image[..]).unwrap();
+
+    let name = |image: &[u8], name_normalization| {
+        let options = Options {
+            name_normalization,
+            ..Options::default()
+        };
+        match &list_partitions(image, &options)?[0].attributes {
+            Attributes::GPT { name, .. } => Ok(name.clone()),
+            Attributes::MBR { .. } => unreachable!(),
+        }
+    };
+
+    assert_eq!(
+        "cafe\u{301}  ",
+        name(&image, NameNormalization::default()).unwrap()
+    );
+
+    let trimmed = NameNormalization {
+        trim: true,
+        ..NameNormalization::default()
+    };
+    assert_eq!("cafe\u{301}", name(&image, trimmed.clone()).unwrap());
+
+    #[cfg(feature = "nfc")]
+    assert_eq!(
+        "caf\u{e9}",
+        name(
+            &image,
+            NameNormalization {
+                nfc: true,
+                ..trimmed
+            }
+        )
+        .unwrap()
+    );
+
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    editor.set_name(id, "two\nlines").unwrap();
+    editor.commit(&mut image[..]).unwrap();
+    assert_eq!(
+        "two\nlines",
+        name(&image, NameNormalization::default()).unwrap()
+    );
+    assert!(matches!(
+        name(
+            &image,
+            NameNormalization {
+                reject_control: true,
+                ..NameNormalization::default()
+            }
+        ),
+        Err(Error::InvalidData { .. })
+    ));
+}
+
+#[test]
+fn entry_sizes() {
+    use bootsector::EntrySizes;
+
+    for &entry_size in &[256u32, 512] {
+        let mut image = vec![0u8; 8 * MIB as usize];
+        let options = CreateOptions {
+            entries: 64,
+            entry_size,
+            ..CreateOptions::new([7; 16])
+        };
+        gpt::create(&mut image[..], 8 * MIB, 512, &options).unwrap();
+        let mut editor = GptEditor::open(&image[..], 512).unwrap();
+        let id = editor.add(MIB, MIB, [1; 16], [2; 16]).unwrap();
+        editor.set_name(id, "wide").unwrap();
+        editor.commit(&mut image[..]).unwrap();
+
+        let table = read_partition_table(&image[..], &Options::default()).unwrap();
+        assert_eq!(entry_size, table.gpt_header.as_ref().unwrap().entry_size);
+        assert_eq!(1, table.partitions.len());
+        match &table.partitions[0].attributes {
+            Attributes::GPT { name, extra, .. } => {
+                assert_eq!("wide", name);
+                assert_eq!(entry_size as usize - 128, extra.len());
+            }
+            Attributes::MBR { .. } => unreachable!(),
+        }
+    }
+
+    // 42 entries of 384 bytes, in the space of 128 of 128 bytes, which doesn't fill a sector
+    let mut image = blank();
+    image[512 + 0x50..512 + 0x54].copy_from_slice(&42u32.to_le_bytes());
+    image[512 + 0x54..512 + 0x58].copy_from_slice(&384u32.to_le_bytes());
+    common::fix_table_crc(&mut image);
+
+    assert!(matches!(
+        read_partition_table(&image[..], &Options::default()).unwrap_err(),
+        Error::InvalidData { .. }
+    ));
+
+    let options = Options {
+        entry_sizes: EntrySizes::Any,
+        ..Options::default()
+    };
+    let table = read_partition_table(&image[..], &options).unwrap();
+    assert!(table.partitions.is_empty());
+    assert_eq!("gpt-entry-size", table.warnings[0].code);
+}
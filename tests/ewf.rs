@@ -0,0 +1,138 @@
+#![cfg(feature = "ewf")]
+
+extern crate bootsector;
+
+use std::io;
+
+use bootsector::ewf::Ewf;
+use bootsector::pio::ReadAt;
+use bootsector::read_partition_table_sized;
+
+const CHUNK: usize = 32 * 1024;
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + u32::from(byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+/// Stand-in for zlib: a single repeated byte, which all the empty chunks compress well with.
+fn decompress(chunk: &[u8], len: usize) -> io::Result<Vec<u8>> {
+    Ok(vec![chunk[0]; len])
+}
+
+fn section(image: &mut Vec<u8>, kind: &str, data: &[u8], last: bool) {
+    let pos = image.len() as u64;
+    let size = 76 + data.len() as u64;
+    let mut descriptor = vec![0u8; 76];
+    descriptor[..kind.len()].copy_from_slice(kind.as_bytes());
+    let next = if last { pos } else { pos + size };
+    descriptor[16..24].copy_from_slice(&next.to_le_bytes());
+    descriptor[24..32].copy_from_slice(&size.to_le_bytes());
+    let checksum = adler32(&descriptor[..72]);
+    descriptor[72..].copy_from_slice(&checksum.to_le_bytes());
+    image.extend_from_slice(&descriptor);
+    image.extend_from_slice(data);
+}
+
+fn segment(number: u16, disk_len: usize, chunks: &[&[u8]], last: bool) -> Vec<u8> {
+    let mut image = b"EVF\x09\x0d\x0a\xff\x00\x01".to_vec();
+    image.extend_from_slice(&number.to_le_bytes());
+    image.extend_from_slice(&[0, 0]);
+
+    if 1 == number {
+        let mut volume = vec![0u8; 94];
+        volume[4..8].copy_from_slice(&(((disk_len + CHUNK - 1) / CHUNK) as u32).to_le_bytes());
+        volume[8..12].copy_from_slice(&((CHUNK / 512) as u32).to_le_bytes());
+        volume[12..16].copy_from_slice(&512u32.to_le_bytes());
+        volume[16..24].copy_from_slice(&((disk_len / 512) as u64).to_le_bytes());
+        section(&mut image, "volume", &volume, false);
+    }
+
+    let mut sectors = Vec::new();
+    let mut entries = Vec::new();
+    let start = image.len() + 76;
+    for &chunk in chunks {
+        let offset = (start + sectors.len()) as u32;
+        if chunk.iter().all(|&b| b == chunk[0]) {
+            entries.push(offset | 1 << 31);
+            sectors.push(chunk[0]);
+        } else {
+            entries.push(offset);
+            sectors.extend_from_slice(chunk);
+            sectors.extend_from_slice(&adler32(chunk).to_le_bytes());
+        }
+    }
+    section(&mut image, "sectors", &sectors, false);
+
+    let mut table = vec![0u8; 24];
+    table[..4].copy_from_slice(&(entries.len() as u32).to_le_bytes());
+    let checksum = adler32(&table[..20]);
+    table[20..24].copy_from_slice(&checksum.to_le_bytes());
+    for entry in &entries {
+        table.extend_from_slice(&entry.to_le_bytes());
+    }
+    section(&mut image, "table", &table, false);
+
+    section(&mut image, if last { "done" } else { "next" }, &[], true);
+    image
+}
+
+#[test]
+fn two_segments() {
+    let raw = &include_bytes!("test-data/labels.img")[..];
+    let chunks: Vec<&[u8]> = raw.chunks(CHUNK).collect();
+    let (first, second) = chunks.split_at(chunks.len() / 2);
+    let segments = [
+        segment(1, raw.len(), first, false),
+        segment(2, raw.len(), second, true),
+    ];
+    assert!(segments.iter().map(|s| s.len()).sum::<usize>() < raw.len());
+
+    let segments: Vec<&[u8]> = segments.iter().map(|s| &s[..]).collect();
+    let disc = Ewf::open(segments, decompress).unwrap();
+    assert_eq!(raw.len() as u64, disc.disk_len());
+
+    let mut read = vec![0u8; raw.len()];
+    disc.read_exact_at(0, &mut read).unwrap();
+    assert_eq!(raw, &read[..]);
+
+    let table = read_partition_table_sized(&disc, &Default::default()).unwrap();
+    assert_eq!(7, table.partitions.len());
+}
+
+#[test]
+fn missing_segment() {
+    let raw = &include_bytes!("test-data/labels.img")[..];
+    let chunks: Vec<&[u8]> = raw.chunks(CHUNK).collect();
+    let first = segment(1, raw.len(), &chunks[..10], false);
+    assert!(Ewf::open(vec![&first[..]], decompress).is_err());
+}
+
+#[test]
+fn not_ewf() {
+    let raw = &include_bytes!("test-data/labels.img")[..];
+    assert!(matches!(
+        Ewf::open(vec![raw], decompress),
+        Err(bootsector::Error::NotFound)
+    ));
+}
+
+#[test]
+fn huge_volume() {
+    let mut image = b"EVF\x09\x0d\x0a\xff\x00\x01\x01\x00\x00\x00".to_vec();
+    let mut volume = vec![0u8; 94];
+    volume[8..12].copy_from_slice(&64u32.to_le_bytes());
+    volume[12..16].copy_from_slice(&512u32.to_le_bytes());
+    volume[16..24].copy_from_slice(&(u64::MAX / 512).to_le_bytes());
+    section(&mut image, "volume", &volume, false);
+    section(&mut image, "done", &[], true);
+
+    assert!(matches!(
+        Ewf::open(vec![&image[..]], decompress),
+        Err(bootsector::Error::Overflow)
+    ));
+}
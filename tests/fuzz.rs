@@ -0,0 +1,39 @@
+extern crate bootsector;
+
+mod common;
+
+use bootsector::fuzz::{fuzz_gpt, fuzz_mbr};
+use bootsector::{list_partitions, Error, Options};
+
+#[test]
+fn matches_reading() {
+    let image = &include_bytes!("test-data/labels.img")[..];
+    let (header, partitions) = fuzz_gpt(image, 512).unwrap();
+    assert_eq!(1, header.my_lba);
+    assert_eq!(
+        list_partitions(image, &Options::default()).unwrap(),
+        partitions
+    );
+
+    let image = &include_bytes!("test-data/mbr-ubuntu-raspi3-16.04.img")[..];
+    let mut sector = [0u8; 512];
+    sector.copy_from_slice(&image[..512]);
+    assert_eq!(
+        list_partitions(image, &Options::default()).unwrap(),
+        fuzz_mbr(&sector).unwrap()
+    );
+}
+
+#[test]
+fn huge_table_is_not_allocated() {
+    let mut image = include_bytes!("test-data/labels.img")[..2048].to_vec();
+    // 65535 entries of 65535 bytes, and a usable area after them; the header is
+    // otherwise valid, so this fails on the length of the image
+    image[512 + 80..512 + 88].copy_from_slice(&[0xff, 0xff, 0, 0, 0xff, 0xff, 0, 0]);
+    image[512 + 40..512 + 48].copy_from_slice(&(1u64 << 24).to_le_bytes());
+    image[512 + 48..512 + 56].copy_from_slice(&(1u64 << 25).to_le_bytes());
+    common::fix_header_crc(&mut image);
+    assert!(matches!(fuzz_gpt(&image, 512), Err(Error::UnexpectedEof)));
+    assert!(fuzz_gpt(&image, 0).is_err());
+    assert!(fuzz_gpt(&[], 4096).is_err());
+}
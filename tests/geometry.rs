@@ -0,0 +1,53 @@
+extern crate bootsector;
+
+use bootsector::{Attributes, Partition};
+
+fn part(id: usize, first_byte: u64, len: u64) -> Partition {
+    Partition {
+        id,
+        first_byte,
+        len,
+        attributes: Attributes::MBR {
+            bootable: false,
+            type_code: 0x83,
+        },
+        label: None,
+    }
+}
+
+#[test]
+fn contains_and_overlaps() {
+    let first = part(0, 1024, 512);
+    assert_eq!(1024..1536, first.byte_range());
+    assert!(!first.contains(1023));
+    assert!(first.contains(1024));
+    assert!(first.contains(1535));
+    assert!(!first.contains(1536));
+
+    // adjacent
+    let second = part(1, 1536, 512);
+    assert!(!first.overlaps(&second));
+    assert!(!second.overlaps(&first));
+
+    let straddling = part(2, 1535, 2);
+    assert!(first.overlaps(&straddling));
+    assert!(second.overlaps(&straddling));
+
+    let empty = part(3, 1200, 0);
+    assert!(!first.overlaps(&empty));
+    assert!(!empty.contains(1200));
+
+    let huge = part(4, u64::MAX - 10, 100);
+    assert_eq!(u64::MAX, huge.byte_range().end);
+    assert!(huge.contains(u64::MAX - 1));
+}
+
+#[test]
+fn ends() {
+    let first = part(0, 1024, 512);
+    assert_eq!(1536, first.end_byte());
+    assert_eq!(Some(1535), first.last_byte());
+
+    assert_eq!(None, part(1, 1024, 0).last_byte());
+    assert_eq!(u64::MAX, part(2, u64::MAX - 10, 100).end_byte());
+}
@@ -0,0 +1,49 @@
+extern crate bootsector;
+
+use bootsector::guid;
+
+#[test]
+fn round_trip() {
+    let text = "0FC63DAF-8483-4772-8E79-3D69D8477DE4";
+    let bytes = guid::parse(text).unwrap();
+    assert_eq!(
+        [
+            0xaf, 0x3d, 0xc6, 0x0f, 0x83, 0x84, 0x72, 0x47, 0x8e, 0x79, 0x3d, 0x69, 0xd8, 0x47,
+            0x7d, 0xe4
+        ],
+        bytes
+    );
+    assert_eq!(text, guid::to_string(&bytes));
+    assert_eq!(bytes, guid::parse(&text.to_lowercase()).unwrap());
+}
+
+#[test]
+fn invalid() {
+    for text in &[
+        "",
+        "0FC63DAF84834772-8E79-3D69D8477DE4",
+        "0FC63DAF-8483-4772-8E79-3D69D8477DE",
+        "{0FC63DAF-8483-4772-8E79-3D69D8477DE4}",
+        "0FC63DAF-8483-4772-8E79-3D69D8477DEG",
+        "0FC63DAF+8483-4772-8E79-3D69D8477DE4",
+    ] {
+        assert!(guid::parse(text).is_err(), "{}", text);
+    }
+}
+
+#[test]
+fn constants() {
+    use bootsector::gpt::guids;
+
+    for (text, bytes) in &[
+        ("C12A7328-F81F-11D2-BA4B-00A0C93EC93B", guids::ESP),
+        (
+            "0FC63DAF-8483-4772-8E79-3D69D8477DE4",
+            guids::LINUX_FILESYSTEM,
+        ),
+        ("EBD0A0A2-B9E5-4433-87C0-68B6B72699C7", guids::MS_BASIC_DATA),
+        ("7C3457EF-0000-11AA-AA11-00306543ECAC", guids::APPLE_APFS),
+    ] {
+        assert_eq!(*text, guid::to_string(bytes));
+    }
+}
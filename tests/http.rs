@@ -0,0 +1,92 @@
+#![cfg(feature = "http")]
+
+extern crate bootsector;
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use bootsector::http::HttpReadAt;
+use bootsector::{list_partitions, read_partition_table_sized, Options};
+
+/// Serve an image over plain HTTP, honouring range requests if `ranges`, and recording
+/// the ranges asked for.
+fn serve(image: &'static [u8], ranges: bool) -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/disc.img", listener.local_addr().unwrap());
+    let requested = Arc::new(Mutex::new(Vec::new()));
+    let log = requested.clone();
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut range = None;
+            for line in BufReader::new(&stream).lines() {
+                let line = line.unwrap();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some(value) = line.strip_prefix("Range: bytes=") {
+                    range = Some(value.to_string());
+                }
+            }
+
+            let (status, body, extra) = match range.filter(|_| ranges) {
+                Some(range) => {
+                    log.lock().unwrap().push(range.clone());
+                    let mut bounds = range.split('-').map(|n| n.parse::<usize>().unwrap());
+                    let first = bounds.next().unwrap();
+                    let last = bounds.next().unwrap().min(image.len() - 1);
+                    let header = format!(
+                        "Content-Range: bytes {}-{}/{}\r\n",
+                        first,
+                        last,
+                        image.len()
+                    );
+                    ("206 Partial Content", &image[first..=last], header)
+                }
+                None => ("200 OK", image, String::new()),
+            };
+
+            let head = format!(
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n",
+                status,
+                body.len(),
+                extra
+            );
+            // the client may hang up early, e.g. after a failed open
+            let _ = stream.write_all(head.as_bytes());
+            let _ = stream.write_all(body);
+        }
+    });
+
+    (url, requested)
+}
+
+#[test]
+fn range_requests() {
+    let image: &'static [u8] = include_bytes!("test-data/labels.img");
+    let (url, requested) = serve(image, true);
+
+    let disc = HttpReadAt::open(&url).unwrap();
+    assert_eq!(image.len() as u64, disc.disk_len());
+
+    let table = read_partition_table_sized(&disc, &Options::default()).unwrap();
+    assert_eq!(7, table.partitions.len());
+
+    let requested = requested.lock().unwrap();
+    assert_eq!("0-0", requested[0]);
+    assert_eq!("0-511", requested[1]);
+    assert_eq!(requested.len(), table.stats.reads + 1);
+}
+
+#[test]
+fn ranges_required() {
+    let (url, _) = serve(include_bytes!("test-data/labels.img"), false);
+    assert!(HttpReadAt::open(&url).is_err());
+
+    let (url, _) = serve(include_bytes!("test-data/labels.img"), true);
+    let disc = HttpReadAt::open(&url).unwrap();
+    assert_eq!(7, list_partitions(disc, &Options::default()).unwrap().len());
+}
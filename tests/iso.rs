@@ -0,0 +1,108 @@
+extern crate bootsector;
+
+use bootsector::edit::GptEditor;
+use bootsector::gpt::{self, guids, CreateOptions};
+use bootsector::iso::{read_isohybrid, read_primary_volume};
+use bootsector::{read_partition_table, Attributes, Options};
+
+const MIB: u64 = 1024 * 1024;
+
+/// A GPT disc, with an EFI system partition, and an ISO filesystem which claims to cover
+/// the whole disc, like `xorriso` makes.
+fn isohybrid() -> Vec<u8> {
+    let mut image = vec![0u8; 4 * MIB as usize];
+    gpt::create(&mut image[..], 4 * MIB, 512, &CreateOptions::new([7; 16])).unwrap();
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    editor.add(MIB, MIB, guids::ESP, [2; 16]).unwrap();
+    editor.commit(&mut image[..]).unwrap();
+
+    // an El Torito boot record, then the primary volume descriptor, then the terminator
+    for (sector, kind) in [(16, 0u8), (17, 1), (18, 255)].iter() {
+        let descriptor = &mut image[sector * 2048..(sector + 1) * 2048];
+        descriptor[0] = *kind;
+        descriptor[1..7].copy_from_slice(b"CD001\x01");
+    }
+    let pvd = &mut image[17 * 2048..18 * 2048];
+    pvd[40..72].copy_from_slice(b"TEST_LIVE                       ");
+    pvd[80..84].copy_from_slice(&2048u32.to_le_bytes());
+    pvd[84..88].copy_from_slice(&2048u32.to_be_bytes());
+    pvd[128..130].copy_from_slice(&2048u16.to_le_bytes());
+    pvd[130..132].copy_from_slice(&2048u16.to_be_bytes());
+
+    image
+}
+
+/// Replace the protective MBR with entries for the whole ISO, and the ESP.
+fn hybrid_mbr(image: &mut [u8]) {
+    let entry = |status: u8, type_code: u8, first_lba: u32, sectors: u32| {
+        let mut entry = [0u8; 16];
+        entry[0] = status;
+        entry[4] = type_code;
+        entry[8..12].copy_from_slice(&first_lba.to_le_bytes());
+        entry[12..16].copy_from_slice(&sectors.to_le_bytes());
+        entry
+    };
+    image[446..462].copy_from_slice(&entry(0x80, 0x17, 0, 8192));
+    image[462..478].copy_from_slice(&entry(0, 0xef, 2048, 2048));
+    for byte in &mut image[478..510] {
+        *byte = 0;
+    }
+}
+
+#[test]
+fn gpt_only() {
+    let image = isohybrid();
+    let volume = read_primary_volume(&image[..]).unwrap().unwrap();
+    assert_eq!("TEST_LIVE", volume.volume_id);
+    assert_eq!(4 * MIB, volume.len);
+
+    let iso = read_isohybrid(&image[..]).unwrap().unwrap();
+    let gpt = iso.gpt.unwrap();
+    assert_eq!(1, gpt.partitions.len());
+    assert_eq!(MIB, gpt.partitions[0].first_byte);
+
+    // the protective mbr, read literally
+    let mbr = iso.mbr.unwrap();
+    match mbr.partitions[0].attributes {
+        Attributes::MBR { type_code, .. } => assert_eq!(0xee, type_code),
+        _ => panic!("expected an mbr partition"),
+    }
+}
+
+#[test]
+fn hybrid() {
+    let mut image = isohybrid();
+    hybrid_mbr(&mut image);
+
+    // a normal read only sees the mbr, as it doesn't point to the gpt
+    let table = read_partition_table(&image[..], &Options::default()).unwrap();
+    assert_eq!(2, table.partitions.len());
+    assert!(table.gpt_header.is_none());
+
+    let iso = read_isohybrid(&image[..]).unwrap().unwrap();
+    let mbr = iso.mbr.unwrap();
+    assert_eq!(0, mbr.partitions[0].first_byte);
+    assert!(mbr.partitions[0].overlaps(&mbr.partitions[1]));
+
+    let gpt = iso.gpt.unwrap();
+    assert_eq!(MIB, gpt.partitions[0].first_byte);
+}
+
+#[test]
+fn not_iso() {
+    let mut image = isohybrid();
+    for byte in &mut image[16 * 2048..19 * 2048] {
+        *byte = 0;
+    }
+    assert_eq!(None, read_isohybrid(&image[..]).unwrap());
+
+    // too short to have a volume descriptor
+    assert_eq!(None, read_primary_volume(&image[..20 * 1024]).unwrap());
+
+    // an iso, but no tables
+    let mut image = isohybrid();
+    for byte in &mut image[..16 * 1024] {
+        *byte = 0;
+    }
+    assert_eq!(None, read_isohybrid(&image[..]).unwrap());
+}
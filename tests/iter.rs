@@ -0,0 +1,94 @@
+extern crate bootsector;
+
+mod common;
+
+use bootsector::edit::GptEditor;
+use bootsector::gpt::{self, CreateOptions};
+use bootsector::{iter_partitions, list_partitions, Error, Options, Order};
+
+const MIB: u64 = 1024 * 1024;
+
+fn image() -> Vec<u8> {
+    let mut image = vec![0u8; 8 * MIB as usize];
+    gpt::create(&mut image[..], 8 * MIB, 512, &CreateOptions::new([7; 16])).unwrap();
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    editor.add(3 * MIB, MIB, [1; 16], [1; 16]).unwrap();
+    editor.add(MIB, MIB, [2; 16], [2; 16]).unwrap();
+    editor.add(2 * MIB, MIB, [3; 16], [3; 16]).unwrap();
+    editor.commit(&mut image[..]).unwrap();
+    image
+}
+
+#[test]
+fn same_as_list() {
+    let image = image();
+    let options = Options::default();
+    let all: Vec<_> = iter_partitions(&image[..], &options)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(list_partitions(&image[..], &options).unwrap(), all);
+
+    let by_disk = Options {
+        order: Order::Disk,
+        ..Options::default()
+    };
+    let ids: Vec<usize> = iter_partitions(&image[..], &by_disk)
+        .map(|part| part.unwrap().id)
+        .collect();
+    assert_eq!(vec![1, 2, 0], ids);
+}
+
+#[test]
+fn stops_early() {
+    let mut image = image();
+
+    // give the last entry an invalid name, and fix up the primary's checksums
+    let name = 1024 + 2 * 128 + 0x38;
+    image[name..name + 2].copy_from_slice(&0xdc00u16.to_le_bytes());
+    common::fix_table_crc(&mut image);
+
+    let options = Options::default();
+    assert!(list_partitions(&image[..], &options).is_err());
+
+    let first = iter_partitions(&image[..], &options)
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(3 * MIB, first.first_byte);
+
+    let mut parts = iter_partitions(&image[..], &options);
+    assert!(parts.next().unwrap().is_ok());
+    assert!(parts.next().unwrap().is_ok());
+    assert!(matches!(parts.next(), Some(Err(Error::InvalidData { .. }))));
+    assert!(parts.next().is_none());
+}
+
+#[test]
+fn unchecked_reads_lazily() {
+    use std::cell::Cell;
+
+    let mut image = image();
+    // corrupt an unused entry, so the entries' checksum is wrong
+    image[1024 + 100 * 128 + 0x38] = 0xff;
+
+    assert!(list_partitions(&image[..], &Options::default()).is_err());
+
+    let options = Options {
+        dangerously_skip_entries_crc: true,
+        ..Options::default()
+    };
+    let table = bootsector::read_partition_table(&image[..], &options).unwrap();
+    assert_eq!(3, table.partitions.len());
+    assert_eq!("gpt-entries-unchecked", table.warnings[0].code);
+
+    let furthest = Cell::new(0u64);
+    let disc = bootsector::io::ReadFn(|pos: u64, buf: &mut [u8]| {
+        let end = pos + buf.len() as u64;
+        furthest.set(furthest.get().max(end));
+        buf.copy_from_slice(&image[pos as usize..end as usize]);
+        Ok(())
+    });
+    let first = iter_partitions(disc, &options).next().unwrap().unwrap();
+    assert_eq!(3 * MIB, first.first_byte);
+    assert_eq!(1024 + 128, furthest.get());
+}
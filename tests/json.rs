@@ -0,0 +1,42 @@
+extern crate bootsector;
+
+use bootsector::{json, read_partition_table, Options};
+
+#[test]
+fn gpt_schema() {
+    let table = read_partition_table(
+        &include_bytes!("test-data/labels.img")[..],
+        &Options::default(),
+    )
+    .expect("success");
+
+    let value = serde_json::to_value(json::Table::from(&table)).unwrap();
+
+    assert_eq!(1, value["schema_version"]);
+    assert_eq!("gpt", value["table_type"]);
+    assert_eq!(512, value["sector_size"]);
+    assert!(value["disk_guid"].is_string());
+
+    let first = &value["partitions"][0];
+    assert_eq!("0FC63DAF-8483-4772-8E79-3D69D8477DE4", first["type_guid"]);
+    assert_eq!("first", first["name"]);
+    assert_eq!(1024 * 1024, first["first_byte"]);
+    assert!(first.get("type_code").is_none());
+}
+
+#[test]
+fn mbr_schema() {
+    let table = read_partition_table(
+        &include_bytes!("test-data/mbr-ubuntu-raspi3-16.04.img")[..],
+        &Options::default(),
+    )
+    .expect("success");
+
+    let value = serde_json::to_value(json::Table::from(&table)).unwrap();
+
+    assert_eq!("mbr", value["table_type"]);
+    assert!(value.get("disk_guid").is_none());
+    assert_eq!(12, value["partitions"][0]["type_code"]);
+    assert_eq!(true, value["partitions"][0]["bootable"]);
+    assert!(value["partitions"][0].get("name").is_none());
+}
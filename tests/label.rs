@@ -0,0 +1,118 @@
+extern crate bootsector;
+
+use bootsector::edit::GptEditor;
+use bootsector::gpt::{self, CreateOptions};
+use bootsector::label::probe_label;
+use bootsector::{list_partitions, Options};
+
+const MIB: usize = 1024 * 1024;
+
+fn put(image: &mut [u8], at: usize, data: &[u8]) {
+    image[at..at + data.len()].copy_from_slice(data);
+}
+
+fn fat16(image: &mut [u8], at: usize, label: &[u8; 11]) {
+    put(image, at + 0x16, &1u16.to_le_bytes());
+    image[at + 0x26] = 0x29;
+    put(image, at + 0x2b, label);
+    put(image, at + 0x36, b"FAT16   ");
+    put(image, at + 510, &[0x55, 0xaa]);
+}
+
+fn fat32(image: &mut [u8], at: usize, label: &[u8; 11]) {
+    image[at + 0x42] = 0x29;
+    put(image, at + 0x47, label);
+    put(image, at + 0x52, b"FAT32   ");
+    put(image, at + 510, &[0x55, 0xaa]);
+}
+
+fn ext4(image: &mut [u8], at: usize, label: &str) {
+    put(image, at + 1024 + 0x38, &0xef53u16.to_le_bytes());
+    put(image, at + 1024 + 0x78, label.as_bytes());
+}
+
+/// 4KiB clusters, the MFT at cluster 4, and 1KiB records.
+fn ntfs(image: &mut [u8], at: usize, label: &str) {
+    put(image, at + 3, b"NTFS    ");
+    put(image, at + 0x0b, &512u16.to_le_bytes());
+    image[at + 0x0d] = 8;
+    put(image, at + 0x30, &4u64.to_le_bytes());
+    image[at + 0x40] = (-10i8) as u8;
+
+    let record = at + 4 * 4096 + 3 * 1024;
+    put(image, record, b"FILE");
+    // update sequence: number 7, then the (zero) original ends of the two sectors
+    put(image, record + 0x04, &0x30u16.to_le_bytes());
+    put(image, record + 0x06, &3u16.to_le_bytes());
+    put(image, record + 0x30, &7u16.to_le_bytes());
+    put(image, record + 510, &7u16.to_le_bytes());
+    put(image, record + 1022, &7u16.to_le_bytes());
+    put(image, record + 0x14, &0x38u16.to_le_bytes());
+
+    let name: Vec<u8> = label
+        .encode_utf16()
+        .flat_map(|u| u.to_le_bytes().to_vec())
+        .collect();
+    let attribute = record + 0x38;
+    let len = (0x18 + name.len() + 7) / 8 * 8;
+    put(image, attribute, &0x60u32.to_le_bytes());
+    put(image, attribute + 4, &(len as u32).to_le_bytes());
+    put(image, attribute + 0x10, &(name.len() as u32).to_le_bytes());
+    put(image, attribute + 0x14, &0x18u16.to_le_bytes());
+    put(image, attribute + 0x18, &name);
+    put(image, attribute + len, &0xffff_ffffu32.to_le_bytes());
+}
+
+#[test]
+fn labels() {
+    let mut image = vec![0u8; 8 * MIB];
+    gpt::create(
+        &mut image[..],
+        8 * MIB as u64,
+        512,
+        &CreateOptions::new([7; 16]),
+    )
+    .unwrap();
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    for i in 1..=6 {
+        editor
+            .add((i * MIB) as u64, MIB as u64, [1; 16], [i as u8; 16])
+            .unwrap();
+    }
+    editor.commit(&mut image[..]).unwrap();
+
+    fat16(&mut image, MIB, b"BOOT       ");
+    fat32(&mut image, 2 * MIB, b"NO NAME    ");
+    ext4(&mut image, 3 * MIB, "rootfs");
+    ntfs(&mut image, 4 * MIB, "Windows \u{2603}");
+    fat32(&mut image, 5 * MIB, b"DATA       ");
+
+    let parts = list_partitions(&image[..], &Options::default()).unwrap();
+    assert!(parts.iter().all(|part| part.label.is_none()));
+    assert_eq!(
+        Some("rootfs".to_string()),
+        probe_label(&image[..], &parts[2]).unwrap()
+    );
+
+    let options = Options {
+        probe_labels: true,
+        ..Options::default()
+    };
+    let labels: Vec<Option<String>> = list_partitions(&image[..], &options)
+        .unwrap()
+        .into_iter()
+        .map(|part| part.label)
+        .collect();
+
+    assert_eq!(
+        vec![
+            Some("BOOT".to_string()),
+            None,
+            Some("rootfs".to_string()),
+            Some("Windows \u{2603}".to_string()),
+            Some("DATA".to_string()),
+            None,
+        ],
+        labels
+    );
+}
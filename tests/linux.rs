@@ -0,0 +1,105 @@
+#![cfg(all(feature = "linux", target_os = "linux"))]
+
+extern crate bootsector;
+
+use std::io::Write;
+
+use bootsector::{linux, read_partition_table};
+
+/// From linux's errno.h.
+const EOPNOTSUPP: i32 = 95;
+
+#[test]
+fn image_file() {
+    let path = std::env::temp_dir().join(format!("bootsector-linux-{}.img", std::process::id()));
+    std::fs::File::create(&path)
+        .unwrap()
+        .write_all(include_bytes!("test-data/labels.img"))
+        .unwrap();
+
+    let file = std::fs::File::open(&path).unwrap();
+    let geometry = linux::geometry(&file).unwrap();
+    assert_eq!(10 * 1024 * 1024, geometry.len);
+    assert_eq!(512, geometry.logical_sector_size);
+
+    let options = linux::options(&file).unwrap();
+    let table = read_partition_table(&file, &options).unwrap();
+    assert_eq!(7, table.partitions.len());
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn not_a_block_device() {
+    let file = std::fs::File::open("/dev/null").unwrap();
+    assert!(linux::geometry(&file).is_err());
+}
+
+#[test]
+fn loop_device() {
+    // needs root, and a kernel with loop support
+    if std::fs::File::open("/dev/loop-control").is_err() {
+        return;
+    }
+
+    let path = std::env::temp_dir().join(format!("bootsector-loop-{}.img", std::process::id()));
+    std::fs::File::create(&path)
+        .unwrap()
+        .write_all(include_bytes!("test-data/labels.img"))
+        .unwrap();
+
+    let file = std::fs::File::open(&path).unwrap();
+    let table = read_partition_table(&file, &Default::default()).unwrap();
+    let part = &table.partitions[0];
+
+    let device = linux::attach_partition(&file, part, true).expect("attach");
+    let geometry = linux::geometry(&std::fs::File::open(device.path()).unwrap()).unwrap();
+    assert_eq!(part.len, geometry.len);
+    device.detach().unwrap();
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn discard_image() {
+    use std::io::Read;
+
+    let path = std::env::temp_dir().join(format!("bootsector-discard-{}.img", std::process::id()));
+    let mut image = include_bytes!("test-data/labels.img").to_vec();
+    let table = read_partition_table(&image[..], &Default::default()).unwrap();
+    let part = table.partitions[1].clone();
+    let range = part.first_byte as usize..(part.first_byte + part.len) as usize;
+    for b in &mut image[range.clone()] {
+        *b = 0xff;
+    }
+    std::fs::write(&path, &image).unwrap();
+
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .unwrap();
+
+    let mut unaligned = part.clone();
+    unaligned.len -= 1;
+    assert!(linux::discard_partition(&file, &unaligned).is_err());
+
+    match linux::discard_partition(&file, &part) {
+        Ok(()) => {
+            let mut read = Vec::new();
+            std::fs::File::open(&path)
+                .unwrap()
+                .read_to_end(&mut read)
+                .unwrap();
+            assert!(read[range.clone()].iter().all(|&b| 0 == b));
+            assert_eq!(image[..range.start], read[..range.start]);
+            assert_eq!(image[range.end..], read[range.end..]);
+        }
+        // some filesystems can't punch holes
+        Err(bootsector::Error::Io { ref source, .. })
+            if Some(EOPNOTSUPP) == source.raw_os_error() => {}
+        Err(e) => panic!("{:?}", e),
+    }
+
+    std::fs::remove_file(&path).unwrap();
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::bool_assert_comparison, clippy::field_reassign_with_default)]
+
 extern crate bootsector;
 
 use bootsector::Options;
@@ -206,3 +208,391 @@ fn gpt_name(part: &Partition) -> &str {
         _ => panic!("not a GPT partition"),
     }
 }
+
+#[test]
+fn generated_protective_mbr() {
+    let table = bootsector::mbr::parse_partition_table(&bootsector::mbr::protective_mbr(8))
+        .expect("valid mbr");
+    assert_eq!(1, table.len());
+    assert!(bootsector::gpt::is_protective(&table[0]));
+    assert_eq!(512, table[0].first_byte);
+    assert_eq!(7 * 512, table[0].len);
+
+    let huge = bootsector::mbr::protective_mbr(u64::MAX);
+    assert_eq!([0xff; 4], huge[446 + 12..446 + 16]);
+}
+
+#[test]
+fn const_entries() {
+    use bootsector::mbr::{parse_entries, protective_mbr, Entry, InvalidStatus};
+
+    const ZEROS: [u8; 512] = [0u8; 512];
+    const EMPTY: Result<[Option<Entry>; 4], InvalidStatus> = parse_entries(&ZEROS);
+    assert_eq!(Ok([None; 4]), EMPTY);
+
+    let mut sector = protective_mbr(8);
+    let entries = parse_entries(&sector).expect("valid");
+    assert_eq!(
+        Some(Entry {
+            bootable: false,
+            type_code: 0xee,
+            first_lba: 1,
+            sectors: 7,
+        }),
+        entries[0]
+    );
+
+    sector[446 + 16] = 0x12;
+    assert_eq!(
+        Err(InvalidStatus {
+            entry: 1,
+            status: 0x12
+        }),
+        parse_entries(&sector)
+    );
+}
+
+#[test]
+fn lenient_status() {
+    let mut image = include_bytes!("test-data/mbr-ubuntu-raspi3-16.04.img").to_vec();
+    image[446 + 16] = 0x12;
+
+    assert!(list_partitions(&image[..], &Options::default()).is_err());
+
+    let options = Options {
+        mbr: bootsector::ReadMBR::Lenient,
+        ..Options::default()
+    };
+    let table = bootsector::read_partition_table(&image[..], &options).expect("tolerated");
+    assert_eq!(2, table.partitions.len());
+    assert_eq!(Some(2), table.free_slots());
+    match table.partitions[1].attributes {
+        Attributes::MBR { bootable, .. } => assert!(!bootable),
+        Attributes::GPT { .. } => panic!("mbr"),
+    }
+    assert_eq!(
+        vec!["mbr-status"],
+        table.warnings.iter().map(|w| w.code).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn legacy_layouts() {
+    use bootsector::mbr::Layout;
+
+    let mut image = include_bytes!("test-data/mbr-ubuntu-raspi3-16.04.img").to_vec();
+    // an AAP entry, of type 0x0b, for sectors 8..16, which overwrites the disc signature
+    image[0x1ae..0x1be].copy_from_slice(&[0; 16]);
+    image[0x1ac..0x1ae].copy_from_slice(&[0x78, 0x56]);
+    image[0x1ae + 4] = 0x0b;
+    image[0x1ae + 8] = 8;
+    image[0x1ae + 12] = 8;
+
+    let table = bootsector::read_partition_table(&image[..], &Options::default()).unwrap();
+    assert_eq!(Layout::Standard, table.mbr_layout);
+    assert_eq!(2, table.partitions.len());
+
+    let options = Options {
+        legacy_mbr_layouts: true,
+        ..Options::default()
+    };
+    let table = bootsector::read_partition_table(&image[..], &options).unwrap();
+    assert_eq!(Layout::Aap, table.mbr_layout);
+    assert_eq!(Some(2), table.free_slots());
+    let aap = &table.partitions[2];
+    assert_eq!((4, 8 * 512, 8 * 512), (aap.id, aap.first_byte, aap.len));
+
+    // the sixteenth Disk Manager entry is the lowest
+    image[0xfc..0xfe].copy_from_slice(&[0xaa, 0x55]);
+    image[0xfe + 4] = 0x06;
+    let table = bootsector::read_partition_table(&image[..], &options).unwrap();
+    assert_eq!(Layout::DiskManager, table.mbr_layout);
+    assert_eq!(Some(12), table.free_slots());
+    assert_eq!(15, table.partitions[3].id);
+}
+
+#[test]
+fn labels_header() {
+    let table = bootsector::read_partition_table(
+        cursor(include_bytes!("test-data/labels.img")),
+        &Options::default(),
+    )
+    .expect("success");
+
+    let header = table.gpt_header.expect("gpt");
+    assert_eq!(0x0001_0000, header.revision);
+    assert_eq!(1, header.my_lba);
+    assert_eq!(10 * 1024 * 1024 / 512 - 1, header.alternate_lba);
+    assert_eq!(2, header.entries_lba);
+    assert_eq!(128, header.entries);
+    assert_eq!(128, header.entry_size);
+    assert_eq!(table.disk_guid, Some(header.disk_guid));
+}
+
+#[test]
+fn protective_entry_is_returned() {
+    let table = bootsector::read_partition_table(
+        cursor(include_bytes!("test-data/labels.img")),
+        &Options::default(),
+    )
+    .expect("success");
+
+    assert_eq!(1, table.mbr_partitions.len());
+    assert_eq!(512, table.mbr_partitions[0].first_byte);
+    assert_eq!(10 * 1024 * 1024 - 512, table.mbr_partitions[0].len);
+}
+
+#[test]
+fn junk_in_header_tail() {
+    let mut image = include_bytes!("test-data/labels.img").to_vec();
+    image[512 + 200] = 0x42;
+
+    assert!(list_partitions(&image[..], &Options::default()).is_err());
+
+    let options = Options {
+        reserved: bootsector::Reserved::Tolerate,
+        ..Options::default()
+    };
+    let table = bootsector::read_partition_table(&image[..], &options).expect("tolerated");
+    assert_eq!(7, table.partitions.len());
+    assert_eq!(
+        vec!["gpt-primary-tail"],
+        table.warnings.iter().map(|w| w.code).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn relaxed_protective() {
+    let mut image = include_bytes!("test-data/labels.img").to_vec();
+    // move the protective entry to the second slot, and mark it bootable
+    let entry = image[446..462].to_vec();
+    image[462..478].copy_from_slice(&entry);
+    image[462] = 0x80;
+    for byte in &mut image[446..462] {
+        *byte = 0;
+    }
+
+    let table = bootsector::read_partition_table(&image[..], &Options::default()).unwrap();
+    assert_eq!(bootsector::TableType::MBR, table.table_type);
+
+    let options = Options {
+        protective: bootsector::Protective::Relaxed,
+        ..Options::default()
+    };
+    let table = bootsector::read_partition_table(&image[..], &options).unwrap();
+    assert_eq!(bootsector::TableType::GPT, table.table_type);
+    assert_eq!(7, table.partitions.len());
+    assert_eq!("mbr-protective-shape", table.warnings[0].code);
+}
+
+#[test]
+fn strictness() {
+    use bootsector::Strictness;
+
+    let mut image = include_bytes!("test-data/labels.img").to_vec();
+    image[512 + 200] = 0x42;
+    let entry = image[446..462].to_vec();
+    image[462..478].copy_from_slice(&entry);
+    for byte in &mut image[446..462] {
+        *byte = 0;
+    }
+
+    let strict = Options::with_strictness(Strictness::Strict);
+    let table = bootsector::read_partition_table(&image[..], &strict).unwrap();
+    assert_eq!(bootsector::TableType::MBR, table.table_type);
+
+    let standard = Options::with_strictness(Strictness::Standard);
+    let table = bootsector::read_partition_table(&image[..], &standard).unwrap();
+    assert_eq!(bootsector::TableType::MBR, table.table_type);
+
+    let permissive = Options::with_strictness(Strictness::Permissive);
+    let table = bootsector::read_partition_table(&image[..], &permissive).unwrap();
+    assert_eq!(bootsector::TableType::GPT, table.table_type);
+    assert_eq!(
+        vec!["mbr-protective-shape", "gpt-primary-tail"],
+        table.warnings.iter().map(|w| w.code).collect::<Vec<_>>()
+    );
+
+    let overridden = Options {
+        reserved: bootsector::Reserved::MustBeZero,
+        ..Options::with_strictness(Strictness::Permissive)
+    };
+    assert!(bootsector::read_partition_table(&image[..], &overridden).is_err());
+}
+
+#[test]
+fn truncated() {
+    // this image is cut off long before its backup header
+    let image = &include_bytes!("test-data/4t-gpt.img")[..];
+    let options = Options {
+        disk_len: Some(image.len() as u64),
+        ..Options::default()
+    };
+    match bootsector::read_partition_table(image, &options) {
+        Err(Error::Truncated { what, .. }) => assert_eq!("the backup gpt header", what),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn sized_reader() {
+    let image = &include_bytes!("test-data/4t-gpt.img")[..];
+    assert!(matches!(
+        bootsector::read_partition_table_sized(image, &Options::default()),
+        Err(Error::Truncated { .. })
+    ));
+
+    let image = &include_bytes!("test-data/labels.img")[..];
+    bootsector::read_partition_table_sized(image, &Options::default()).expect("whole");
+}
+
+#[test]
+fn boot_code() {
+    use bootsector::mbr::{bootstrap_code, identify_boot_code, BootCode};
+
+    let sector = |image: &[u8]| {
+        let mut sector = [0u8; 512];
+        sector.copy_from_slice(&image[..512]);
+        sector
+    };
+
+    let empty = sector(include_bytes!("test-data/fdisk-empty-mbr.img"));
+    assert_eq!(440, bootstrap_code(&empty).len());
+    assert_eq!(BootCode::Empty, identify_boot_code(&empty));
+
+    let raspi = sector(include_bytes!("test-data/mbr-ubuntu-raspi3-16.04.img"));
+    assert_eq!(0xfa, bootstrap_code(&raspi)[0]);
+    assert_eq!(BootCode::Unknown, identify_boot_code(&raspi));
+
+    let with = |at: usize, text: &[u8]| {
+        let mut code = raspi;
+        code[at..at + text.len()].copy_from_slice(text);
+        identify_boot_code(&code)
+    };
+    assert_eq!(
+        BootCode::Grub,
+        with(0x180, b"GRUB \0Geom\0Hard Disk\0Read\0 Error")
+    );
+    assert_eq!(BootCode::Lilo, with(6, b"LILO"));
+    let windows = b"Invalid partition table\0Error loading operating system\0";
+    assert_eq!(BootCode::WindowsNt, with(0x160, windows));
+    let mut vista = raspi;
+    vista[0x100..0x104].copy_from_slice(b"TCPA");
+    vista[0x163..0x163 + windows.len()].copy_from_slice(windows);
+    assert_eq!(BootCode::WindowsVista, identify_boot_code(&vista));
+}
+
+#[test]
+fn table_boot_code() {
+    use bootsector::mbr::BootCode;
+
+    let raspi = &include_bytes!("test-data/mbr-ubuntu-raspi3-16.04.img")[..];
+    let table = bootsector::read_partition_table(raspi, &Options::default()).unwrap();
+    assert_eq!(Some(BootCode::Unknown), table.boot_code);
+    assert_eq!(None, table.boot_code_bytes);
+
+    let options = Options {
+        keep_boot_code: true,
+        ..Options::default()
+    };
+    let table = bootsector::read_partition_table(raspi, &options).unwrap();
+    assert_eq!(Some(&raspi[..440]), table.boot_code_bytes.as_deref());
+
+    let gpt = &include_bytes!("test-data/labels.img")[..];
+    let table = bootsector::read_partition_table(gpt, &options).unwrap();
+    assert_eq!(Some(BootCode::Empty), table.boot_code);
+    assert_eq!(Some(&[0u8; 440][..]), table.boot_code_bytes.as_deref());
+}
+
+#[test]
+fn hidden_types() {
+    let mut disc = vec![0u8; 1024 * 1024];
+    for (id, type_code) in [0x1cu8, 0x0c].iter().enumerate() {
+        let entry = &mut disc[446 + 16 * id..446 + 16 * (id + 1)];
+        entry[4] = *type_code;
+        entry[8..12].copy_from_slice(&(64 + 512 * id as u32).to_le_bytes());
+        entry[12..16].copy_from_slice(&256u32.to_le_bytes());
+    }
+    disc[510..512].copy_from_slice(&[0x55, 0xAA]);
+
+    let parts = list_partitions(&disc[..], &Options::default()).unwrap();
+    assert!(bootsector::mbr::is_hidden(&parts[0]));
+    assert!(!bootsector::mbr::is_hidden(&parts[1]));
+    assert_eq!(0x0c, bootsector::mbr::unhidden_type(0x1c));
+    assert_eq!(0x07, bootsector::mbr::unhidden_type(0x17));
+    assert!(!bootsector::mbr::is_hidden_type(0x83));
+}
+
+#[test]
+fn identities() {
+    use bootsector::{read_partition_table, Identity};
+    use std::collections::{BTreeSet, HashMap};
+
+    let gpt = read_partition_table(
+        &include_bytes!("test-data/labels.img")[..],
+        &Options::default(),
+    )
+    .unwrap();
+    let by_identity: HashMap<Identity, &Partition> = gpt
+        .partitions
+        .iter()
+        .map(|part| (gpt.identity(part), part))
+        .collect();
+    assert_eq!(gpt.partitions.len(), by_identity.len());
+
+    let rescan = read_partition_table(
+        &include_bytes!("test-data/labels.img")[..],
+        &Options::default(),
+    )
+    .unwrap();
+    for part in &rescan.partitions {
+        assert_eq!(part, by_identity[&rescan.identity(part)]);
+    }
+
+    let mbr = read_partition_table(
+        &include_bytes!("test-data/tiny.img")[..],
+        &Options::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        Identity::MBR {
+            disk_signature: mbr.disk_signature,
+            id: 0,
+            first_byte: mbr.partitions[0].first_byte,
+        },
+        mbr.identity(&mbr.partitions[0])
+    );
+
+    let sorted: BTreeSet<&Partition> = gpt.partitions.iter().rev().collect();
+    assert!(sorted.iter().zip(&gpt.partitions).all(|(a, b)| *a == b));
+}
+
+#[test]
+fn accessors() {
+    let gpt = bootsector::list_partitions(
+        &include_bytes!("test-data/labels.img")[..],
+        &Options::default(),
+    )
+    .unwrap();
+    assert_eq!(36, gpt[0].type_display().len());
+    assert!(gpt.iter().any(|part| part.name().is_some()));
+
+    let mbr = bootsector::list_partitions(
+        &include_bytes!("test-data/tiny.img")[..],
+        &Options::default(),
+    )
+    .unwrap();
+    let part = &mbr[0];
+    assert_eq!(None, part.name());
+    match part.attributes {
+        Attributes::MBR {
+            bootable,
+            type_code,
+        } => {
+            assert_eq!(bootable, part.is_bootable());
+            assert_eq!(format!("0x{:02x}", type_code), part.type_display());
+        }
+        _ => panic!("expected MBR"),
+    }
+}
@@ -0,0 +1,80 @@
+extern crate bootsector;
+
+use bootsector::{iter_partitions, list_partitions, read_partition_table, Options};
+
+fn entry(sector: &mut [u8], slot: usize, type_code: u8, first_lba: u32, sectors: u32) {
+    let entry = &mut sector[446 + slot * 16..446 + (slot + 1) * 16];
+    entry[4] = type_code;
+    entry[8..12].copy_from_slice(&first_lba.to_le_bytes());
+    entry[12..16].copy_from_slice(&sectors.to_le_bytes());
+}
+
+/// A 1MiB disc, with a Minix partition from sector 64, holding three subpartitions, one
+/// of which isn't Minix, and one bogus entry outside the partition.
+fn image() -> Vec<u8> {
+    let mut image = vec![0u8; 1024 * 1024];
+    entry(&mut image[..512], 0, 0x81, 64, 1984);
+    entry(&mut image[..512], 1, 0x0c, 1, 63);
+    image[510..512].copy_from_slice(&[0x55, 0xAA]);
+
+    let sub = &mut image[64 * 512..65 * 512];
+    entry(sub, 0, 0x81, 72, 512);
+    entry(sub, 1, 0x81, 584, 512);
+    entry(sub, 2, 0x83, 1096, 512);
+    entry(sub, 3, 0x81, 4000, 8);
+    sub[510..512].copy_from_slice(&[0x55, 0xAA]);
+    image
+}
+
+#[test]
+fn subpartitions() {
+    let image = image();
+
+    let plain = list_partitions(&image[..], &Options::default()).unwrap();
+    assert_eq!(2, plain.len());
+
+    let subs = bootsector::minix::read_subpartitions(&image[..], &plain[0]).unwrap();
+    assert_eq!(
+        vec![(0, 72 * 512), (1, 584 * 512)],
+        subs.iter()
+            .map(|part| (part.id, part.first_byte))
+            .collect::<Vec<_>>()
+    );
+    assert!(bootsector::minix::read_subpartitions(&image[..], &plain[1])
+        .unwrap()
+        .is_empty());
+
+    let options = Options {
+        minix_subpartitions: true,
+        ..Options::default()
+    };
+    let table = read_partition_table(&image[..], &options).unwrap();
+    assert_eq!(
+        vec![(0, 64 * 512), (1, 512), (4, 72 * 512), (5, 584 * 512)],
+        table
+            .partitions
+            .iter()
+            .map(|part| (part.id, part.first_byte))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(2, table.mbr_partitions.len());
+    assert_eq!(Some(2), table.free_slots());
+
+    let lazy: Vec<_> = iter_partitions(&image[..], &options)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(table.partitions, lazy);
+}
+
+#[test]
+fn not_a_subpartition_table() {
+    let mut image = image();
+    // the first entry must be Minix
+    image[64 * 512 + 446 + 4] = 0x83;
+
+    let options = Options {
+        minix_subpartitions: true,
+        ..Options::default()
+    };
+    assert_eq!(2, list_partitions(&image[..], &options).unwrap().len());
+}
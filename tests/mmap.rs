@@ -0,0 +1,24 @@
+#![cfg(all(feature = "mmap", unix))]
+
+extern crate bootsector;
+
+use std::io::Write;
+
+use bootsector::mmap::MappedFile;
+use bootsector::read_partition_table_sized;
+
+#[test]
+fn labels() {
+    let path = std::env::temp_dir().join(format!("bootsector-mmap-{}.img", std::process::id()));
+    std::fs::File::create(&path)
+        .unwrap()
+        .write_all(include_bytes!("test-data/labels.img"))
+        .unwrap();
+
+    let image = MappedFile::open(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(10 * 1024 * 1024, image.len());
+    let table = read_partition_table_sized(&image, &Default::default()).unwrap();
+    assert_eq!(7, table.partitions.len());
+}
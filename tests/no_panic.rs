@@ -0,0 +1,117 @@
+extern crate bootsector;
+
+mod common;
+
+use bootsector::{list_partitions, Options, SectorSize};
+/// A tiny, deterministic xorshift, so failures are reproducible without a dependency.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, max: usize) -> usize {
+        (self.next() % max as u64) as usize
+    }
+}
+
+fn all_options() -> Vec<Options> {
+    [
+        None,
+        Some(0),
+        Some(1),
+        Some(512),
+        Some(4096),
+        Some(u16::MAX),
+    ]
+    .iter()
+    .map(|size| {
+        let mut options = Options::default();
+        if let Some(size) = size {
+            options.sector_size = SectorSize::Known(*size);
+        }
+        options
+    })
+    .collect()
+}
+
+#[test]
+fn mutated_fixtures_never_panic() {
+    let fixtures: &[&[u8]] = &[
+        include_bytes!("test-data/4t-gpt.img"),
+        include_bytes!("test-data/fdisk-1m-part.img"),
+        include_bytes!("test-data/fdisk-empty-gpt.img"),
+        include_bytes!("test-data/fdisk-empty-mbr.img"),
+        include_bytes!("test-data/mbr-ubuntu-raspi3-16.04.img"),
+        include_bytes!("test-data/pirroman-short-header.img"),
+        include_bytes!("test-data/tiny.img"),
+    ];
+
+    let options = all_options();
+    let mut rng = Rng(0x5eed_b007_5ec7_0125);
+
+    for fixture in fixtures {
+        // mutations are focussed on the MBR, the primary header, and the start of the table
+        let hot = fixture.len().min(3 * 512);
+        for _ in 0..2_000 {
+            let mut image = fixture.to_vec();
+            for _ in 0..1 + rng.below(8) {
+                let pos = rng.below(hot);
+                image[pos] = rng.next() as u8;
+            }
+            if image.len() >= 1024 && 0 == rng.below(2) {
+                common::fix_header_crc(&mut image);
+            }
+            for options in &options {
+                let _ = list_partitions(&image[..], options);
+            }
+        }
+    }
+}
+
+#[test]
+fn ten_mebibyte_headers_never_panic() {
+    let mut image = include_bytes!("test-data/labels.img").to_vec();
+    assert_eq!(10 * 1024 * 1024, image.len());
+
+    let options = all_options();
+    let mut rng = Rng(0xdead_beef_cafe_f00d);
+
+    // every interesting field in the protective MBR and the primary header
+    let fields: &[(usize, usize)] = &[
+        (446, 16),
+        (512 + 0x08, 4),
+        (512 + 0x0c, 4),
+        (512 + 0x14, 4),
+        (512 + 0x18, 8),
+        (512 + 0x28, 8),
+        (512 + 0x30, 8),
+        (512 + 0x48, 8),
+        (512 + 0x50, 4),
+        (512 + 0x54, 4),
+        (1024, 128),
+    ];
+
+    for _ in 0..500 {
+        let (start, len) = fields[rng.below(fields.len())];
+        let original = image[start..start + len].to_vec();
+        for byte in &mut image[start..start + len] {
+            *byte = match rng.below(3) {
+                0 => 0,
+                1 => 0xff,
+                _ => rng.next() as u8,
+            };
+        }
+        common::fix_header_crc(&mut image);
+
+        for options in &options {
+            let _ = list_partitions(&image[..], options);
+        }
+
+        image[start..start + len].copy_from_slice(&original);
+    }
+}
@@ -0,0 +1,71 @@
+#![cfg(feature = "qcow2")]
+
+extern crate bootsector;
+
+use bootsector::pio::ReadAt;
+use bootsector::qcow2::Qcow2;
+use bootsector::read_partition_table_sized;
+
+const CLUSTER_BITS: u32 = 16;
+const CLUSTER: usize = 1 << CLUSTER_BITS;
+
+/// A version 2 image: header, L1 table, a single L2 table, then only the non-zero clusters.
+fn qcow2(raw: &[u8]) -> Vec<u8> {
+    let l1_offset = CLUSTER as u64;
+    let l2_offset = 2 * CLUSTER as u64;
+
+    let mut image = vec![0u8; 3 * CLUSTER];
+    image[..4].copy_from_slice(b"QFI\xfb");
+    image[4..8].copy_from_slice(&2u32.to_be_bytes());
+    image[20..24].copy_from_slice(&CLUSTER_BITS.to_be_bytes());
+    image[24..32].copy_from_slice(&(raw.len() as u64).to_be_bytes());
+    image[36..40].copy_from_slice(&1u32.to_be_bytes());
+    image[40..48].copy_from_slice(&l1_offset.to_be_bytes());
+
+    let l1 = l1_offset as usize;
+    image[l1..l1 + 8].copy_from_slice(&(l2_offset | 1 << 63).to_be_bytes());
+
+    for (i, cluster) in raw.chunks(CLUSTER).enumerate() {
+        if cluster.iter().all(|&b| 0 == b) {
+            continue;
+        }
+        let host = image.len() as u64;
+        image.extend_from_slice(cluster);
+        image.resize(image.len() + CLUSTER - cluster.len(), 0);
+        let entry = l2_offset as usize + i * 8;
+        image[entry..entry + 8].copy_from_slice(&(host | 1 << 63).to_be_bytes());
+    }
+
+    image
+}
+
+#[test]
+fn labels() {
+    let raw = &include_bytes!("test-data/labels.img")[..];
+    let image = qcow2(raw);
+    assert!(image.len() < raw.len());
+
+    let disc = Qcow2::open(&image[..]).unwrap();
+    assert_eq!(raw.len() as u64, disc.disk_len());
+
+    let mut read = vec![0u8; raw.len()];
+    disc.read_exact_at(0, &mut read).unwrap();
+    assert_eq!(raw, &read[..]);
+
+    let table = read_partition_table_sized(&disc, &Default::default()).unwrap();
+    assert_eq!(7, table.partitions.len());
+}
+
+#[test]
+fn not_qcow2() {
+    let raw = &include_bytes!("test-data/labels.img")[..];
+    assert!(matches!(Qcow2::open(raw), Err(bootsector::Error::NotFound)));
+}
+
+#[test]
+fn backing_file() {
+    let raw = &include_bytes!("test-data/labels.img")[..];
+    let mut image = qcow2(raw);
+    image[8..16].copy_from_slice(&512u64.to_be_bytes());
+    assert!(Qcow2::open(&image[..]).is_err());
+}
@@ -0,0 +1,105 @@
+extern crate bootsector;
+
+use bootsector::io::{ReadAt, ReadFn};
+use bootsector::{list_partitions, Error};
+
+fn reader(image: &'static [u8]) -> ReadFn<impl Fn(u64, &mut [u8]) -> Result<(), Error>> {
+    ReadFn(move |pos: u64, buf: &mut [u8]| {
+        let start = pos as usize;
+        let data = image
+            .get(start..start + buf.len())
+            .ok_or(Error::UnexpectedEof)?;
+        buf.copy_from_slice(data);
+        Ok(())
+    })
+}
+
+#[test]
+fn labels() {
+    let partitions = list_partitions(
+        reader(include_bytes!("test-data/labels.img")),
+        &Default::default(),
+    )
+    .unwrap();
+    assert_eq!(7, partitions.len());
+}
+
+#[test]
+fn errors_are_passed_through() {
+    let image = &include_bytes!("test-data/labels.img")[..1024];
+    let err = list_partitions(reader(image), &Default::default()).unwrap_err();
+    match &err {
+        Error::Read { what, pos, .. } => {
+            assert_eq!("the gpt partition entries", *what);
+            assert_eq!(1024, *pos);
+        }
+        other => panic!("unexpected: {:?}", other),
+    }
+    assert!(err
+        .to_string()
+        .starts_with("reading the gpt partition entries at byte 1024: "));
+}
+
+#[test]
+fn boxed() {
+    let image: &'static [u8] = include_bytes!("test-data/labels.img");
+    let readers: Vec<Box<dyn ReadAt>> = vec![Box::new(image), Box::new(reader(image))];
+    for disc in readers {
+        assert_eq!(
+            7,
+            list_partitions(&*disc, &Default::default()).unwrap().len()
+        );
+        assert_eq!(7, list_partitions(disc, &Default::default()).unwrap().len());
+    }
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn io_error_kinds() {
+    use std::io;
+
+    let blank = [0u8; 1024];
+    let err: io::Error = list_partitions(&blank[..], &Default::default())
+        .unwrap_err()
+        .into();
+    assert_eq!(io::ErrorKind::NotFound, err.kind());
+
+    let image = &include_bytes!("test-data/labels.img")[..1024];
+    let err: io::Error = list_partitions(reader(image), &Default::default())
+        .unwrap_err()
+        .into();
+    assert_eq!(io::ErrorKind::UnexpectedEof, err.kind());
+}
+
+#[test]
+fn stats() {
+    use std::cell::Cell;
+
+    let image: &'static [u8] = include_bytes!("test-data/labels.img");
+    let reads = Cell::new(0);
+    let bytes = Cell::new(0u64);
+    let counted = ReadFn(|pos: u64, buf: &mut [u8]| {
+        reads.set(reads.get() + 1);
+        bytes.set(bytes.get() + buf.len() as u64);
+        buf.copy_from_slice(&image[pos as usize..pos as usize + buf.len()]);
+        Ok(())
+    });
+
+    let options = bootsector::Options {
+        probe_labels: true,
+        ..Default::default()
+    };
+    let table = bootsector::read_partition_table(counted, &options).unwrap();
+    assert_eq!(reads.get(), table.stats.reads);
+    assert_eq!(bytes.get(), table.stats.bytes_read);
+    assert_eq!(
+        vec![
+            "mbr",
+            "gpt sector size",
+            "gpt header",
+            "gpt entries",
+            "labels"
+        ],
+        table.stats.probes
+    );
+}
@@ -0,0 +1,78 @@
+extern crate bootsector;
+
+use bootsector::edit::GptEditor;
+use bootsector::gpt::{self, CreateOptions};
+use bootsector::recover::{find_deleted_gpt_entries, find_gpt_headers};
+use bootsector::verify::GptCopy;
+
+const MIB: u64 = 1024 * 1024;
+
+fn gpt_disc(len: u64) -> Vec<u8> {
+    let mut image = vec![0u8; len as usize];
+    gpt::create(&mut image[..], len, 512, &CreateOptions::new([7; 16])).unwrap();
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    editor.add(MIB, MIB, [1; 16], [2; 16]).unwrap();
+    editor.commit(&mut image[..]).unwrap();
+    image
+}
+
+#[test]
+fn wiped_primary() {
+    let mut image = gpt_disc(4 * MIB);
+    let found = find_gpt_headers(&image[..], 0, 4 * MIB).unwrap();
+    assert_eq!(
+        vec![512, 4 * MIB - 512],
+        found.iter().map(|f| f.byte_offset).collect::<Vec<_>>()
+    );
+
+    // e.g. `dd` of a small image over the start of the disc
+    for byte in &mut image[..64 * 1024] {
+        *byte = 0xa5;
+    }
+    let found = find_gpt_headers(&image[..], 0, 4 * MIB).unwrap();
+    assert_eq!(1, found.len());
+    assert_eq!(4 * MIB - 512, found[0].byte_offset);
+    assert_eq!(Some(512), found[0].sector_size());
+    assert!(found[0].entries_valid);
+
+    let parts = gpt::read_entries(&image[..], &found[0].header, 512).unwrap();
+    assert_eq!(MIB, parts[0].first_byte);
+}
+
+#[test]
+fn moved_image() {
+    // a disc image stored in a file, at an odd offset, on a bigger disc
+    let inner = gpt_disc(3 * MIB);
+    let mut image = vec![0u8; 8 * MIB as usize];
+    let at = 3 * MIB as usize + 1536;
+    image[at..at + inner.len()].copy_from_slice(&inner);
+
+    let found = find_gpt_headers(&image[..], MIB, 7 * MIB).unwrap();
+    assert_eq!(2, found.len());
+    assert_eq!(at as u64 + 512, found[0].byte_offset);
+    assert_eq!(1, found[0].header.my_lba);
+    assert_eq!(None, found[0].sector_size());
+    assert!(!found[0].entries_valid);
+}
+
+#[test]
+fn deleted_from_primary() {
+    let mut image = gpt_disc(4 * MIB);
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    editor.add(2 * MIB, MIB / 2, [3; 16], [4; 16]).unwrap();
+    editor.commit(&mut image[..]).unwrap();
+    assert!(find_deleted_gpt_entries(&image[..], 4 * MIB, 512)
+        .unwrap()
+        .is_empty());
+
+    // a tool which only rewrote the primary entry array
+    for byte in &mut image[1024 + 128..1024 + 256] {
+        *byte = 0;
+    }
+
+    let deleted = find_deleted_gpt_entries(&image[..], 4 * MIB, 512).unwrap();
+    assert_eq!(1, deleted.len());
+    assert_eq!(GptCopy::Backup, deleted[0].found_in);
+    assert_eq!(1, deleted[0].partition.id);
+    assert_eq!(2 * MIB, deleted[0].partition.first_byte);
+}
@@ -0,0 +1,56 @@
+extern crate bootsector;
+
+use bootsector::edit::GptEditor;
+use bootsector::gpt::{self, CreateOptions};
+use bootsector::{read_partition_table, Options, TableType};
+
+const MIB: u64 = 1024 * 1024;
+
+fn gpt_disc(image: &mut [u8], partitions: &[(u64, u64)]) {
+    let len = image.len() as u64;
+    gpt::create(&mut image[..], len, 512, &CreateOptions::new([7; 16])).unwrap();
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    for (id, &(first_byte, len)) in partitions.iter().enumerate() {
+        let guid = [id as u8 + 1; 16];
+        editor.add(first_byte, len, guid, guid).unwrap();
+    }
+    editor.commit(&mut image[..]).unwrap();
+}
+
+/// An 8MiB disc, whose first partition holds a disc image with a partition of its own,
+/// and whose second partition is empty.
+fn image() -> Vec<u8> {
+    let mut image = vec![0u8; 8 * MIB as usize];
+    gpt_disc(&mut image, &[(MIB, 4 * MIB), (5 * MIB, MIB)]);
+    gpt_disc(
+        &mut image[MIB as usize..5 * MIB as usize],
+        &[(MIB, 2 * MIB)],
+    );
+    image
+}
+
+#[test]
+fn nested_disc() {
+    let image = image();
+
+    let plain = read_partition_table(&image[..], &Options::default()).unwrap();
+    assert_eq!(2, plain.partitions.len());
+    assert!(plain.nested.is_empty());
+
+    let options = Options {
+        recurse: 2,
+        ..Options::default()
+    };
+    let table = read_partition_table(&image[..], &options).unwrap();
+    assert_eq!(plain.partitions, table.partitions);
+    assert_eq!(1, table.nested.len());
+
+    let nested = &table.nested[0];
+    assert_eq!(0, nested.parent);
+    assert_eq!(TableType::GPT, nested.table.table_type);
+    assert_eq!(1, nested.table.partitions.len());
+    assert_eq!(2 * MIB, nested.table.partitions[0].first_byte);
+    assert_eq!(2 * MIB, nested.table.partitions[0].len);
+    assert_eq!(Some(34), nested.table.first_usable_lba());
+    assert!(nested.table.nested.is_empty());
+}
@@ -0,0 +1,114 @@
+extern crate bootsector;
+
+use bootsector::edit::GptEditor;
+use bootsector::gpt::{self, CreateOptions};
+use bootsector::render::{render_table, write_layout, LayoutOptions};
+use bootsector::{read_partition_table, Attributes, Options, Partition};
+
+const MIB: u64 = 1024 * 1024;
+
+#[test]
+fn display() {
+    let mut part = Partition {
+        id: 0,
+        first_byte: MIB,
+        len: 3 * MIB / 2,
+        attributes: Attributes::MBR {
+            bootable: true,
+            type_code: 0x0c,
+        },
+        label: Some("BOOT".to_string()),
+    };
+    assert_eq!(
+        "0: 1.5 MiB at byte 1048576, 0x0c, bootable, label \"BOOT\"",
+        part.to_string()
+    );
+
+    part.len = 1023;
+    part.label = None;
+    assert_eq!(
+        "0: 1023 B at byte 1048576, 0x0c, bootable",
+        part.to_string()
+    );
+
+    // rounds up into the next unit
+    part.len = MIB - 1;
+    assert_eq!(
+        "0: 1.0 MiB at byte 1048576, 0x0c, bootable",
+        part.to_string()
+    );
+}
+
+#[test]
+fn table() {
+    let mut image = vec![0u8; 8 * MIB as usize];
+    gpt::create(&mut image[..], 8 * MIB, 512, &CreateOptions::new([7; 16])).unwrap();
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    let esp = [
+        0x28, 0x73, 0x2a, 0xc1, 0x1f, 0xf8, 0xd2, 0x11, 0xba, 0x4b, 0x00, 0xa0, 0xc9, 0x3e, 0xc9,
+        0x3b,
+    ];
+    let id = editor.add(MIB, MIB, esp, [1; 16]).unwrap();
+    editor.set_name(id, "EFI").unwrap();
+    editor.add(2 * MIB, 5 * MIB, [0x42; 16], [2; 16]).unwrap();
+    editor.commit(&mut image[..]).unwrap();
+
+    let table = read_partition_table(&image[..], &Options::default()).unwrap();
+    assert_eq!(
+        "GPT, 512 byte sectors, disk guid 07070707-0707-0707-0707-070707070707
+ID Boot Start   End Sectors    Size Type                                 Name
+ 0       2048  4095    2048 1.0 MiB C12A7328-F81F-11D2-BA4B-00A0C93EC93B EFI
+ 1       4096 14335   10240 5.0 MiB 42424242-4242-4242-4242-424242424242
+",
+        render_table(&table)
+    );
+}
+
+#[test]
+fn layout() {
+    let mut image = vec![0u8; 8 * MIB as usize];
+    gpt::create(&mut image[..], 8 * MIB, 512, &CreateOptions::new([7; 16])).unwrap();
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    editor.add(MIB, MIB, [0x42; 16], [1; 16]).unwrap();
+    editor.add(2 * MIB, 5 * MIB, [0x43; 16], [2; 16]).unwrap();
+    editor.commit(&mut image[..]).unwrap();
+
+    let mut table = read_partition_table(&image[..], &Options::default()).unwrap();
+    let options = LayoutOptions {
+        width: 8,
+        unicode: false,
+    };
+    let mut out = String::new();
+    write_layout(&mut out, &table, 8 * MIB, &options).unwrap();
+    assert_eq!(
+        "|.011111.|
+=: 0..17408 (17.0 KiB), MBR, GPT header and entries
+.: 17408..1048576 (1007.0 KiB), free
+0: 1048576..2097152 (1.0 MiB), 42424242-4242-4242-4242-424242424242
+1: 2097152..7340032 (5.0 MiB), 43434343-4343-4343-4343-434343434343
+.: 7340032..8371712 (1007.5 KiB), free
+=: 8371712..8388608 (16.5 KiB), backup GPT entries and header
+",
+        out
+    );
+
+    table.partitions[0].len = 2 * MIB;
+    let mut out = String::new();
+    write_layout(&mut out, &table, 8 * MIB, &options).unwrap();
+    assert!(out.starts_with("|.0!1111.|\n"), "{}", out);
+    assert!(out.ends_with("!: 0 and 1 overlap, 2097152..3145728 (1.0 MiB)\n"));
+}
+
+#[test]
+fn sizes() {
+    use bootsector::render::ByteSize;
+
+    let both = |len| (format!("{}", ByteSize(len)), format!("{:#}", ByteSize(len)));
+    let pair = |a: &str, b: &str| (a.to_string(), b.to_string());
+
+    assert_eq!(pair("0 B", "0 B"), both(0));
+    assert_eq!(pair("1000 B", "1.0 kB"), both(1000));
+    assert_eq!(pair("1.0 KiB", "1.0 kB"), both(1024));
+    assert_eq!(pair("931.3 GiB", "1.0 TB"), both(1_000_000_000_000));
+    assert_eq!(pair("16.0 EiB", "18.4 EB"), both(u64::MAX));
+}
@@ -0,0 +1,155 @@
+extern crate bootsector;
+
+use bootsector::edit::GptEditor;
+use bootsector::gpt::{self, CreateOptions};
+use bootsector::verify::{verify, Severity};
+use bootsector::{list_partitions, read_partition_table, Options};
+
+const MIB: u64 = 1024 * 1024;
+
+#[test]
+fn backup_after_growing() {
+    let mut image = vec![0u8; 4 * MIB as usize];
+    gpt::create(&mut image[..], 4 * MIB, 512, &CreateOptions::new([7; 16])).unwrap();
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    editor.add(MIB, MIB, [1; 16], [2; 16]).unwrap();
+    editor.commit(&mut image[..]).unwrap();
+
+    image.resize(8 * MIB as usize, 0);
+    let findings = verify(&image[..], Some(8 * MIB), &Options::default()).unwrap();
+    assert!(findings.iter().any(|f| "gpt-backup-location" == f.code));
+
+    gpt::repair_backup(&mut image[..], 8 * MIB, 512).unwrap();
+
+    let findings = verify(&image[..], Some(8 * MIB), &Options::default()).unwrap();
+    assert!(
+        findings
+            .iter()
+            .all(|f| Severity::Error != f.severity && "gpt-backup-location" != f.code),
+        "{:?}",
+        findings
+    );
+}
+
+#[test]
+fn primary_from_backup() {
+    let original = &include_bytes!("test-data/labels.img")[..];
+    let expected = list_partitions(original, &Options::default()).unwrap();
+
+    let mut image = original.to_vec();
+    for byte in &mut image[512..1024] {
+        *byte = 0;
+    }
+    assert!(list_partitions(&image[..], &Options::default()).is_err());
+
+    let len = image.len() as u64;
+    gpt::restore_primary(&mut image[..], len, 512).unwrap();
+
+    assert_eq!(
+        expected,
+        list_partitions(&image[..], &Options::default()).unwrap()
+    );
+}
+
+#[test]
+fn no_backup_to_restore_from() {
+    let mut image = include_bytes!("test-data/fdisk-empty-mbr.img").to_vec();
+    let len = image.len() as u64;
+    assert!(gpt::restore_primary(&mut image[..], len, 512).is_err());
+}
+
+#[test]
+fn wipe_table() {
+    let mut image = include_bytes!("test-data/labels.img").to_vec();
+    let len = image.len() as u64;
+    let parts = list_partitions(&image[..], &Options::default()).unwrap();
+    let last = parts.iter().map(|p| p.first_byte + p.len).max().unwrap() as usize;
+    image[MIB as usize] = 0x42;
+    image[last - 1] = 0x69;
+
+    gpt::wipe_table(&mut image[..], len, 512).unwrap();
+
+    assert!(list_partitions(&image[..], &Options::default()).is_err());
+    assert!(image[..34 * 512].iter().all(|&b| 0 == b));
+    assert!(image[image.len() - 33 * 512..].iter().all(|&b| 0 == b));
+    assert_eq!(0x42, image[MIB as usize]);
+    assert_eq!(0x69, image[last - 1]);
+}
+
+#[test]
+fn read_backup_directly() {
+    let mut image = vec![0u8; 4 * MIB as usize];
+    gpt::create(&mut image[..], 4 * MIB, 512, &CreateOptions::new([7; 16])).unwrap();
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    editor.add(MIB, MIB, [1; 16], [2; 16]).unwrap();
+    editor.commit(&mut image[..]).unwrap();
+
+    let last = 4 * MIB - 512;
+    let backup = gpt::read_header_at(&image[..], last, 512).unwrap();
+    assert_eq!(last / 512, backup.my_lba);
+    assert_eq!(1, backup.alternate_lba);
+    assert_eq!(
+        list_partitions(&image[..], &Options::default()).unwrap(),
+        gpt::read_entries(&image[..], &backup, 512).unwrap()
+    );
+
+    let primary = gpt::read_header_at(&image[..], 512, 512).unwrap();
+    assert_eq!(backup.alternate_lba, primary.my_lba);
+
+    // a copy of the backup header elsewhere isn't where it claims to be
+    let (rest, last_sector) = image.split_at_mut(last as usize);
+    rest[MIB as usize..MIB as usize + 512].copy_from_slice(last_sector);
+    assert!(gpt::read_header_at(&image[..], MIB, 512).is_err());
+    assert!(gpt::read_header_at(&image[..], last + 1, 512).is_err());
+}
+
+#[test]
+fn read_backup_after_overwrite() {
+    let mut image = vec![0u8; 4 * MIB as usize];
+    gpt::create(&mut image[..], 4 * MIB, 4096, &CreateOptions::new([7; 16])).unwrap();
+    let mut editor = GptEditor::open(&image[..], 4096).unwrap();
+    editor.add(MIB, MIB, [1; 16], [2; 16]).unwrap();
+    editor.commit(&mut image[..]).unwrap();
+    let expected = list_partitions(&image[..], &Options::default()).unwrap();
+
+    for byte in &mut image[..64 * 1024] {
+        *byte = 0x69;
+    }
+    assert!(list_partitions(&image[..], &Options::default()).is_err());
+    assert!(gpt::read_backup(&image[..], 4 * MIB, 512).is_err());
+
+    let (header, partitions) = gpt::read_backup(&image[..], 4 * MIB, 4096).unwrap();
+    assert_eq!(1, header.alternate_lba);
+    assert_eq!(expected, partitions);
+}
+
+#[test]
+fn search_offsets() {
+    let mut image = vec![0u8; 4 * MIB as usize];
+    gpt::create(&mut image[..], 4 * MIB, 512, &CreateOptions::new([7; 16])).unwrap();
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    editor.add(MIB, MIB, [1; 16], [2; 16]).unwrap();
+    editor.commit(&mut image[..]).unwrap();
+    let expected = list_partitions(&image[..], &Options::default()).unwrap();
+
+    // as if copied onto a bigger disc, and the start then overwritten
+    image.resize(8 * MIB as usize, 0);
+    for byte in &mut image[..64 * 1024] {
+        *byte = 0;
+    }
+    assert!(read_partition_table(&image[..], &Options::default()).is_err());
+
+    let options = Options {
+        gpt_search_offsets: vec![MIB, 4 * MIB - 512, 8 * MIB - 512],
+        ..Options::default()
+    };
+    let table = read_partition_table(&image[..], &options).unwrap();
+    assert_eq!(expected, table.partitions);
+    assert_eq!(512, table.sector_size);
+    assert_eq!(vec!["gpt-relocated"], codes(&table.warnings));
+    assert_eq!(4 * MIB / 512 - 1, table.gpt_header.unwrap().my_lba);
+}
+
+fn codes(findings: &[bootsector::verify::Finding]) -> Vec<&str> {
+    findings.iter().map(|f| f.code).collect()
+}
@@ -0,0 +1,91 @@
+#![cfg(feature = "std")]
+
+extern crate bootsector;
+
+use std::cell::Cell;
+use std::io;
+use std::time::Duration;
+
+use bootsector::io::RetryingReadAt;
+use bootsector::pio;
+use bootsector::read_partition_table;
+
+/// Fails every read until `failures` have failed, with `kind`.
+struct Flaky<'a> {
+    data: &'a [u8],
+    kind: io::ErrorKind,
+    failures: Cell<usize>,
+}
+
+impl<'a> Flaky<'a> {
+    fn new(failures: usize, kind: io::ErrorKind) -> Flaky<'a> {
+        Flaky {
+            data: &include_bytes!("test-data/labels.img")[..],
+            kind,
+            failures: Cell::new(failures),
+        }
+    }
+}
+
+impl<'a> pio::ReadAt for Flaky<'a> {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        if 0 != self.failures.get() {
+            self.failures.set(self.failures.get() - 1);
+            return Err(io::Error::new(self.kind, "flaky"));
+        }
+        pio::ReadAt::read_at(&self.data, pos, buf)
+    }
+}
+
+#[test]
+fn retries_transient_failures() {
+    assert!(
+        read_partition_table(Flaky::new(2, io::ErrorKind::Other), &Default::default()).is_err()
+    );
+
+    let disc = RetryingReadAt::new(
+        Flaky::new(2, io::ErrorKind::Other),
+        2,
+        Duration::from_millis(1),
+    );
+    assert_eq!(
+        7,
+        read_partition_table(&disc, &Default::default())
+            .unwrap()
+            .partitions
+            .len()
+    );
+
+    let disc = RetryingReadAt::new(
+        Flaky::new(3, io::ErrorKind::Other),
+        2,
+        Duration::from_millis(1),
+    );
+    assert!(read_partition_table(&disc, &Default::default()).is_err());
+}
+
+#[test]
+fn classifier() {
+    let disc = RetryingReadAt::new(
+        Flaky::new(1, io::ErrorKind::PermissionDenied),
+        2,
+        Duration::from_millis(1),
+    );
+    assert!(read_partition_table(&disc, &Default::default()).is_err());
+
+    let disc = RetryingReadAt::new(
+        Flaky::new(1, io::ErrorKind::Other),
+        2,
+        Duration::from_millis(1),
+    )
+    .retry_if(|e| io::ErrorKind::TimedOut == e.kind());
+    assert!(read_partition_table(&disc, &Default::default()).is_err());
+
+    let disc = RetryingReadAt::new(
+        Flaky::new(1, io::ErrorKind::TimedOut),
+        2,
+        Duration::from_millis(1),
+    )
+    .retry_if(|e| io::ErrorKind::TimedOut == e.kind());
+    assert!(read_partition_table(&disc, &Default::default()).is_ok());
+}
@@ -0,0 +1,96 @@
+#![cfg(feature = "scan")]
+
+extern crate bootsector;
+
+use std::path::Path;
+
+use bootsector::scan::{scan_dir, scan_many, Class};
+use bootsector::{Options, TableType};
+
+#[test]
+fn many_readers() {
+    let images: Vec<&[u8]> = vec![
+        include_bytes!("test-data/labels.img"),
+        &[0u8; 4096],
+        include_bytes!("test-data/fdisk-1m-part.img"),
+    ];
+    let tables = scan_many(images, &Options::default());
+    assert_eq!(3, tables.len());
+    assert_eq!(7, tables[0].as_ref().unwrap().partitions.len());
+    assert!(tables[1].is_err());
+    assert_eq!(TableType::GPT, tables[2].as_ref().unwrap().table_type);
+}
+
+#[test]
+fn many_paths() {
+    let paths = vec![
+        Path::new("tests/test-data/labels.img"),
+        Path::new("tests/test-data/missing.img"),
+    ];
+    let tables = scan_many(paths, &Options::default());
+    assert_eq!(7, tables[0].as_ref().unwrap().partitions.len());
+    assert!(tables[1].is_err());
+}
+
+#[test]
+fn directory() {
+    let report = scan_dir("tests/test-data", &Options::default()).unwrap();
+    assert_eq!(8, report.images.len());
+
+    let class = |name: &str| {
+        report
+            .images
+            .iter()
+            .find(|image| image.path.ends_with(name))
+            .unwrap()
+            .class()
+    };
+    assert_eq!(Class::GPT, class("fdisk-1m-part.img"));
+    assert_eq!(Class::MBR, class("fdisk-empty-mbr.img"));
+    // truncated, so the backup header is missing
+    assert_eq!(Class::Failed, class("4t-gpt.img"));
+
+    let summary = report.summary();
+    assert_eq!(
+        8,
+        summary.mbr + summary.gpt + summary.unpartitioned + summary.failed
+    );
+    assert_eq!(
+        report
+            .images
+            .iter()
+            .filter_map(|image| image.result.as_ref().ok())
+            .map(|table| table.partitions.len())
+            .sum::<usize>(),
+        summary.partitions
+    );
+}
+
+#[cfg(all(feature = "serde", feature = "serde_json"))]
+#[test]
+fn directory_json() {
+    let report = scan_dir("tests/test-data", &Options::default()).unwrap();
+    let json = serde_json::to_value(bootsector::json::Scan::from(&report)).unwrap();
+
+    let images = json["images"].as_array().unwrap();
+    assert_eq!(8, images.len());
+    let labels = images
+        .iter()
+        .find(|image| image["path"].as_str().unwrap().ends_with("labels.img"))
+        .unwrap();
+    assert_eq!("gpt", labels["class"]);
+    assert_eq!(7, labels["partitions"]);
+    assert!(labels.get("error").is_none());
+
+    let truncated = images
+        .iter()
+        .find(|image| image["path"].as_str().unwrap().ends_with("4t-gpt.img"))
+        .unwrap();
+    assert_eq!("failed", truncated["class"]);
+    assert!(truncated["error"].is_string());
+
+    assert_eq!(
+        report.summary().partitions as u64,
+        json["summary"]["partitions"]
+    );
+}
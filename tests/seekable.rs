@@ -0,0 +1,88 @@
+#![cfg(feature = "seekable")]
+
+extern crate bootsector;
+
+use std::cell::Cell;
+use std::io;
+
+use bootsector::pio::ReadAt;
+use bootsector::read_partition_table_sized;
+use bootsector::seekable::Seekable;
+
+const FRAME: usize = 300 * 1024;
+
+/// A very poor compressor: runs of a byte, as (length, byte) pairs.
+fn rle(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut rest = data;
+    while let Some(&b) = rest.first() {
+        let run = rest.iter().take(255).take_while(|&&c| c == b).count();
+        out.push(run as u8);
+        out.push(b);
+        rest = &rest[run..];
+    }
+    out
+}
+
+fn unrle(frame: &[u8], len: usize) -> io::Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(len);
+    for pair in frame.chunks_exact(2) {
+        out.extend(std::iter::repeat(pair[1]).take(usize::from(pair[0])));
+    }
+    Ok(out)
+}
+
+/// Frames, then the seek table in a skippable frame, with checksums.
+fn seekable(raw: &[u8]) -> Vec<u8> {
+    let mut image = Vec::new();
+    let mut table = Vec::new();
+    let frames = raw.chunks(FRAME).count() as u32;
+    for frame in raw.chunks(FRAME) {
+        let compressed = rle(frame);
+        image.extend_from_slice(&compressed);
+        table.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+        table.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        table.extend_from_slice(&0u32.to_le_bytes());
+    }
+
+    image.extend_from_slice(&0x184d_2a5eu32.to_le_bytes());
+    image.extend_from_slice(&(table.len() as u32 + 9).to_le_bytes());
+    image.extend_from_slice(&table);
+    image.extend_from_slice(&frames.to_le_bytes());
+    image.push(0x80);
+    image.extend_from_slice(&0x8f92_eab1u32.to_le_bytes());
+    image
+}
+
+#[test]
+fn zstd_seek_table() {
+    let raw = &include_bytes!("test-data/labels.img")[..];
+    let image = seekable(raw);
+    assert!(image.len() < raw.len());
+
+    let calls = Cell::new(0);
+    let disc = Seekable::zstd(&image[..], |frame: &[u8], len| {
+        calls.set(calls.get() + 1);
+        unrle(frame, len)
+    })
+    .unwrap();
+    assert_eq!(raw.len() as u64, disc.disk_len());
+    assert_eq!((raw.len() + FRAME - 1) / FRAME, disc.frames().len());
+
+    let table = read_partition_table_sized(&disc, &Default::default()).unwrap();
+    assert_eq!(7, table.partitions.len());
+    assert!(calls.get() < disc.frames().len());
+
+    let mut read = vec![0u8; raw.len()];
+    disc.read_exact_at(0, &mut read).unwrap();
+    assert_eq!(raw, &read[..]);
+}
+
+#[test]
+fn not_seekable() {
+    let raw = &include_bytes!("test-data/labels.img")[..];
+    assert!(matches!(
+        Seekable::zstd(raw, unrle),
+        Err(bootsector::Error::NotFound)
+    ));
+}
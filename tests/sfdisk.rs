@@ -0,0 +1,79 @@
+extern crate bootsector;
+
+use bootsector::{read_partition_table, sfdisk, Attributes, Options, TableType};
+
+#[test]
+fn gpt_round_trip() {
+    let table = read_partition_table(
+        &include_bytes!("test-data/labels.img")[..],
+        &Options::default(),
+    )
+    .expect("success");
+
+    let dump = sfdisk::to_dump(&table, "/dev/sda");
+    assert!(dump.starts_with("label: gpt\n"));
+    assert!(dump
+        .contains("/dev/sda1 : start=2048, size=2049, type=0FC63DAF-8483-4772-8E79-3D69D8477DE4"));
+
+    let parsed = sfdisk::parse(&dump).expect("parse");
+    assert_eq!(table.partitions, parsed.partitions);
+    assert_eq!(table.disk_guid, parsed.disk_guid);
+}
+
+#[test]
+fn mbr_round_trip() {
+    let table = read_partition_table(
+        &include_bytes!("test-data/mbr-ubuntu-raspi3-16.04.img")[..],
+        &Options::default(),
+    )
+    .expect("success");
+
+    let dump = sfdisk::to_dump(&table, "/dev/mmcblk0");
+    assert!(dump.contains("/dev/mmcblk0p1 : start=8192, size=262144, type=c, bootable\n"));
+
+    let parsed = sfdisk::parse(&dump).expect("parse");
+    // the dump doesn't record how the table was read, or the boot code
+    let table = bootsector::PartitionTable {
+        stats: Default::default(),
+        boot_code: None,
+        ..table
+    };
+    assert_eq!(table, parsed);
+}
+
+#[test]
+fn util_linux_output() {
+    let dump = r#"label: gpt
+label-id: 3C5E1B9A-2A5F-4B8E-9C44-2F0B5E6A7D11
+device: /dev/vda
+unit: sectors
+first-lba: 34
+last-lba: 41943006
+sector-size: 512
+
+/dev/vda1 : start=        2048, size=     1048576, type=C12A7328-F81F-11D2-BA4B-00A0C93EC93B, uuid=0B0A5C0E-1D3A-4C55-8E4E-7D25D3F9C0F1, name="EFI \"system\", partition", attrs="RequiredPartition GUID:63"
+/dev/vda3 : start=     1050624, size=    40890335, type=0FC63DAF-8483-4772-8E79-3D69D8477DE4, uuid=6E2A1F53-9B0C-4F27-A3D8-5C1E7B4A9D20
+"#;
+
+    let table = sfdisk::parse(dump).expect("parse");
+    assert_eq!(TableType::GPT, table.table_type);
+    assert_eq!(2, table.partitions.len());
+    assert_eq!(2, table.partitions[1].id);
+    assert_eq!(1050624 * 512, table.partitions[1].first_byte);
+
+    match &table.partitions[0].attributes {
+        Attributes::GPT {
+            name, attributes, ..
+        } => {
+            assert_eq!("EFI \"system\", partition", name);
+            assert_eq!((1u64 << 63) | 1, u64::from_le_bytes(*attributes));
+        }
+        _ => panic!("not gpt"),
+    }
+}
+
+#[test]
+fn nul_in_name() {
+    let dump = "label: gpt\n\n/dev/vda1 : start=2048, size=2048, type=0FC63DAF-8483-4772-8E79-3D69D8477DE4, name=\"a\0b\"\n";
+    assert!(sfdisk::parse(dump).is_err());
+}
@@ -0,0 +1,36 @@
+extern crate bootsector;
+
+use bootsector::sgdisk::Backup;
+use bootsector::{list_partitions, Options};
+
+#[test]
+fn round_trip() {
+    let image = &include_bytes!("test-data/labels.img")[..];
+    let backup = Backup::read(image, 512).expect("read");
+
+    let bytes = backup.to_bytes();
+    assert_eq!(3 * 512 + 128 * 128, bytes.len());
+
+    let parsed = Backup::parse(&bytes).expect("parse");
+    assert_eq!(
+        list_partitions(image, &Options::default()).unwrap(),
+        parsed.partitions(512).unwrap()
+    );
+}
+
+#[test]
+fn missing_backup_is_regenerated() {
+    // this image is truncated; the backup header is far beyond the end
+    let image = &include_bytes!("test-data/4t-gpt.img")[..];
+    let backup = Backup::read(image, 512).expect("read");
+    let parsed = Backup::parse(&backup.to_bytes()).expect("parse");
+    assert_eq!(2, parsed.partitions(512).unwrap().len());
+}
+
+#[test]
+fn corrupt_entries() {
+    let image = &include_bytes!("test-data/labels.img")[..];
+    let mut bytes = Backup::read(image, 512).expect("read").to_bytes();
+    bytes[3 * 512 + 0x38] ^= 0xff;
+    assert!(Backup::parse(&bytes).is_err());
+}
@@ -0,0 +1,105 @@
+extern crate bootsector;
+
+use bootsector::solaris::read_vtoc;
+use bootsector::{list_partitions, Error, Options};
+
+fn slice(label: &mut [u8], id: usize, tag: u16, start: u32, sectors: u32) {
+    let slice = &mut label[72 + id * 12..72 + (id + 1) * 12];
+    slice[0..2].copy_from_slice(&tag.to_le_bytes());
+    slice[4..8].copy_from_slice(&start.to_le_bytes());
+    slice[8..12].copy_from_slice(&sectors.to_le_bytes());
+}
+
+fn checksum(label: &mut [u8]) {
+    let sum = label[..510]
+        .chunks(2)
+        .fold(0, |sum, word| sum ^ u16::from_le_bytes([word[0], word[1]]));
+    label[510..512].copy_from_slice(&sum.to_le_bytes());
+}
+
+/// A 1MiB disc, with a Solaris partition from sector 64, and a VTOC with root, backup,
+/// and one bogus slice which doesn't fit.
+fn image() -> Vec<u8> {
+    let mut image = vec![0u8; 1024 * 1024];
+    let entry = &mut image[446..462];
+    entry[4] = 0xbf;
+    entry[8..12].copy_from_slice(&64u32.to_le_bytes());
+    entry[12..16].copy_from_slice(&1984u32.to_le_bytes());
+    image[510..512].copy_from_slice(&[0x55, 0xAA]);
+
+    let label = &mut image[65 * 512..66 * 512];
+    label[12..16].copy_from_slice(&0x600d_deeeu32.to_le_bytes());
+    label[16..20].copy_from_slice(&1u32.to_le_bytes());
+    label[20..24].copy_from_slice(b"test");
+    label[28..30].copy_from_slice(&512u16.to_le_bytes());
+    label[30..32].copy_from_slice(&16u16.to_le_bytes());
+    slice(label, 0, 2, 16, 512);
+    slice(label, 2, 5, 0, 1984);
+    slice(label, 8, 8, 600, 3000);
+    label[508..510].copy_from_slice(&0xdabeu16.to_le_bytes());
+    checksum(label);
+    image
+}
+
+#[test]
+fn slices() {
+    let image = image();
+
+    let plain = list_partitions(&image[..], &Options::default()).unwrap();
+    assert_eq!(1, plain.len());
+
+    let vtoc = read_vtoc(&image[..], &plain[0]).unwrap().expect("vtoc");
+    assert_eq!(b"test\0\0\0\0", &vtoc.volume);
+    assert_eq!(
+        vec![(0, 2, 80 * 512), (2, 5, 64 * 512)],
+        vtoc.slices
+            .iter()
+            .map(|slice| (slice.id, slice.tag, slice.first_byte))
+            .collect::<Vec<_>>()
+    );
+
+    let options = Options {
+        solaris_slices: true,
+        ..Options::default()
+    };
+    let parts = list_partitions(&image[..], &options).unwrap();
+    assert_eq!(
+        vec![(0, 64 * 512), (4, 80 * 512), (5, 64 * 512)],
+        parts
+            .iter()
+            .map(|part| (part.id, part.first_byte))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(1984 * 512, parts[2].len);
+}
+
+#[test]
+fn not_a_vtoc() {
+    let mut image = image();
+    // Linux swap shares a type code, but has no VTOC
+    image[446 + 4] = 0x82;
+    image[65 * 512 + 12] = 0;
+
+    let options = Options {
+        solaris_slices: true,
+        ..Options::default()
+    };
+    let parts = list_partitions(&image[..], &options).unwrap();
+    assert_eq!(1, parts.len());
+    assert_eq!(None, read_vtoc(&image[..], &parts[0]).unwrap());
+}
+
+#[test]
+fn bad_checksum() {
+    let mut image = image();
+    image[65 * 512 + 20] = b'b';
+
+    let options = Options {
+        solaris_slices: true,
+        ..Options::default()
+    };
+    assert!(matches!(
+        list_partitions(&image[..], &options),
+        Err(Error::InvalidStatic { .. })
+    ));
+}
@@ -0,0 +1,38 @@
+#![cfg(feature = "std")]
+
+extern crate bootsector;
+
+use std::io::Read;
+
+use bootsector::stream::read_partition_table;
+use bootsector::{read_partition_table_sized, Options};
+
+#[test]
+fn gpt() {
+    let image = &include_bytes!("test-data/labels.img")[..];
+    let mut reader = image;
+    let streamed = read_partition_table(&mut reader, &Options::default()).unwrap();
+    assert_eq!(
+        read_partition_table_sized(image, &Options::default()).unwrap(),
+        streamed.table
+    );
+
+    // only the start was consumed, and the rest is still there
+    assert!(streamed.prefix.len() < 1024 * 1024);
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest).unwrap();
+    assert_eq!(image, &[streamed.prefix, rest].concat()[..]);
+}
+
+#[test]
+fn short_stream() {
+    let image = &include_bytes!("test-data/mbr-ubuntu-raspi3-16.04.img")[..];
+    let streamed = read_partition_table(image, &Options::default());
+    let expected = read_partition_table_sized(image, &Options::default());
+    assert_eq!(
+        format!("{:?}", expected),
+        format!("{:?}", streamed.map(|s| s.table))
+    );
+
+    assert!(read_partition_table(&image[..100], &Options::default()).is_err());
+}
@@ -0,0 +1,78 @@
+extern crate bootsector;
+
+use bootsector::superfloppy::{read_table_or_filesystem, read_whole_device, Contents, Filesystem};
+use bootsector::{list_partitions, Options};
+
+/// A FAT32 filesystem covering a 64MiB stick, as `mkfs.fat -F 32 -n STICK` makes.
+fn fat32() -> Vec<u8> {
+    let mut image = vec![0u8; 64 * 1024];
+    let boot = &mut image[..512];
+    boot[..3].copy_from_slice(&[0xeb, 0x58, 0x90]);
+    boot[3..11].copy_from_slice(b"mkfs.fat");
+    boot[0x0b..0x0d].copy_from_slice(&512u16.to_le_bytes());
+    boot[0x0d] = 1;
+    boot[0x0e..0x10].copy_from_slice(&32u16.to_le_bytes());
+    boot[0x10] = 2;
+    boot[0x15] = 0xf8;
+    boot[0x20..0x24].copy_from_slice(&131_072u32.to_le_bytes());
+    boot[0x42] = 0x29;
+    boot[0x47..0x52].copy_from_slice(b"STICK      ");
+    boot[0x52..0x5a].copy_from_slice(b"FAT32   ");
+    // boot code, which isn't a partition table
+    for byte in &mut boot[0x1be..0x1fe] {
+        *byte = 0xcd;
+    }
+    boot[510] = 0x55;
+    boot[511] = 0xaa;
+    image
+}
+
+#[test]
+fn fat() {
+    let image = fat32();
+    assert!(list_partitions(&image[..], &Options::default()).is_err());
+
+    let found = read_whole_device(&image[..]).unwrap().unwrap();
+    assert_eq!(Filesystem::FAT, found.filesystem);
+    assert_eq!(64 * 1024 * 1024, found.len);
+    assert_eq!(Some("STICK"), found.label.as_deref());
+
+    match read_table_or_filesystem(&image[..], &Options::default()).unwrap() {
+        Contents::WholeDevice(whole) => assert_eq!(found, whole),
+        Contents::Table(table) => panic!("unexpected table: {:?}", table),
+    }
+}
+
+#[test]
+fn exfat() {
+    let mut image = vec![0u8; 4096];
+    image[..3].copy_from_slice(&[0xeb, 0x76, 0x90]);
+    image[3..11].copy_from_slice(b"EXFAT   ");
+    image[0x48..0x50].copy_from_slice(&2048u64.to_le_bytes());
+    image[0x6c] = 9;
+    image[510] = 0x55;
+    image[511] = 0xaa;
+
+    let found = read_whole_device(&image[..]).unwrap().unwrap();
+    assert_eq!(Filesystem::ExFAT, found.filesystem);
+    assert_eq!(1024 * 1024, found.len);
+    assert_eq!(None, found.label);
+}
+
+#[test]
+fn tables() {
+    let image = include_bytes!("test-data/tiny.img");
+    assert_eq!(None, read_whole_device(&image[..]).unwrap());
+    match read_table_or_filesystem(&image[..], &Options::default()).unwrap() {
+        Contents::Table(table) => assert!(!table.partitions.is_empty()),
+        Contents::WholeDevice(whole) => panic!("unexpected filesystem: {:?}", whole),
+    }
+
+    // GRUB's boot code starts with a jump, but has no parameters after it
+    let mut image = image.to_vec();
+    image[..3].copy_from_slice(&[0xeb, 0x63, 0x90]);
+    for byte in &mut image[3..0x5a] {
+        *byte = 0;
+    }
+    assert_eq!(None, read_whole_device(&image[..]).unwrap());
+}
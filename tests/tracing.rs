@@ -0,0 +1,74 @@
+extern crate bootsector;
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use bootsector::{read_partition_table, Options};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+/// Records the message of every event.
+#[derive(Clone, Default)]
+struct Messages(Arc<Mutex<Vec<String>>>);
+
+struct Message<'m>(&'m mut String);
+
+impl<'m> Visit for Message<'m> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if "message" == field.name() {
+            *self.0 = format!("{:?}", value);
+        }
+    }
+}
+
+impl Subscriber for Messages {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn new_span(&self, _: &Attributes) -> Id {
+        Id::from_u64(1)
+    }
+
+    fn record(&self, _: &Id, _: &Record) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event) {
+        let mut message = String::new();
+        event.record(&mut Message(&mut message));
+        self.0.lock().unwrap().push(message);
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+#[test]
+fn decisions() {
+    let messages = Messages::default();
+    // with_default needs tracing's std feature, which the library doesn't enable
+    tracing::subscriber::set_global_default(messages.clone()).unwrap();
+    read_partition_table(
+        &include_bytes!("test-data/4t-gpt.img")[..],
+        &Options::default(),
+    )
+    .unwrap();
+
+    let messages = messages.0.lock().unwrap();
+    assert_eq!("read", messages[0]);
+    for expected in &[
+        "sector size chosen",
+        "checking primary gpt header",
+        "found table",
+    ] {
+        assert!(
+            messages.iter().any(|message| message == expected),
+            "{} in {:?}",
+            expected,
+            messages
+        );
+    }
+}
@@ -0,0 +1,88 @@
+#![cfg(feature = "vdi")]
+
+extern crate bootsector;
+
+use bootsector::pio::ReadAt;
+use bootsector::read_partition_table_sized;
+use bootsector::vdi::Vdi;
+
+const BLOCK: usize = 1024 * 1024;
+
+fn put(image: &mut [u8], at: usize, bytes: &[u8]) {
+    image[at..at + bytes.len()].copy_from_slice(bytes);
+}
+
+/// A dynamic image: header, block map at 0x200, blocks from 0x1000 in reverse order.
+fn vdi(raw: &[u8]) -> Vec<u8> {
+    let blocks = (raw.len() + BLOCK - 1) / BLOCK;
+
+    let mut image = vec![0u8; 0x1000];
+    put(&mut image, 0, b"<<< Oracle VM VirtualBox Disk Image >>>\n");
+    put(&mut image, 0x40, &0xbeda_107fu32.to_le_bytes());
+    put(&mut image, 0x44, &0x0001_0001u32.to_le_bytes());
+    put(&mut image, 0x48, &0x190u32.to_le_bytes());
+    put(&mut image, 0x4c, &1u32.to_le_bytes());
+    put(&mut image, 0x154, &0x200u32.to_le_bytes());
+    put(&mut image, 0x158, &0x1000u32.to_le_bytes());
+    put(&mut image, 0x168, &512u32.to_le_bytes());
+    put(&mut image, 0x170, &(raw.len() as u64).to_le_bytes());
+    put(&mut image, 0x178, &(BLOCK as u32).to_le_bytes());
+    put(&mut image, 0x180, &(blocks as u32).to_le_bytes());
+
+    let mut allocated = 0u32;
+    for (i, block) in raw.chunks(BLOCK).enumerate().rev() {
+        let entry = if block.iter().all(|&b| 0 == b) {
+            0xffff_ffffu32
+        } else {
+            image.extend_from_slice(block);
+            allocated += 1;
+            allocated - 1
+        };
+        put(&mut image, 0x200 + i * 4, &entry.to_le_bytes());
+    }
+    put(&mut image, 0x184, &allocated.to_le_bytes());
+
+    image
+}
+
+#[test]
+fn dynamic() {
+    let raw = &include_bytes!("test-data/labels.img")[..];
+    let image = vdi(raw);
+    assert!(image.len() < raw.len());
+
+    let disc = Vdi::open(&image[..]).unwrap();
+    assert_eq!(raw.len() as u64, disc.disk_len());
+
+    let mut read = vec![0u8; raw.len()];
+    disc.read_exact_at(0, &mut read).unwrap();
+    assert_eq!(raw, &read[..]);
+
+    let table = read_partition_table_sized(&disc, &Default::default()).unwrap();
+    assert_eq!(7, table.partitions.len());
+}
+
+#[test]
+fn not_vdi() {
+    let raw = &include_bytes!("test-data/labels.img")[..];
+    assert!(matches!(Vdi::open(raw), Err(bootsector::Error::NotFound)));
+}
+
+#[test]
+fn crafted_headers() {
+    let raw = &include_bytes!("test-data/labels.img")[..];
+
+    let mut huge_map = vdi(raw);
+    put(&mut huge_map, 0x180, &u32::MAX.to_le_bytes());
+    assert!(Vdi::open(&huge_map[..]).is_err());
+
+    // a block which lives beyond 2^64
+    let mut far = vdi(raw);
+    put(&mut far, 0x178, &0xffff_fe00u32.to_le_bytes());
+    put(&mut far, 0x17c, &u32::MAX.to_le_bytes());
+    put(&mut far, 0x180, &1u32.to_le_bytes());
+    put(&mut far, 0x200, &0xffff_fffdu32.to_le_bytes());
+    let disc = Vdi::open(&far[..]).unwrap();
+    let err = disc.read_exact_at(0, &mut [0u8; 512]).unwrap_err();
+    assert_eq!(std::io::ErrorKind::InvalidData, err.kind());
+}
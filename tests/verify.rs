@@ -0,0 +1,186 @@
+extern crate bootsector;
+
+mod common;
+
+use bootsector::verify::{verify, Severity};
+use bootsector::Options;
+
+fn codes(image: &[u8], disk_len: Option<u64>) -> Vec<(Severity, &'static str)> {
+    verify(image, disk_len, &Options::default())
+        .expect("readable")
+        .into_iter()
+        .map(|finding| (finding.severity, finding.code))
+        .collect()
+}
+
+#[test]
+fn labels_is_clean() {
+    let image = include_bytes!("test-data/labels.img");
+    let findings = codes(image, Some(image.len() as u64));
+    assert!(
+        findings
+            .iter()
+            .all(|(severity, _)| Severity::Error != *severity),
+        "{:?}",
+        findings
+    );
+}
+
+#[test]
+fn truncated_image_has_no_backup() {
+    let image = include_bytes!("test-data/4t-gpt.img");
+    let findings = codes(image, None);
+    assert!(findings.contains(&(Severity::Error, "gpt-backup-unreadable")));
+}
+
+#[test]
+fn corrupt_primary_header() {
+    let mut image = include_bytes!("test-data/labels.img").to_vec();
+    image[512 + 0x20] ^= 0xff;
+    let findings = codes(&image, Some(image.len() as u64));
+    assert!(findings.contains(&(Severity::Error, "gpt-primary-header-crc")));
+    assert!(!findings.contains(&(Severity::Error, "gpt-backup-header-crc")));
+}
+
+#[test]
+fn mbr_only() {
+    let findings = codes(
+        include_bytes!("test-data/mbr-ubuntu-raspi3-16.04.img"),
+        None,
+    );
+    assert!(findings.is_empty(), "{:?}", findings);
+}
+
+#[test]
+fn report_evidence() {
+    use bootsector::verify::{report, GptCopy};
+
+    let mut image = include_bytes!("test-data/labels.img").to_vec();
+    let len = image.len() as u64;
+
+    let clean = report(&image[..], Some(len), &Options::default()).expect("readable");
+    assert_eq!(Some(GptCopy::Primary), clean.gpt_header_used);
+    assert_eq!(3, clean.signatures.len());
+    assert!(clean.signatures.iter().all(|signature| signature.found));
+    let checked: Vec<&str> = clean.checksums.iter().map(|crc| crc.what).collect();
+    assert_eq!(
+        vec![
+            "primary gpt header",
+            "primary gpt entry array",
+            "backup gpt header",
+            "backup gpt entry array"
+        ],
+        checked
+    );
+    assert!(clean.checksums.iter().all(|crc| crc.matches()));
+
+    image[512 + 0x20] ^= 0xff;
+    let corrupt = report(&image[..], Some(len), &Options::default()).expect("readable");
+    assert_eq!(Some(GptCopy::Backup), corrupt.gpt_header_used);
+    assert!(!corrupt.checksums[0].matches());
+    assert_eq!(512, corrupt.checksums[0].pos);
+}
+
+#[test]
+fn lint() {
+    use bootsector::edit::GptEditor;
+    use bootsector::gpt::{self, CreateOptions};
+
+    const MIB: u64 = 1024 * 1024;
+    let lint_codes = |image: &[u8], disk_len: u64| -> Vec<(Severity, &'static str)> {
+        let table = bootsector::read_partition_table(image, &Options::default()).unwrap();
+        bootsector::verify::lint(&table, disk_len)
+            .into_iter()
+            .map(|finding| (finding.severity, finding.code))
+            .collect()
+    };
+
+    let mut image = vec![0u8; 8 * MIB as usize];
+    gpt::create(&mut image[..], 8 * MIB, 512, &CreateOptions::new([7; 16])).unwrap();
+    let mut editor = GptEditor::open(&image[..], 512).unwrap();
+    editor.add(4 * MIB, MIB, [1; 16], [1; 16]).unwrap();
+    editor.add(MIB, MIB, [1; 16], [2; 16]).unwrap();
+    let id = editor.add(6 * MIB + 512, MIB, [1; 16], [3; 16]).unwrap();
+    editor.set_name(id, "tab\there").unwrap();
+    editor.commit(&mut image[..]).unwrap();
+
+    assert_eq!(
+        vec![
+            (Severity::Info, "partition-order"),
+            (Severity::Warning, "partition-unaligned"),
+            (Severity::Warning, "partition-name-control"),
+        ],
+        lint_codes(&image, 8 * MIB)
+    );
+
+    // as if the image had been copied to a larger disc
+    let grown = lint_codes(&image, 16 * MIB);
+    for code in &[
+        "mbr-protective-size",
+        "gpt-backup-location",
+        "gpt-usable-end",
+    ] {
+        assert!(grown.contains(&(Severity::Warning, code)), "{:?}", grown);
+    }
+}
+
+#[test]
+fn huge_entry_array() {
+    let mut image = include_bytes!("test-data/labels.img").to_vec();
+
+    // a validly checksummed primary header, claiming a 16EiB entry array
+    image[512 + 0x50..512 + 0x58].copy_from_slice(&[0xff; 8]);
+    common::fix_header_crc(&mut image);
+
+    let findings = codes(&image, Some(image.len() as u64));
+    assert!(findings.contains(&(Severity::Error, "gpt-primary-table-crc")));
+}
+
+#[test]
+fn implausible_sector_size_guess() {
+    let mut image = include_bytes!("test-data/labels.img").to_vec();
+
+    // no valid header to probe for, and a protective partition starting near 2TiB
+    image[512] ^= 0xff;
+    image[446 + 8..446 + 12].copy_from_slice(&0xffff_fff0u32.to_le_bytes());
+    let findings = codes(&image, Some(image.len() as u64));
+    assert!(findings.contains(&(Severity::Error, "sector-size")));
+
+    // a plausible size, but with no header there
+    image[446 + 8..446 + 12].copy_from_slice(&8u32.to_le_bytes());
+    let findings = codes(&image, Some(image.len() as u64));
+    assert!(findings.contains(&(Severity::Error, "sector-size")));
+}
+
+#[test]
+fn odd_entry_size() {
+    use bootsector::gpt::{self, CreateOptions};
+    use bootsector::EntrySizes;
+
+    const MIB: u64 = 1024 * 1024;
+    let mut image = vec![0u8; 4 * MIB as usize];
+    gpt::create(&mut image[..], 4 * MIB, 512, &CreateOptions::new([7; 16])).unwrap();
+
+    // 120 entries of 136 bytes, in the space of 128 of 128 bytes
+    image[512 + 0x50..512 + 0x54].copy_from_slice(&120u32.to_le_bytes());
+    image[512 + 0x54..512 + 0x58].copy_from_slice(&136u32.to_le_bytes());
+    common::fix_table_crc(&mut image);
+
+    let options = Options {
+        entry_sizes: EntrySizes::Any,
+        ..Options::default()
+    };
+    let table = bootsector::read_partition_table(&image[..], &options).unwrap();
+    assert_eq!("gpt-entry-size", table.warnings[0].code);
+
+    let findings: Vec<&str> = verify(&image[..], Some(image.len() as u64), &options)
+        .unwrap()
+        .into_iter()
+        .map(|finding| finding.code)
+        .collect();
+    assert!(
+        !findings.contains(&"gpt-primary-table-crc"),
+        "{:?}",
+        findings
+    );
+}
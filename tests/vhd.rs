@@ -0,0 +1,254 @@
+#![cfg(feature = "vhd")]
+
+extern crate bootsector;
+
+use bootsector::pio::ReadAt;
+use bootsector::read_partition_table_sized;
+use bootsector::vhd::{DiskType, Vhd};
+use bootsector::vhdx::Vhdx;
+
+const MIB: usize = 1024 * 1024;
+
+fn labels() -> &'static [u8] {
+    &include_bytes!("test-data/labels.img")[..]
+}
+
+fn check<R: ReadAt + bootsector::pio::Size>(raw: &[u8], disc: R) {
+    let mut read = vec![0u8; raw.len()];
+    disc.read_exact_at(0, &mut read).unwrap();
+    assert_eq!(raw, &read[..]);
+
+    let table = read_partition_table_sized(&disc, &Default::default()).unwrap();
+    assert_eq!(7, table.partitions.len());
+}
+
+fn put(image: &mut [u8], at: usize, bytes: &[u8]) {
+    image[at..at + bytes.len()].copy_from_slice(bytes);
+}
+
+fn vhd_checksum(data: &mut [u8], at: usize) {
+    let sum = data
+        .iter()
+        .fold(0u32, |sum, &b| sum.wrapping_add(u32::from(b)));
+    put(data, at, &(!sum).to_be_bytes());
+}
+
+fn vhd_footer(len: usize, disk_type: u32, data_offset: u64) -> Vec<u8> {
+    let mut footer = vec![0u8; 512];
+    put(&mut footer, 0, b"conectix");
+    put(&mut footer, 8, &2u32.to_be_bytes());
+    put(&mut footer, 12, &0x0001_0000u32.to_be_bytes());
+    put(&mut footer, 16, &data_offset.to_be_bytes());
+    put(&mut footer, 40, &(len as u64).to_be_bytes());
+    put(&mut footer, 48, &(len as u64).to_be_bytes());
+    put(&mut footer, 60, &disk_type.to_be_bytes());
+    vhd_checksum(&mut footer, 64);
+    footer
+}
+
+#[test]
+fn fixed() {
+    let raw = labels();
+    let mut image = raw.to_vec();
+    image.extend_from_slice(&vhd_footer(raw.len(), 2, u64::MAX));
+
+    let disc = Vhd::open(&image[..]).unwrap();
+    assert_eq!(DiskType::Fixed, disc.disk_type());
+    check(raw, disc);
+}
+
+#[test]
+fn dynamic() {
+    let raw = labels();
+    let block = 2 * MIB;
+    let entries = (raw.len() + block - 1) / block;
+
+    let footer = vhd_footer(raw.len(), 3, 512);
+    let mut image = footer.clone();
+
+    let mut header = vec![0u8; 1024];
+    put(&mut header, 0, b"cxsparse");
+    put(&mut header, 8, &u64::MAX.to_be_bytes());
+    put(&mut header, 16, &1536u64.to_be_bytes());
+    put(&mut header, 24, &0x0001_0000u32.to_be_bytes());
+    put(&mut header, 28, &(entries as u32).to_be_bytes());
+    put(&mut header, 32, &(block as u32).to_be_bytes());
+    vhd_checksum(&mut header, 36);
+    image.extend_from_slice(&header);
+
+    let bat = image.len();
+    image.resize(bat + (entries * 4 + 511) / 512 * 512, 0xff);
+
+    for (i, data) in raw.chunks(block).enumerate() {
+        if data.iter().all(|&b| 0 == b) {
+            continue;
+        }
+        let sector = (image.len() / 512) as u32;
+        put(&mut image, bat + i * 4, &sector.to_be_bytes());
+        image.extend_from_slice(&[0xff; 512]);
+        image.extend_from_slice(data);
+    }
+    image.extend_from_slice(&footer);
+
+    let disc = Vhd::open(&image[..]).unwrap();
+    assert_eq!(DiskType::Dynamic, disc.disk_type());
+    check(raw, disc);
+}
+
+#[test]
+fn huge_block_table() {
+    let footer = vhd_footer(4 * MIB, 3, 512);
+    let mut image = footer.clone();
+
+    let mut header = vec![0u8; 1024];
+    put(&mut header, 0, b"cxsparse");
+    put(&mut header, 8, &u64::MAX.to_be_bytes());
+    put(&mut header, 16, &1536u64.to_be_bytes());
+    put(&mut header, 24, &0x0001_0000u32.to_be_bytes());
+    put(&mut header, 28, &u32::MAX.to_be_bytes());
+    put(&mut header, 32, &(2 * MIB as u32).to_be_bytes());
+    vhd_checksum(&mut header, 36);
+    image.extend_from_slice(&header);
+    image.extend_from_slice(&footer);
+    assert_eq!(2048, image.len());
+
+    assert!(Vhd::open(&image[..]).is_err());
+}
+
+#[test]
+fn not_vhd() {
+    assert!(matches!(
+        Vhd::open(labels()),
+        Err(bootsector::Error::NotFound)
+    ));
+    assert!(matches!(
+        Vhdx::open(labels()),
+        Err(bootsector::Error::NotFound)
+    ));
+}
+
+fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in data {
+        crc ^= u32::from(b);
+        for _ in 0..8 {
+            crc = if 0 != crc & 1 {
+                (crc >> 1) ^ 0x82f6_3b78
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn with_crc32c(image: &mut [u8], at: usize, len: usize) {
+    put(image, at + 4, &[0; 4]);
+    let crc = crc32c(&image[at..at + len]);
+    put(image, at + 4, &crc.to_le_bytes());
+}
+
+/// The on-disc, mixed-endian, form of a GUID.
+fn guid(text: &str) -> [u8; 16] {
+    let hex: String = text.chars().filter(|&c| '-' != c).collect();
+    let mut b = [0u8; 16];
+    for (i, byte) in b.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap();
+    }
+    [
+        b[3], b[2], b[1], b[0], b[5], b[4], b[7], b[6], b[8], b[9], b[10], b[11], b[12], b[13],
+        b[14], b[15],
+    ]
+}
+
+/// Log at 1MiB, metadata at 2MiB, BAT at 3MiB, then 1MiB data blocks.
+fn vhdx(raw: &[u8]) -> Vec<u8> {
+    let mut image = vec![0u8; 4 * MIB];
+    put(&mut image, 0, b"vhdxfile");
+
+    for &(at, sequence) in &[(64 * 1024, 1u64), (128 * 1024, 0)] {
+        put(&mut image, at, b"head");
+        put(&mut image, at + 8, &sequence.to_le_bytes());
+        put(&mut image, at + 66, &1u16.to_le_bytes());
+        put(&mut image, at + 68, &(MIB as u32).to_le_bytes());
+        put(&mut image, at + 72, &(MIB as u64).to_le_bytes());
+        with_crc32c(&mut image, at, 4096);
+    }
+
+    for &at in &[192 * 1024, 256 * 1024] {
+        put(&mut image, at, b"regi");
+        put(&mut image, at + 8, &2u32.to_le_bytes());
+        let regions = [
+            ("2DC27766-F623-4200-9D64-115E9BFD4A08", 3 * MIB),
+            ("8B7CA206-4790-4B9A-B8FE-575F050F886E", 2 * MIB),
+        ];
+        for (i, &(id, offset)) in regions.iter().enumerate() {
+            let entry = at + 16 + i * 32;
+            put(&mut image, entry, &guid(id));
+            put(&mut image, entry + 16, &(offset as u64).to_le_bytes());
+            put(&mut image, entry + 24, &(MIB as u32).to_le_bytes());
+            put(&mut image, entry + 28, &1u32.to_le_bytes());
+        }
+        with_crc32c(&mut image, at, 64 * 1024);
+    }
+
+    let metadata = 2 * MIB;
+    put(&mut image, metadata, b"metadata");
+    put(&mut image, metadata + 10, &3u16.to_le_bytes());
+    let items: [(&str, Vec<u8>); 3] = [
+        (
+            "CAA16737-FA36-4D43-B3B6-33F0AA44E76B",
+            [(MIB as u32).to_le_bytes(), 0u32.to_le_bytes()].concat(),
+        ),
+        (
+            "2FA54224-CD1B-4876-B211-5DBED83BF4B8",
+            (raw.len() as u64).to_le_bytes().to_vec(),
+        ),
+        (
+            "8141BF1D-A96F-4709-BA47-F233A8FAAB5F",
+            512u32.to_le_bytes().to_vec(),
+        ),
+    ];
+    for (i, (id, value)) in items.iter().enumerate() {
+        let entry = metadata + 32 + i * 32;
+        let offset = 64 * 1024 + i * 8;
+        put(&mut image, entry, &guid(id));
+        put(&mut image, entry + 16, &(offset as u32).to_le_bytes());
+        put(&mut image, entry + 20, &(value.len() as u32).to_le_bytes());
+        put(&mut image, entry + 24, &0b100u32.to_le_bytes());
+        put(&mut image, metadata + offset, value);
+    }
+
+    let bat = 3 * MIB;
+    for (i, data) in raw.chunks(MIB).enumerate() {
+        if data.iter().all(|&b| 0 == b) {
+            continue;
+        }
+        let entry = image.len() as u64 | 6;
+        put(&mut image, bat + i * 8, &entry.to_le_bytes());
+        image.extend_from_slice(data);
+        image.resize((image.len() + MIB - 1) / MIB * MIB, 0);
+    }
+
+    image
+}
+
+#[test]
+fn vhdx_dynamic() {
+    let raw = labels();
+    let image = vhdx(raw);
+
+    let disc = Vhdx::open(&image[..]).unwrap();
+    assert_eq!(512, disc.logical_sector_size());
+    check(raw, disc);
+}
+
+#[test]
+fn vhdx_log() {
+    let mut image = vhdx(labels());
+    for &at in &[64 * 1024, 128 * 1024] {
+        image[at + 48] = 1;
+        with_crc32c(&mut image, at, 4096);
+    }
+    assert!(Vhdx::open(&image[..]).is_err());
+}
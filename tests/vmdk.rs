@@ -0,0 +1,127 @@
+#![cfg(feature = "vmdk")]
+
+extern crate bootsector;
+
+use std::path::PathBuf;
+
+use bootsector::pio::ReadAt;
+use bootsector::read_partition_table_sized;
+use bootsector::vmdk::Vmdk;
+
+const GRAIN: usize = 64 * 1024;
+const GTES: usize = 512;
+
+fn labels() -> &'static [u8] {
+    &include_bytes!("test-data/labels.img")[..]
+}
+
+fn check<R: ReadAt>(raw: &[u8], disc: Vmdk<R>) {
+    assert_eq!(raw.len() as u64, disc.disk_len());
+
+    let mut read = vec![0u8; raw.len()];
+    disc.read_exact_at(0, &mut read).unwrap();
+    assert_eq!(raw, &read[..]);
+
+    let table = read_partition_table_sized(&disc, &Default::default()).unwrap();
+    assert_eq!(7, table.partitions.len());
+}
+
+fn put(image: &mut [u8], at: usize, bytes: &[u8]) {
+    image[at..at + bytes.len()].copy_from_slice(bytes);
+}
+
+/// Header, grain directory at sector 1, grain tables from sector 2, then grains.
+fn sparse(raw: &[u8]) -> Vec<u8> {
+    let grains = (raw.len() + GRAIN - 1) / GRAIN;
+    let tables = (grains + GTES - 1) / GTES;
+
+    let mut image = vec![0u8; 1024 + tables * GTES * 4];
+    put(&mut image, 0, b"KDMV");
+    put(&mut image, 4, &1u32.to_le_bytes());
+    put(&mut image, 8, &3u32.to_le_bytes());
+    put(&mut image, 12, &(raw.len() as u64 / 512).to_le_bytes());
+    put(&mut image, 20, &(GRAIN as u64 / 512).to_le_bytes());
+    put(&mut image, 44, &(GTES as u32).to_le_bytes());
+    put(&mut image, 56, &1u64.to_le_bytes());
+    put(&mut image, 73, b"\n \r\n");
+
+    for table in 0..tables {
+        let sector = 2 + (table * GTES * 4 / 512) as u32;
+        put(&mut image, 512 + table * 4, &sector.to_le_bytes());
+    }
+
+    for (i, grain) in raw.chunks(GRAIN).enumerate() {
+        if grain.iter().all(|&b| 0 == b) {
+            continue;
+        }
+        let sector = (image.len() / 512) as u32;
+        put(&mut image, 1024 + i * 4, &sector.to_le_bytes());
+        image.extend_from_slice(grain);
+    }
+
+    image
+}
+
+#[test]
+fn monolithic_sparse() {
+    let raw = labels();
+    let image = sparse(raw);
+    assert!(image.len() < raw.len());
+    check(raw, Vmdk::open(&image[..]).unwrap());
+}
+
+#[test]
+fn huge_directory() {
+    let mut image = sparse(labels());
+    put(&mut image, 12, &(u64::MAX / 512).to_le_bytes());
+    put(&mut image, 20, &8u64.to_le_bytes());
+    put(&mut image, 44, &1u32.to_le_bytes());
+    assert!(Vmdk::open(&image[..]).is_err());
+}
+
+#[test]
+fn not_vmdk() {
+    assert!(matches!(
+        Vmdk::open(labels()),
+        Err(bootsector::Error::NotFound)
+    ));
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("bootsector-vmdk-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn split_extents() {
+    let raw = labels();
+    let half = raw.len() / 2;
+    let dir = temp_dir("split");
+
+    let mut flat = vec![0u8; 4096];
+    flat.extend_from_slice(&raw[..half]);
+    std::fs::write(dir.join("disc flat.vmdk"), &flat).unwrap();
+    std::fs::write(dir.join("disc-s002.vmdk"), sparse(&raw[half..])).unwrap();
+    std::fs::write(
+        dir.join("disc.vmdk"),
+        format!(
+            "# Disk DescriptorFile\n\
+             version=1\n\
+             CID=fffffffe\n\
+             parentCID=ffffffff\n\
+             createType=\"custom\"\n\
+             \n\
+             # Extent description\n\
+             RW {} FLAT \"disc flat.vmdk\" 8\n\
+             RW {} SPARSE \"disc-s002.vmdk\"\n",
+            half / 512,
+            (raw.len() - half) / 512
+        ),
+    )
+    .unwrap();
+
+    let disc = Vmdk::open_descriptor(dir.join("disc.vmdk"));
+    std::fs::remove_dir_all(&dir).unwrap();
+    check(raw, disc.unwrap());
+}